@@ -0,0 +1,342 @@
+//! Accessibility report of the images on scraped pages.
+//!
+//! Conversion to markdown can drop the alt text of an image or the caption of
+//! a figure, and some images never had alt text to begin with. Every run
+//! writes `.scraper/accessibility.json`, listing the images of the pages it
+//! scraped that have no alt text in the saved markdown, and the captions that
+//! got lost. When the page's HTML is available (direct backend, or `--formats
+//! html` or `raw-html`), images whose alt text was lost in conversion are told
+//! apart from images that never had any.
+//!
+//! With `--alt-text-endpoint`, images without alt text are described by a
+//! vision model behind an OpenAI-compatible chat completions endpoint, and the
+//! description is written into the saved markdown.
+
+use chrono::{DateTime, Utc};
+use regex::{Captures, Regex};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tracing::warn;
+use url::Url;
+
+use crate::cli::ScrapeOptions;
+use crate::html;
+use crate::redact;
+
+/// File name of the accessibility report inside the state directory.
+pub const ACCESSIBILITY_FILE_NAME: &str = "accessibility.json";
+
+/// Environment variable holding the API key of the alt text endpoint.
+pub const ALT_TEXT_API_KEY_VAR: &str = "ALT_TEXT_API_KEY";
+
+/// Instruction sent with every image to the alt text endpoint.
+const ALT_TEXT_PROMPT: &str = "Write the alt text for this image from a documentation page: a single \
+    sentence under 125 characters saying what the image shows. Reply with the alt text only.";
+
+/// A markdown image: `![alt](source "optional title")`.
+static MARKDOWN_IMAGE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"!\[([^\]]*)\]\(\s*<?([^)\s>]+)>?(?:\s+"[^"]*")?\s*\)"#).unwrap());
+
+/// Images and captions of the pages scraped in a run.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessibilityReport {
+    /// When the report was written
+    pub generated_at: DateTime<Utc>,
+
+    /// Pages with at least one finding, in the order they were scraped
+    pub pages: Vec<PageImages>,
+}
+
+/// The findings of one page.
+#[derive(Debug, Serialize)]
+pub struct PageImages {
+    /// URL of the page
+    pub url: String,
+
+    /// File the page was saved to, relative to the output directory
+    pub file: String,
+
+    /// Images and captions needing attention
+    pub images: Vec<ImageFinding>,
+}
+
+/// An image without alt text, or a figure whose caption was lost.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageFinding {
+    /// Image source as written in the page, resolved against the page URL
+    pub src: String,
+
+    /// What is wrong with the image
+    pub issue: ImageIssue,
+
+    /// Alt text the image had in the page's HTML, for `lostAlt`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_alt: Option<String>,
+
+    /// Caption of the figure in the page's HTML, for `lostCaption`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+
+    /// Alt text generated with `--alt-text-endpoint` and written into the page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_alt: Option<String>,
+}
+
+/// Kind of accessibility finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageIssue {
+    /// The image has no alt text, and had none in the HTML (or the HTML is unavailable)
+    EmptyAlt,
+    /// The image had alt text in the HTML that the markdown lost
+    LostAlt,
+    /// The caption of a figure doesn't appear in the markdown
+    LostCaption,
+}
+
+impl ImageFinding {
+    /// Returns whether the image still has no alt text in the saved page.
+    pub fn missing_alt(&self) -> bool {
+        self.issue != ImageIssue::LostCaption && self.generated_alt.is_none()
+    }
+}
+
+impl AccessibilityReport {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        AccessibilityReport {
+            generated_at: Utc::now(),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Writes the report as pretty-printed JSON into the state directory of `output_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state directory cannot be created or the file cannot be written
+    pub fn write(&self, output_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        let dir = crate::state_dir(output_dir);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(ACCESSIBILITY_FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| format!("Failed to write accessibility report {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+/// Finds the images without alt text and the lost figure captions of a page.
+///
+/// # Arguments
+///
+/// * `markdown` - The page's markdown
+/// * `html` - The page's HTML, if the response included it
+/// * `page_url` - URL of the page, used to match image sources between markdown and HTML
+///
+/// # Examples
+///
+/// ```text
+/// let html = r#"<img src="/shot.png" alt="The dashboard">"#;
+/// let findings = check_images("![](/shot.png)", Some(html), "https://docs.example.com/");
+/// assert_eq!(findings[0].issue, ImageIssue::LostAlt);
+/// assert_eq!(findings[0].source_alt.as_deref(), Some("The dashboard"));
+/// ```
+pub fn check_images(markdown: &str, html: Option<&str>, page_url: &str) -> Vec<ImageFinding> {
+    let base = Url::parse(page_url).ok();
+    let resolve = |src: &str| resolve(base.as_ref(), src);
+
+    let source_alts: HashMap<String, String> = html
+        .map(|html| {
+            html::tags(html, &["img"])
+                .into_iter()
+                .filter_map(|tag| {
+                    let src = html::attribute(tag, "src")?;
+                    let alt = html::attribute(tag, "alt").filter(|alt| !alt.trim().is_empty())?;
+                    Some((resolve(&src), alt.trim().to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut findings: Vec<ImageFinding> = MARKDOWN_IMAGE
+        .captures_iter(markdown)
+        .filter(|captures| captures[1].trim().is_empty())
+        .map(|captures| {
+            let src = resolve(&captures[2]);
+            let source_alt = source_alts.get(&src).cloned();
+            ImageFinding {
+                issue: if source_alt.is_some() { ImageIssue::LostAlt } else { ImageIssue::EmptyAlt },
+                src,
+                source_alt,
+                caption: None,
+                generated_alt: None,
+            }
+        })
+        .collect();
+
+    let text = normalize_space(markdown);
+    for figure in html.map(|html| html::elements(html, "figure")).unwrap_or_default() {
+        let Some(caption) = html::element_text(figure, "figcaption").map(|caption| normalize_space(&caption)) else {
+            continue;
+        };
+        if caption.is_empty() || text.contains(&caption) {
+            continue;
+        }
+        let src = html::tags(figure, &["img"])
+            .first()
+            .and_then(|tag| html::attribute(tag, "src"))
+            .map(|src| resolve(&src))
+            .unwrap_or_default();
+        findings.push(ImageFinding {
+            src,
+            issue: ImageIssue::LostCaption,
+            source_alt: None,
+            caption: Some(caption),
+            generated_alt: None,
+        });
+    }
+    findings
+}
+
+/// Client of an OpenAI-compatible chat completions endpoint describing images.
+pub struct AltTextGenerator {
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl AltTextGenerator {
+    /// Creates a generator for `--alt-text-endpoint`, if it was given.
+    ///
+    /// The API key is read from `ALT_TEXT_API_KEY` and registered for
+    /// redaction; endpoints that need no key (such as a local model server)
+    /// work without it.
+    pub fn from_options(options: &ScrapeOptions) -> Option<Self> {
+        let endpoint = options.alt_text_endpoint.clone()?;
+        let api_key = std::env::var(ALT_TEXT_API_KEY_VAR).ok().filter(|key| !key.is_empty());
+        if let Some(key) = &api_key {
+            redact::register_secret(key);
+        }
+        Some(AltTextGenerator {
+            endpoint,
+            model: options.alt_text_model.clone(),
+            api_key,
+        })
+    }
+
+    /// Asks the model to describe the image at `image_url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response holds no text
+    pub async fn describe(&self, client: &Client, image_url: &str) -> Result<String, Box<dyn Error>> {
+        let body = json!({
+            "model": self.model,
+            "max_tokens": 100,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": ALT_TEXT_PROMPT },
+                    { "type": "image_url", "image_url": { "url": image_url } }
+                ]
+            }]
+        });
+        let mut request = client.post(&self.endpoint).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(format!("Alt text endpoint answered with status {}: {}", status, redact::preview(&text)).into());
+        }
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| format!("Failed to parse alt text response ({}): {}", e, redact::preview(&text)))?;
+        let alt = value["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or_else(|| format!("No alt text in response: {}", redact::preview(&text)))?;
+
+        // The alt text goes between square brackets on a single line
+        let alt = alt.trim().trim_matches('"').replace(['[', ']'], "").replace(['\r', '\n'], " ");
+        if alt.is_empty() {
+            return Err("The alt text endpoint returned an empty description".into());
+        }
+        Ok(alt)
+    }
+}
+
+/// Writes alt text generated for images without any into the markdown.
+///
+/// Each image is described once, even if the page shows it several times. An
+/// image that can't be described is reported and left without alt text.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client
+/// * `generator` - The alt text endpoint
+/// * `markdown` - The page's markdown
+/// * `page_url` - URL of the page, used to resolve relative image sources
+/// * `findings` - Findings of `check_images` for the page; generated alt text is recorded in them
+///
+/// # Returns
+///
+/// The markdown with the generated alt text filled in
+pub async fn fill_alt_text(
+    client: &Client,
+    generator: &AltTextGenerator,
+    markdown: &str,
+    page_url: &str,
+    findings: &mut [ImageFinding],
+) -> String {
+    let mut generated: HashMap<String, String> = HashMap::new();
+    for finding in findings.iter_mut().filter(|finding| finding.issue != ImageIssue::LostCaption) {
+        if !generated.contains_key(&finding.src) {
+            match generator.describe(client, &finding.src).await {
+                Ok(alt) => {
+                    generated.insert(finding.src.clone(), alt);
+                }
+                Err(e) => {
+                    warn!("Failed to generate alt text for {}: {}", finding.src, e);
+                    continue;
+                }
+            }
+        }
+        finding.generated_alt = generated.get(&finding.src).cloned();
+    }
+    if generated.is_empty() {
+        return markdown.to_string();
+    }
+
+    let base = Url::parse(page_url).ok();
+    MARKDOWN_IMAGE
+        .replace_all(markdown, |captures: &Captures| {
+            match generated.get(&resolve(base.as_ref(), &captures[2])).filter(|_| captures[1].trim().is_empty()) {
+                // Keep everything after the alt text: the source and any title
+                Some(alt) => format!("![{}{}", alt, &captures[0][2 + captures[1].len()..]),
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Resolves an image source against the page URL, keeping it as it is if it can't be resolved.
+fn resolve(base: Option<&Url>, src: &str) -> String {
+    base.and_then(|base| base.join(src).ok())
+        .map_or_else(|| src.to_string(), String::from)
+}
+
+/// Collapses runs of whitespace into single spaces.
+fn normalize_space(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
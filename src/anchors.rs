@@ -0,0 +1,105 @@
+//! Heading anchor slugs.
+//!
+//! Markdown renderers turn headings into anchor ids with slightly different
+//! rules, so a deep link such as `page.md#getting-started` only keeps working
+//! when anchors are generated the way the target renderer does it. Every
+//! export target has a default slug style, which can be overridden with
+//! `--slug-style <FORMAT>=<STYLE>`.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Algorithm used to turn heading text into an anchor id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SlugStyle {
+    /// GitHub: lowercase, punctuation removed, spaces become hyphens
+    #[default]
+    Github,
+    /// mdBook: like GitHub, but only ASCII letters are lowercased
+    Mdbook,
+    /// Docusaurus: like GitHub, but an explicit `{#id}` at the end of a heading wins
+    Docusaurus,
+}
+
+/// Generates unique anchors for the headings of one document.
+///
+/// Repeated headings get `-1`, `-2`, ... appended, as all supported renderers do.
+#[derive(Debug, Default)]
+pub struct Slugger {
+    style: SlugStyle,
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    /// Creates a slugger for a new document.
+    pub fn new(style: SlugStyle) -> Self {
+        Slugger {
+            style,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Returns the anchor of the next heading with the given text.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let mut slugger = Slugger::new(SlugStyle::Github);
+    /// assert_eq!(slugger.slug("Getting Started!"), "getting-started");
+    /// assert_eq!(slugger.slug("Getting Started!"), "getting-started-1");
+    /// ```
+    pub fn slug(&mut self, heading: &str) -> String {
+        let base = slugify(heading, self.style);
+        let mut slug = base.clone();
+
+        // The counter of the base slug is advanced until an unused suffix is found
+        while self.seen.contains_key(&slug) {
+            let count = self.seen.entry(base.clone()).or_insert(0);
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+/// Turns heading text into an anchor id, without deduplication.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(slugify("API: `Client::new()`", SlugStyle::Github), "api-clientnew");
+/// assert_eq!(slugify("Über Uns", SlugStyle::Github), "über-uns");
+/// assert_eq!(slugify("Über Uns", SlugStyle::Mdbook), "Über-uns");
+/// assert_eq!(slugify("Install {#setup}", SlugStyle::Docusaurus), "setup");
+/// ```
+pub fn slugify(heading: &str, style: SlugStyle) -> String {
+    if style == SlugStyle::Docusaurus {
+        if let Some(id) = explicit_id(heading) {
+            return id.to_string();
+        }
+    }
+
+    let mut slug = String::with_capacity(heading.len());
+    for c in heading.trim().chars() {
+        if c.is_whitespace() {
+            slug.push('-');
+        } else if c.is_alphanumeric() || c == '-' || c == '_' {
+            match style {
+                SlugStyle::Github | SlugStyle::Docusaurus => slug.extend(c.to_lowercase()),
+                SlugStyle::Mdbook => slug.push(c.to_ascii_lowercase()),
+            }
+        }
+    }
+    slug
+}
+
+/// Returns the id of a heading ending in an explicit `{#id}`.
+fn explicit_id(heading: &str) -> Option<&str> {
+    let rest = heading.trim_end().strip_suffix('}')?;
+    let start = rest.rfind("{#")?;
+    let id = &rest[start + 2..];
+    (!id.is_empty() && !id.contains(char::is_whitespace)).then_some(id)
+}
@@ -0,0 +1,754 @@
+//! Talking to the FireCrawl API: the request and response types of its scrape
+//! endpoint, and the requests themselves.
+//!
+//! Every request goes through `make_api_request` or `send_api_request`, which
+//! retry transient failures and tell an API that can't be reached
+//! (`ApiUnavailable`) from one that rejects the request, so the run can wait
+//! for the API to come back instead of failing its pages.
+
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::time::Instant;
+use tracing::{debug, trace};
+
+use crate::bandwidth;
+use crate::cli::Backend;
+use crate::metrics::CallMetrics;
+use crate::redact;
+use crate::retry::{self, Retrier, TransientFailure};
+
+/// Represents the different actions that can be performed during web scraping.
+/// 
+/// Each variant corresponds to a specific action supported by the FireCrawl API.
+/// Actions are serialized with a "type" field indicating the action type.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::Action;
+/// let actions = vec![
+///     Action::Wait { milliseconds: Some(2000), selector: None },
+///     Action::Click { selector: "#submit-button".to_string() },
+///     Action::Screenshot { selector: Some(".content".to_string()) }
+/// ];
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Action {
+    /// Wait for a specific duration or element to appear.
+    /// 
+    /// Either `milliseconds` or `selector` must be provided, but not both.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// // Wait for 2 seconds
+    /// let pause = Action::Wait { milliseconds: Some(2000), selector: None };
+    /// 
+    /// // Wait for element to appear
+    /// let loaded = Action::Wait { milliseconds: None, selector: Some("#loading".to_string()) };
+    /// ```
+    #[serde(rename = "wait")]
+    Wait {
+        /// Duration to wait in milliseconds
+        #[serde(skip_serializing_if = "Option::is_none")]
+        milliseconds: Option<u32>,
+        /// CSS selector to wait for
+        #[serde(skip_serializing_if = "Option::is_none")]
+        selector: Option<String>,
+    },
+
+    /// Take a screenshot of the page or a specific element.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// // Full page screenshot
+    /// let page = Action::Screenshot { selector: None };
+    /// 
+    /// // Screenshot specific element
+    /// let content = Action::Screenshot { selector: Some("#content".to_string()) };
+    /// ```
+    #[serde(rename = "screenshot")]
+    Screenshot {
+        /// Optional CSS selector for the element to screenshot
+        selector: Option<String>,
+    },
+
+    /// Click on an element identified by a CSS selector.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// let click = Action::Click { selector: "#submit-button".to_string() };
+    /// ```
+    #[serde(rename = "click")]
+    Click {
+        /// CSS selector for the element to click
+        selector: String,
+    },
+
+    /// Write text into an input element.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// let search = Action::WriteText { 
+    ///     selector: "#search".to_string(),
+    ///     text: "search query".to_string()
+    /// };
+    /// ```
+    #[serde(rename = "write")]
+    WriteText {
+        /// CSS selector for the input element
+        selector: String,
+        /// Text to write into the element
+        text: String,
+    },
+
+    /// Simulate pressing a keyboard key.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// let enter = Action::PressKey { key: "Enter".to_string() };
+    /// ```
+    #[serde(rename = "press")]
+    PressKey {
+        /// Key to simulate pressing (e.g., "Enter", "Tab", "ArrowDown")
+        key: String,
+    },
+
+    /// Scroll the page by a specific number of pixels.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// // Scroll down 500 pixels
+    /// let down = Action::Scroll { pixels: 500 };
+    /// 
+    /// // Scroll up 200 pixels
+    /// let up = Action::Scroll { pixels: -200 };
+    /// ```
+    #[serde(rename = "scroll")]
+    Scroll {
+        /// Number of pixels to scroll (positive for down, negative for up)
+        pixels: i32,
+    },
+
+    /// Extract content from a specific element.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// let article = Action::Scrape { selector: ".article-content".to_string() };
+    /// ```
+    #[serde(rename = "scrape")]
+    Scrape {
+        /// CSS selector for the element to scrape
+        selector: String,
+    },
+
+    /// Execute custom JavaScript code.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # use scraper::Action;
+    /// let hide_menu = Action::ExecuteJavaScript { 
+    ///     script: "document.querySelector('.menu').style.display = 'none'".to_string() 
+    /// };
+    /// ```
+    #[serde(rename = "execute")]
+    ExecuteJavaScript {
+        /// JavaScript code to execute
+        script: String,
+    },
+}
+
+/// Represents the geographical and language preferences for web scraping.
+/// 
+/// This struct allows specifying the country of origin for the request and
+/// preferred languages for content negotiation.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::Location;
+/// let location = Location {
+///     country: Some("AU".to_string()),  // Request from Australia
+///     languages: Some(vec!["en-AU".to_string(), "en".to_string()]),
+/// };
+/// 
+/// // Default US location with English
+/// let default_location = Location {
+///     country: None,  // Defaults to "US"
+///     languages: Some(vec!["en".to_string()]),
+/// };
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    /// The ISO 3166-1 alpha-2 country code for the request origin.
+    /// 
+    /// If not provided, defaults to "US". Examples: "AU", "GB", "DE".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    
+    /// List of preferred languages and locales in order of priority.
+    /// 
+    /// Languages should be specified using IETF language tags (e.g., "en-US", "fr-FR", "de").
+    /// The first language in the list has the highest priority.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<Vec<String>>,
+}
+
+/// Represents a request to the FireCrawl API for web scraping.
+/// 
+/// This struct contains all possible parameters for configuring a scraping request,
+/// including content selection, behavior options, and extraction preferences.
+/// 
+/// A request can also be read from a template with the API's field names;
+/// missing fields take their default values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::ScrapeRequest;
+/// let request = ScrapeRequest {
+///     url: "https://example.com".to_string(),
+///     formats: vec!["markdown".to_string()],
+///     only_main_content: Some(true),
+///     timeout: Some(30000),
+///     block_ads: Some(true),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ScrapeRequest {
+    /// The URL to scrape
+    pub url: String,
+
+    /// List of output formats to return (e.g., "markdown", "html", "links")
+    pub formats: Vec<String>,
+
+    /// Whether to extract only the main content, excluding navigation and footers
+    #[serde(skip_serializing_if = "Option::is_none", rename = "onlyMainContent")]
+    pub only_main_content: Option<bool>,
+
+    /// HTML tags to include in the extraction
+    #[serde(skip_serializing_if = "Option::is_none", rename = "includeTags")]
+    pub include_tags: Option<Vec<String>>,
+
+    /// HTML tags to exclude from the extraction
+    #[serde(skip_serializing_if = "Option::is_none", rename = "excludeTags")]
+    pub exclude_tags: Option<Vec<String>>,
+
+    /// Custom HTTP headers for the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<serde_json::Value>,
+
+    /// Time in milliseconds to wait before extraction
+    #[serde(skip_serializing_if = "Option::is_none", rename = "waitFor")]
+    pub wait_for: Option<i32>,
+
+    /// Whether to use mobile user agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile: Option<bool>,
+
+    /// Whether to skip TLS certificate verification
+    #[serde(skip_serializing_if = "Option::is_none", rename = "skipTlsVerification")]
+    pub skip_tls_verification: Option<bool>,
+
+    /// Request timeout in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<i32>,
+
+    /// Options for JSON extraction
+    #[serde(skip_serializing_if = "Option::is_none", rename = "jsonOptions")]
+    pub json_options: Option<JsonOptions>,
+
+    /// List of actions to perform before extraction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<Action>>,
+
+    /// Geographical and language preferences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+
+    /// Whether to remove base64 encoded images
+    #[serde(skip_serializing_if = "Option::is_none", rename = "removeBase64Images")]
+    pub remove_base64_images: Option<bool>,
+
+    /// Whether to block advertisements
+    #[serde(skip_serializing_if = "Option::is_none", rename = "blockAds")]
+    pub block_ads: Option<bool>,
+}
+
+/// Options for JSON extraction and transformation using AI models.
+/// 
+/// This struct allows configuring how JSON data is extracted from web content,
+/// either using a predefined schema or AI-guided extraction.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::JsonOptions;
+/// // Using schema-based extraction
+/// let json_options = JsonOptions {
+///     schema: Some(serde_json::json!({
+///         "type": "object",
+///         "properties": {
+///             "title": { "type": "string" },
+///             "price": { "type": "number" }
+///         }
+///     })),
+///     system_prompt: None,
+///     prompt: None,
+/// };
+/// 
+/// // Using AI-guided extraction
+/// let ai_options = JsonOptions {
+///     schema: None,
+///     system_prompt: Some("You are a product information extractor".to_string()),
+///     prompt: Some("Extract product details from the content".to_string()),
+/// };
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct JsonOptions {
+    /// JSON Schema defining the structure of data to extract.
+    /// 
+    /// When provided, the extractor will attempt to find and structure data
+    /// according to this schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+
+    /// System prompt for AI-guided extraction.
+    /// 
+    /// Defines the AI's role and general behavior when extracting data.
+    #[serde(skip_serializing_if = "Option::is_none", rename = "systemPrompt")]
+    pub system_prompt: Option<String>,
+
+    /// User prompt for AI-guided extraction.
+    /// 
+    /// Specific instructions for what data to extract when not using a schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+/// Response from the FireCrawl API's scrape endpoint.
+/// 
+/// Contains the success status of the request and the scraped data.
+/// A successful response (`success = true`) will contain the requested
+/// content formats in the `data` field.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::{Metadata, ScrapeData, ScrapeResponse};
+/// // Successful response with markdown content
+/// let response = ScrapeResponse {
+///     success: true,
+///     data: ScrapeData {
+///         markdown: Some("# Title\nContent...".to_string()),
+///         metadata: Metadata {
+///             title: Some("Page Title".to_string()),
+///             source_url: Some("https://example.com".to_string()),
+///             ..Default::default()
+///         },
+///         ..Default::default()
+///     },
+/// };
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScrapeResponse {
+    /// Indicates whether the scraping request was successful
+    pub success: bool,
+    /// Contains the scraped content and metadata
+    pub data: ScrapeData,
+}
+
+/// Contains the scraped content and metadata from a web page.
+/// 
+/// Different content formats can be requested in the scrape request,
+/// and the corresponding fields will be populated in the response.
+/// The metadata field is always included.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::{Metadata, ScrapeData};
+/// let data = ScrapeData {
+///     markdown: Some("# Page Title\nContent...".to_string()),
+///     html: Some("<h1>Page Title</h1><p>Content...</p>".to_string()),
+///     raw_html: None,
+///     screenshot: None,
+///     links: Some(vec![
+///         "https://example.com/page1".to_string(),
+///         "https://example.com/page2".to_string(),
+///     ]),
+///     metadata: Metadata {
+///         title: Some("Page Title".to_string()),
+///         source_url: Some("https://example.com".to_string()),
+///         ..Default::default()
+///     },
+///     warning: None,
+///     change_tracking: None,
+/// };
+/// ```
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ScrapeData {
+    /// Markdown version of the scraped content
+    pub markdown: Option<String>,
+
+    /// Clean HTML version of the content with unwanted elements removed
+    pub html: Option<String>,
+
+    /// Original HTML content of the page
+    #[serde(rename = "rawHtml")]
+    pub raw_html: Option<String>,
+
+    /// Base64-encoded screenshot of the page or element
+    pub screenshot: Option<String>,
+
+    /// List of URLs found on the page
+    pub links: Option<Vec<String>>,
+
+    /// Metadata about the scraped page
+    pub metadata: Metadata,
+
+    /// Warning messages from the scraping process, if any
+    pub warning: Option<String>,
+
+    /// Whether the page changed since FireCrawl last scraped it, when the
+    /// `changeTracking` format was requested
+    #[serde(rename = "changeTracking", default, skip_serializing_if = "Option::is_none")]
+    pub change_tracking: Option<ChangeTracking>,
+}
+
+/// Result of the `changeTracking` format, comparing a page with FireCrawl's previous scrape of it.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::{ChangeStatus, ChangeTracking};
+/// let tracking: ChangeTracking = serde_json::from_str(
+///     r#"{"previousScrapeAt": "2025-01-01T00:00:00Z", "changeStatus": "changed", "visibility": "visible"}"#,
+/// )?;
+/// assert_eq!(tracking.change_status, ChangeStatus::Changed);
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeTracking {
+    /// When FireCrawl previously scraped the page, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_scrape_at: Option<String>,
+
+    /// How the page compares with the previous scrape
+    pub change_status: ChangeStatus,
+
+    /// Whether the page can still be found by following links (`visible`) or only by its URL (`hidden`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+}
+
+/// How a page compares with FireCrawl's previous scrape of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeStatus {
+    /// FireCrawl hadn't scraped the page before
+    New,
+    /// The page's content changed
+    Changed,
+    /// The page's content is the same
+    Same,
+    /// The page no longer exists
+    Removed,
+}
+
+impl fmt::Display for ChangeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChangeStatus::New => "new",
+            ChangeStatus::Changed => "changed",
+            ChangeStatus::Same => "same",
+            ChangeStatus::Removed => "removed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Metadata extracted from the scraped web page.
+/// 
+/// Contains information about the page such as title, description,
+/// language, source URL, and any errors encountered during scraping.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::Metadata;
+/// let metadata = Metadata {
+///     title: Some("Page Title".to_string()),
+///     description: Some("Page description for SEO".to_string()),
+///     language: Some("en-US".to_string()),
+///     source_url: Some("https://example.com/page".to_string()),
+///     status_code: Some(200),
+///     error: None,
+/// };
+/// 
+/// // Metadata with an error
+/// let error_metadata = Metadata {
+///     title: None,
+///     description: None,
+///     language: None,
+///     source_url: Some("https://example.com/404".to_string()),
+///     status_code: Some(404),
+///     error: Some("Page not found".to_string()),
+/// };
+/// ```
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Metadata {
+    /// Page title from the HTML <title> tag or meta tags
+    pub title: Option<String>,
+
+    /// Page description from meta tags
+    pub description: Option<String>,
+
+    /// Page language (e.g., "en-US", "fr-FR")
+    pub language: Option<String>,
+
+    /// Original URL of the scraped page
+    #[serde(rename = "sourceURL")]
+    pub source_url: Option<String>,
+
+    /// HTTP status code from the page request
+    #[serde(rename = "statusCode")]
+    pub status_code: Option<i32>,
+
+    /// Error message if scraping failed
+    pub error: Option<String>,
+}
+
+/// The FireCrawl API a run makes its requests to.
+#[derive(Clone, Copy)]
+pub struct Api<'a> {
+    /// The HTTP client
+    pub client: &'a Client,
+    /// The FireCrawl scrape endpoint
+    pub url: &'a str,
+    /// The FireCrawl API key
+    pub key: &'a str,
+}
+
+/// Makes a request to the FireCrawl API with the given request body.
+/// 
+/// # Arguments
+/// 
+/// * `client` - The HTTP client
+/// * `api_url` - The FireCrawl API endpoint
+/// * `api_key` - The API authentication key
+/// * `request` - The request body
+/// * `retrier` - Retrier the request is retried with after a transient failure
+/// * `max_size` - Largest response body read, in bytes
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the API response, the call's latency, size and status,
+/// and the raw response body
+/// 
+/// # Errors
+/// 
+/// Returns an error, once the retries have run out for transient failures, if:
+/// - The HTTP request fails; `ApiUnavailable` if the API cannot be reached
+///   or answers that it is temporarily unavailable
+/// - The response status is not successful
+/// - The response is larger than `max_size`; `ResponseTooLarge`
+/// - The response is not JSON or cannot be parsed
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let request = ScrapeRequest {
+///     url: "https://example.com".to_string(),
+///     formats: vec!["markdown".to_string()],
+///     ..Default::default()
+/// };
+/// 
+/// let retrier = Retrier::new(retry::DEFAULT_RETRIES, retry::DEFAULT_RETRY_DELAY);
+/// let (response, call, body) = make_api_request(&client, &api_url, &api_key, request, &retrier, bandwidth::DEFAULT_MAX_RESPONSE_SIZE).await?;
+/// ```
+pub async fn make_api_request(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    request: ScrapeRequest,
+    retrier: &Retrier,
+    max_size: u64,
+) -> Result<(ScrapeResponse, CallMetrics, Vec<u8>), Box<dyn Error>> {
+    trace!(formats = ?request.formats, only_main_content = ?request.only_main_content, "API request");
+    retrier
+        .run(|| send_api_request(client.post(api_url).bearer_auth(api_key).json(&request), Some(max_size)))
+        .await
+}
+
+/// Sends a request to the FireCrawl API and parses its JSON response.
+/// 
+/// # Arguments
+/// 
+/// * `request` - The request, with its endpoint, credentials and body
+/// * `max_size` - Largest response body read, in bytes; unlimited if `None`
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the parsed response, the call's latency, size and
+/// status, and the raw response body
+/// 
+/// # Errors
+/// 
+/// Returns an error in the same cases as `make_api_request`
+pub async fn send_api_request<T: DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+    max_size: Option<u64>,
+) -> Result<(T, CallMetrics, Vec<u8>), Box<dyn Error>> {
+    let started = Instant::now();
+    let response = request
+        .send()
+        .await
+        .map_err(|e| -> Box<dyn Error> {
+            if e.is_connect() || e.is_timeout() {
+                Box::new(ApiUnavailable(e.to_string()))
+            } else {
+                e.into()
+            }
+        })?;
+
+    let status = response.status();
+    if matches!(status.as_u16(), 502..=504) {
+        return Err(Box::new(ApiUnavailable(format!("API answered with status {}", status))));
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(retry::parse_retry_after);
+        let error_body = response.text().await?;
+        return Err(Box::new(TransientFailure {
+            message: format!("API request failed with status {}: {}", status, redact::preview(&error_body)),
+            retry_after,
+        }));
+    }
+    if !status.is_success() {
+        let error_body = response.text().await?;
+        return Err(format!("API request failed with status {}: {}", status, redact::preview(&error_body)).into());
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = match max_size {
+        Some(max_size) => bandwidth::read_capped(response, max_size).await?,
+        None => response.bytes().await?.to_vec(),
+    };
+    let call = CallMetrics::new(started.elapsed(), body.len(), status.as_u16());
+    debug!(
+        status = status.as_u16(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        bytes = body.len(),
+        "API call"
+    );
+    let response = decode_json(content_type.as_deref(), &body)?;
+    Ok((response, call, body))
+}
+
+/// Error returned when the FireCrawl API cannot be reached or is temporarily unavailable.
+/// 
+/// Unlike other request errors, this is not a problem with the page being
+/// scraped: the run waits for the API to come back instead of failing the page.
+#[derive(Debug)]
+pub struct ApiUnavailable(String);
+
+impl fmt::Display for ApiUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ApiUnavailable {}
+
+/// Parses a JSON API response body, rejecting responses that aren't JSON.
+/// 
+/// Proxies and captive portals answer API requests with HTML pages; instead of
+/// an opaque parse error, the error names the content type received and shows
+/// the beginning of the body.
+/// 
+/// # Arguments
+/// 
+/// * `content_type` - The `Content-Type` header of the response, if any
+/// * `body` - The response body
+/// 
+/// # Errors
+/// 
+/// Returns an error if the content type is not JSON or the body cannot be parsed
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let err = decode_json::<ScrapeResponse>(Some("text/html"), b"<html>Log in</html>").unwrap_err();
+/// assert!(err.to_string().contains("<html>Log in</html>"));
+/// ```
+pub fn decode_json<T: DeserializeOwned>(content_type: Option<&str>, body: &[u8]) -> Result<T, Box<dyn Error>> {
+    let text = String::from_utf8_lossy(body);
+
+    if let Some(content_type) = content_type {
+        let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        if !mime.ends_with("/json") && !mime.ends_with("+json") {
+            return Err(format!(
+                "Expected a JSON response but received {} (is a proxy or captive portal intercepting requests?): {}",
+                mime,
+                redact::preview(&text)
+            )
+            .into());
+        }
+    }
+
+    serde_json::from_slice(body)
+        .map_err(|e| format!("Failed to parse JSON response ({}): {}", e, redact::preview(&text)).into())
+}
+
+/// Reads the FireCrawl API key from the environment.
+/// 
+/// The direct backend never talks to the API, so a missing key is only an
+/// error for the FireCrawl backend. The key is registered for redaction so it
+/// never appears in logged output.
+pub fn api_key(backend: Backend) -> Result<String, Box<dyn Error>> {
+    let key = match backend {
+        Backend::Firecrawl => std::env::var("FIRECRAWL_API_KEY")
+            .map_err(|_| "FIRECRAWL_API_KEY must be set in .env file")?,
+        Backend::Direct => std::env::var("FIRECRAWL_API_KEY").unwrap_or_default(),
+    };
+    redact::register_secret(&key);
+    Ok(key)
+}
+
+/// Returns the FireCrawl scrape endpoint, below `FIRECRAWL_API_URL` if it is set.
+pub fn api_url() -> String {
+    format!("{}/v1/scrape", 
+        std::env::var("FIRECRAWL_API_URL")
+            .unwrap_or_else(|_| "https://api.firecrawl.dev".to_string())
+    )
+}
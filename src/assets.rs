@@ -0,0 +1,468 @@
+//! Downloading of the images and PDFs pages refer to.
+//!
+//! With `--download-assets`, the images a page shows and the PDFs it links to
+//! are downloaded into the `assets/` directory of the output directory, and
+//! the page's links are pointed at the local copies. All pages of a run share
+//! one `AssetFetcher`, which keeps to limits of its own, apart from those of
+//! the page scrapes: at most `--asset-concurrency` downloads at a time,
+//! `--asset-delay-ms` between two downloads from the same host, a few retries
+//! of transient failures, and nothing larger than `--max-asset-size`.
+//!
+//! Files are content-addressed: named after the SHA-256 hash of their bytes,
+//! so an image served under several URLs is stored once. A URL is downloaded
+//! at most once per run however many pages refer to it, and the URLs already
+//! downloaded are recorded in `.scraper/assets.json`, so later runs reuse the
+//! files instead of downloading them again. The assets of each page are
+//! listed with their dimensions and hashes in its frontmatter (see `media`).
+
+use futures::future::join_all;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, Semaphore};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::bandwidth::{self, Bandwidth};
+use crate::cli::ScrapeOptions;
+use crate::media::{MediaFile, MediaKind};
+use crate::navigation::MARKDOWN_LINK;
+use crate::retry::{self, Retrier, TransientFailure};
+use crate::urls;
+
+/// Name of the directory inside the output directory that holds downloaded assets.
+pub const ASSETS_DIR_NAME: &str = "assets";
+
+/// File in the state directory recording the file each downloaded URL was saved as.
+const INDEX_FILE_NAME: &str = "assets.json";
+
+/// Downloads running at the same time when `--asset-concurrency` isn't given.
+pub const DEFAULT_ASSET_CONCURRENCY: usize = 4;
+
+/// Time between two downloads from the same host when `--asset-delay-ms` isn't given.
+pub const DEFAULT_ASSET_DELAY: Duration = Duration::from_millis(100);
+
+/// Largest asset downloaded when `--max-asset-size` isn't given.
+pub const DEFAULT_MAX_ASSET_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Retries of a download that failed for a transient reason.
+const ASSET_RETRIES: u32 = 2;
+
+/// Delay before the first retry of a download.
+const ASSET_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Hex digits of the content hash an asset file is named after.
+const HASH_DIGITS: usize = 16;
+
+/// Kinds of assets that are downloaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AssetKind {
+    /// An image shown on the page
+    Image,
+    /// A PDF document the page links to
+    Pdf,
+}
+
+impl AssetKind {
+    /// Returns what an asset of this kind is to the page referring to it.
+    fn media_kind(self) -> MediaKind {
+        match self {
+            AssetKind::Image => MediaKind::Image,
+            AssetKind::Pdf => MediaKind::Pdf,
+        }
+    }
+}
+
+/// Downloads the assets of a run's pages, shared by all of them.
+pub struct AssetFetcher<'a> {
+    client: &'a Client,
+    options: &'a ScrapeOptions,
+
+    /// Directory the assets are saved in
+    dir: PathBuf,
+
+    /// Permits for the downloads running at the same time
+    slots: Semaphore,
+
+    /// Time between two downloads from the same host
+    host_delay: Duration,
+
+    /// Earliest time the next download from each host may start
+    next_start: Mutex<HashMap<String, Instant>>,
+
+    /// Largest asset downloaded, in bytes
+    max_size: u64,
+
+    /// File each URL of this run was saved as, or `None` if it couldn't be downloaded,
+    /// set once by the first page referring to it
+    downloads: Mutex<HashMap<String, Arc<OnceCell<Option<MediaFile>>>>>,
+
+    /// File each URL was saved as by this or an earlier run, relative to `dir`
+    index: Mutex<BTreeMap<String, String>>,
+
+    downloaded: AtomicUsize,
+    reused: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+/// Assets handled during a run, as written to the run report.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetSummary {
+    /// Assets downloaded in this run
+    pub downloaded: usize,
+
+    /// Assets found already downloaded by an earlier run
+    pub reused: usize,
+
+    /// Assets that could not be downloaded, whose links were left pointing at the site
+    pub failed: usize,
+}
+
+impl<'a> AssetFetcher<'a> {
+    /// Creates the fetcher of a run saving into `output_dir`, with the limits chosen by the options.
+    ///
+    /// The URLs downloaded by earlier runs are read from the state directory.
+    pub fn new(client: &'a Client, output_dir: &Path, options: &'a ScrapeOptions) -> Self {
+        let index = fs::read_to_string(index_path(output_dir))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        AssetFetcher {
+            client,
+            options,
+            dir: output_dir.join(ASSETS_DIR_NAME),
+            slots: Semaphore::new(options.asset_concurrency.unwrap_or(DEFAULT_ASSET_CONCURRENCY).max(1)),
+            host_delay: options.asset_delay_ms.map_or(DEFAULT_ASSET_DELAY, Duration::from_millis),
+            next_start: Mutex::new(HashMap::new()),
+            max_size: options.max_asset_size.unwrap_or(DEFAULT_MAX_ASSET_SIZE),
+            downloads: Mutex::new(HashMap::new()),
+            index: Mutex::new(index),
+            downloaded: AtomicUsize::new(0),
+            reused: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Downloads the images and PDFs a page refers to and points its links at the local copies.
+    ///
+    /// Relative URLs are resolved against the page URL. Links to assets that
+    /// can't be downloaded are left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `markdown` - The page's markdown
+    /// * `page_url` - URL of the page
+    /// * `file` - File the page is saved to, relative to the output directory
+    /// * `bandwidth` - Meter the downloads are counted by; nothing is downloaded once its cap is reached
+    ///
+    /// # Returns
+    ///
+    /// The markdown with its links rewritten, the assets it refers to and the
+    /// number of assets that could not be downloaded
+    pub async fn localize(
+        &self,
+        markdown: &str,
+        page_url: &str,
+        file: &str,
+        bandwidth: &Bandwidth,
+    ) -> (String, Vec<MediaFile>, usize) {
+        let Ok(base) = Url::parse(page_url) else {
+            return (markdown.to_string(), Vec::new(), 0);
+        };
+
+        // Byte range of each asset URL in the markdown, with the URL it resolves to
+        let mut links = Vec::new();
+        for captures in MARKDOWN_LINK.captures_iter(markdown) {
+            let (Some(link), Some(target)) = (captures.get(0), captures.get(2)) else {
+                continue;
+            };
+            let Some(resolved) = base.join(target.as_str()).ok().filter(|url| matches!(url.scheme(), "http" | "https"))
+            else {
+                continue;
+            };
+            let kind = if markdown[..link.start()].ends_with('!') {
+                AssetKind::Image
+            } else if resolved.path().to_ascii_lowercase().ends_with(".pdf") {
+                AssetKind::Pdf
+            } else {
+                continue;
+            };
+            links.push((target.range(), resolved.to_string(), kind));
+        }
+        if links.is_empty() {
+            return (markdown.to_string(), Vec::new(), 0);
+        }
+
+        let files = join_all(links.iter().map(|(_, url, kind)| self.fetch(url, *kind, bandwidth))).await;
+        let mut localized = String::with_capacity(markdown.len());
+        let mut media: Vec<MediaFile> = Vec::new();
+        let mut position = 0;
+        let mut failed = 0;
+        for ((range, _, _), saved) in links.into_iter().zip(files) {
+            let Some(saved) = saved else {
+                failed += 1;
+                continue;
+            };
+            localized.push_str(&markdown[position..range.start]);
+            localized.push_str(&saved.path_from(file));
+            position = range.end;
+            if !media.iter().any(|known| known.file == saved.file) {
+                media.push(saved);
+            }
+        }
+        localized.push_str(&markdown[position..]);
+        (localized, media, failed)
+    }
+
+    /// Returns the file an asset is saved as, downloading it unless this or an earlier run already did.
+    ///
+    /// Pages referring to an asset while it is being downloaded wait for that
+    /// download instead of starting another one.
+    async fn fetch(&self, url: &str, kind: AssetKind, bandwidth: &Bandwidth) -> Option<MediaFile> {
+        let cell = {
+            let mut downloads = self.downloads.lock().unwrap_or_else(PoisonError::into_inner);
+            downloads.entry(url.to_string()).or_default().clone()
+        };
+        cell.get_or_init(|| self.download(url, kind, bandwidth)).await.clone()
+    }
+
+    /// Downloads an asset into the assets directory, named after its content hash.
+    ///
+    /// An asset downloaded by an earlier run is read back to describe it.
+    async fn download(&self, url: &str, kind: AssetKind, bandwidth: &Bandwidth) -> Option<MediaFile> {
+        let known = self.index.lock().unwrap_or_else(PoisonError::into_inner).get(url).cloned();
+        if let Some((file, bytes)) = known.and_then(|file| fs::read(self.dir.join(&file)).ok().map(|bytes| (file, bytes))) {
+            debug!("Reusing {} for {}", file, url);
+            self.reused.fetch_add(1, Ordering::Relaxed);
+            return Some(self.describe(&file, kind, &bytes));
+        }
+        if bandwidth.cap_reached() {
+            warn!("Asset not downloaded: --max-bytes reached: {}", url);
+            self.failed.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let _permit = self.slots.acquire().await.ok()?;
+        let retrier = Retrier::new(ASSET_RETRIES, ASSET_RETRY_DELAY);
+        let result = retrier
+            .run(|| async {
+                self.wait_for_host(url).await;
+                self.get(url, kind).await
+            })
+            .await
+            .and_then(|(extension, bytes)| self.save(&extension, &bytes).map(|file| (file, bytes)));
+        match result {
+            Ok((file, bytes)) => {
+                bandwidth.record_asset(bytes.len() as u64);
+                info!("Downloaded {} as {}", url, file);
+                self.index
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(url.to_string(), file.clone());
+                self.downloaded.fetch_add(1, Ordering::Relaxed);
+                Some(self.describe(&file, kind, &bytes))
+            }
+            Err(e) => {
+                warn!("Asset not downloaded: {}: {}", url, e);
+                self.failed.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Describes an asset saved as `file` in the assets directory.
+    fn describe(&self, file: &str, kind: AssetKind, bytes: &[u8]) -> MediaFile {
+        MediaFile::new(&format!("{}/{}", ASSETS_DIR_NAME, file), kind.media_kind(), bytes)
+    }
+
+    /// Waits until the host of a URL may be asked for the next asset.
+    async fn wait_for_host(&self, url: &str) {
+        let Some(host) = Url::parse(url).ok().and_then(|url| urls::ascii_host(&url)) else {
+            return;
+        };
+        let wait = {
+            let mut next_start = self.next_start.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let start = next_start.get(&host).copied().filter(|start| *start > now).unwrap_or(now);
+            next_start.insert(host, start + self.host_delay);
+            start - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Requests an asset once.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the file extension the asset is saved with and its bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TransientFailure` for timeouts, connection errors, `429` and
+    /// `5xx` answers, and an error if the asset is of another kind than
+    /// expected or larger than `--max-asset-size`
+    async fn get(&self, url: &str, kind: AssetKind) -> Result<(String, Vec<u8>), Box<dyn Error>> {
+        let mut request = self.client.get(url);
+        if let Some(auth) = self.options.credentials_for(url) {
+            request = request.header(reqwest::header::AUTHORIZATION, auth.header_value());
+        }
+        let mut response = request.send().await.map_err(|e| -> Box<dyn Error> {
+            if e.is_timeout() || e.is_connect() {
+                Box::new(TransientFailure {
+                    message: e.to_string(),
+                    retry_after: None,
+                })
+            } else {
+                e.into()
+            }
+        })?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(retry::parse_retry_after);
+            return Err(Box::new(TransientFailure {
+                message: format!("status {}", status),
+                retry_after,
+            }));
+        }
+        if !status.is_success() {
+            return Err(format!("status {}", status).into());
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or("").trim().to_ascii_lowercase());
+        let expected = match kind {
+            AssetKind::Image => content_type.as_deref().is_none_or(|mime| mime.starts_with("image/")),
+            AssetKind::Pdf => content_type
+                .as_deref()
+                .is_none_or(|mime| matches!(mime, "application/pdf" | "application/octet-stream")),
+        };
+        if !expected {
+            return Err(format!("served as {}", content_type.unwrap_or_default()).into());
+        }
+
+        let too_large = || format!("larger than {}", bandwidth::format_bytes(self.max_size));
+        let length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if length.is_some_and(|length| length > self.max_size) {
+            return Err(too_large().into());
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (bytes.len() + chunk.len()) as u64 > self.max_size {
+                return Err(too_large().into());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        let extension = content_type
+            .as_deref()
+            .and_then(extension_for)
+            .map(str::to_string)
+            .or_else(|| url_extension(url))
+            .unwrap_or_else(|| if kind == AssetKind::Pdf { "pdf" } else { "bin" }.to_string());
+        Ok((extension, bytes))
+    }
+
+    /// Saves an asset under its content hash, unless a file with the same content exists.
+    fn save(&self, extension: &str, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+        let hash = format!("{:x}", Sha256::digest(bytes));
+        let file = format!("{}.{}", &hash[..HASH_DIGITS], extension);
+        let path = self.dir.join(&file);
+        if !path.exists() {
+            fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to create directory {}: {}", self.dir.display(), e))?;
+            fs::write(&path, bytes).map_err(|e| format!("Failed to write file {}: {}", path.display(), e))?;
+        }
+        Ok(file)
+    }
+
+    /// Saves the URLs downloaded so far for later runs and summarizes the run's assets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be written
+    pub fn finish(&self, output_dir: &Path) -> Result<AssetSummary, Box<dyn Error>> {
+        let index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+        if !index.is_empty() {
+            let path = index_path(output_dir);
+            fs::create_dir_all(crate::state_dir(output_dir))?;
+            fs::write(&path, serde_json::to_string_pretty(&*index)?)
+                .map_err(|e| format!("Failed to write asset index {}: {}", path.display(), e))?;
+        }
+        let summary = AssetSummary {
+            downloaded: self.downloaded.load(Ordering::Relaxed),
+            reused: self.reused.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        };
+        info!(
+            "{} assets downloaded, {} reused and {} not downloaded",
+            summary.downloaded, summary.reused, summary.failed
+        );
+        Ok(summary)
+    }
+}
+
+/// Returns the path of the asset index in the state directory of `output_dir`.
+fn index_path(output_dir: &Path) -> PathBuf {
+    crate::state_dir(output_dir).join(INDEX_FILE_NAME)
+}
+
+/// Returns the file extension of a MIME type assets are served as.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(extension_for("image/svg+xml"), Some("svg"));
+/// assert_eq!(extension_for("text/html"), None);
+/// ```
+fn extension_for(mime: &str) -> Option<&'static str> {
+    Some(match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "application/pdf" => "pdf",
+        _ => return None,
+    })
+}
+
+/// Returns the extension of the last path segment of a URL, if it has a short alphanumeric one.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(url_extension("https://example.com/img/logo.PNG?v=2").as_deref(), Some("png"));
+/// assert_eq!(url_extension("https://example.com/img/logo"), None);
+/// ```
+fn url_extension(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let segment = url.path_segments()?.next_back()?;
+    let (_, extension) = segment.rsplit_once('.')?;
+    (!extension.is_empty() && extension.len() <= 5 && extension.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then(|| extension.to_ascii_lowercase())
+}
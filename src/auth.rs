@@ -0,0 +1,77 @@
+//! HTTP basic authentication for protected documentation sites.
+//!
+//! Credentials come from `--basic-auth` (used for every page) or from
+//! per-host entries in the configuration file, whose passwords are read from
+//! environment variables; `ScrapeOptions::credentials_for` picks the right
+//! ones for a URL. They are only ever placed in request headers: they
+//! are registered for redaction and never written to manifests or reports.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::RequestBuilder;
+use std::fmt;
+use crate::redact;
+
+/// A username and password for HTTP basic authentication.
+#[derive(Clone)]
+pub struct BasicAuth {
+    username: String,
+    password: String,
+}
+
+impl BasicAuth {
+    /// Creates credentials and registers them for redaction from logged output.
+    pub fn new(username: String, password: String) -> Self {
+        let auth = BasicAuth { username, password };
+        redact::register_secret(&auth.password);
+        redact::register_secret(&auth.encoded());
+        auth
+    }
+
+    /// Returns the value of the `Authorization` header for these credentials.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let auth = BasicAuth::new("user".to_string(), "pass".to_string());
+    /// assert_eq!(auth.header_value(), "Basic dXNlcjpwYXNz");
+    /// ```
+    pub fn header_value(&self) -> String {
+        format!("Basic {}", self.encoded())
+    }
+
+    /// Returns the base64 encoding of `username:password`.
+    fn encoded(&self) -> String {
+        STANDARD.encode(format!("{}:{}", self.username, self.password))
+    }
+}
+
+// Never print the password, even in debug output
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("username", &self.username)
+            .field("password", &redact::REDACTED)
+            .finish()
+    }
+}
+
+/// Parses `--basic-auth` values of the form `user:pass`.
+///
+/// # Errors
+///
+/// Returns an error if the value has no `:` separator
+pub fn parse_basic_auth(value: &str) -> Result<BasicAuth, String> {
+    let (username, password) = value
+        .split_once(':')
+        .ok_or("expected USER:PASS")?;
+    Ok(BasicAuth::new(username.to_string(), password.to_string()))
+}
+
+/// Adds credentials, if any, to a direct HTTP request.
+pub fn authorize(request: RequestBuilder, auth: Option<&BasicAuth>) -> RequestBuilder {
+    match auth {
+        Some(auth) => request.basic_auth(&auth.username, Some(&auth.password)),
+        None => request,
+    }
+}
@@ -0,0 +1,172 @@
+//! Backlog of pages that failed in earlier runs.
+//!
+//! A page whose scrape fails is recorded in the manifest with the number of
+//! runs in a row it has failed in. Later runs in the same output directory
+//! scrape such pages first, even if discovery no longer finds them, but only
+//! once a backoff has passed that doubles with every failure, so a page that is
+//! down for a while isn't requested again by every run. After `--max-failures`
+//! failed runs the page is moved to the manifest's permanently failing pages,
+//! with the last error, and left alone until `--retry-failing` asks for
+//! another attempt or it is scraped successfully.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::cli::ScrapeOptions;
+use crate::frontier::PendingPage;
+use crate::manifest::{FailedEntry, Manifest};
+
+/// Failed runs in a row after which a page is given up on when `--max-failures` isn't given.
+pub const DEFAULT_MAX_FAILURES: u32 = 5;
+
+/// Time before the first retry of a failed page, doubled for every further failure.
+const FAILURE_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Longest time before a failed page is retried.
+const MAX_FAILURE_BACKOFF: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// What the backlog changed about the pages of a run.
+#[derive(Debug, Default)]
+pub struct Prioritized {
+    /// Pages that failed before and are retried first
+    pub retried: usize,
+    /// Pages that failed before and are left out until their backoff has passed
+    pub deferred: usize,
+    /// Permanently failing pages left out
+    pub skipped: usize,
+}
+
+/// Returns the time to wait before retrying a page that has failed `attempts` runs in a row.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(backoff(1), Duration::from_secs(3600));
+/// assert_eq!(backoff(3), Duration::from_secs(4 * 3600));
+/// assert_eq!(backoff(20), MAX_FAILURE_BACKOFF);
+/// ```
+fn backoff(attempts: u32) -> Duration {
+    FAILURE_BACKOFF
+        .checked_mul(1 << attempts.saturating_sub(1).min(16))
+        .map_or(MAX_FAILURE_BACKOFF, |backoff| backoff.min(MAX_FAILURE_BACKOFF))
+}
+
+/// Records a failed scrape of a page, counting it towards giving up on the page.
+///
+/// # Returns
+///
+/// The failure as recorded, and whether the page was given up on and moved
+/// to the permanently failing pages
+pub fn record_failure(manifest: &mut Manifest, url: &str, error: &str, max_failures: u32) -> (FailedEntry, bool) {
+    let failed_at = Utc::now();
+    let previous = manifest.failed.remove(url).or_else(|| manifest.permanently_failing.remove(url));
+    let attempts = previous.map_or(1, |previous| previous.attempts + 1);
+    let given_up = attempts >= max_failures;
+    let failure = FailedEntry {
+        failed_at,
+        error: error.to_string(),
+        attempts,
+        retry_after: (!given_up).then(|| failed_at + backoff(attempts)),
+    };
+    if given_up {
+        warn!("Failed in {} runs in a row; giving up on this page until --retry-failing", attempts);
+        manifest.permanently_failing.insert(url.to_string(), failure.clone());
+    } else {
+        manifest.failed.insert(url.to_string(), failure.clone());
+    }
+    (failure, given_up)
+}
+
+/// Forgets the failures of a page that was scraped successfully.
+pub fn record_success(manifest: &mut Manifest, url: &str) {
+    manifest.failed.remove(url);
+    manifest.permanently_failing.remove(url);
+}
+
+/// Puts the pages that failed in earlier runs at the front of a run's pages.
+///
+/// Failed pages whose backoff has passed are moved to the front, or added
+/// there if discovery didn't find them this time and the URL filters allow
+/// them. Those still backing off are left out of the run, as are the
+/// permanently failing pages unless `--retry-failing` is given. Pages that
+/// failed more often than `--max-failures` allows, for example because it was
+/// lowered, are given up on first.
+///
+/// # Arguments
+///
+/// * `pages` - The pages of the run, in the order they would be scraped
+/// * `manifest` - The manifest of the output directory
+/// * `options` - Options with the URL filters, `--max-failures` and `--retry-failing`
+/// * `now` - The time backoffs are compared with
+pub fn prioritize(
+    pages: &mut Vec<PendingPage>,
+    manifest: &mut Manifest,
+    options: &ScrapeOptions,
+    now: DateTime<Utc>,
+) -> Prioritized {
+    let max_failures = options.max_failures.unwrap_or(DEFAULT_MAX_FAILURES);
+    let exhausted: Vec<String> = manifest
+        .failed
+        .iter()
+        .filter(|(_, failure)| failure.attempts >= max_failures)
+        .map(|(url, _)| url.clone())
+        .collect();
+    for url in exhausted {
+        if let Some(mut failure) = manifest.failed.remove(&url) {
+            failure.retry_after = None;
+            manifest.permanently_failing.insert(url, failure);
+        }
+    }
+
+    let mut summary = Prioritized::default();
+    let mut retried = Vec::new();
+    let mut left_out = HashSet::new();
+    for (url, failure) in &manifest.failed {
+        if failure.retry_after.is_some_and(|retry_after| retry_after > now) {
+            summary.deferred += 1;
+            left_out.insert(url.as_str());
+        } else {
+            retried.push(url.as_str());
+        }
+    }
+    for url in manifest.permanently_failing.keys() {
+        if options.retry_failing {
+            retried.push(url.as_str());
+        } else {
+            summary.skipped += 1;
+            left_out.insert(url.as_str());
+        }
+    }
+
+    let mut queued: Vec<PendingPage> = Vec::new();
+    for url in retried {
+        let page = match pages.iter().position(|page| page.url == url) {
+            Some(position) => pages.remove(position),
+            None if options.filter.allows(url) => PendingPage {
+                url: url.to_string(),
+                file: None,
+            },
+            None => continue,
+        };
+        queued.push(page);
+    }
+    pages.retain(|page| !left_out.contains(page.url.as_str()));
+    summary.retried = queued.len();
+    pages.splice(0..0, queued);
+
+    if summary.retried > 0 {
+        info!("Retrying {} pages that failed in earlier runs first", summary.retried);
+    }
+    if summary.deferred > 0 {
+        info!("Leaving out {} pages that failed recently until their backoff has passed", summary.deferred);
+    }
+    if summary.skipped > 0 {
+        info!(
+            "Leaving out {} permanently failing pages listed in the manifest; --retry-failing tries them again",
+            summary.skipped
+        );
+    }
+    summary
+}
@@ -0,0 +1,214 @@
+//! Accounting of the bytes downloaded during a run.
+//!
+//! On metered connections and CI runners the amount of data a run downloads
+//! matters as much as its API credits. Every response body a run receives is
+//! counted by one `Bandwidth` meter: API payloads (scrapes, crawl job results,
+//! discovery, pages fetched by the direct backend) and assets downloaded
+//! separately, such as screenshots returned as a URL and the images and PDFs
+//! of `--download-assets`. `--max-bytes` caps the total; once it is reached
+//! no more assets are downloaded, and with `--stop-at-max-bytes` the
+//! remaining pages are parked for a later run.
+//!
+//! A single response is also limited, by `--max-response-size`: a page whose
+//! raw HTML or screenshot runs to tens of megabytes is not read past the limit,
+//! so it can't exhaust memory or the budget on its own.
+
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::Response;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Largest response read when `--max-response-size` is not given.
+pub const DEFAULT_MAX_RESPONSE_SIZE: u64 = 50 << 20;
+
+/// Counts the bytes downloaded during a run against an optional cap.
+#[derive(Debug)]
+pub struct Bandwidth {
+    /// Bytes the run may download before the cap is reached; `None` when unlimited
+    cap: Option<u64>,
+
+    /// Largest single response read, in bytes
+    max_response: u64,
+
+    /// Bytes of API responses and directly fetched pages
+    payload: AtomicU64,
+
+    /// Bytes of assets downloaded separately
+    assets: AtomicU64,
+}
+
+impl Bandwidth {
+    /// Creates a meter capped at `cap` bytes, or unlimited if `None`, reading
+    /// responses of at most `max_response` bytes.
+    pub fn new(cap: Option<u64>, max_response: u64) -> Self {
+        Bandwidth {
+            cap,
+            max_response,
+            payload: AtomicU64::new(0),
+            assets: AtomicU64::new(0),
+        }
+    }
+
+    /// Records the body of an API response or directly fetched page.
+    pub fn record_payload(&self, bytes: u64) {
+        self.payload.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records an asset downloaded separately.
+    pub fn record_asset(&self, bytes: u64) {
+        self.assets.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Returns the bytes downloaded so far.
+    pub fn total(&self) -> u64 {
+        self.payload.load(Ordering::Relaxed) + self.assets.load(Ordering::Relaxed)
+    }
+
+    /// Returns the cap, if any.
+    pub fn cap(&self) -> Option<u64> {
+        self.cap
+    }
+
+    /// Returns whether the downloads have reached the cap.
+    pub fn cap_reached(&self) -> bool {
+        self.cap.is_some_and(|cap| self.total() >= cap)
+    }
+
+    /// Returns the largest single response read, in bytes.
+    pub fn max_response(&self) -> u64 {
+        self.max_response
+    }
+
+    /// Summarizes the downloads so far for the run report.
+    pub fn summary(&self) -> BandwidthSummary {
+        BandwidthSummary {
+            payload_bytes: self.payload.load(Ordering::Relaxed),
+            asset_bytes: self.assets.load(Ordering::Relaxed),
+            total_bytes: self.total(),
+            max_bytes: self.cap,
+            cap_reached: self.cap_reached(),
+        }
+    }
+}
+
+/// Bytes downloaded during a run, as written to the run report.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthSummary {
+    /// Bytes of API responses and directly fetched pages
+    pub payload_bytes: u64,
+
+    /// Bytes of assets downloaded separately
+    pub asset_bytes: u64,
+
+    /// All bytes downloaded
+    pub total_bytes: u64,
+
+    /// The `--max-bytes` cap, if one was set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+
+    /// Whether the cap was reached
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cap_reached: bool,
+}
+
+/// Parses a `--max-bytes` size: a number of bytes, optionally followed by
+/// `K`, `M` or `G` (powers of 1024, with or without a trailing `B`).
+///
+/// # Errors
+///
+/// Returns an error if the value is not such a size, or is too large
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(parse_size("1500"), Ok(1500));
+/// assert_eq!(parse_size("500M"), Ok(500 * 1024 * 1024));
+/// assert_eq!(parse_size("2 GB"), Ok(2 * 1024 * 1024 * 1024));
+/// ```
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim();
+    let digits = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("{} is not a size such as 1500, 200K, 500M or 2G", value))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let multiplier: u64 = match unit.strip_suffix('B').unwrap_or(&unit) {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(format!("{} is not a size such as 1500, 200K, 500M or 2G", value)),
+    };
+    number.checked_mul(multiplier).ok_or_else(|| format!("{} is too large", value))
+}
+
+/// Reads the body of a response of at most `limit` bytes.
+///
+/// A response announcing a larger `Content-Length` is refused before its body
+/// is read; otherwise the body is read in chunks and reading stops as soon as
+/// it grows past the limit.
+///
+/// # Errors
+///
+/// Returns `ResponseTooLarge` if the response is larger than the limit, or an
+/// error if reading the body fails
+pub async fn read_capped(mut response: Response, limit: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    let length = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if length.is_some_and(|length| length > limit) {
+        return Err(Box::new(ResponseTooLarge { limit }));
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > limit {
+            return Err(Box::new(ResponseTooLarge { limit }));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Error returned when a response is larger than `--max-response-size`.
+#[derive(Debug)]
+pub struct ResponseTooLarge {
+    /// The limit the response exceeded, in bytes
+    pub limit: u64,
+}
+
+impl fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Response larger than {}", format_bytes(self.limit))
+    }
+}
+
+impl Error for ResponseTooLarge {}
+
+/// Formats a number of bytes for log messages, e.g. `12.5 MiB`.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(format_bytes(512), "512 B");
+/// assert_eq!(format_bytes(1536), "1.5 KiB");
+/// ```
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -0,0 +1,175 @@
+//! Checkpoints that let an interrupted run be resumed.
+//!
+//! The manifest is only written when a run ends, so a run that is killed or
+//! crashes part way would lose track of everything it scraped, and the next
+//! run would scrape (and pay for) all of it again. While pages are scraped, the
+//! run's state is saved to `.scraper/checkpoint.json` every few seconds: the
+//! command line it was started with, the pages still to be scraped (including
+//! those in flight), and the pages saved, found empty, failed or skipped for
+//! their error status so far.
+//!
+//! `scraper resume <DIR>` reads the checkpoint back, records the finished pages
+//! in the manifest and scrapes only the pages that were still to be done, with
+//! the options of the original command. A run that ends normally, even with
+//! an error, removes its checkpoint; its manifest and frontier already record
+//! where it stopped. A run stopped with Ctrl-C or SIGTERM keeps it, saved once
+//! the pages in flight are done.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::frontier::PendingPage;
+use crate::manifest::{EmptyContentEntry, FailedEntry, Manifest, ManifestEntry};
+
+/// File name of the checkpoint inside the state directory.
+pub const CHECKPOINT_FILE_NAME: &str = "checkpoint.json";
+
+/// How often the checkpoint of a running scrape is saved.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Command line arguments of the running process, without the binary name.
+static COMMAND: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Records the command line a checkpoint resumes with.
+///
+/// Called once at startup; `resume` records the command of the checkpoint it
+/// resumes, so a resumed run can itself be resumed.
+pub fn record_command(args: Vec<String>) {
+    let _ = COMMAND.set(args);
+}
+
+/// State of a scrape in progress.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// Command line arguments the run was started with, without the binary name;
+    /// empty for runs started by an embedding application
+    pub command: Vec<String>,
+
+    /// The URL or directory the run started from
+    pub start_url: String,
+
+    /// When the checkpoint was saved
+    pub saved_at: Option<DateTime<Utc>>,
+
+    /// Pages still to be scraped, in order, including those in flight
+    pub pending: Vec<PendingPage>,
+
+    /// Pages saved or replaced by a tombstone so far
+    #[serde(default)]
+    pub completed: BTreeMap<String, ManifestEntry>,
+
+    /// Pages returned without content so far
+    #[serde(default)]
+    pub empty_content: BTreeMap<String, EmptyContentEntry>,
+
+    /// Pages that failed so far
+    #[serde(default)]
+    pub failed: BTreeMap<String, FailedEntry>,
+
+    /// Pages left out so far because they answered with an error status, with
+    /// `--error-pages skip`, and the status they answered with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub skipped: BTreeMap<String, u16>,
+}
+
+impl Checkpoint {
+    /// Creates an empty checkpoint for a run of the current command starting from `start_url`.
+    pub fn new(start_url: &str) -> Self {
+        Checkpoint {
+            command: COMMAND.get().cloned().unwrap_or_default(),
+            start_url: start_url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the checkpoint path for the given output directory.
+    pub fn path(output_dir: &Path) -> PathBuf {
+        crate::state_dir(output_dir).join(CHECKPOINT_FILE_NAME)
+    }
+
+    /// Loads the checkpoint of an output directory, if an interrupted run left one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint exists but cannot be read or parsed
+    pub fn load(output_dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read checkpoint {}: {}", path.display(), e))?;
+        let checkpoint = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse checkpoint {}: {}", path.display(), e))?;
+        Ok(Some(checkpoint))
+    }
+
+    /// Saves the checkpoint with the given pages still to be scraped.
+    ///
+    /// The file is written next to the checkpoint and renamed over it, so an
+    /// interruption while saving leaves the previous checkpoint intact.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state directory cannot be created or the file cannot be written
+    pub fn save(&mut self, output_dir: &Path, pending: Vec<PendingPage>) -> Result<(), Box<dyn Error>> {
+        self.saved_at = Some(Utc::now());
+        self.pending = pending;
+
+        let path = Self::path(output_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string(self)?)
+            .and_then(|()| fs::rename(&partial, &path))
+            .map_err(|e| format!("Failed to write checkpoint {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// Records the finished pages of the checkpoint in a manifest.
+    pub fn restore(&self, manifest: &mut Manifest) {
+        for (url, entry) in &self.completed {
+            manifest.empty_content.remove(url);
+            manifest.failed.remove(url);
+            manifest.permanently_failing.remove(url);
+            manifest.pages.insert(url.clone(), entry.clone());
+        }
+        for (url, entry) in &self.empty_content {
+            manifest.failed.remove(url);
+            manifest.permanently_failing.remove(url);
+            manifest.empty_content.insert(url.clone(), entry.clone());
+        }
+        for url in self.skipped.keys() {
+            manifest.failed.remove(url);
+            manifest.permanently_failing.remove(url);
+        }
+        // Pages given up on are moved back to the permanently failing ones by `backlog::prioritize`
+        for (url, entry) in &self.failed {
+            manifest.permanently_failing.remove(url);
+            manifest.failed.insert(url.clone(), entry.clone());
+        }
+    }
+
+    /// Removes the checkpoint of an output directory, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be removed
+    pub fn clear(output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove checkpoint {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
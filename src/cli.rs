@@ -0,0 +1,689 @@
+//! Command line interface definition.
+
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use url::Url;
+
+use crate::auth::{parse_basic_auth, BasicAuth};
+use crate::bandwidth;
+use crate::discovery::{DiscoveryKind, PageOrder};
+use crate::encoding::LineEndings;
+use crate::anchors::SlugStyle;
+use crate::export::{parse_slug_style_override, ExportFormat};
+use crate::formats::PageFormat;
+use crate::frontmatter::{parse_timezone, DateFormat, DateStyle, DateZone};
+use crate::lock::IfLocked;
+use crate::middleware::Pipeline;
+use crate::output::{DirNaming, OutputFormat};
+use crate::platform::Platform;
+use crate::preset::Preset;
+use crate::quality;
+use crate::redact::ContentRule;
+use crate::scaffold::Template;
+use crate::throttle;
+use crate::schema::FrontmatterSchema;
+use crate::template::FrontmatterTemplate;
+use crate::urls::{self, RewriteRule, UrlFilter, UrlNormalization};
+use crate::workspace::DEFAULT_WORKSPACE_FILE;
+
+/// Help text listing the environment variables read at startup.
+const ENV_HELP: &str = "Environment variables:
+  FIRECRAWL_API_KEY  Required. Your API authentication key
+  FIRECRAWL_API_URL  Optional. Defaults to https://api.firecrawl.dev
+  ALT_TEXT_API_KEY   Optional. API key of the --alt-text-endpoint
+  TRANSLATION_API_KEY  Optional. API key of the --translation-endpoint";
+
+/// Scrapes documentation from a website and saves it as markdown files.
+#[derive(Debug, Parser)]
+#[command(
+    name = "scraper",
+    version,
+    about,
+    after_help = ENV_HELP,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// The URL to start scraping from
+    #[arg(required_unless_present = "from_file")]
+    pub url: Option<String>,
+
+    /// Scrape exactly the URLs listed in this file, one per line, instead of discovering pages;
+    /// `-` reads the list from standard input
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["url", "discovery", "url_file", "seed_query", "crawl_job"])]
+    pub from_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub options: ScrapeOptions,
+
+    /// Log more details: `-v` adds every API call and fetch, `-vv` everything
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Only log warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Write a zip file with the effective options, the resolved URLs, what was sent and received
+    /// for every page and the debug log, with secrets redacted, for attaching to a bug report
+    #[arg(long, value_name = "PATH", global = true)]
+    pub debug_bundle: Option<PathBuf>,
+}
+
+/// Subcommands other than the default scrape.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Re-scrape the pages of an existing mirror in place, using the `url` in each file's frontmatter
+    Refresh {
+        /// The mirror directory to refresh
+        dir: PathBuf,
+
+        #[command(flatten)]
+        options: Box<ScrapeOptions>,
+    },
+
+    /// Continue an interrupted scrape, `refresh` or `run` from the checkpoint it left behind,
+    /// scraping only the pages it hadn't finished
+    Resume {
+        /// The output directory of the interrupted run
+        dir: PathBuf,
+    },
+
+    /// List the URLs of a site from FireCrawl's map endpoint, without scraping them
+    Map {
+        /// The URL of the site to map
+        url: String,
+
+        /// Save the list to this file instead of printing it, e.g. for `--from-file`
+        #[arg(long, value_name = "PATH")]
+        save: Option<PathBuf>,
+
+        #[command(flatten)]
+        options: Box<ScrapeOptions>,
+    },
+
+    /// Scrape a sample of a site's pages with and without main-content extraction and
+    /// report the differences, to choose extraction settings before a full crawl
+    Compare {
+        /// The URL of the site to sample
+        url: String,
+
+        /// Number of pages to compare, spread over the site's map
+        #[arg(long, value_name = "N", default_value_t = crate::compare::DEFAULT_SAMPLE_SIZE)]
+        sample: usize,
+
+        /// Directory to write both versions of each page, their diffs and the report to
+        #[arg(long, value_name = "DIR", default_value = "extraction-comparison")]
+        out: PathBuf,
+
+        #[command(flatten)]
+        options: Box<ScrapeOptions>,
+    },
+
+    /// Scrape a site defined under `[sites.<name>]` in the configuration file
+    Run {
+        /// Name of the site
+        name: String,
+
+        /// Further scrape options, overriding the site's own
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "OPTIONS")]
+        args: Vec<String>,
+    },
+
+    /// Scrape fixture pages through a mock FireCrawl API to try out options and configuration
+    #[cfg(feature = "test-harness")]
+    Harness {
+        /// TOML file describing the fixture site
+        fixtures: PathBuf,
+
+        /// Directory to write the output to
+        #[arg(long, value_name = "DIR", default_value = "harness-output")]
+        out: PathBuf,
+
+        /// Inject latency, 429s, 503s and malformed responses into page scrapes, optionally
+        /// configured as e.g. "latency=500,429=0.2,503=0.05,malformed=0.1,seed=7"
+        #[arg(long, value_name = "SPEC", num_args = 0..=1, default_missing_value = "", value_parser = crate::harness::parse_chaos)]
+        chaos: Option<crate::harness::Chaos>,
+
+        #[command(flatten)]
+        options: Box<ScrapeOptions>,
+    },
+
+    /// Build a manifest for a directory of previously scraped files from their frontmatter
+    Import {
+        /// The directory of previously scraped files
+        dir: PathBuf,
+    },
+
+    /// Show which saved pages of a mirror the `[[replace]]` rules of the configuration file
+    /// would change, and how, without changing anything
+    PreviewReplacements {
+        /// The mirror directory
+        dir: PathBuf,
+
+        /// Configuration file to read instead of ./scraper.toml
+        #[arg(long, value_name = "PATH")]
+        config: Option<PathBuf>,
+    },
+
+    /// Scrape every site defined in a workspace file that is due according to its schedule
+    RunAll {
+        /// The workspace file
+        #[arg(default_value = DEFAULT_WORKSPACE_FILE)]
+        file: PathBuf,
+
+        /// Only run the named site; may be repeated
+        #[arg(long = "site", value_name = "NAME")]
+        sites: Vec<String>,
+
+        /// Run sites even if they are not due according to their schedule
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Keep running and scrape the sites of a workspace whenever they are due, until stopped
+    Daemon {
+        /// The workspace file
+        #[arg(default_value = DEFAULT_WORKSPACE_FILE)]
+        file: PathBuf,
+
+        /// Only run the named site; may be repeated
+        #[arg(long = "site", value_name = "NAME")]
+        sites: Vec<String>,
+
+        /// Serve status.json and the site badges over HTTP on this address, e.g. 127.0.0.1:8080
+        #[arg(long, value_name = "ADDR")]
+        status_addr: Option<SocketAddr>,
+    },
+
+    /// Run sites of a workspace again that were paused for tripping a `pause_when` condition
+    Unpause {
+        /// The sites to unpause
+        #[arg(required = true, value_name = "SITE")]
+        sites: Vec<String>,
+
+        /// The workspace file
+        #[arg(long, default_value = DEFAULT_WORKSPACE_FILE)]
+        file: PathBuf,
+    },
+
+    /// Create a repository for mirrored documentation, set up for `run-all`; without a
+    /// template, interactively set up `scraper.toml` and `.env` in the current directory
+    Init {
+        /// The kind of repository to create
+        #[arg(value_enum)]
+        template: Option<Template>,
+
+        /// The directory to create it in
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+
+        /// The documentation site to mirror
+        #[arg(long, requires = "template")]
+        url: Option<String>,
+
+        /// Name of the site; derived from the URL's host by default
+        #[arg(long, requires = "template")]
+        name: Option<String>,
+    },
+
+    /// Print a shell completion script, including the names of the sites configured here
+    Completions {
+        /// The shell to complete in
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
+
+/// Parser for scrape options given outside the command line, such as in a workspace file.
+#[derive(Debug, Parser)]
+#[command(name = "scraper", no_binary_name = true, args_override_self = true)]
+struct OptionsParser {
+    #[command(flatten)]
+    options: ScrapeOptions,
+}
+
+/// Options controlling how pages are scraped and saved.
+#[derive(Debug, Args, Default)]
+pub struct ScrapeOptions {
+    /// Append a provenance footer (source URL, scrape date, license note) to every saved page
+    #[arg(long)]
+    pub citation_footer: bool,
+
+    /// License note included in the citation footer
+    #[arg(long, value_name = "TEXT", requires = "citation_footer")]
+    pub license_note: Option<String>,
+
+    /// Only discover pages and list what would be scraped, without scraping pages or writing files
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Check robots.txt, license metadata and terms pages before crawling
+    #[arg(long)]
+    pub compliance_check: bool,
+
+    /// Proceed even if the compliance check finds that the site disallows scraping
+    #[arg(long, requires = "compliance_check")]
+    pub acknowledge: bool,
+
+    /// How pages are fetched
+    #[arg(long, value_enum, default_value_t = Backend::Firecrawl)]
+    pub backend: Backend,
+
+    /// Directory to create the per-site output directory in, instead of the current directory
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Save pages directly into --output-dir, without a per-site directory
+    #[arg(long, requires = "output_dir", conflicts_with_all = ["dir_naming", "dir_template"])]
+    pub no_domain_dir: bool,
+
+    /// How the per-site output directory is named
+    #[arg(long, value_enum, default_value_t = DirNaming::Flat)]
+    pub dir_naming: DirNaming,
+
+    /// Directory name template for `--dir-naming custom-template`; supports {host}, {domain},
+    /// {subdomain}, {port} and {reversed}, and may contain `/` for nested directories
+    #[arg(long, value_name = "TEMPLATE")]
+    pub dir_template: Option<String>,
+
+    /// How pages are written: markdown files, or instead of page files, jsonl for one
+    /// `pages.jsonl` line per page or sqlite for a `pages.sqlite` database [default: markdown]
+    #[arg(long, alias = "output", value_enum, value_name = "FORMAT")]
+    pub output_format: Option<OutputFormat>,
+
+    /// Path of each page file inside the output directory, e.g. "{path_dir}/{slug}.md"; supports
+    /// {host}, {path}, {path_dir}, {slug}, {title}, {yyyy}, {mm}, {dd}, {lang} and {type}
+    #[arg(long, value_name = "TEMPLATE")]
+    pub path_template: Option<String>,
+
+    /// How dates are written in the frontmatter [default: rfc3339]
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub date_format: Option<DateFormat>,
+
+    /// Time zone of frontmatter dates: utc, local or an offset such as +02:00 [default: utc]
+    #[arg(long, value_name = "ZONE", value_parser = parse_timezone, allow_hyphen_values = true)]
+    pub timezone: Option<DateZone>,
+
+    /// Line endings of saved pages [default: lf]
+    #[arg(long, value_enum, value_name = "STYLE")]
+    pub line_endings: Option<LineEndings>,
+
+    /// Configuration file to read instead of ./scraper.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Don't adjust extraction options to the page type guessed from each URL
+    #[arg(long)]
+    pub no_page_type_defaults: bool,
+
+    /// Extract only the main content of landing pages, dropping marketing sections around it
+    #[arg(long, conflicts_with = "no_page_type_defaults")]
+    pub landing_main_content: bool,
+
+    /// Extraction preset of the site's documentation generator: auto, docusaurus, mkdocs-material,
+    /// sphinx, gitbook, mintlify or none [default: auto, detected from each host's first page]
+    #[arg(long, value_enum, value_name = "PRESET")]
+    pub preset: Option<Preset>,
+
+    /// Save pages with a quality score below this (0 to 1) in a `_review/` directory
+    #[arg(long, value_name = "SCORE", value_parser = quality::parse_min_quality)]
+    pub min_quality: Option<f64>,
+
+    /// Exit with an error status when pages were saved with warnings, not only when pages failed
+    #[arg(long)]
+    pub fail_on_warning: bool,
+
+    /// Handlebars template rendering the frontmatter of every page, instead of the built-in one
+    #[arg(long, value_name = "PATH", value_parser = FrontmatterTemplate::parse)]
+    pub frontmatter_template: Option<FrontmatterTemplate>,
+
+    /// JSON Schema file the frontmatter of every page must match
+    #[arg(long, value_name = "PATH", value_parser = FrontmatterSchema::parse)]
+    pub frontmatter_schema: Option<FrontmatterSchema>,
+
+    /// What happens to a page whose frontmatter doesn't match --frontmatter-schema
+    #[arg(long, value_enum, value_name = "ACTION", default_value_t)]
+    pub schema_violations: SchemaViolations,
+
+    /// What happens to a page answered with a 4xx or 5xx status: retry it in later runs, save it flagged
+    /// with `error:` in its frontmatter, or skip it [default: flag]
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub error_pages: Option<ErrorPages>,
+
+    /// OpenAI-compatible chat completions endpoint asked to write alt text for images without any
+    #[arg(long, value_name = "URL")]
+    pub alt_text_endpoint: Option<String>,
+
+    /// Vision model asked for alt text at `--alt-text-endpoint`
+    #[arg(long, value_name = "MODEL", default_value = "gpt-4o-mini")]
+    pub alt_text_model: String,
+
+    /// Translate pages into this language (e.g. `en`, `de`) before saving them
+    #[arg(long, value_name = "LANG")]
+    pub translate_to: Option<String>,
+
+    /// OpenAI-compatible chat completions endpoint asked to translate pages for `--translate-to`
+    #[arg(long, value_name = "URL")]
+    pub translation_endpoint: Option<String>,
+
+    /// Model asked for translations at `--translation-endpoint`
+    #[arg(long, value_name = "MODEL", default_value = "gpt-4o-mini")]
+    pub translation_model: String,
+
+    /// Credentials for sites behind HTTP basic auth, sent with every page request
+    #[arg(long, value_name = "USER:PASS", value_parser = parse_basic_auth)]
+    pub basic_auth: Option<BasicAuth>,
+
+    /// Directory to keep what runs learn about each site in [default: ~/.cache/scraper/domains]
+    #[arg(long, value_name = "DIR")]
+    pub domain_profiles: Option<PathBuf>,
+
+    /// Don't read or save per-site profiles; robots.txt is fetched again and nothing is remembered
+    #[arg(long, conflicts_with = "domain_profiles")]
+    pub no_domain_profiles: bool,
+
+    /// How long to keep probing an unreachable API before giving up, in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 1800)]
+    pub max_outage: u64,
+
+    /// What to do when another run is using the output directory
+    #[arg(long, value_enum, value_name = "ACTION", default_value_t)]
+    pub if_locked: IfLocked,
+
+    /// Retry a page scrape this many times after a timeout, 429 or 5xx answer [default: 3]
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Wait this many milliseconds before the first retry, doubling for every further one [default: 500]
+    #[arg(long, value_name = "MS")]
+    pub retry_delay_ms: Option<u64>,
+
+    /// Replace previously scraped pages that now answer 404 or 410 with a tombstone,
+    /// keeping their last version under _removed/
+    #[arg(long)]
+    pub tombstones: bool,
+
+    #[command(flatten)]
+    pub filter: UrlFilter,
+
+    #[command(flatten)]
+    pub normalization: UrlNormalization,
+
+    /// Scrape up to this many pages at the same time [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub concurrency: Option<usize>,
+
+    /// Scrape at most this many pages, parking the rest for the next run
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_pages: Option<usize>,
+
+    /// Scrape only a sample of this many of the pages found, spread across the site's sections,
+    /// to check the extraction and settings before the full run
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with = "crawl_job")]
+    pub sample: Option<usize>,
+
+    /// Give up on a page after it failed in this many runs in a row, listing it as permanently
+    /// failing in the manifest [default: 5]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_failures: Option<u32>,
+
+    /// Also retry the pages given up on after failing in too many runs in a row
+    #[arg(long)]
+    pub retry_failing: bool,
+
+    /// Spend at most this many API credits per minute, spacing calls evenly
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub credits_per_minute: Option<u32>,
+
+    /// Make at most this many FireCrawl requests per second, e.g. 0.5; applies to every request
+    #[arg(long, value_name = "N", value_parser = throttle::parse_rate)]
+    pub rps: Option<f64>,
+
+    /// Wait at least this many milliseconds between the starts of two FireCrawl requests
+    #[arg(long, value_name = "MS")]
+    pub delay_ms: Option<u64>,
+
+    /// Ask FireCrawl whether each page changed since it last scraped it, recording the answer
+    /// as `changeStatus` in the frontmatter, manifest and run report
+    #[arg(long)]
+    pub change_tracking: bool,
+
+    /// Share the --credits-per-minute budget and the --rps and --delay-ms limits with other scraper processes using the same API key
+    #[arg(long)]
+    pub shared_budget: bool,
+
+    /// Stop downloading assets such as screenshots once the run has downloaded this much,
+    /// e.g. 500M; takes K, M and G suffixes
+    #[arg(long, value_name = "SIZE", value_parser = bandwidth::parse_size)]
+    pub max_bytes: Option<u64>,
+
+    /// Also stop scraping pages once --max-bytes is reached, parking the rest for the next run
+    #[arg(long, requires = "max_bytes")]
+    pub stop_at_max_bytes: bool,
+
+    /// Largest response read for a single page, e.g. 20M; takes K, M and G suffixes [default: 50M]
+    #[arg(long, value_name = "SIZE", value_parser = bandwidth::parse_size)]
+    pub max_response_size: Option<u64>,
+
+    /// Request a page whose FireCrawl response is larger than --max-response-size again with
+    /// markdown only, dropping its other formats, instead of failing it
+    #[arg(long)]
+    pub drop_oversized_formats: bool,
+
+    /// Download the images and PDFs pages refer to into `assets/` and link to the local copies
+    #[arg(long)]
+    pub download_assets: bool,
+
+    /// Assets downloaded at the same time [default: 4]
+    #[arg(long, value_name = "N", requires = "download_assets")]
+    pub asset_concurrency: Option<usize>,
+
+    /// Milliseconds between two asset downloads from the same host [default: 100]
+    #[arg(long, value_name = "MS", requires = "download_assets")]
+    pub asset_delay_ms: Option<u64>,
+
+    /// Largest asset downloaded, e.g. 5M; takes K, M and G suffixes [default: 20M]
+    #[arg(long, value_name = "SIZE", value_parser = bandwidth::parse_size, requires = "download_assets")]
+    pub max_asset_size: Option<u64>,
+
+    /// Where to find the pages to scrape; may be repeated to combine sources [default: links]
+    #[arg(long = "discover", value_enum, value_name = "SOURCE")]
+    pub discovery: Vec<DiscoveryKind>,
+
+    /// Order in which discovered pages are scraped [default: discovery]
+    #[arg(long, value_enum, value_name = "ORDER")]
+    pub order: Option<PageOrder>,
+
+    /// Also scrape the URLs listed in this file, one per line
+    #[arg(long, value_name = "PATH")]
+    pub url_file: Option<PathBuf>,
+
+    /// Scrape the site's pages most relevant to this search query first, found with the map endpoint
+    #[arg(long, value_name = "QUERY")]
+    pub seed_query: Option<String>,
+
+    /// Documentation platform whose hosts are grouped into one site: auto, gitbook, readme,
+    /// netlify or none [default: auto]
+    #[arg(long, value_enum, value_name = "PLATFORM")]
+    pub platform: Option<Platform>,
+
+    /// Another host serving the same pages as the start URL's host, such as a custom domain;
+    /// its links are followed and saved with the site's pages; may be repeated
+    #[arg(long = "site-host", value_name = "HOST")]
+    pub site_hosts: Vec<String>,
+
+    /// Also follow links to every subdomain of the start URL's domain, such as `api.example.com`
+    /// when starting from `docs.example.com`; their pages are saved under their own host
+    #[arg(long)]
+    pub include_subdomains: bool,
+
+    /// A subdomain of the start URL's domain whose links are followed, given as a label (`api`)
+    /// or a full host; may be repeated
+    #[arg(long = "subdomain", value_name = "NAME")]
+    pub subdomains: Vec<String>,
+
+    /// Another domain whose links are followed, such as `example.github.io` for documentation
+    /// split across domains; its pages are saved in a subdirectory named after it; may be repeated
+    #[arg(long = "allow-domain", value_name = "DOMAIN", conflicts_with = "crawl_job")]
+    pub allowed_domains: Vec<String>,
+
+    /// Crawl the site with a FireCrawl crawl job instead of discovering and scraping pages one by one
+    #[arg(long, conflicts_with_all = ["discovery", "url_file", "seed_query", "dry_run"])]
+    pub crawl_job: bool,
+
+    /// Also export scraped pages in this format, written as each page is scraped; may be repeated
+    #[arg(long = "export", value_enum, value_name = "FORMAT")]
+    pub exports: Vec<ExportFormat>,
+
+    /// Also combine all pages into one markdown document with a table of contents, like
+    /// `--export single-file`
+    #[arg(long)]
+    pub single_file: bool,
+
+    /// Also render all pages into one paginated PDF with a cover page and table of contents, like
+    /// `--export pdf`
+    #[arg(long)]
+    pub pdf: bool,
+
+    /// Also save these formats of every page next to its markdown file: html, raw-html, links or
+    /// screenshot; comma-separated or repeated
+    #[arg(long, value_enum, value_name = "FORMAT", value_delimiter = ',')]
+    pub formats: Vec<PageFormat>,
+
+    /// Heading anchor style for an export format, as FORMAT=STYLE (github, mdbook or docusaurus);
+    /// may be repeated
+    #[arg(long = "slug-style", value_name = "FORMAT=STYLE", value_parser = parse_slug_style_override)]
+    pub slug_styles: Vec<(ExportFormat, SlugStyle)>,
+
+    /// Per-host basic auth credentials from the configuration file, by ASCII host name
+    #[arg(skip)]
+    pub host_credentials: Vec<(String, BasicAuth)>,
+
+    /// URL rewrite rules from the configuration file
+    #[arg(skip)]
+    pub rewrite_rules: Vec<RewriteRule>,
+
+    /// Content redaction rules from the configuration file
+    #[arg(skip)]
+    pub content_rules: Vec<ContentRule>,
+
+    /// Search-and-replace rules from the configuration file
+    #[arg(skip)]
+    pub replace_rules: Vec<ContentRule>,
+
+    /// Hooks registered by an application embedding the scraper
+    #[arg(skip)]
+    pub middleware: Pipeline,
+}
+
+impl ScrapeOptions {
+    /// Parses scrape options from command line arguments without the program name,
+    /// e.g. `["--export", "jsonl"]`.
+    ///
+    /// An option given more than once takes its last value, so arguments
+    /// appended later override earlier ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns a one-line description if the arguments are not valid scrape options
+    pub fn parse_args<I, T>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        OptionsParser::try_parse_from(args).map(|parser| parser.options).map_err(|e| {
+            // Only the first line; the rest is usage help for the command line
+            let message = e.render().to_string();
+            message.lines().next().unwrap_or_default().trim_start_matches("error: ").to_string()
+        })
+    }
+
+    /// Returns the format and time zone of frontmatter dates.
+    pub fn date_style(&self) -> DateStyle {
+        DateStyle {
+            format: self.date_format.unwrap_or_default(),
+            zone: self.timezone.unwrap_or_default(),
+        }
+    }
+
+    /// Returns the export formats of the run, including the ones selected by `--single-file`,
+    /// `--pdf` and `--output-format`.
+    pub fn export_formats(&self) -> Vec<ExportFormat> {
+        let mut formats = self.exports.clone();
+        let selected = [
+            self.single_file.then_some(ExportFormat::SingleFile),
+            self.pdf.then_some(ExportFormat::Pdf),
+            match self.output_format.unwrap_or_default() {
+                OutputFormat::Markdown => None,
+                OutputFormat::Jsonl => Some(ExportFormat::Jsonl),
+                OutputFormat::Sqlite => Some(ExportFormat::Sqlite),
+            },
+        ];
+        for format in selected.into_iter().flatten() {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        formats
+    }
+
+    /// Returns whether every page is saved to its own markdown file, rather than only to
+    /// `pages.jsonl` or `pages.sqlite`.
+    pub fn writes_page_files(&self) -> bool {
+        self.output_format.unwrap_or_default() == OutputFormat::Markdown
+    }
+
+    /// Returns the basic auth credentials to use for a URL.
+    ///
+    /// Credentials configured for the URL's host take precedence over `--basic-auth`.
+    pub fn credentials_for(&self, url: &str) -> Option<&BasicAuth> {
+        let host = Url::parse(url).ok().and_then(|url| urls::ascii_host(&url));
+        host.and_then(|host| {
+            self.host_credentials
+                .iter()
+                .find(|(configured, _)| *configured == host)
+                .map(|(_, auth)| auth)
+        })
+        .or(self.basic_auth.as_ref())
+    }
+}
+
+/// What happens to a page whose frontmatter doesn't match the schema.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SchemaViolations {
+    /// Fail the page without saving it
+    #[default]
+    Fail,
+    /// Save the page and record the violations as warnings
+    Warn,
+}
+
+/// What happens to a page answered with a 4xx or 5xx status, as chosen with `--error-pages`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorPages {
+    /// Fail the page without saving it, so later runs retry it (see `backlog`)
+    Retry,
+    /// Save the page with its status as `error` in the frontmatter and a warning
+    #[default]
+    Flag,
+    /// Leave the page out without saving it or counting it as failed
+    Skip,
+}
+
+/// Backend used to fetch pages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Scrape pages through the FireCrawl API
+    #[default]
+    Firecrawl,
+    /// Fetch pages directly over HTTP and convert them locally, using HTTP caching headers
+    Direct,
+}
@@ -0,0 +1,288 @@
+//! Comparison of main-content and full-page extraction on a sample of pages.
+//!
+//! FireCrawl can extract only the main content of a page, dropping navigation,
+//! sidebars and footers, or convert the whole page. Which works better depends
+//! on the site: on some, main-content extraction removes exactly the chrome; on
+//! others it cuts away half of the documentation. `scraper compare` scrapes a
+//! few pages of a site both ways before a full crawl is started, saves both
+//! versions with a diff of what main-content extraction drops, and writes a
+//! report scoring each version.
+
+use reqwest::Client;
+use similar::{ChangeTag, TextDiff};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::api::make_api_request;
+use crate::bandwidth;
+use crate::cli::ScrapeOptions;
+use crate::output::sanitize_filename;
+use crate::page_type::{self, PageType};
+use crate::quality::{self, Quality};
+use crate::retry::Retrier;
+use crate::throttle::{self, CreditPacer};
+use crate::{urls, ScrapeRequest};
+
+/// Pages compared when `--sample` isn't given.
+pub const DEFAULT_SAMPLE_SIZE: usize = 5;
+
+/// File name of the comparison report inside the output directory.
+pub const REPORT_FILE_NAME: &str = "comparison.md";
+
+/// Share of the full page's words below which main-content extraction is
+/// suspected of dropping documentation rather than chrome.
+const MIN_KEPT_RATIO: f64 = 0.2;
+
+/// Which extraction a comparison favors for a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Main-content extraction keeps the content and drops the chrome
+    MainContent,
+    /// Main-content extraction loses too much, so the whole page is better
+    FullPage,
+}
+
+/// One page scraped both ways.
+#[derive(Debug)]
+pub struct PageComparison {
+    /// URL of the page
+    pub url: String,
+
+    /// Kind of page, as classified from the full page
+    pub page_type: PageType,
+
+    /// Words extracted with main-content extraction
+    pub main_words: usize,
+
+    /// Words extracted from the whole page
+    pub full_words: usize,
+
+    /// Quality score of the main-content extraction
+    pub main_quality: Quality,
+
+    /// Quality score of the full-page extraction
+    pub full_quality: Quality,
+
+    /// Lines of the full page that main-content extraction drops
+    pub lines_dropped: usize,
+
+    /// Which extraction the page favors
+    pub verdict: Verdict,
+
+    /// Name the page's files share inside the output directory
+    pub file_stem: String,
+}
+
+/// Picks up to `size` URLs spread evenly over a list, so the sample covers
+/// every section of a site rather than its first few pages.
+///
+/// # Examples
+///
+/// ```text
+/// let urls: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+/// assert_eq!(sample(&urls, 3), vec!["0", "3", "6"]);
+/// ```
+pub fn sample(urls: &[String], size: usize) -> Vec<String> {
+    if urls.len() <= size {
+        return urls.to_vec();
+    }
+    (0..size).map(|i| urls[i * urls.len() / size].clone()).collect()
+}
+
+/// Scrapes every page with and without main-content extraction and writes
+/// both versions, their diffs and the comparison report to `output_dir`.
+///
+/// # Returns
+///
+/// A `Result` containing the comparisons of the pages that could be scraped
+/// both ways and the path of the report
+///
+/// # Errors
+///
+/// Returns an error if the output directory or a file cannot be written; pages
+/// that fail to scrape are logged and left out of the report
+#[allow(clippy::too_many_arguments)]
+pub async fn compare_pages(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    urls: &[String],
+    output_dir: &Path,
+    options: &ScrapeOptions,
+    pacer: &CreditPacer,
+    retrier: &Retrier,
+) -> Result<(Vec<PageComparison>, PathBuf), Box<dyn Error>> {
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create directory {}: {}", output_dir.display(), e))?;
+
+    let mut comparisons = Vec::new();
+    for url in urls {
+        info!("Comparing extraction of {}", url);
+        let (main, full) = match scrape_both(client, api_url, api_key, url, options, pacer, retrier).await {
+            Ok(pages) => pages,
+            Err(e) => {
+                warn!("Failed to scrape {}: {}", url, e);
+                continue;
+            }
+        };
+
+        let file_stem = file_stem(url);
+        let diff = TextDiff::from_lines(&main, &full);
+        let lines_dropped = diff.iter_all_changes().filter(|change| change.tag() == ChangeTag::Insert).count();
+        let unified = diff
+            .unified_diff()
+            .context_radius(2)
+            .header(&format!("{}.main.md", file_stem), &format!("{}.full.md", file_stem))
+            .to_string();
+
+        for (extension, content) in [("main.md", &main), ("full.md", &full), ("diff", &unified)] {
+            let path = output_dir.join(format!("{}.{}", file_stem, extension));
+            fs::write(&path, content).map_err(|e| format!("Failed to write file {}: {}", path.display(), e))?;
+        }
+
+        let main_words = main.split_whitespace().count();
+        let full_words = full.split_whitespace().count();
+        let main_quality = quality::score(&main, None, false);
+        let full_quality = quality::score(&full, None, false);
+        let verdict = verdict(main_words, full_words, &main_quality, &full_quality);
+        comparisons.push(PageComparison {
+            url: url.clone(),
+            page_type: page_type::classify(url, &full),
+            main_words,
+            full_words,
+            main_quality,
+            full_quality,
+            lines_dropped,
+            verdict,
+            file_stem,
+        });
+    }
+
+    let path = output_dir.join(REPORT_FILE_NAME);
+    fs::write(&path, render_report(&comparisons))
+        .map_err(|e| format!("Failed to write report {}: {}", path.display(), e))?;
+    Ok((comparisons, path))
+}
+
+/// Returns the name the files of a page share, from its URL without the scheme.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(file_stem("https://docs.example.com/guides/setup/"), "docs_example_com_guides_setup");
+/// ```
+fn file_stem(url: &str) -> String {
+    let display = urls::display_url(url);
+    let without_scheme = display.split_once("://").map_or(display.as_str(), |(_, rest)| rest);
+    sanitize_filename(without_scheme.trim_end_matches('/'))
+}
+
+/// Scrapes a page with main-content extraction and as a whole page.
+///
+/// # Returns
+///
+/// A `Result` containing the markdown of both scrapes, main content first
+///
+/// # Errors
+///
+/// Returns an error if either request fails
+async fn scrape_both(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    url: &str,
+    options: &ScrapeOptions,
+    pacer: &CreditPacer,
+    retrier: &Retrier,
+) -> Result<(String, String), Box<dyn Error>> {
+    let mut pages = Vec::new();
+    for only_main_content in [true, false] {
+        pacer.acquire(throttle::SCRAPE_CREDITS).await;
+        let headers = options
+            .credentials_for(url)
+            .map(|auth| serde_json::json!({ "Authorization": auth.header_value() }));
+        let request = ScrapeRequest {
+            url: url.to_string(),
+            formats: vec!["markdown".to_string()],
+            only_main_content: Some(only_main_content),
+            headers,
+            ..Default::default()
+        };
+        let max_size = options.max_response_size.unwrap_or(bandwidth::DEFAULT_MAX_RESPONSE_SIZE);
+        let (response, _, _) = make_api_request(client, api_url, api_key, request, retrier, max_size).await?;
+        pages.push(response.data.markdown.unwrap_or_default());
+    }
+    let full = pages.pop().unwrap_or_default();
+    let main = pages.pop().unwrap_or_default();
+    Ok((main, full))
+}
+
+/// Decides which extraction a page favors.
+///
+/// Main-content extraction wins unless it keeps less than `MIN_KEPT_RATIO` of
+/// the full page's words or scores lower than the full page.
+fn verdict(main_words: usize, full_words: usize, main_quality: &Quality, full_quality: &Quality) -> Verdict {
+    let kept = kept_ratio(main_words, full_words);
+    if kept < MIN_KEPT_RATIO || main_quality.score < full_quality.score {
+        Verdict::FullPage
+    } else {
+        Verdict::MainContent
+    }
+}
+
+/// Returns the share of the full page's words main-content extraction keeps.
+fn kept_ratio(main_words: usize, full_words: usize) -> f64 {
+    if full_words == 0 {
+        return 1.0;
+    }
+    main_words as f64 / full_words as f64
+}
+
+/// Renders the comparison report as markdown.
+fn render_report(comparisons: &[PageComparison]) -> String {
+    let mut report = String::from("# Extraction Comparison\n\n");
+    if comparisons.is_empty() {
+        report.push_str("No page could be scraped both ways.\n");
+        return report;
+    }
+
+    let favor_main = comparisons.iter().filter(|page| page.verdict == Verdict::MainContent).count();
+    let _ = writeln!(
+        report,
+        "Main-content extraction suits {} of {} sampled pages.\n",
+        favor_main,
+        comparisons.len()
+    );
+    report.push_str("| Page | Type | Words (main / full) | Kept | Quality (main / full) | Lines dropped | Favors | Diff |\n");
+    report.push_str("|------|------|---------------------|------|-----------------------|---------------|--------|------|\n");
+    for page in comparisons {
+        let favors = match page.verdict {
+            Verdict::MainContent => "main content",
+            Verdict::FullPage => "full page",
+        };
+        let _ = writeln!(
+            report,
+            "| {} | {} | {} / {} | {:.0}% | {:.2} / {:.2} | {} | {} | [{}.diff]({}.diff) |",
+            page.url,
+            page.page_type,
+            page.main_words,
+            page.full_words,
+            kept_ratio(page.main_words, page.full_words) * 100.0,
+            page.main_quality.score,
+            page.full_quality.score,
+            page.lines_dropped,
+            favors,
+            page.file_stem,
+            page.file_stem
+        );
+    }
+    report.push_str(
+        "\nEach diff shows the lines of the full page (`+`) that main-content extraction drops. \
+         Pages favoring the full page keep less than 20% of its words or score lower with \
+         main-content extraction.\n",
+    );
+    report
+}
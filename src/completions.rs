@@ -0,0 +1,91 @@
+//! Shell completion scripts.
+//!
+//! `scraper completions <shell>` prints a completion script for the
+//! subcommands and options. The names of the sites configured in the current
+//! directory, under `[sites.<name>]` in `scraper.toml` and in `sites.toml`, are
+//! written into the script as the values `run <NAME>`, `run-all --site` and
+//! `daemon --site` and `unpause` complete to, so the script has to be generated again after adding a site.
+
+use clap::builder::PossibleValuesParser;
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use tracing::warn;
+
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::workspace::{Workspace, DEFAULT_WORKSPACE_FILE};
+
+/// Writes the completion script for `shell`.
+///
+/// A configuration or workspace file that cannot be read is reported, and its
+/// site names are left out of the script.
+///
+/// # Errors
+///
+/// Returns an error if the script cannot be written
+pub fn generate(shell: Shell, out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    let mut command = Cli::command();
+
+    let site_names: Vec<String> = match Config::load(None) {
+        Ok(config) => config.sites.into_keys().collect(),
+        Err(e) => {
+            warn!("Site names left out of the completions: {}", e);
+            Vec::new()
+        }
+    };
+    let workspace_file = Path::new(DEFAULT_WORKSPACE_FILE);
+    let workspace_names = if workspace_file.exists() {
+        match Workspace::load(workspace_file) {
+            Ok(workspace) => workspace.sites.into_iter().map(|site| site.name).collect(),
+            Err(e) => {
+                warn!("Workspace site names left out of the completions: {}", e);
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    command = complete_values(command, "run", "name", &site_names);
+    command = complete_values(command, "run-all", "sites", &workspace_names);
+    command = complete_values(command, "daemon", "sites", &workspace_names);
+    command = complete_values(command, "unpause", "sites", &workspace_names);
+
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, &name, &mut script);
+
+    // The fish script only completes the values of options, not of positional arguments
+    if shell == Shell::Fish && !site_names.is_empty() {
+        writeln!(
+            script,
+            "complete -c {} -n \"__fish_{}_using_subcommand run; and __fish_is_nth_token 2\" -f -a \"{}\"",
+            name,
+            name,
+            site_names.join(" ")
+        )?;
+    }
+    out.write_all(&script)?;
+    Ok(())
+}
+
+/// Makes an argument of a subcommand complete to the given names.
+fn complete_values(command: Command, subcommand: &str, arg: &str, names: &[String]) -> Command {
+    if names.is_empty() {
+        return command;
+    }
+    // `mut_arg` would move the argument behind the others, reordering positionals
+    let parser = PossibleValuesParser::new(names.iter().cloned());
+    command.mut_subcommand(subcommand, |sub| {
+        sub.mut_args(|candidate| {
+            if candidate.get_id() == arg {
+                candidate.value_parser(parser.clone())
+            } else {
+                candidate
+            }
+        })
+    })
+}
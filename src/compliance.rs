@@ -0,0 +1,223 @@
+//! Pre-crawl check for robots.txt rules, license metadata and terms of service.
+//!
+//! The check is heuristic: it looks for explicit signals that a site does not
+//! want to be scraped and records everything it finds so the decision to
+//! proceed can be reviewed in the run report.
+
+use reqwest::Client;
+use serde::Serialize;
+use url::Url;
+
+use crate::html;
+use crate::robots::Robots;
+
+/// User agent tokens checked against robots.txt groups.
+///
+/// FireCrawl fetches pages on our behalf, so rules aimed at it apply as well
+/// as rules aimed at this tool.
+const USER_AGENTS: &[&str] = &["FireCrawlAgent", "scraper"];
+
+/// Common locations of terms of service pages.
+const TERMS_PATHS: &[&str] = &["/terms", "/terms-of-service", "/terms-of-use", "/tos", "/legal"];
+
+/// Words that indicate automated access is being discussed.
+const SCRAPING_TERMS: &[&str] = &["scrap", "crawl", "spider", "automated means", "data mining"];
+
+/// Words that indicate the discussed activity is prohibited.
+const PROHIBITION_TERMS: &[&str] = &["not ", "prohibit", "forbid", "without our prior", "without the prior"];
+
+/// Maximum length of a quoted excerpt in a finding.
+const EXCERPT_LENGTH: usize = 200;
+
+/// A single signal found during the compliance check.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    /// Where the signal was found (robots.txt URL, page URL or terms URL)
+    pub source: String,
+    /// Human readable description of the signal
+    pub message: String,
+    /// Whether the signal indicates the site disallows scraping
+    pub blocking: bool,
+}
+
+/// Outcome of the compliance pre-check.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComplianceReport {
+    /// Everything found during the check
+    pub findings: Vec<Finding>,
+    /// Whether any finding indicates the site disallows scraping
+    pub disallowed: bool,
+    /// Whether the user acknowledged the findings with `--acknowledge`
+    pub acknowledged: bool,
+    /// Final decision: "proceed" or "refused"
+    pub decision: String,
+}
+
+impl ComplianceReport {
+    /// Records the final decision based on the findings and the acknowledgement flag.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the crawl may proceed
+    pub fn decide(&mut self, acknowledged: bool) -> bool {
+        self.acknowledged = acknowledged;
+        let proceed = !self.disallowed || acknowledged;
+        self.decision = if proceed { "proceed" } else { "refused" }.to_string();
+        proceed
+    }
+}
+
+/// Checks robots.txt, license metadata and common terms pages of the site hosting `start_url`.
+///
+/// Network failures while fetching any of the documents are not errors; the
+/// corresponding check is simply skipped.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client
+/// * `start_url` - The URL the crawl will start from
+/// * `robots_txt` - The site's robots.txt, from its domain profile; `None` if it couldn't be fetched
+///
+/// # Returns
+///
+/// A `ComplianceReport` with all findings; the decision is made by `ComplianceReport::decide`
+///
+/// # Errors
+///
+/// Returns an error if the start URL cannot be parsed
+///
+/// # Examples
+///
+/// ```text
+/// let mut report = check_compliance(&client, "https://docs.example.com", profile.robots_txt()).await?;
+/// if !report.decide(false) {
+///     // The site disallows scraping and --acknowledge was not given
+/// }
+/// ```
+pub async fn check_compliance(
+    client: &Client,
+    start_url: &str,
+    robots_txt: Option<&str>,
+) -> Result<ComplianceReport, Box<dyn std::error::Error>> {
+    let url = Url::parse(start_url)?;
+    let mut findings = Vec::new();
+
+    if let Some(text) = robots_txt {
+        check_robots(&url, text, &mut findings);
+    }
+    check_license_meta(client, &url, &mut findings).await;
+    check_terms(client, &url, &mut findings).await;
+
+    let disallowed = findings.iter().any(|f| f.blocking);
+    Ok(ComplianceReport {
+        findings,
+        disallowed,
+        acknowledged: false,
+        decision: String::new(),
+    })
+}
+
+/// Fetches a document, returning its body only for successful responses.
+async fn fetch_text(client: &Client, url: &Url) -> Option<String> {
+    let response = client.get(url.clone()).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.text().await.ok()
+}
+
+/// Checks whether robots.txt disallows the start URL for any of our user agents.
+fn check_robots(url: &Url, text: &str, findings: &mut Vec<Finding>) {
+    let Ok(robots_url) = url.join("/robots.txt") else {
+        return;
+    };
+
+    let robots = Robots::parse(text);
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+
+    for agent in USER_AGENTS {
+        if !robots.is_allowed(agent, &path) {
+            findings.push(Finding {
+                source: robots_url.to_string(),
+                message: format!("robots.txt disallows {} for user agent {}", path, agent),
+                blocking: true,
+            });
+        }
+    }
+}
+
+/// Records `license` meta tags and `rel="license"` links of the start page.
+async fn check_license_meta(client: &Client, url: &Url, findings: &mut Vec<Finding>) {
+    let Some(page) = fetch_text(client, url).await else {
+        return;
+    };
+
+    for tag in html::tags(&page, &["meta", "link"]) {
+        let lower = tag.to_lowercase();
+        let is_license_meta = lower.contains("name=\"license\"") || lower.contains("name='license'");
+        let is_license_link = lower.contains("rel=\"license\"") || lower.contains("rel='license'");
+        let is_noai = (lower.contains("name=\"robots\"") || lower.contains("name='robots'"))
+            && (lower.contains("noai") || lower.contains("noimageai"));
+
+        if is_license_meta || is_license_link {
+            findings.push(Finding {
+                source: url.to_string(),
+                message: format!("License metadata: {}", tag),
+                blocking: false,
+            });
+        } else if is_noai {
+            findings.push(Finding {
+                source: url.to_string(),
+                message: format!("Robots meta tag opts out of AI use: {}", tag),
+                blocking: true,
+            });
+        }
+    }
+}
+
+/// Looks for terms of service pages that prohibit scraping.
+async fn check_terms(client: &Client, url: &Url, findings: &mut Vec<Finding>) {
+    for path in TERMS_PATHS {
+        let Ok(terms_url) = url.join(path) else {
+            continue;
+        };
+        let Some(page) = fetch_text(client, &terms_url).await else {
+            continue;
+        };
+
+        let text = html::strip_tags(&page);
+        let prohibition = text.split(['.', '\n']).find(|sentence| {
+            let lower = sentence.to_lowercase();
+            SCRAPING_TERMS.iter().any(|t| lower.contains(t))
+                && PROHIBITION_TERMS.iter().any(|t| lower.contains(t))
+        });
+
+        match prohibition {
+            Some(sentence) => findings.push(Finding {
+                source: terms_url.to_string(),
+                message: format!("Terms appear to prohibit scraping: \"{}\"", excerpt(sentence)),
+                blocking: true,
+            }),
+            None => findings.push(Finding {
+                source: terms_url.to_string(),
+                message: "Terms page found; no scraping prohibition detected".to_string(),
+                blocking: false,
+            }),
+        }
+    }
+}
+
+/// Collapses whitespace and shortens a sentence for display.
+fn excerpt(sentence: &str) -> String {
+    let collapsed = sentence.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() > EXCERPT_LENGTH {
+        format!("{}...", collapsed.chars().take(EXCERPT_LENGTH).collect::<String>())
+    } else {
+        collapsed
+    }
+}
@@ -0,0 +1,582 @@
+//! Configuration file support.
+//!
+//! Settings that are awkward to pass on the command line live in a TOML file,
+//! `scraper.toml` in the current directory by default or the file given with
+//! `--config`. Values from the file are merged into the parsed command line
+//! options before a run starts.
+//!
+//! ```toml
+//! # Save every site under ./mirrors instead of the current directory
+//! output_dir = "mirrors"
+//!
+//! # Also save the original HTML and a screenshot of every page
+//! formats = ["raw-html", "screenshot"]
+//!
+//! # Write frontmatter dates as they are expected by the site generator
+//! date_format = "date"
+//! timezone = "+02:00"
+//!
+//! # Funnel equivalent URLs to a single canonical target during discovery
+//! [[rewrite]]
+//! pattern = "^http://"
+//! replacement = "https://"
+//!
+//! [[rewrite]]
+//! pattern = "://beta\\.docs\\."
+//! replacement = "://docs."
+//!
+//! # Basic auth for an internal site; the password is read from the environment
+//! [[credentials]]
+//! host = "docs.internal.example.com"
+//! username = "reader"
+//! password_env = "DOCS_PASSWORD"
+//!
+//! # Remove sensitive content from pages before they are saved
+//! [[redact]]
+//! pattern = "[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\\.[A-Za-z]{2,}"
+//! replacement = "[EMAIL]"
+//!
+//! [[redact]]
+//! literal = "wiki.corp.example.com"
+//!
+//! # Rewrite the markdown of pages before they are saved
+//! [[replace]]
+//! pattern = "https://cdn\\.example-vendor\\.net/docs/(\\S+)"
+//! replacement = "https://docs.example.com/$1"
+//!
+//! # A site scraped with `scraper run tokio`
+//! [sites.tokio]
+//! url = "https://tokio.rs/tokio/tutorial"
+//! include = ["/tokio/**"]
+//! exclude_regex = ["/tokio/v0\\.[0-9]+/"]
+//! args = ["--export", "jsonl"]
+//! ```
+
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::auth::BasicAuth;
+use crate::bandwidth;
+use crate::discovery::PageOrder;
+use crate::cli::{ErrorPages, ScrapeOptions};
+use crate::encoding::LineEndings;
+use crate::formats::PageFormat;
+use crate::frontmatter::{self, DateFormat};
+use crate::output::OutputFormat;
+use crate::platform::Platform;
+use crate::preset::Preset;
+use crate::quality;
+use crate::redact::{ContentRule, REDACTED};
+use crate::schema::FrontmatterSchema;
+use crate::template::FrontmatterTemplate;
+use crate::throttle;
+use crate::urls::RewriteRule;
+
+/// Configuration file read when `--config` is not given.
+pub const DEFAULT_CONFIG_FILE: &str = "scraper.toml";
+
+/// Contents of a configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory the per-site output directories are created in, like `--output-dir`
+    pub output_dir: Option<PathBuf>,
+
+    /// Whether pages are saved in a per-site directory; `false` is like `--no-domain-dir`
+    pub domain_dir: Option<bool>,
+
+    /// Formats saved next to every page, like `--formats`
+    pub formats: Vec<PageFormat>,
+
+    /// How dates are written in the frontmatter, like `--date-format`
+    pub date_format: Option<DateFormat>,
+
+    /// Time zone of frontmatter dates, like `--timezone`
+    pub timezone: Option<String>,
+
+    /// Line endings of saved pages, like `--line-endings`
+    pub line_endings: Option<LineEndings>,
+
+    /// How pages are written, like `--output-format`
+    pub output_format: Option<OutputFormat>,
+
+    /// What happens to pages answered with a 4xx or 5xx status, like `--error-pages`
+    pub error_pages: Option<ErrorPages>,
+
+    /// API credits spent per minute at most, like `--credits-per-minute`
+    pub credits_per_minute: Option<u32>,
+
+    /// Pages scraped at the same time, like `--concurrency`
+    pub concurrency: Option<usize>,
+
+    /// Pages a run scrapes at most, like `--max-pages`
+    pub max_pages: Option<usize>,
+
+    /// Failed runs in a row after which a page is given up on, like `--max-failures`
+    pub max_failures: Option<u32>,
+
+    /// FireCrawl requests per second at most, like `--rps`
+    pub rps: Option<f64>,
+
+    /// Milliseconds between FireCrawl requests at least, like `--delay-ms`
+    pub delay_ms: Option<u64>,
+
+    /// Retries of a page scrape after a transient failure, like `--retries`
+    pub retries: Option<u32>,
+
+    /// Milliseconds before the first retry, like `--retry-delay-ms`
+    pub retry_delay_ms: Option<u64>,
+
+    /// Ask FireCrawl whether each page changed since it last scraped it, like `--change-tracking`
+    pub change_tracking: Option<bool>,
+
+    /// Share the credit budget with other processes, like `--shared-budget`
+    pub shared_budget: Option<bool>,
+
+    /// Bytes a run downloads before it stops downloading assets, like `--max-bytes`
+    pub max_bytes: Option<String>,
+
+    /// Largest response read for a single page, like `--max-response-size`
+    pub max_response_size: Option<String>,
+
+    /// Request oversized pages again with markdown only, like `--drop-oversized-formats`
+    pub drop_oversized_formats: Option<bool>,
+
+    /// Whether the images and PDFs pages refer to are downloaded, like `--download-assets`
+    pub download_assets: Option<bool>,
+
+    /// Assets downloaded at the same time, like `--asset-concurrency`
+    pub asset_concurrency: Option<usize>,
+
+    /// Milliseconds between two asset downloads from the same host, like `--asset-delay-ms`
+    pub asset_delay_ms: Option<u64>,
+
+    /// Largest asset downloaded, like `--max-asset-size`
+    pub max_asset_size: Option<String>,
+
+    /// Extraction preset of the site's documentation generator, like `--preset`
+    pub preset: Option<Preset>,
+
+    /// Quality score below which pages are saved for review, like `--min-quality`
+    pub min_quality: Option<f64>,
+
+    /// Handlebars template rendering the frontmatter of every page, like `--frontmatter-template`
+    pub frontmatter_template: Option<String>,
+
+    /// JSON Schema file the frontmatter of every page must match, like `--frontmatter-schema`
+    pub frontmatter_schema: Option<String>,
+
+    /// Endpoint asked to write missing alt text, like `--alt-text-endpoint`
+    pub alt_text_endpoint: Option<String>,
+
+    /// Language pages are translated into, like `--translate-to`
+    pub translate_to: Option<String>,
+
+    /// Endpoint asked to translate pages, like `--translation-endpoint`
+    pub translation_endpoint: Option<String>,
+
+    /// Documentation platform whose hosts are grouped into one site, like `--platform`
+    pub platform: Option<Platform>,
+
+    /// Further hosts serving the same pages as the start URL's host, like `--site-host`
+    pub site_hosts: Vec<String>,
+
+    /// Whether every subdomain of the start URL's domain belongs to the site, like `--include-subdomains`
+    pub include_subdomains: Option<bool>,
+
+    /// Subdomains of the start URL's domain that belong to the site, like `--subdomain`
+    pub subdomains: Vec<String>,
+
+    /// Further domains whose pages are saved with the site's, like `--allow-domain`
+    pub allowed_domains: Vec<String>,
+
+    /// Whether trailing slashes are dropped from page URLs, like `--strip-trailing-slash`
+    pub strip_trailing_slash: Option<bool>,
+
+    /// Query parameters dropped from page URLs, like `--strip-query-param`
+    pub strip_query_params: Vec<String>,
+
+    /// Order in which discovered pages are scraped, like `--order`
+    pub order: Option<PageOrder>,
+
+    /// URL rewrite rules applied to discovered links, in order
+    #[serde(rename = "rewrite")]
+    pub rewrites: Vec<RewriteConfig>,
+
+    /// Basic auth credentials for individual hosts
+    pub credentials: Vec<CredentialsConfig>,
+
+    /// Redaction rules applied to page content before saving, in order
+    #[serde(rename = "redact")]
+    pub redactions: Vec<RedactConfig>,
+
+    /// Search-and-replace rules applied to the markdown of pages before saving, after redaction, in order
+    #[serde(rename = "replace")]
+    pub replacements: Vec<ReplaceConfig>,
+
+    /// Named sites that can be scraped with `scraper run <name>`
+    pub sites: BTreeMap<String, SiteConfig>,
+}
+
+/// A named site as written in the configuration file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SiteConfig {
+    /// The URL to start scraping from
+    pub url: String,
+    /// Only scrape URLs whose path matches one of these globs, like `--include`
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Don't scrape URLs whose path matches any of these globs, like `--exclude`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Only scrape URLs matching one of these regular expressions, like `--include-regex`
+    #[serde(default)]
+    pub include_regex: Vec<String>,
+    /// Don't scrape URLs matching any of these regular expressions, like `--exclude-regex`
+    #[serde(default)]
+    pub exclude_regex: Vec<String>,
+    /// Further scrape options, written as on the command line
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// A URL rewrite rule as written in the configuration file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteConfig {
+    /// Regular expression matched against the full URL
+    pub pattern: String,
+    /// Replacement text; may reference capture groups as `$1` or `${name}`
+    pub replacement: String,
+}
+
+/// A content redaction rule as written in the configuration file.
+///
+/// Exactly one of `pattern` and `literal` must be given.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedactConfig {
+    /// Regular expression matched against page content
+    pub pattern: Option<String>,
+    /// Text matched literally against page content
+    pub literal: Option<String>,
+    /// Replacement text, `[REDACTED]` by default; patterns may reference capture groups as `$1`
+    pub replacement: Option<String>,
+}
+
+/// A search-and-replace rule as written in the configuration file.
+///
+/// Exactly one of `pattern` and `literal` must be given.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplaceConfig {
+    /// Regular expression matched against the page's markdown
+    pub pattern: Option<String>,
+    /// Text matched literally against the page's markdown
+    pub literal: Option<String>,
+    /// Replacement text; patterns may reference capture groups as `$1` or `${name}`
+    pub replacement: String,
+}
+
+/// Basic auth credentials for one host as written in the configuration file.
+///
+/// Passwords can only come from the environment so they never end up in a
+/// file that is shared or committed.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CredentialsConfig {
+    /// Host the credentials are sent to
+    pub host: String,
+    /// Username
+    pub username: Option<String>,
+    /// Environment variable holding the username, used when `username` is not set
+    pub username_env: Option<String>,
+    /// Environment variable holding the password
+    pub password_env: String,
+}
+
+impl Config {
+    /// Loads the configuration file.
+    ///
+    /// When `path` is `None`, `scraper.toml` in the current directory is read if
+    /// it exists; otherwise an empty configuration is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an explicitly given file doesn't exist, or a file
+    /// cannot be read or parsed
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Path::new(DEFAULT_CONFIG_FILE),
+            None => return Ok(Config::default()),
+        };
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        let config = toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse config {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    /// Merges the configuration into the command line options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - A rewrite pattern is not a valid regular expression
+    /// - An environment variable named by a credentials entry is not set
+    /// - A redaction rule has neither or both of `pattern` and `literal`, or an invalid pattern
+    /// - A replace rule has neither or both of `pattern` and `literal`, or an invalid pattern
+    /// - `domain_dir = false` is set without an output directory
+    /// - `timezone` is not a valid time zone
+    /// - `min_quality` is not between 0 and 1
+    /// - `max_bytes`, `max_response_size` or `max_asset_size` is not a valid size
+    /// - `rps` is not a positive number
+    /// - `frontmatter_template` can't be read or is not a valid Handlebars template
+    /// - `frontmatter_schema` can't be read or is not a valid JSON Schema
+    /// - A target language is set without a translation endpoint
+    pub fn apply(self, options: &mut ScrapeOptions) -> Result<(), Box<dyn Error>> {
+        // The command line takes precedence over the file
+        if options.output_dir.is_none() {
+            options.output_dir = self.output_dir;
+        }
+        if self.domain_dir == Some(false) {
+            if options.output_dir.is_none() {
+                return Err("domain_dir = false needs output_dir or --output-dir".into());
+            }
+            options.no_domain_dir = true;
+        }
+        if options.formats.is_empty() {
+            options.formats = self.formats;
+        }
+        if options.date_format.is_none() {
+            options.date_format = self.date_format;
+        }
+        if let (None, Some(timezone)) = (options.timezone, &self.timezone) {
+            options.timezone = Some(frontmatter::parse_timezone(timezone)?);
+        }
+        if options.line_endings.is_none() {
+            options.line_endings = self.line_endings;
+        }
+        if options.error_pages.is_none() {
+            options.error_pages = self.error_pages;
+        }
+        if options.output_format.is_none() {
+            options.output_format = self.output_format;
+        }
+        if options.credits_per_minute.is_none() {
+            options.credits_per_minute = self.credits_per_minute.filter(|credits| *credits > 0);
+        }
+        if options.concurrency.is_none() {
+            options.concurrency = self.concurrency.filter(|concurrency| *concurrency > 0);
+        }
+        if options.max_pages.is_none() {
+            options.max_pages = self.max_pages.filter(|max_pages| *max_pages > 0);
+        }
+        if options.max_failures.is_none() {
+            options.max_failures = self.max_failures.filter(|max_failures| *max_failures > 0);
+        }
+        if let (None, Some(rps)) = (options.rps, self.rps) {
+            options.rps = Some(throttle::parse_rate(&rps.to_string())?);
+        }
+        if options.delay_ms.is_none() {
+            options.delay_ms = self.delay_ms;
+        }
+        if options.retries.is_none() {
+            options.retries = self.retries;
+        }
+        if options.retry_delay_ms.is_none() {
+            options.retry_delay_ms = self.retry_delay_ms;
+        }
+        if self.change_tracking == Some(true) {
+            options.change_tracking = true;
+        }
+        if self.shared_budget == Some(true) {
+            options.shared_budget = true;
+        }
+        if let (None, Some(max_bytes)) = (options.max_bytes, &self.max_bytes) {
+            options.max_bytes = Some(bandwidth::parse_size(max_bytes)?);
+        }
+        if let (None, Some(max_response_size)) = (options.max_response_size, &self.max_response_size) {
+            options.max_response_size = Some(bandwidth::parse_size(max_response_size)?);
+        }
+        if self.drop_oversized_formats == Some(true) {
+            options.drop_oversized_formats = true;
+        }
+        if self.download_assets == Some(true) {
+            options.download_assets = true;
+        }
+        if options.asset_concurrency.is_none() {
+            options.asset_concurrency = self.asset_concurrency;
+        }
+        if options.asset_delay_ms.is_none() {
+            options.asset_delay_ms = self.asset_delay_ms;
+        }
+        if let (None, Some(max_asset_size)) = (options.max_asset_size, &self.max_asset_size) {
+            options.max_asset_size = Some(bandwidth::parse_size(max_asset_size)?);
+        }
+        if options.alt_text_endpoint.is_none() {
+            options.alt_text_endpoint = self.alt_text_endpoint;
+        }
+        if options.translate_to.is_none() {
+            options.translate_to = self.translate_to;
+        }
+        if options.translation_endpoint.is_none() {
+            options.translation_endpoint = self.translation_endpoint;
+        }
+        if options.translate_to.is_some() && options.translation_endpoint.is_none() {
+            return Err("--translate-to needs --translation-endpoint or translation_endpoint in the configuration file".into());
+        }
+        if options.preset.is_none() {
+            options.preset = self.preset;
+        }
+        if let (None, Some(min_quality)) = (options.min_quality, self.min_quality) {
+            options.min_quality = Some(quality::parse_min_quality(&min_quality.to_string())?);
+        }
+        if let (None, Some(template)) = (&options.frontmatter_template, &self.frontmatter_template) {
+            options.frontmatter_template = Some(FrontmatterTemplate::parse(template)?);
+        }
+        if let (None, Some(schema)) = (&options.frontmatter_schema, &self.frontmatter_schema) {
+            options.frontmatter_schema = Some(FrontmatterSchema::parse(schema)?);
+        }
+
+        if options.platform.is_none() {
+            options.platform = self.platform;
+        }
+        if options.site_hosts.is_empty() {
+            options.site_hosts = self.site_hosts;
+        }
+        if self.include_subdomains == Some(true) {
+            options.include_subdomains = true;
+        }
+        if options.subdomains.is_empty() {
+            options.subdomains = self.subdomains;
+        }
+        if options.allowed_domains.is_empty() {
+            options.allowed_domains = self.allowed_domains;
+        }
+        if self.strip_trailing_slash == Some(true) {
+            options.normalization.strip_trailing_slash = true;
+        }
+        if options.normalization.strip_query_params.is_empty() {
+            options.normalization.strip_query_params = self.strip_query_params;
+        }
+        if options.order.is_none() {
+            options.order = self.order;
+        }
+
+        for rewrite in self.rewrites {
+            let regex = Regex::new(&rewrite.pattern)
+                .map_err(|e| format!("Invalid rewrite pattern {:?}: {}", rewrite.pattern, e))?;
+            options.rewrite_rules.push(RewriteRule::new(regex, rewrite.replacement));
+        }
+
+        for credentials in self.credentials {
+            let username = match (credentials.username, &credentials.username_env) {
+                (Some(username), _) => username,
+                (None, Some(name)) => env_var(name, &credentials.host)?,
+                (None, None) => {
+                    return Err(format!("Credentials for {} need username or username_env", credentials.host).into())
+                }
+            };
+            let password = env_var(&credentials.password_env, &credentials.host)?;
+            let host = credentials.host.trim_end_matches('.').to_ascii_lowercase();
+            let host = idna::domain_to_ascii(&host).unwrap_or(host);
+            options.host_credentials.push((host, BasicAuth::new(username, password)));
+        }
+
+        for redaction in self.redactions {
+            let replacement = redaction.replacement.unwrap_or_else(|| REDACTED.to_string());
+            let rule = match (redaction.pattern, redaction.literal) {
+                (Some(pattern), None) => {
+                    let regex = Regex::new(&pattern)
+                        .map_err(|e| format!("Invalid redaction pattern {:?}: {}", pattern, e))?;
+                    ContentRule::new(regex, replacement)
+                }
+                (None, Some(literal)) if !literal.is_empty() => ContentRule::literal(&literal, replacement),
+                _ => return Err("Redaction rules need exactly one non-empty pattern or literal".into()),
+            };
+            options.content_rules.push(rule);
+        }
+
+        for replacement in self.replacements {
+            let rule = match (replacement.pattern, replacement.literal) {
+                (Some(pattern), None) => {
+                    let regex = Regex::new(&pattern)
+                        .map_err(|e| format!("Invalid replace pattern {:?}: {}", pattern, e))?;
+                    ContentRule::new(regex, replacement.replacement)
+                }
+                (None, Some(literal)) if !literal.is_empty() => ContentRule::literal(&literal, replacement.replacement),
+                _ => return Err("Replace rules need exactly one non-empty pattern or literal".into()),
+            };
+            options.replace_rules.push(rule);
+        }
+        Ok(())
+    }
+}
+
+/// Builds the start URL and options of a site defined under `[sites.<name>]`.
+///
+/// The site's filters and arguments come first and `extra_args` (given on the
+/// command line after the site name) last, so they override the site's
+/// settings. The configuration file itself is the one named by `--config` in
+/// `extra_args`, or `scraper.toml`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The arguments are not valid scrape options
+/// - The configuration file cannot be loaded or applied
+/// - No site with the given name is defined
+///
+/// # Examples
+///
+/// ```text
+/// let (url, options) = config::site_options("tokio", &["--export".to_string(), "jsonl".to_string()])?;
+/// ```
+pub fn site_options(name: &str, extra_args: &[String]) -> Result<(String, ScrapeOptions), Box<dyn Error>> {
+    let given = ScrapeOptions::parse_args(extra_args)?;
+    let mut config = Config::load(given.config.as_deref())?;
+    let Some(site) = config.sites.remove(name) else {
+        let known: Vec<&str> = config.sites.keys().map(String::as_str).collect();
+        return Err(if known.is_empty() {
+            format!("No site named {}; no sites are defined in the configuration file", name)
+        } else {
+            format!("No site named {}; defined sites: {}", name, known.join(", "))
+        }
+        .into());
+    };
+
+    let mut args = Vec::new();
+    for pattern in site.include {
+        args.extend(["--include".to_string(), pattern]);
+    }
+    for pattern in site.exclude {
+        args.extend(["--exclude".to_string(), pattern]);
+    }
+    for pattern in site.include_regex {
+        args.extend(["--include-regex".to_string(), pattern]);
+    }
+    for pattern in site.exclude_regex {
+        args.extend(["--exclude-regex".to_string(), pattern]);
+    }
+    args.extend(site.args);
+    args.extend(extra_args.iter().cloned());
+
+    let mut options =
+        ScrapeOptions::parse_args(&args).map_err(|e| format!("Invalid options for site {}: {}", name, e))?;
+    config.apply(&mut options)?;
+    Ok((site.url, options))
+}
+
+/// Reads an environment variable named by a credentials entry.
+fn env_var(name: &str, host: &str) -> Result<String, Box<dyn Error>> {
+    std::env::var(name)
+        .map_err(|_| format!("Environment variable {} (credentials for {}) is not set", name, host).into())
+}
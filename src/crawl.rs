@@ -0,0 +1,353 @@
+//! Scraping through FireCrawl crawl jobs.
+//!
+//! With `--crawl-job`, pages are neither discovered nor scraped one by one:
+//! the start URL is submitted to FireCrawl's `/v1/crawl` endpoint, which
+//! crawls and scrapes the site on its side, and the job's status endpoint is
+//! polled for the pages it has finished. Each poll asks only for the results
+//! after those already received (`skip`) and follows the `next` links of
+//! results too large for one response, so pages are saved while the job is
+//! still running. The job is asked for no more than `--max-pages` pages, and a
+//! run that stops before the job has finished cancels it, so FireCrawl doesn't
+//! go on scraping, and charging for, pages nobody collects.
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::api::{send_api_request, ApiUnavailable};
+use crate::cli::ScrapeOptions;
+use crate::frontier;
+use crate::metrics::CallMetrics;
+use crate::platform::SiteHosts;
+use crate::throttle::CreditPacer;
+use crate::urls::{UrlFilter, UrlGlob};
+use crate::preset::Preset;
+use crate::{Action, ScrapeData};
+
+/// Time between two polls of a job that has no new results.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Request body of the crawl endpoint.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrawlRequest<'a> {
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allow_subdomains: Option<bool>,
+    /// Most pages the job scrapes, from `--max-pages`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    /// Path expressions of `--include`, so the job only crawls matching pages
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    include_paths: Vec<String>,
+    /// Path expressions of `--exclude`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    exclude_paths: Vec<String>,
+    scrape_options: CrawlScrapeOptions,
+}
+
+/// How the job scrapes each page it finds.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrawlScrapeOptions {
+    formats: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    only_main_content: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    include_tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exclude_tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wait_for: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actions: Option<Vec<Action>>,
+}
+
+/// Response of the crawl endpoint to a new job.
+#[derive(Debug, Deserialize)]
+struct CrawlStarted {
+    id: String,
+}
+
+/// Response of the job status endpoint.
+#[derive(Debug, Deserialize)]
+struct CrawlStatus {
+    /// `scraping`, `completed`, `failed` or `cancelled`
+    status: String,
+    #[serde(default)]
+    total: usize,
+    #[serde(default)]
+    completed: usize,
+    /// URL of the results following these ones, if there are more
+    next: Option<String>,
+    #[serde(default)]
+    data: Vec<ScrapeData>,
+}
+
+/// A crawl job submitted to FireCrawl.
+pub struct CrawlJob {
+    id: String,
+    status_url: String,
+    only_main_content: Option<bool>,
+    /// Hosts of the site, when the job may crawl subdomains that aren't all part of it
+    hosts: Option<SiteHosts>,
+    /// Results received so far
+    received: usize,
+    /// Pages the job has scraped, as last reported
+    completed: usize,
+    done: bool,
+    /// API calls made since they were last taken
+    calls: Vec<CallMetrics>,
+}
+
+impl CrawlJob {
+    /// Submits a crawl job for the site of `start_url`.
+    ///
+    /// The job scrapes every page in the formats the options ask for, with the
+    /// given main-content extraction setting, extraction preset and the site's
+    /// basic auth credentials, if any. With `--include-subdomains` or `--subdomain`, the
+    /// job follows links to subdomains; results from subdomains not given with
+    /// `--subdomain` are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the job cannot be submitted
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start(
+        client: &Client,
+        api_url: &str,
+        api_key: &str,
+        start_url: &str,
+        options: &ScrapeOptions,
+        only_main_content: Option<bool>,
+        preset: Option<Preset>,
+        pacer: &CreditPacer,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut formats = vec!["markdown".to_string()];
+        for format in &options.formats {
+            let name = format.api_name().to_string();
+            if !formats.contains(&name) {
+                formats.push(name);
+            }
+        }
+        if options.change_tracking {
+            formats.push("changeTracking".to_string());
+        }
+        let hosts = SiteHosts::new(start_url, options)?;
+        let (include_paths, exclude_paths) = path_filters(&options.filter)?;
+        let extraction = preset.and_then(Preset::extraction);
+        let request = CrawlRequest {
+            url: start_url,
+            allow_subdomains: hosts.has_subdomains().then_some(true),
+            limit: options.max_pages,
+            include_paths,
+            exclude_paths,
+            scrape_options: CrawlScrapeOptions {
+                formats,
+                only_main_content,
+                include_tags: extraction.and_then(|extraction| extraction.include_tags()),
+                exclude_tags: extraction.and_then(|extraction| extraction.exclude_tags()),
+                headers: options
+                    .credentials_for(start_url)
+                    .map(|auth| serde_json::json!({ "Authorization": auth.header_value() })),
+                wait_for: extraction.and_then(|extraction| extraction.wait_for),
+                actions: extraction.and_then(|extraction| extraction.actions()),
+            },
+        };
+
+        let crawl_url = crawl_url(api_url);
+        // The job's credits are charged as it scrapes, at FireCrawl's pace
+        pacer.acquire_request().await;
+        let (started, call, _) =
+            send_api_request::<CrawlStarted>(client.post(&crawl_url).bearer_auth(api_key).json(&request), None).await?;
+        info!("Started crawl job {}", started.id);
+        Ok(CrawlJob {
+            status_url: format!("{}/{}", crawl_url, started.id),
+            id: started.id,
+            only_main_content,
+            hosts: (hosts.has_subdomains() && !options.include_subdomains).then_some(hosts),
+            received: 0,
+            completed: 0,
+            done: false,
+            calls: vec![call],
+        })
+    }
+
+    /// Returns the main-content extraction setting the job scrapes pages with.
+    pub fn only_main_content(&self) -> Option<bool> {
+        self.only_main_content
+    }
+
+    /// Returns whether every result of the job has been received.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns the API calls made since the last call, for the run's metrics.
+    pub fn take_calls(&mut self) -> Vec<CallMetrics> {
+        std::mem::take(&mut self.calls)
+    }
+
+    /// Cancels the job, for a run that stops before collecting all of its results.
+    ///
+    /// A job that can't be cancelled is reported and left running on FireCrawl's side.
+    pub async fn cancel(&mut self, client: &Client, api_key: &str, pacer: &CreditPacer) {
+        pacer.acquire_request().await;
+        let request = client.delete(&self.status_url).bearer_auth(api_key);
+        match send_api_request::<serde_json::Value>(request, None).await {
+            Ok((_, call, _)) => {
+                self.calls.push(call);
+                self.done = true;
+                info!("Cancelled crawl job {}; its remaining pages are not scraped", self.id);
+            }
+            Err(e) => warn!("Failed to cancel crawl job {}: {}; it keeps scraping on FireCrawl's side", self.id, e),
+        }
+    }
+
+    /// Waits for pages the job has finished and returns them.
+    ///
+    /// Polls the job until it has new results or has completed; an empty list
+    /// means the job completed without further results. While the API is
+    /// unavailable, polling is retried with growing delays for up to
+    /// `max_outage`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the job failed or was cancelled, a poll fails, or
+    /// the API stays unavailable for longer than `max_outage`
+    pub async fn next_pages(
+        &mut self,
+        client: &Client,
+        api_key: &str,
+        max_outage: Duration,
+        pacer: &CreditPacer,
+    ) -> Result<Vec<ScrapeData>, Box<dyn Error>> {
+        let mut outage_started: Option<Instant> = None;
+        let mut probe_delay = frontier::INITIAL_PROBE_DELAY;
+
+        loop {
+            // The error isn't kept while waiting, so a future running this stays `Send`
+            let delay = match self.poll(client, api_key, pacer).await {
+                Ok(pages) if !pages.is_empty() || self.done => return Ok(pages),
+                Ok(_) => POLL_INTERVAL,
+                Err(e) if e.is::<ApiUnavailable>() => {
+                    let started = *outage_started.get_or_insert_with(Instant::now);
+                    if started.elapsed() >= max_outage {
+                        return Err(format!(
+                            "API unavailable for more than {} seconds; crawl job {} can no longer be followed",
+                            max_outage.as_secs(),
+                            self.id
+                        )
+                        .into());
+                    }
+                    warn!("API unavailable ({}); polling crawl job {} again in {} seconds", e, self.id, probe_delay.as_secs());
+                    let delay = probe_delay;
+                    probe_delay = frontier::next_probe_delay(probe_delay);
+                    delay
+                }
+                Err(e) => return Err(e),
+            };
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Fetches the results after those already received, following `next` links.
+    async fn poll(&mut self, client: &Client, api_key: &str, pacer: &CreditPacer) -> Result<Vec<ScrapeData>, Box<dyn Error>> {
+        let mut url = format!("{}?skip={}", self.status_url, self.received);
+        let mut pages = Vec::new();
+        let finished = loop {
+            pacer.acquire_request().await;
+            // A page of results holds many pages, so --max-response-size doesn't apply to it
+            let (status, call, _) = send_api_request::<CrawlStatus>(client.get(&url).bearer_auth(api_key), None).await?;
+            self.calls.push(call);
+
+            if matches!(status.status.as_str(), "failed" | "cancelled") {
+                return Err(format!("Crawl job {} {}", self.id, status.status).into());
+            }
+            if status.completed != self.completed {
+                info!("Crawl job {}: {} of {} pages scraped", self.id, status.completed, status.total);
+                self.completed = status.completed;
+            }
+            let received = status.data.len();
+            pages.extend(status.data);
+            match status.next {
+                // A running job links to the results to come even when there are none yet
+                Some(next) if received > 0 => url = next,
+                _ => break status.status == "completed",
+            }
+        };
+
+        // Results only count as received once the whole poll succeeded
+        self.received += pages.len();
+        self.done = finished;
+        if let Some(hosts) = &self.hosts {
+            pages.retain(|page| {
+                let url = page.metadata.source_url.as_deref().and_then(|url| url::Url::parse(url).ok());
+                url.and_then(|url| hosts.unify(url)).is_some()
+            });
+        }
+        Ok(pages)
+    }
+}
+
+/// Returns the crawl endpoint next to the configured scrape endpoint.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(crawl_url("https://api.firecrawl.dev/v1/scrape"), "https://api.firecrawl.dev/v1/crawl");
+/// ```
+fn crawl_url(api_url: &str) -> String {
+    format!("{}/crawl", api_url.strip_suffix("/scrape").unwrap_or(api_url))
+}
+
+/// Returns the `--include` and `--exclude` globs as the path expressions crawl jobs filter with.
+///
+/// The job matches its filters against URL paths only, so filters matching
+/// the full URL, the `://` globs and `--include-regex` and `--exclude-regex`,
+/// can't be passed on. Leaving them to the results would have the job scrape,
+/// and charge for, pages that are then dropped, so they are refused instead.
+fn path_filters(filter: &UrlFilter) -> Result<(Vec<String>, Vec<String>), Box<dyn Error>> {
+    if !filter.include_regex.is_empty() || !filter.exclude_regex.is_empty() {
+        return Err("--crawl-job can't be combined with --include-regex or --exclude-regex, which match the full URL; \
+             use --include and --exclude path globs"
+            .into());
+    }
+    let paths = |globs: &[UrlGlob]| {
+        globs
+            .iter()
+            .map(|glob| {
+                glob.path_regex().map(str::to_string).ok_or_else(|| {
+                    format!("--crawl-job filters paths only, so the glob {} matching the full URL can't be used", glob.pattern())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+    Ok((paths(&filter.include)?, paths(&filter.exclude)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(args: &[&str]) -> UrlFilter {
+        ScrapeOptions::parse_args(args.iter().copied()).unwrap().filter
+    }
+
+    #[test]
+    fn path_globs_become_anchored_path_expressions() {
+        let (include, exclude) = path_filters(&filter(&["--include", "/docs/**", "--exclude", "**/v1/*"])).unwrap();
+        assert_eq!(include, vec!["^/docs(?:/.*)?$"]);
+        assert_eq!(exclude, vec!["^(?:.*/)?v1/[^/]*$"]);
+    }
+
+    #[test]
+    fn full_url_filters_are_refused() {
+        assert!(path_filters(&filter(&["--include", "https://docs.example.com/**"])).is_err());
+        assert!(path_filters(&filter(&["--exclude-regex", "[?&]lang="])).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+//! Debug bundles for bug reports.
+//!
+//! With `--debug-bundle <PATH>`, everything needed to reproduce a run is
+//! collected while it runs and written to a zip file when it ends, whether it
+//! succeeded or not:
+//!
+//! * `command.txt` - the scraper version, the command line and how the run ended
+//! * `options.txt` - the effective options of every site scraped, after the configuration file was merged in
+//! * `urls.txt` - the pages each site resolved to scrape, after discovery, filtering and ordering
+//! * `pages.jsonl` - one line per page: the request sent for it, the response metadata and the outcome
+//! * `log.txt` - the log of the run at debug level, whatever the console shows
+//!
+//! Registered secrets (the API key, credentials) and credential headers are
+//! redacted from every file, and the values of request headers are left out,
+//! so the bundle can be attached to a public issue.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::cli::ScrapeOptions;
+use crate::metrics::CallMetrics;
+use crate::redact::{self, REDACTED};
+use crate::Metadata;
+
+/// What has been collected for the bundle; `None` unless `--debug-bundle` was given.
+static BUNDLE: Mutex<Option<Bundle>> = Mutex::new(None);
+
+/// Everything collected for a bundle so far.
+#[derive(Default)]
+struct Bundle {
+    /// Captured log output
+    log: Vec<u8>,
+    /// Effective options of every site scraped, in order
+    options: Vec<(String, String)>,
+    /// Pages each site resolved to scrape, in order
+    urls: Vec<(String, Vec<String>)>,
+    /// One record per page, in the order the pages were first requested
+    pages: Vec<PageRecord>,
+    /// Position of each page's record in `pages`
+    page_index: HashMap<String, usize>,
+}
+
+/// What was sent and received for one page.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PageRecord {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_call: Option<CallMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<String>,
+}
+
+impl Bundle {
+    /// Returns the record of a page, adding one if the page has none yet.
+    fn page(&mut self, url: &str) -> &mut PageRecord {
+        let index = *self.page_index.entry(url.to_string()).or_insert_with(|| {
+            self.pages.push(PageRecord {
+                url: url.to_string(),
+                ..Default::default()
+            });
+            self.pages.len() - 1
+        });
+        &mut self.pages[index]
+    }
+}
+
+/// Runs `record` on the bundle, if one is being collected.
+fn with_bundle(record: impl FnOnce(&mut Bundle)) {
+    if let Some(bundle) = BUNDLE.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+        record(bundle);
+    }
+}
+
+/// Starts collecting a bundle; nothing is recorded before this is called.
+pub fn enable() {
+    BUNDLE.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert_with(Bundle::default);
+}
+
+/// Log writer appending to the bundle's log.
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        with_bundle(|bundle| bundle.log.extend_from_slice(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Records the effective options of a site and the pages resolved for it.
+pub fn record_site(start_url: &str, options: &ScrapeOptions, urls: impl IntoIterator<Item = String>) {
+    with_bundle(|bundle| {
+        bundle.options.push((start_url.to_string(), format!("{:#?}", options)));
+        bundle.urls.push((start_url.to_string(), urls.into_iter().collect()));
+    });
+}
+
+/// Records the request sent for a page, with the values of its headers left out.
+pub fn record_request(url: &str, request: &impl Serialize) {
+    with_bundle(|bundle| {
+        let mut request = serde_json::to_value(request).unwrap_or_default();
+        if let Some(headers) = request.get_mut("headers").and_then(|headers| headers.as_object_mut()) {
+            for value in headers.values_mut() {
+                *value = REDACTED.into();
+            }
+        }
+        bundle.page(url).request = Some(request);
+    });
+}
+
+/// Records the metadata of the response for a page and the API call that returned it.
+pub fn record_response(url: &str, metadata: &Metadata, api_call: Option<CallMetrics>) {
+    with_bundle(|bundle| {
+        let page = bundle.page(url);
+        page.response = serde_json::to_value(metadata).ok();
+        page.api_call = api_call;
+    });
+}
+
+/// Records how processing a page ended, e.g. `saved` or the error that stopped it.
+pub fn record_outcome(url: &str, outcome: impl Display) {
+    with_bundle(|bundle| bundle.page(url).outcome = Some(outcome.to_string()));
+}
+
+/// Writes the collected bundle to a zip file at `path`.
+///
+/// # Arguments
+///
+/// * `path` - The zip file to write
+/// * `ended` - How the run ended, e.g. the exit status or the error that stopped it
+///
+/// # Errors
+///
+/// Returns an error if no bundle was collected or the zip file cannot be written
+pub fn write(path: &Path, ended: &str) -> Result<(), Box<dyn Error>> {
+    let guard = BUNDLE.lock().unwrap_or_else(PoisonError::into_inner);
+    let bundle = guard.as_ref().ok_or("No debug bundle was collected")?;
+
+    let command = format!(
+        "scraper {}\nCommand: {}\nPlatform: {} {}\nEnded: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::args().collect::<Vec<_>>().join(" "),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        ended
+    );
+    let options: String = bundle
+        .options
+        .iter()
+        .map(|(start_url, options)| format!("# {}\n{}\n\n", start_url, options))
+        .collect();
+    let urls: String = bundle
+        .urls
+        .iter()
+        .map(|(start_url, urls)| format!("# {}\n{}\n", start_url, urls.iter().map(|url| format!("{}\n", url)).collect::<String>()))
+        .collect();
+    let mut pages = String::new();
+    for page in &bundle.pages {
+        pages.push_str(&serde_json::to_string(page)?);
+        pages.push('\n');
+    }
+
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    for (name, content) in [
+        ("command.txt", command),
+        ("options.txt", options),
+        ("urls.txt", urls),
+        ("pages.jsonl", pages),
+        ("log.txt", String::from_utf8_lossy(&bundle.log).into_owned()),
+    ] {
+        zip.start_file(name, SimpleFileOptions::default())?;
+        zip.write_all(redact::redact(&content).as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
@@ -0,0 +1,298 @@
+//! Direct-fetch backend that downloads pages without the FireCrawl API.
+//!
+//! Pages are fetched with plain HTTP GET requests and converted to markdown
+//! locally. Because the responses come straight from the documentation site,
+//! HTTP caching headers are available: validators from previous runs are sent
+//! as conditional request headers, so unchanged pages are not downloaded again.
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    HeaderMap, CACHE_CONTROL, CONTENT_TYPE, DATE, ETAG, EXPIRES, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Client, Response, StatusCode};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+use url::Url;
+
+use crate::auth::{self, BasicAuth};
+use crate::bandwidth::{self, Bandwidth};
+use crate::encoding;
+use crate::html;
+use crate::manifest::ManifestEntry;
+use crate::{Metadata, ScrapeData};
+
+/// Caching-related headers of a direct fetch response.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheHeaders {
+    /// `ETag` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// `Last-Modified` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// `Cache-Control` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+    /// `Expires` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    /// `Date` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+impl CacheHeaders {
+    /// Collects the caching headers from a response.
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        CacheHeaders {
+            etag: get(ETAG),
+            last_modified: get(LAST_MODIFIED),
+            cache_control: get(CACHE_CONTROL),
+            expires: get(EXPIRES),
+            date: get(DATE),
+        }
+    }
+}
+
+/// Sidecar metadata written next to each page saved by the direct backend.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Sidecar<'a> {
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_code: Option<i32>,
+    fetched_at: DateTime<Utc>,
+    cache: &'a CacheHeaders,
+}
+
+/// Result of fetching a page directly.
+#[derive(Debug)]
+pub enum FetchOutcome {
+    /// The page was downloaded, whatever its status; the status is in the data's metadata
+    Fetched {
+        /// Page content converted to the same shape as a FireCrawl response
+        data: Box<ScrapeData>,
+        /// Caching headers of the response
+        cache: CacheHeaders,
+    },
+    /// The previous copy is still fresh according to its `Cache-Control: max-age`
+    Fresh,
+    /// The server answered `304 Not Modified` to a conditional request
+    NotModified,
+}
+
+/// Fetches a page directly and converts it to markdown.
+///
+/// When a previous manifest entry is given, the page is skipped entirely if its
+/// `Cache-Control: max-age` has not yet expired; otherwise the stored `ETag` and
+/// `Last-Modified` values are sent as `If-None-Match` and `If-Modified-Since`.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client
+/// * `url` - The URL to fetch
+/// * `previous` - Manifest entry from a previous run whose file still exists
+/// * `auth` - Basic auth credentials for the page, if any
+/// * `headers` - Additional headers to send
+/// * `max_size` - Largest page read, in bytes
+///
+/// # Returns
+///
+/// A `Result` containing the `FetchOutcome`
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The HTTP request fails
+/// - The page is larger than `max_size`; `ResponseTooLarge`
+///
+/// # Examples
+///
+/// ```text
+/// match fetch_page(&client, "https://docs.example.com/page", None, None, &BTreeMap::new(), DEFAULT_MAX_RESPONSE_SIZE).await? {
+///     FetchOutcome::Fetched { data, cache } => { /* save data.markdown */ }
+///     FetchOutcome::Fresh | FetchOutcome::NotModified => { /* keep existing file */ }
+/// }
+/// ```
+pub async fn fetch_page(
+    client: &Client,
+    url: &str,
+    previous: Option<&ManifestEntry>,
+    auth: Option<&BasicAuth>,
+    headers: &BTreeMap<String, String>,
+    max_size: u64,
+) -> Result<FetchOutcome, Box<dyn Error>> {
+    let mut request = auth::authorize(client.get(url), auth);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    if let Some(entry) = previous {
+        if is_fresh(entry, Utc::now()) {
+            debug!("Fresh according to the saved Cache-Control; not fetched");
+            return Ok(FetchOutcome::Fresh);
+        }
+        if let Some(etag) = &entry.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    debug!(status = status.as_u16(), "Page fetched");
+
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(FetchOutcome::NotModified);
+    }
+    // Error responses are converted like any other; `--error-pages` decides what happens to them
+    let cache = CacheHeaders::from_headers(response.headers());
+    let final_url = response.url().to_string();
+    let body = decode_body(response, max_size).await?;
+
+    Ok(FetchOutcome::Fetched {
+        data: Box::new(page_data(&body, &final_url, status)),
+        cache,
+    })
+}
+
+/// Fetches a page directly and returns the absolute URLs of all links on it.
+///
+/// The page is counted by the `bandwidth` meter and read up to its largest response size.
+///
+/// # Errors
+///
+/// Returns an error if the request fails, the response status is not
+/// successful or the page is too large
+pub async fn fetch_links(
+    client: &Client,
+    url: &str,
+    auth: Option<&BasicAuth>,
+    bandwidth: &Bandwidth,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let response = auth::authorize(client.get(url), auth).send().await?;
+    if !response.status().is_success() {
+        return Err(format!("Request failed with status {}", response.status()).into());
+    }
+
+    let base = response.url().clone();
+    let body = decode_body(response, bandwidth.max_response()).await?;
+    bandwidth.record_payload(body.len() as u64);
+    Ok(extract_links(&body, &base))
+}
+
+/// Reads the body of a response of at most `max_size` bytes, decoding it with the charset the page declares.
+async fn decode_body(response: Response, max_size: u64) -> Result<String, Box<dyn Error>> {
+    let content_type = response.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).map(str::to_string);
+    let bytes = bandwidth::read_capped(response, max_size).await?;
+    Ok(encoding::decode(&bytes, content_type.as_deref()))
+}
+
+/// Writes the sidecar metadata file for a saved page.
+///
+/// The sidecar has the same name as the page with a `.meta.json` extension.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written
+pub fn write_sidecar(
+    file_path: &Path,
+    url: &str,
+    status_code: Option<i32>,
+    cache: &CacheHeaders,
+    fetched_at: DateTime<Utc>,
+) -> Result<(), Box<dyn Error>> {
+    let sidecar = Sidecar {
+        url,
+        status_code,
+        fetched_at,
+        cache,
+    };
+    let path = file_path.with_extension("meta.json");
+    fs::write(&path, serde_json::to_string_pretty(&sidecar)?)
+        .map_err(|e| format!("Failed to write sidecar {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Returns whether a previous fetch is still fresh according to its `Cache-Control` header.
+fn is_fresh(entry: &ManifestEntry, now: DateTime<Utc>) -> bool {
+    let Some(cache_control) = &entry.cache_control else {
+        return false;
+    };
+
+    let directives: Vec<String> = cache_control
+        .split(',')
+        .map(|d| d.trim().to_ascii_lowercase())
+        .collect();
+    if directives.iter().any(|d| d == "no-cache" || d == "no-store") {
+        return false;
+    }
+
+    directives
+        .iter()
+        .find_map(|d| d.strip_prefix("max-age=")?.parse::<i64>().ok())
+        .is_some_and(|max_age| entry.scraped_at + chrono::Duration::seconds(max_age) > now)
+}
+
+/// Converts a downloaded HTML page into the shape of a FireCrawl response.
+fn page_data(body: &str, url: &str, status: StatusCode) -> ScrapeData {
+    let description = html::tags(body, &["meta"])
+        .into_iter()
+        .find(|tag| html::attribute(tag, "name").is_some_and(|n| n.eq_ignore_ascii_case("description")))
+        .and_then(|tag| html::attribute(tag, "content"));
+    let language = html::tags(body, &["html"])
+        .first()
+        .and_then(|tag| html::attribute(tag, "lang"));
+    let links = Url::parse(url)
+        .map(|base| extract_links(body, &base))
+        .ok();
+
+    // Convert only the body so the <title> and other head content don't leak into the markdown
+    let lower = body.to_ascii_lowercase();
+    let content = match (lower.find("<body"), lower.rfind("</body>")) {
+        (Some(start), Some(end)) if start < end => &body[start..end + "</body>".len()],
+        _ => body,
+    };
+
+    ScrapeData {
+        markdown: Some(html2md::parse_html(content)),
+        html: None,
+        raw_html: Some(body.to_string()),
+        screenshot: None,
+        links,
+        metadata: Metadata {
+            title: html::element_text(body, "title"),
+            description,
+            language,
+            source_url: Some(url.to_string()),
+            status_code: Some(i32::from(status.as_u16())),
+            error: None,
+        },
+        warning: None,
+        change_tracking: None,
+    }
+}
+
+/// Resolves the `href` of every anchor in a page against the page URL.
+fn extract_links(body: &str, base: &Url) -> Vec<String> {
+    html::tags(body, &["a"])
+        .into_iter()
+        .filter_map(|tag| html::attribute(tag, "href"))
+        .filter_map(|href| base.join(&href).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(|url| url.to_string())
+        .collect()
+}
@@ -0,0 +1,51 @@
+//! Discovery from the links on the start page.
+
+use crate::api::make_api_request;
+use crate::cli::Backend;
+use crate::{direct, throttle, ScrapeRequest};
+
+use super::{DiscoveryContext, DiscoveryFuture, DiscoverySource};
+
+/// Finds URLs by collecting the links on the start page.
+///
+/// Uses FireCrawl's `links` format, or fetches the page itself with the
+/// direct backend.
+pub struct LinksSource;
+
+impl DiscoverySource for LinksSource {
+    fn name(&self) -> &str {
+        "page links"
+    }
+
+    fn discover<'a>(&'a self, context: &'a DiscoveryContext<'a>) -> DiscoveryFuture<'a> {
+        Box::pin(async move {
+            match context.backend {
+                Backend::Firecrawl => {
+                    let request = ScrapeRequest {
+                        url: context.start_url.to_string(),
+                        formats: vec!["links".to_string()],
+                        headers: context
+                            .auth
+                            .map(|auth| serde_json::json!({ "Authorization": auth.header_value() })),
+                        ..Default::default()
+                    };
+                    context.pacer.acquire(throttle::SCRAPE_CREDITS).await;
+                    let (response, call, _) = make_api_request(
+                        context.client,
+                        context.api_url,
+                        context.api_key,
+                        request,
+                        context.retrier,
+                        context.bandwidth.max_response(),
+                    )
+                    .await?;
+                    context.bandwidth.record_payload(call.response_bytes);
+                    Ok(response.data.links.unwrap_or_default())
+                }
+                Backend::Direct => {
+                    direct::fetch_links(context.client, context.start_url, context.auth, context.bandwidth).await
+                }
+            }
+        })
+    }
+}
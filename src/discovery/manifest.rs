@@ -0,0 +1,24 @@
+//! Discovery from the manifest of a previous run.
+
+use crate::manifest::Manifest;
+
+use super::{DiscoveryContext, DiscoveryFuture, DiscoverySource};
+
+/// Finds URLs by reading the manifest in the output directory.
+///
+/// Useful to scrape again every page a previous run found, including pages
+/// that are no longer linked from the start page.
+pub struct ManifestSource;
+
+impl DiscoverySource for ManifestSource {
+    fn name(&self) -> &str {
+        "manifest"
+    }
+
+    fn discover<'a>(&'a self, context: &'a DiscoveryContext<'a>) -> DiscoveryFuture<'a> {
+        Box::pin(async move {
+            let manifest = Manifest::load(context.output_dir)?;
+            Ok(manifest.pages.into_keys().collect())
+        })
+    }
+}
@@ -0,0 +1,145 @@
+//! Discovery through FireCrawl's map endpoint.
+//!
+//! The endpoint returns at most `limit` URLs per request. Large sites are
+//! asked for as many as the API allows, and a response that links to more
+//! results with `next` is followed until the list is complete; the pages are
+//! merged without duplicates. A list that stops at the limit without a `next`
+//! link was cut short by the API, which is reported rather than passed over.
+
+use reqwest::header::CONTENT_TYPE;
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use tracing::{info, warn};
+
+use crate::api::decode_json;
+use crate::cli::Backend;
+use crate::throttle;
+use crate::redact;
+
+use super::{DiscoveryContext, DiscoveryFuture, DiscoverySource};
+
+/// Most URLs the map endpoint returns for one request.
+const MAP_LIMIT: usize = 30_000;
+
+/// Most result pages followed through `next` links.
+const MAX_MAP_PAGES: usize = 100;
+
+/// Request body of the map endpoint.
+#[derive(Debug, Serialize)]
+struct MapRequest<'a> {
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search: Option<&'a str>,
+    limit: usize,
+}
+
+/// Response of the map endpoint.
+#[derive(Debug, Deserialize)]
+struct MapResponse {
+    #[serde(default)]
+    links: Vec<String>,
+    /// URL of the results following these ones, if there are more
+    next: Option<String>,
+}
+
+/// Finds URLs with FireCrawl's `/v1/map` endpoint, which lists the URLs of a whole site in one call.
+///
+/// With a search query the endpoint orders the URLs by their relevance to
+/// it, most relevant first.
+pub struct MapSource {
+    /// Search query the URLs are ordered by, from `--seed-query`
+    pub search: Option<String>,
+}
+
+/// Returns the map endpoint next to the configured scrape endpoint.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(map_url("https://api.firecrawl.dev/v1/scrape"), "https://api.firecrawl.dev/v1/map");
+/// ```
+fn map_url(api_url: &str) -> String {
+    format!("{}/map", api_url.strip_suffix("/scrape").unwrap_or(api_url))
+}
+
+impl DiscoverySource for MapSource {
+    fn name(&self) -> &str {
+        match self.search {
+            Some(_) => "map search",
+            None => "map endpoint",
+        }
+    }
+
+    fn discover<'a>(&'a self, context: &'a DiscoveryContext<'a>) -> DiscoveryFuture<'a> {
+        Box::pin(async move {
+            if context.backend == Backend::Direct {
+                return Err::<_, Box<dyn Error>>("the map endpoint requires the FireCrawl backend".into());
+            }
+
+            context.pacer.acquire(throttle::MAP_CREDITS).await;
+            let request = context.client.post(map_url(context.api_url)).json(&MapRequest {
+                url: context.start_url,
+                search: self.search.as_deref(),
+                limit: MAP_LIMIT,
+            });
+            let mut response = fetch_page(context, request).await?;
+
+            // Pages are merged in order, keeping the first occurrence of a URL listed on several
+            let mut seen = HashSet::new();
+            let mut links = Vec::new();
+            let mut pages = 1;
+            loop {
+                let received = response.links.len();
+                links.extend(response.links.into_iter().filter(|link| seen.insert(link.clone())));
+                let Some(next) = response.next.filter(|_| received > 0) else {
+                    if received >= MAP_LIMIT {
+                        warn!(
+                            "The map endpoint stopped at its limit of {} URLs; the site may have more. \
+                             Add `--discover sitemap` or `--discover links` to find them",
+                            MAP_LIMIT
+                        );
+                    }
+                    break;
+                };
+                if pages == MAX_MAP_PAGES {
+                    warn!("Stopped following the map endpoint's results after {} pages", MAX_MAP_PAGES);
+                    break;
+                }
+                context.pacer.acquire_request().await;
+                response = fetch_page(context, context.client.get(&next)).await?;
+                pages += 1;
+            }
+            if pages > 1 {
+                info!("The map endpoint listed {} URLs in {} pages", links.len(), pages);
+            }
+            Ok(links)
+        })
+    }
+}
+
+/// Sends a request for a page of map results and decodes the response.
+///
+/// # Errors
+///
+/// Returns an error if the request fails, the API answers with an error
+/// status or the response cannot be decoded
+async fn fetch_page(context: &DiscoveryContext<'_>, request: RequestBuilder) -> Result<MapResponse, Box<dyn Error>> {
+    let response = request.bearer_auth(context.api_key).send().await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_body = response.text().await?;
+        return Err(format!("Map request failed with status {}: {}", status, redact::preview(&error_body)).into());
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.bytes().await?;
+    context.bandwidth.record_payload(body.len() as u64);
+    decode_json(content_type.as_deref(), &body)
+}
@@ -0,0 +1,311 @@
+//! URL discovery.
+//!
+//! Each way of finding the pages of a site is a `DiscoverySource`. Several
+//! sources can be combined in one run; their results are merged and then
+//! rewritten, filtered to the hosts of the site and deduplicated in one place,
+//! so every source is treated the same way.
+
+use clap::ValueEnum;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::auth::BasicAuth;
+use crate::bandwidth::Bandwidth;
+use crate::cli::Backend;
+use crate::platform::SiteHosts;
+use crate::retry::Retrier;
+use crate::throttle::CreditPacer;
+use crate::urls::{self, RewriteRule, UrlFilter, UrlNormalization};
+
+mod links;
+mod manifest;
+mod map;
+mod sitemap;
+mod url_file;
+
+pub use links::LinksSource;
+pub use manifest::ManifestSource;
+pub use map::MapSource;
+pub use sitemap::SitemapSource;
+pub use url_file::{parse_url_list, UrlFileSource};
+
+/// Future returned by `DiscoverySource::discover`.
+pub type DiscoveryFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<String>, Box<dyn Error>>> + Send + 'a>>;
+
+/// Discovery sources that can be selected with `--discover`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiscoveryKind {
+    /// Links found on the start page
+    Links,
+    /// FireCrawl's map endpoint, which lists the URLs of a whole site
+    Map,
+    /// The site's sitemap.xml, or the links on the start page if it has none
+    Sitemap,
+    /// URLs already recorded in the output directory's manifest
+    Manifest,
+}
+
+/// Orders in which discovered pages are scraped, as chosen with `--order`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageOrder {
+    /// As discovery found them: in sitemap order with `--discover sitemap`, in page order for links
+    #[default]
+    #[value(alias = "sitemap")]
+    Discovery,
+    /// Breadth-first: pages with fewer path segments first, in discovery order within a depth
+    Depth,
+    /// Alphabetically by URL
+    Alphabetical,
+    /// Shortest URL path first, alphabetically among paths of the same length
+    ShortestPath,
+}
+
+/// Puts discovered URLs into the order they are scraped in.
+///
+/// Every order but `Discovery` depends only on the URLs themselves, so runs
+/// finding the same pages scrape and save them in the same order.
+///
+/// # Examples
+///
+/// ```text
+/// let mut urls = vec!["https://example.com/guide/setup".to_string(), "https://example.com/api".to_string()];
+/// discovery::order_urls(&mut urls, PageOrder::Depth);
+/// assert_eq!(urls, vec!["https://example.com/api", "https://example.com/guide/setup"]);
+/// ```
+pub fn order_urls(urls: &mut [String], order: PageOrder) {
+    match order {
+        PageOrder::Discovery => {}
+        PageOrder::Depth => urls.sort_by_cached_key(|url| path_depth(url)),
+        PageOrder::Alphabetical => urls.sort(),
+        PageOrder::ShortestPath => urls.sort_by_cached_key(|url| (path_length(url), url.clone())),
+    }
+}
+
+/// Picks a sample of URLs spread across the sections of a site, for `--sample`.
+///
+/// URLs are grouped by host and the first directory of their path. Every
+/// section gets a page before any gets a second one, and the pages of a
+/// section are picked evenly from its list rather than from its start. The
+/// sample is ordered section by section in turn, so a sample cut short still
+/// covers as many sections as it can.
+///
+/// # Examples
+///
+/// ```text
+/// let urls: Vec<String> = ["/guide/a", "/guide/b", "/guide/c", "/guide/d", "/api/x", "/blog/y"]
+///     .iter()
+///     .map(|path| format!("https://example.com{}", path))
+///     .collect();
+/// let sample = discovery::sample_urls(&urls, 4);
+/// assert_eq!(sample, vec!["https://example.com/guide/a", "https://example.com/api/x", "https://example.com/blog/y", "https://example.com/guide/c"]);
+/// ```
+pub fn sample_urls(urls: &[String], size: usize) -> Vec<String> {
+    let mut sections: Vec<(String, Vec<&String>)> = Vec::new();
+    for url in urls {
+        let section = Url::parse(url).map_or_else(
+            |_| String::new(),
+            |parsed| {
+                // Pages directly below the root make up one section rather than one each
+                let directory = parsed.path().trim_start_matches('/').split_once('/').map(|(first, _)| first);
+                format!("{}/{}", parsed.host_str().unwrap_or_default(), directory.unwrap_or_default())
+            },
+        );
+        match sections.iter_mut().find(|(name, _)| *name == section) {
+            Some((_, pages)) => pages.push(url),
+            None => sections.push((section, vec![url])),
+        }
+    }
+
+    // Pages taken from each section, handed out one at a time in turn
+    let mut quotas = vec![0; sections.len()];
+    let mut remaining = size.min(urls.len());
+    while remaining > 0 {
+        for (quota, (_, pages)) in quotas.iter_mut().zip(&sections) {
+            if remaining > 0 && *quota < pages.len() {
+                *quota += 1;
+                remaining -= 1;
+            }
+        }
+    }
+
+    let rounds = quotas.iter().copied().max().unwrap_or(0);
+    let mut sample = Vec::new();
+    for round in 0..rounds {
+        for (&quota, (_, pages)) in quotas.iter().zip(&sections) {
+            if round < quota {
+                sample.push(pages[round * pages.len() / quota].clone());
+            }
+        }
+    }
+    sample
+}
+
+/// Returns the number of non-empty segments in a URL's path.
+fn path_depth(url: &str) -> usize {
+    Url::parse(url).map_or(0, |url| {
+        url.path_segments().map_or(0, |segments| segments.filter(|segment| !segment.is_empty()).count())
+    })
+}
+
+/// Returns the length of a URL's path and query.
+fn path_length(url: &str) -> usize {
+    Url::parse(url).map_or(url.len(), |url| url.path().len() + url.query().map_or(0, str::len))
+}
+
+/// Everything a discovery source may need to find URLs.
+pub struct DiscoveryContext<'a> {
+    /// The HTTP client
+    pub client: &'a Client,
+    /// The FireCrawl scrape endpoint
+    pub api_url: &'a str,
+    /// The FireCrawl API key
+    pub api_key: &'a str,
+    /// Backend used to fetch pages
+    pub backend: Backend,
+    /// The URL the run starts from
+    pub start_url: &'a str,
+    /// Hosts serving the site, whose URLs are moved onto the start URL's host
+    pub hosts: &'a SiteHosts,
+    /// The directory pages are saved to
+    pub output_dir: &'a Path,
+    /// Basic auth credentials for the start URL's host, if any
+    pub auth: Option<&'a BasicAuth>,
+    /// Pacer every API call is made through
+    pub pacer: &'a CreditPacer,
+    /// Retrier of the FireCrawl requests made for the start page
+    pub retrier: &'a Retrier,
+    /// Meter every download is counted by
+    pub bandwidth: &'a Bandwidth,
+    /// Sitemaps listed in the site's robots.txt
+    pub sitemaps: &'a [String],
+}
+
+/// A way of finding the URLs of the pages to scrape.
+pub trait DiscoverySource: Send + Sync {
+    /// Short name of the source used in log messages.
+    fn name(&self) -> &str;
+
+    /// Returns the URLs found by this source.
+    ///
+    /// URLs are returned as found; rewriting, host filtering and
+    /// deduplication happen once for all sources in `discover`.
+    fn discover<'a>(&'a self, context: &'a DiscoveryContext<'a>) -> DiscoveryFuture<'a>;
+}
+
+/// Builds the discovery sources selected on the command line.
+///
+/// Uses the start page's links when no source is selected, and when the site
+/// has no sitemap for `sitemap` unless `links` is selected as well. A seed
+/// query on its own is enough, for runs after the pages on one topic only.
+///
+/// # Arguments
+///
+/// * `kinds` - Sources selected with `--discover`
+/// * `url_file` - File of URLs given with `--url-file`, if any
+/// * `seed_query` - Search query given with `--seed-query`, if any
+///
+/// # Returns
+///
+/// The search for the seed query, followed by the sources in the order they
+/// were selected and the URL file
+pub fn sources(kinds: &[DiscoveryKind], url_file: Option<&Path>, seed_query: Option<&str>) -> Vec<Box<dyn DiscoverySource>> {
+    let mut sources: Vec<Box<dyn DiscoverySource>> = Vec::new();
+    if let Some(query) = seed_query {
+        sources.push(Box::new(MapSource {
+            search: Some(query.to_string()),
+        }));
+    }
+    for kind in kinds {
+        match kind {
+            DiscoveryKind::Links => sources.push(Box::new(LinksSource)),
+            DiscoveryKind::Map => sources.push(Box::new(MapSource { search: None })),
+            DiscoveryKind::Sitemap => sources.push(Box::new(SitemapSource {
+                fall_back_to_links: !kinds.contains(&DiscoveryKind::Links),
+            })),
+            DiscoveryKind::Manifest => sources.push(Box::new(ManifestSource)),
+        }
+    }
+    if let Some(path) = url_file {
+        sources.push(Box::new(UrlFileSource::new(PathBuf::from(path))));
+    }
+    if sources.is_empty() {
+        sources.push(Box::new(LinksSource));
+    }
+    sources
+}
+
+/// Runs every discovery source and merges their results.
+///
+/// A failing source is reported and skipped as long as at least one other
+/// source succeeds. The merged URLs have the rewrite rules applied, are
+/// limited to the hosts of the site and moved onto the start URL's host,
+/// normalized following `normalization`, limited to those allowed by `filter`
+/// and deduplicated, keeping the order of the sources and of the URLs each one found.
+///
+/// # Errors
+///
+/// Returns an error if every source fails
+///
+/// # Examples
+///
+/// ```text
+/// let sources = discovery::sources(&[DiscoveryKind::Links, DiscoveryKind::Sitemap], None, None);
+/// let urls = discovery::discover(&sources, &context, &options.rewrite_rules, &options.normalization, &options.filter).await?;
+/// ```
+pub async fn discover(
+    sources: &[Box<dyn DiscoverySource>],
+    context: &DiscoveryContext<'_>,
+    rewrite_rules: &[RewriteRule],
+    normalization: &UrlNormalization,
+    filter: &UrlFilter,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut found = Vec::new();
+    let mut first_error = None;
+    let mut succeeded = false;
+
+    for source in sources {
+        match source.discover(context).await {
+            Ok(links) => {
+                if sources.len() > 1 {
+                    info!("Discovered {} URLs from {}", links.len(), source.name());
+                } else {
+                    debug!("Discovered {} URLs from {}", links.len(), source.name());
+                }
+                found.extend(links);
+                succeeded = true;
+            }
+            Err(e) => {
+                warn!("Discovery from {} failed: {}", source.name(), e);
+                first_error.get_or_insert(e.to_string());
+            }
+        }
+    }
+
+    if !succeeded {
+        if let Some(message) = first_error {
+            return Err(message.into());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    Ok(found
+        .into_iter()
+        .filter_map(|link| {
+            let link = urls::rewrite_url(rewrite_rules, &link);
+            Url::parse(&link)
+                .ok()
+                .and_then(|url| context.hosts.unify(url))
+                .and_then(|url| normalization.canonical_url(url.as_str()).ok())
+                .filter(|url| filter.allows(url))
+        })
+        .filter(|url| seen.insert(url.clone()))
+        .collect())
+}
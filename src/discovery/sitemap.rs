@@ -0,0 +1,173 @@
+//! Discovery from the site's sitemap.
+
+use flate2::read::GzDecoder;
+use reqwest::StatusCode;
+use std::error::Error;
+use std::io::Read;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::auth;
+use crate::html;
+
+use super::{DiscoveryContext, DiscoveryFuture, DiscoverySource, LinksSource};
+
+/// Maximum number of sitemaps read from a sitemap index.
+const MAX_CHILD_SITEMAPS: usize = 100;
+
+/// Largest uncompressed sitemap read, as allowed by the sitemap protocol.
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+
+/// First bytes of gzip-compressed data.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Finds URLs by reading the sitemaps listed in the site's robots.txt, or
+/// `/sitemap.xml` at the root of the start URL's site if it lists none.
+///
+/// Sitemap index files are followed one level deep, and gzip-compressed
+/// sitemaps are read as well.
+pub struct SitemapSource {
+    /// Use the links on the start page when the site has no sitemap
+    pub fall_back_to_links: bool,
+}
+
+/// Returns the text of every `<loc>` element in a sitemap.
+///
+/// # Examples
+///
+/// ```text
+/// let xml = "<urlset><url><loc>https://example.com/a?x=1&amp;y=2</loc></url></urlset>";
+/// assert_eq!(locations(xml), vec!["https://example.com/a?x=1&y=2"]);
+/// ```
+fn locations(xml: &str) -> Vec<String> {
+    let lower = xml.to_ascii_lowercase();
+    let mut locations = Vec::new();
+    let mut position = 0;
+
+    while let Some(start) = lower[position..].find("<loc>").map(|i| i + position + "<loc>".len()) {
+        let Some(end) = lower[start..].find("</loc>").map(|i| i + start) else {
+            break;
+        };
+        let location = html::decode_entities(xml[start..end].trim());
+        if !location.is_empty() {
+            locations.push(location);
+        }
+        position = end;
+    }
+
+    locations
+}
+
+/// Downloads a sitemap and returns its text, or `None` if there is no sitemap at `url`.
+///
+/// Gzip-compressed sitemaps (`sitemap.xml.gz`) are decompressed. A missing
+/// file (`404 Not Found` or `410 Gone`) or a response that isn't a sitemap,
+/// such as an HTML page served for every path, counts as no sitemap.
+///
+/// # Errors
+///
+/// Returns an error if the request fails, the response status is not
+/// successful, or a compressed sitemap cannot be decompressed
+async fn fetch_sitemap(context: &DiscoveryContext<'_>, url: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let response = auth::authorize(context.client.get(url), context.auth).send().await?;
+    if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(format!("Request for {} failed with status {}", url, response.status()).into());
+    }
+    let body = response.bytes().await?;
+    context.bandwidth.record_payload(body.len() as u64);
+
+    let xml = if body.starts_with(&GZIP_MAGIC) {
+        let mut xml = String::new();
+        GzDecoder::new(&body[..])
+            .take(MAX_SITEMAP_BYTES + 1)
+            .read_to_string(&mut xml)
+            .map_err(|e| format!("Failed to decompress sitemap {}: {}", url, e))?;
+        if xml.len() as u64 > MAX_SITEMAP_BYTES {
+            return Err(format!("Sitemap {} is larger than 50 MiB uncompressed", url).into());
+        }
+        xml
+    } else {
+        String::from_utf8_lossy(&body).into_owned()
+    };
+
+    let lower = xml.to_ascii_lowercase();
+    Ok((lower.contains("<urlset") || lower.contains("<sitemapindex")).then_some(xml))
+}
+
+impl DiscoverySource for SitemapSource {
+    fn name(&self) -> &str {
+        "sitemap"
+    }
+
+    fn discover<'a>(&'a self, context: &'a DiscoveryContext<'a>) -> DiscoveryFuture<'a> {
+        Box::pin(async move {
+            let sitemaps = match context.sitemaps {
+                [] => vec![Url::parse(context.start_url)?.join("/sitemap.xml")?.to_string()],
+                listed => listed.to_vec(),
+            };
+
+            // A listed sitemap that fails is skipped as long as another one can be read
+            let mut urls = Vec::new();
+            let mut found = false;
+            let mut failure = None;
+            for sitemap in &sitemaps {
+                match read_sitemap(context, sitemap).await {
+                    Ok(Some(listed)) => {
+                        found = true;
+                        urls.extend(listed);
+                    }
+                    Ok(None) => warn!("No sitemap at {}", sitemap),
+                    Err(e) if sitemaps.len() > 1 => {
+                        warn!("Skipping sitemap {}: {}", sitemap, e);
+                        failure = Some(e.to_string());
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if let (false, None) = (found, &failure) {
+                if !self.fall_back_to_links {
+                    return Err(format!("No sitemap found at {}", sitemaps.join(", ")).into());
+                }
+                info!("The site has no sitemap; using the links on the start page instead");
+                return LinksSource.discover(context).await;
+            }
+            match failure {
+                Some(message) if urls.is_empty() => Err(message.into()),
+                _ => Ok(urls),
+            }
+        })
+    }
+}
+
+/// Returns the URLs listed in a sitemap, following a sitemap index one level
+/// deep, or `None` if there is no sitemap at `sitemap_url`.
+async fn read_sitemap(context: &DiscoveryContext<'_>, sitemap_url: &str) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+    let Some(xml) = fetch_sitemap(context, sitemap_url).await? else {
+        return Ok(None);
+    };
+    if !xml.to_ascii_lowercase().contains("<sitemapindex") {
+        return Ok(Some(locations(&xml)));
+    }
+
+    let children = locations(&xml);
+    if children.len() > MAX_CHILD_SITEMAPS {
+        warn!(
+            "Sitemap index {} lists {} sitemaps; only the first {} are read",
+            sitemap_url,
+            children.len(),
+            MAX_CHILD_SITEMAPS
+        );
+    }
+    let mut urls = Vec::new();
+    for child in children.into_iter().take(MAX_CHILD_SITEMAPS) {
+        match fetch_sitemap(context, &child).await {
+            Ok(Some(child_xml)) => urls.extend(locations(&child_xml)),
+            Ok(None) => warn!("Skipping sitemap {}: not found", child),
+            Err(e) => warn!("Skipping sitemap {}: {}", child, e),
+        }
+    }
+    Ok(Some(urls))
+}
@@ -0,0 +1,47 @@
+//! Discovery from a file of URLs.
+
+use std::fs;
+use std::path::PathBuf;
+
+use super::{DiscoveryContext, DiscoveryFuture, DiscoverySource};
+
+/// Finds URLs by reading a text file with one URL per line.
+///
+/// Blank lines and lines starting with `#` are ignored.
+pub struct UrlFileSource {
+    path: PathBuf,
+}
+
+impl UrlFileSource {
+    /// Creates a source reading the given file.
+    pub fn new(path: PathBuf) -> Self {
+        UrlFileSource { path }
+    }
+}
+
+impl DiscoverySource for UrlFileSource {
+    fn name(&self) -> &str {
+        "URL file"
+    }
+
+    fn discover<'a>(&'a self, _context: &'a DiscoveryContext<'a>) -> DiscoveryFuture<'a> {
+        Box::pin(async move {
+            let content = fs::read_to_string(&self.path)
+                .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+            Ok(parse_url_list(&content))
+        })
+    }
+}
+
+/// Returns the URLs listed in the content of a URL file, one per line.
+///
+/// Blank lines and lines starting with `#` are skipped and surrounding
+/// whitespace is removed.
+pub fn parse_url_list(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
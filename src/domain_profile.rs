@@ -0,0 +1,360 @@
+//! Per-domain profiles of what earlier runs learned about a site.
+//!
+//! Some things about a site only have to be found out once: its robots.txt,
+//! where its sitemaps are, how long it asks crawlers to wait between requests,
+//! which documentation generator built it, and whether FireCrawl's
+//! main-content extraction works on its pages. They
+//! are kept in one JSON file per host in the profile directory
+//! (`$XDG_CACHE_HOME/scraper/domains`, `~/.cache/scraper/domains` or
+//! `--domain-profiles <DIR>`), so later scrapes of the same site, from any
+//! output directory, start with known-good settings. robots.txt is fetched
+//! again once the cached copy is a day old.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::auth::BasicAuth;
+use crate::cli::ScrapeOptions;
+use crate::preset::{self, Preset};
+use crate::robots::Robots;
+
+/// Hours a cached robots.txt is used before it is fetched again.
+const ROBOTS_MAX_AGE_HOURS: i64 = 24;
+
+/// User agent token whose `Crawl-delay` the direct backend honors.
+const USER_AGENT: &str = "scraper";
+
+/// Longest crawl delay honored; longer ones are shortened to this.
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+/// Pages a run must scrape on a host before its extraction settings are adjusted.
+const MIN_PAGES_TO_LEARN: usize = 2;
+
+/// What is known about one host.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainProfile {
+    /// Host, with the port if it isn't the default one
+    pub host: String,
+
+    /// When the profile last changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+
+    /// The site's robots.txt as last fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robots: Option<CachedRobots>,
+
+    /// Sitemaps listed in robots.txt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sitemaps: Vec<String>,
+
+    /// Seconds robots.txt asks crawlers to wait between requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crawl_delay: Option<f64>,
+
+    /// Documentation generator detected from the markup of the host's pages; `none` if none was recognized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+
+    /// Main-content extraction setting to request; `false` once it left most pages of a run empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_main_content: Option<bool>,
+
+    /// When the direct backend last fetched a page from the host in this run
+    #[serde(skip)]
+    last_fetch: Option<Instant>,
+
+    /// Whether detecting the generator was tried in this run
+    #[serde(skip)]
+    preset_checked: bool,
+
+    /// Pages scraped with main-content extraction in this run
+    #[serde(skip)]
+    main_content_pages: usize,
+
+    /// Of those, pages that came back without content
+    #[serde(skip)]
+    main_content_empty: usize,
+
+    /// Whether the profile has to be saved
+    #[serde(skip)]
+    changed: bool,
+}
+
+/// A cached robots.txt.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedRobots {
+    /// When the file was fetched
+    pub fetched_at: DateTime<Utc>,
+
+    /// Its text; empty if the site has none
+    pub text: String,
+}
+
+impl DomainProfile {
+    /// Fetches robots.txt unless a cached copy younger than a day exists.
+    ///
+    /// A missing robots.txt is cached as an empty one. If the file cannot be
+    /// fetched, the cached copy (if any) is kept and tried again next time.
+    async fn refresh_robots(&mut self, client: &Client, url: &Url) {
+        let max_age = TimeDelta::hours(ROBOTS_MAX_AGE_HOURS);
+        if self.robots.as_ref().is_some_and(|robots| Utc::now() - robots.fetched_at < max_age) {
+            return;
+        }
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return;
+        };
+
+        let text = match client.get(robots_url.clone()).send().await {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(text) => text,
+                Err(e) => {
+                    debug!("Failed to read {}: {}", robots_url, e);
+                    return;
+                }
+            },
+            // Access to a site without robots.txt is unrestricted (RFC 9309)
+            Ok(response) if response.status().is_client_error() => String::new(),
+            Ok(response) => {
+                debug!("{} answered with status {}", robots_url, response.status());
+                return;
+            }
+            Err(e) => {
+                debug!("Failed to fetch {}: {}", robots_url, e);
+                return;
+            }
+        };
+
+        let robots = Robots::parse(&text);
+        self.sitemaps = robots.sitemaps().to_vec();
+        self.crawl_delay = robots.crawl_delay(USER_AGENT);
+        if let Some(delay) = self.crawl_delay {
+            info!("robots.txt of {} asks for a crawl delay of {}s", self.host, delay);
+        }
+        self.robots = Some(CachedRobots {
+            fetched_at: Utc::now(),
+            text,
+        });
+        self.mark_changed();
+    }
+
+    /// Detects the documentation generator from the markup of a page, unless it is known already.
+    ///
+    /// The page is fetched from the site itself. If it cannot be fetched, the
+    /// generator stays unknown and detection is tried again in the next run.
+    async fn detect_preset(&mut self, client: &Client, url: &Url, auth: Option<&BasicAuth>) {
+        if self.preset.is_some() || self.preset_checked {
+            return;
+        }
+        self.preset_checked = true;
+        let mut request = client.get(url.clone());
+        if let Some(auth) = auth {
+            request = request.header(reqwest::header::AUTHORIZATION, auth.header_value());
+        }
+        let html = match request.send().await {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(html) => html,
+                Err(e) => {
+                    debug!("Failed to read {}: {}", url, e);
+                    return;
+                }
+            },
+            Ok(response) => {
+                debug!("{} answered with status {}", url, response.status());
+                return;
+            }
+            Err(e) => {
+                debug!("Failed to fetch {}: {}", url, e);
+                return;
+            }
+        };
+
+        let detected = preset::detect(&html);
+        if detected != Preset::None {
+            info!("{} is built with {}; using its extraction preset", self.host, detected.name());
+        }
+        self.preset = Some(detected);
+        self.mark_changed();
+    }
+
+    /// Returns the text of the cached robots.txt, if it could be fetched.
+    pub fn robots_txt(&self) -> Option<&str> {
+        self.robots.as_ref().map(|robots| robots.text.as_str())
+    }
+
+    /// Waits until the crawl delay has passed since the last page fetched from the host.
+    pub async fn wait_politely(&mut self) {
+        let delay = self
+            .crawl_delay
+            .map(|delay| Duration::from_secs_f64(delay).min(MAX_CRAWL_DELAY))
+            .unwrap_or_default();
+        if let Some(last_fetch) = self.last_fetch {
+            let wait = delay.saturating_sub(last_fetch.elapsed());
+            if !wait.is_zero() {
+                debug!("Waiting {} ms for the crawl delay", wait.as_millis());
+                tokio::time::sleep(wait).await;
+            }
+        }
+        self.last_fetch = Some(Instant::now());
+    }
+
+    /// Records a page scraped with main-content extraction, and whether it came back empty.
+    pub fn record_main_content(&mut self, empty: bool) {
+        self.main_content_pages += 1;
+        if empty {
+            self.main_content_empty += 1;
+        }
+    }
+
+    /// Switches main-content extraction off if it left most pages of the run empty.
+    fn learn_extraction(&mut self) {
+        if self.main_content_pages < MIN_PAGES_TO_LEARN || self.main_content_empty * 2 <= self.main_content_pages {
+            return;
+        }
+        info!(
+            "{} of {} pages of {} came back empty with main-content extraction; later runs extract whole pages",
+            self.main_content_empty, self.main_content_pages, self.host
+        );
+        self.only_main_content = Some(false);
+        self.mark_changed();
+    }
+
+    fn mark_changed(&mut self) {
+        self.updated_at = Some(Utc::now());
+        self.changed = true;
+    }
+}
+
+/// The profiles of the hosts a run scrapes, loaded as they are first needed.
+pub struct DomainProfiles {
+    /// Directory the profiles are stored in; `None` when they are only kept for the run
+    dir: Option<PathBuf>,
+    profiles: HashMap<String, DomainProfile>,
+}
+
+impl DomainProfiles {
+    /// Opens the profile directory chosen by the options.
+    ///
+    /// With `--no-domain-profiles`, or without a home directory to put the
+    /// default directory in, profiles are still built during the run but
+    /// neither read nor saved.
+    pub fn open(options: &ScrapeOptions) -> Self {
+        let dir = if options.no_domain_profiles {
+            None
+        } else {
+            options.domain_profiles.clone().or_else(default_dir)
+        };
+        DomainProfiles {
+            dir,
+            profiles: HashMap::new(),
+        }
+    }
+
+    /// Returns the profile of a URL's host, or `None` if the URL has no host.
+    pub fn get(&mut self, url: &str) -> Option<&mut DomainProfile> {
+        let url = Url::parse(url).ok()?;
+        let host = host_key(&url)?;
+        let dir = self.dir.as_deref();
+        Some(self.profiles.entry(host.clone()).or_insert_with(|| load(dir, &host)))
+    }
+
+    /// Returns the profile of a URL's host, fetching its robots.txt first if the cached copy is missing or stale.
+    pub async fn with_robots(&mut self, client: &Client, url: &str) -> Option<&mut DomainProfile> {
+        let parsed = Url::parse(url).ok()?;
+        let profile = self.get(url)?;
+        profile.refresh_robots(client, &parsed).await;
+        Some(profile)
+    }
+
+    /// Returns the profile of a URL's host, detecting the host's documentation generator from the page first if it isn't known yet.
+    pub async fn with_preset(&mut self, client: &Client, url: &str, auth: Option<&BasicAuth>) -> Option<&mut DomainProfile> {
+        let parsed = Url::parse(url).ok()?;
+        let profile = self.get(url)?;
+        profile.detect_preset(client, &parsed, auth).await;
+        Some(profile)
+    }
+
+    /// Saves the profiles that changed in this run.
+    ///
+    /// Failing to save a profile is reported but isn't an error, since the run
+    /// itself succeeded.
+    pub fn save(&mut self) {
+        for profile in self.profiles.values_mut() {
+            profile.learn_extraction();
+        }
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        for profile in self.profiles.values().filter(|profile| profile.changed) {
+            if let Err(e) = save(dir, profile) {
+                warn!("Failed to save the profile of {}: {}", profile.host, e);
+            }
+        }
+    }
+}
+
+/// Returns the default profile directory, below the user's cache directory.
+fn default_dir() -> Option<PathBuf> {
+    Some(cache_dir()?.join("domains"))
+}
+
+/// Returns the scraper's directory in the user's cache directory:
+/// `$XDG_CACHE_HOME/scraper` or `~/.cache/scraper`.
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache.join("scraper"))
+}
+
+/// Returns the key a host's profile is stored under, e.g. `docs.example.com` or `localhost_8080`.
+fn host_key(url: &Url) -> Option<String> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some(match url.port() {
+        Some(port) => format!("{}_{}", host, port),
+        None => host,
+    })
+}
+
+/// Loads a host's profile, starting a new one if there is none or it cannot be read.
+fn load(dir: Option<&Path>, host: &str) -> DomainProfile {
+    let new = || DomainProfile {
+        host: host.to_string(),
+        ..Default::default()
+    };
+    let Some(path) = dir.map(|dir| dir.join(format!("{}.json", host))) else {
+        return new();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return new();
+    };
+    match serde_json::from_str(&content) {
+        Ok(profile) => {
+            debug!("Using the profile in {}", path.display());
+            profile
+        }
+        Err(e) => {
+            warn!("Ignoring unreadable profile {}: {}", path.display(), e);
+            new()
+        }
+    }
+}
+
+/// Writes a profile as pretty-printed JSON into the profile directory.
+fn save(dir: &Path, profile: &DomainProfile) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.json", profile.host));
+    fs::write(&path, serde_json::to_string_pretty(profile)?)?;
+    Ok(())
+}
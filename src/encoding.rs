@@ -0,0 +1,134 @@
+//! Character encoding and line ending normalization of saved pages.
+//!
+//! Pages are always saved as UTF-8. Pages fetched directly are decoded with
+//! the charset of their `Content-Type` header or `<meta charset>` tag, so
+//! sites still serving Latin-1 or Shift JIS don't end up as mojibake. Before a
+//! page is written, control characters are removed and line endings are
+//! converted to those chosen with `--line-endings`; values placed in the
+//! frontmatter are cleaned so they always stay on one line, which keeps the
+//! files readable by strict YAML parsers.
+
+use clap::ValueEnum;
+use encoding_rs::{Encoding, UTF_8};
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::LazyLock;
+use tracing::debug;
+
+/// Bytes at the start of a page searched for a `<meta charset>` tag, as browsers do.
+const META_SNIFF_LENGTH: usize = 1024;
+
+static META_CHARSET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?\s*([a-z0-9_:.-]+)"#).unwrap());
+
+/// Line endings of saved pages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineEndings {
+    /// Unix line endings (`\n`)
+    #[default]
+    Lf,
+    /// Windows line endings (`\r\n`)
+    Crlf,
+}
+
+/// Decodes a fetched page into a string.
+///
+/// A byte order mark wins over the charset of the `Content-Type` header, which
+/// wins over a `<meta charset>` tag near the start of the page. Pages naming
+/// no known charset are read as UTF-8. Invalid bytes become U+FFFD.
+///
+/// # Examples
+///
+/// ```text
+/// let text = decode(b"caf\xe9", Some("text/html; charset=ISO-8859-1"));
+/// assert_eq!(text, "café");
+/// ```
+pub fn decode(bytes: &[u8], content_type: Option<&str>) -> String {
+    let declared = content_type
+        .and_then(header_charset)
+        .or_else(|| meta_charset(&bytes[..bytes.len().min(META_SNIFF_LENGTH)]))
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    let (text, encoding, had_errors) = declared.decode(bytes);
+    if had_errors {
+        debug!(encoding = encoding.name(), "Page has bytes that aren't valid in its encoding");
+    }
+    text.into_owned()
+}
+
+/// Returns the `charset` parameter of a `Content-Type` header value.
+fn header_charset(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']).to_string())
+    })
+}
+
+/// Returns the charset named by a `<meta charset>` or `<meta http-equiv>` tag.
+fn meta_charset(head: &[u8]) -> Option<String> {
+    let head = String::from_utf8_lossy(head);
+    META_CHARSET.captures(&head).map(|captures| captures[1].to_string())
+}
+
+/// Normalizes the text of a page before it is written.
+///
+/// A leading byte order mark and control characters other than tabs and line
+/// breaks are removed, and `\r\n` and lone `\r` line breaks are converted to
+/// `line_endings`.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(normalize_text("\u{feff}# Intro\r\nText\u{7}\r", LineEndings::Lf), "# Intro\nText\n");
+/// assert_eq!(normalize_text("a\nb", LineEndings::Crlf), "a\r\nb");
+/// ```
+pub fn normalize_text(text: &str, line_endings: LineEndings) -> String {
+    let newline = match line_endings {
+        LineEndings::Lf => "\n",
+        LineEndings::Crlf => "\r\n",
+    };
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let mut normalized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                normalized.push_str(newline);
+            }
+            '\n' => normalized.push_str(newline),
+            '\t' => normalized.push(c),
+            c if c.is_control() => {}
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Cleans a value placed in the frontmatter.
+///
+/// Line breaks and tabs, including the Unicode line and paragraph separators
+/// YAML treats as line breaks, become spaces; other control characters and
+/// byte order marks are removed.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(clean_field("Intro\r\n\u{0}Guide\u{2028}v2"), "Intro Guide v2");
+/// ```
+pub fn clean_field(value: &str) -> String {
+    value
+        .replace("\r\n", " ")
+        .chars()
+        .filter_map(|c| match c {
+            '\r' | '\n' | '\t' | '\u{2028}' | '\u{2029}' => Some(' '),
+            '\u{feff}' => None,
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
@@ -0,0 +1,255 @@
+//! Pieces shared by the exporters that write the pages as a documentation site project.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+use url::Url;
+
+use crate::markdown::Fence;
+use crate::media::MediaFile;
+use crate::navigation::MARKDOWN_LINK;
+
+/// A chapter of a table of contents: one level of the site's URL paths.
+#[derive(Default)]
+pub(super) struct Chapter {
+    /// Last path segment, titling the chapter if no page has its path
+    pub name: String,
+    /// Index of the page with this path
+    pub page: Option<usize>,
+    pub children: Vec<Chapter>,
+}
+
+/// Returns the path segments a page is filed under, e.g. `["guide", "setup"]` for `/guide/setup.html`.
+///
+/// `.html` and `.htm` are dropped from the last segment, and an `index` page
+/// is filed under its directory.
+pub(super) fn page_segments(url: &Url) -> Vec<String> {
+    let mut segments: Vec<String> = url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    // `/guide/index.html` and `/guide/setup.html` are the pages `/guide` and `/guide/setup`
+    if let Some(last) = segments.pop() {
+        let stem = last.strip_suffix(".html").or_else(|| last.strip_suffix(".htm")).unwrap_or(&last);
+        if stem != "index" {
+            segments.push(stem.to_string());
+        }
+    }
+    segments
+}
+
+/// Returns whether the pages come from more than one host, which then make up the top level.
+pub(super) fn several_hosts(urls: &[&str]) -> bool {
+    let hosts: BTreeSet<&str> = urls
+        .iter()
+        .filter_map(|url| url.split_once("://").map(|(_, rest)| rest.split('/').next().unwrap_or(rest)))
+        .collect();
+    hosts.len() > 1
+}
+
+/// Arranges pages into chapters following their URL paths.
+///
+/// The chapters refer to the pages by their index in `urls`. The hosts are
+/// the top level when the pages come from several of them.
+pub(super) fn chapters(urls: &[&str]) -> Chapter {
+    let by_host = several_hosts(urls);
+    let mut root = Chapter::default();
+    for (index, url) in urls.iter().enumerate() {
+        let Ok(url) = Url::parse(url) else {
+            continue;
+        };
+        let mut segments = page_segments(&url);
+        if by_host {
+            segments.insert(0, url.host_str().unwrap_or_default().to_string());
+        }
+
+        let mut chapter = &mut root;
+        for segment in segments {
+            let position = match chapter.children.iter().position(|child| child.name == segment) {
+                Some(position) => position,
+                None => {
+                    chapter.children.push(Chapter {
+                        name: segment,
+                        ..Default::default()
+                    });
+                    chapter.children.len() - 1
+                }
+            };
+            chapter = &mut chapter.children[position];
+        }
+        // Two URLs with the same path, such as with and without a query, become siblings
+        if chapter.page.is_some() {
+            let name = chapter.name.clone();
+            chapter.children.push(Chapter {
+                name,
+                page: Some(index),
+                children: Vec::new(),
+            });
+        } else {
+            chapter.page = Some(index);
+        }
+    }
+    root
+}
+
+/// Returns the title of a chapter named after a path segment, e.g. `getting-started` becomes `Getting started`.
+pub(super) fn chapter_title(segment: &str) -> String {
+    let words = segment.replace(['-', '_'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+/// Copies the screenshots and assets shown by the pages from the output directory into a project.
+///
+/// Files that can't be copied are reported and skipped.
+pub(super) fn copy_media<'a>(output_dir: &Path, target_dir: &Path, media: impl IntoIterator<Item = &'a MediaFile>) {
+    let files: BTreeSet<&str> = media.into_iter().map(|file| file.file.as_str()).collect();
+    for file in files {
+        let target = target_dir.join(file);
+        if let Some(parent) = target.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                warn!("Failed to create directory {}: {}", parent.display(), e);
+                continue;
+            }
+        }
+        if let Err(e) = fs::copy(output_dir.join(file), &target) {
+            warn!("Failed to copy {} into {}: {}", file, target_dir.display(), e);
+        }
+    }
+}
+
+/// Finds the scraped page a link points to, with or without a trailing slash.
+pub(super) fn lookup(pages_by_url: &HashMap<String, usize>, url: &Url) -> Option<usize> {
+    let url = url.as_str();
+    let other = match url.strip_suffix('/') {
+        Some(stripped) => stripped.to_string(),
+        None => format!("{}/", url),
+    };
+    pages_by_url.get(url).or_else(|| pages_by_url.get(&other)).copied()
+}
+
+/// Rewrites the links of a page's markdown, leaving those inside fenced code blocks alone.
+///
+/// `replace` is given each link's text, its target and whether it is an
+/// image, and returns what replaces the whole link, the `!` of an image
+/// included, or `None` to keep it.
+pub(super) fn rewrite_links(markdown: &str, mut replace: impl FnMut(&str, &str, bool) -> Option<String>) -> String {
+    let mut rewritten = String::with_capacity(markdown.len());
+    let mut fence: Option<Fence> = None;
+    for line in markdown.split_inclusive('\n') {
+        if let Some(open) = fence {
+            if open.closes(line) {
+                fence = None;
+            }
+            rewritten.push_str(line);
+            continue;
+        }
+        fence = Fence::open(line);
+        if fence.is_some() {
+            rewritten.push_str(line);
+            continue;
+        }
+
+        let mut last = 0;
+        for capture in MARKDOWN_LINK.captures_iter(line) {
+            let link = capture.get(0).unwrap();
+            let image = line[..link.start()].ends_with('!');
+            let Some(replacement) = replace(&capture[1], &capture[2], image) else {
+                continue;
+            };
+            // The `!` of an image is part of the link
+            let start = if image { link.start() - 1 } else { link.start() };
+            rewritten.push_str(&line[last..start]);
+            rewritten.push_str(&replacement);
+            last = link.end();
+        }
+        rewritten.push_str(&line[last..]);
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(url: &str) -> Vec<String> {
+        page_segments(&Url::parse(url).unwrap())
+    }
+
+    /// Lists the chapters one per line, indented by depth, as `name` or `name=page`.
+    fn outline(chapter: &Chapter) -> Vec<String> {
+        fn walk(chapter: &Chapter, depth: usize, lines: &mut Vec<String>) {
+            for child in &chapter.children {
+                let page = child.page.map(|page| format!("={}", page)).unwrap_or_default();
+                lines.push(format!("{}{}{}", "  ".repeat(depth), child.name, page));
+                walk(child, depth + 1, lines);
+            }
+        }
+        let mut lines = Vec::new();
+        walk(chapter, 0, &mut lines);
+        lines
+    }
+
+    #[test]
+    fn page_segments_follow_the_url_path() {
+        assert_eq!(segments("https://example.com/guide/setup.html"), ["guide", "setup"]);
+        assert_eq!(segments("https://example.com/guide/setup.htm"), ["guide", "setup"]);
+        assert_eq!(segments("https://example.com/guide/index.html"), ["guide"]);
+        assert_eq!(segments("https://example.com/guide/index"), ["guide"]);
+        assert_eq!(segments("https://example.com/guide//setup/"), ["guide", "setup"]);
+        assert_eq!(segments("https://example.com/api/v1.2?tab=x"), ["api", "v1.2"]);
+        assert!(segments("https://example.com/").is_empty());
+        assert!(segments("https://example.com/index.html").is_empty());
+    }
+
+    #[test]
+    fn chapters_nest_pages_below_their_parents() {
+        let urls = [
+            "https://example.com/",
+            "https://example.com/guide",
+            "https://example.com/guide/setup",
+            "https://example.com/api/users/list",
+            "https://example.com/guide/index.html",
+        ];
+        let root = chapters(&urls);
+        assert_eq!(root.page, Some(0));
+        assert_eq!(
+            outline(&root),
+            ["guide=1", "  setup=2", "  guide=4", "api", "  users", "    list=3"]
+        );
+    }
+
+    #[test]
+    fn chapters_start_with_the_hosts_when_there_are_several() {
+        let urls = [
+            "https://docs.example.com/guide",
+            "https://api.example.com/",
+            "https://docs.example.com/",
+            "not a url",
+        ];
+        let root = chapters(&urls);
+        assert_eq!(root.page, None);
+        assert_eq!(outline(&root), ["docs.example.com=2", "  guide=0", "api.example.com=1"]);
+    }
+
+    #[test]
+    fn chapter_titles_are_made_from_segments() {
+        assert_eq!(chapter_title("getting-started"), "Getting started");
+        assert_eq!(chapter_title("api_reference"), "Api reference");
+        assert_eq!(chapter_title("über"), "Über");
+        assert_eq!(chapter_title(""), "");
+    }
+
+    #[test]
+    fn links_in_code_blocks_are_left_alone() {
+        let markdown = "[a](a.md)\n````\n```\n[b](b.md)\n````\n[c](c.md)\n~~~\n[d](d.md)\n~~~\n![e](e.png)\n";
+        let rewritten = rewrite_links(markdown, |text, target, image| {
+            Some(format!("{}[{}]({})", if image { "!" } else { "" }, text.to_uppercase(), target))
+        });
+        assert_eq!(rewritten, "[A](a.md)\n````\n```\n[b](b.md)\n````\n[C](c.md)\n~~~\n[d](d.md)\n~~~\n![E](e.png)\n");
+    }
+}
@@ -0,0 +1,136 @@
+//! Change feed exporter: only the pages that changed since the previous run.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::manifest::ManifestEntry;
+use crate::page::PageChange;
+
+use super::{ExportPage, Exporter};
+
+/// File name of the change feed inside the output directory.
+pub const CHANGES_FILE_NAME: &str = "changes.jsonl";
+
+/// A single line of the change feed.
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum ChangeRecord<'a> {
+    /// A page that was added or whose content changed; sinks insert or replace it
+    #[serde(rename_all = "camelCase")]
+    Upsert {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<&'a str>,
+        url: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<&'a str>,
+        file: &'a str,
+        /// File the page was saved to before, when it moved; sinks keyed by file rename it
+        #[serde(skip_serializing_if = "Option::is_none")]
+        previous_file: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content_hash: Option<&'a str>,
+        markdown: &'a str,
+        scraped_at: DateTime<Utc>,
+    },
+    /// A page that was removed from the site; sinks delete it
+    #[serde(rename_all = "camelCase")]
+    Delete {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<&'a str>,
+        url: &'a str,
+        file: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        removed_at: Option<DateTime<Utc>>,
+    },
+}
+
+/// Writes the pages added, changed or removed in this run to `changes.jsonl`.
+///
+/// Pages whose content hash is unchanged since the previous run are left out,
+/// so a downstream sink (a database, a vector store, a wiki) applying the feed
+/// only updates what changed instead of importing every page again. Lines are
+/// flushed as they are written, like the JSON Lines export.
+pub struct ChangesExporter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    upserts: usize,
+    deletes: usize,
+}
+
+impl ChangesExporter {
+    /// Creates (or truncates) `changes.jsonl` in the output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created
+    pub fn create(output_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = output_dir.join(CHANGES_FILE_NAME);
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        Ok(ChangesExporter {
+            path,
+            writer: BufWriter::new(file),
+            upserts: 0,
+            deletes: 0,
+        })
+    }
+
+    /// Appends one record to the feed.
+    fn write(&mut self, record: &ChangeRecord) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Exporter for ChangesExporter {
+    fn name(&self) -> &str {
+        "Changes"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        if page.change == PageChange::Unchanged {
+            return Ok(());
+        }
+        self.write(&ChangeRecord::Upsert {
+            id: page.entry.id.as_deref(),
+            url: page.url,
+            title: page.entry.title.as_deref(),
+            file: &page.entry.file,
+            previous_file: page.previous_file,
+            content_hash: page.entry.content_hash.as_deref(),
+            markdown: page.markdown,
+            scraped_at: page.entry.scraped_at,
+        })?;
+        self.upserts += 1;
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        self.write(&ChangeRecord::Delete {
+            id: entry.id.as_deref(),
+            url,
+            file: &entry.file,
+            removed_at: entry.removed.as_ref().map(|removal| removal.removed_at),
+        })?;
+        self.deletes += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        info!(
+            "Exported {} changed and {} removed pages to: {}",
+            self.upserts,
+            self.deletes,
+            self.path.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! Docusaurus exporter: the scraped pages as docs and a sidebar to drop into a Docusaurus site.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use url::Url;
+
+use crate::frontmatter::escape_yaml_string;
+use crate::manifest::ManifestEntry;
+use crate::media::MediaFile;
+
+use super::book::{self, Chapter};
+use super::{ExportPage, Exporter};
+
+/// Name of the directory inside the output directory holding `docs/` and `sidebars.js`.
+pub const DOCUSAURUS_DIR_NAME: &str = "docusaurus";
+
+/// A page kept until the docs are written.
+struct DocPage {
+    url: String,
+    title: String,
+    file: String,
+    markdown: String,
+    media: Vec<MediaFile>,
+}
+
+/// Writes the pages as Docusaurus docs in `docusaurus/`: `docs/` with the
+/// pages, given Docusaurus frontmatter, and the screenshots and assets they
+/// show, and a `sidebars.js` nesting the pages like the site's URL paths.
+///
+/// Every page keeps its path relative to the output directory, which makes
+/// its doc id, and gets the path of its URL as its `slug`, so the site serves
+/// it at the same path as the original. Positions and the sidebar need every
+/// page, so the docs are written when the run finishes; `docs/` and
+/// `sidebars.js` are written anew each time. An interrupted run leaves the
+/// previous docs in place.
+pub struct DocusaurusExporter {
+    output_dir: PathBuf,
+    dir: PathBuf,
+    pages: Vec<DocPage>,
+}
+
+impl DocusaurusExporter {
+    /// Creates an exporter writing the docs into the output directory.
+    pub fn new(output_dir: &Path) -> Self {
+        DocusaurusExporter {
+            output_dir: output_dir.to_path_buf(),
+            dir: output_dir.join(DOCUSAURUS_DIR_NAME),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Returns the doc id of a page: its file's path without the extension.
+    fn doc_id(&self, index: usize) -> String {
+        let file = &self.pages[index].file;
+        file.strip_suffix(".md").unwrap_or(file).to_string()
+    }
+
+    /// Returns the URL path a page is served at, the host first when the pages come from several hosts.
+    fn slug(&self, index: usize, by_host: bool) -> String {
+        let Ok(url) = Url::parse(&self.pages[index].url) else {
+            return format!("/{}", self.doc_id(index));
+        };
+        let mut segments = book::page_segments(&url);
+        if by_host {
+            segments.insert(0, url.host_str().unwrap_or_default().to_string());
+        }
+        format!("/{}", segments.join("/"))
+    }
+
+    /// Numbers the pages of a chapter's sub-chapters in sidebar order, from `first` on.
+    fn positions(chapter: &Chapter, first: usize, positions: &mut [usize]) {
+        for (position, child) in (first..).zip(&chapter.children) {
+            if let Some(index) = child.page {
+                positions[index] = position;
+            }
+            Self::positions(child, 1, positions);
+        }
+    }
+
+    /// Renders `sidebars.js` with a single `docs` sidebar.
+    fn sidebars(&self, root: &Chapter) -> String {
+        let mut items = String::new();
+        if let Some(home) = root.page {
+            items.push_str(&format!("    {},\n", js_string(&self.doc_id(home))));
+        }
+        for chapter in &root.children {
+            self.push_item(&mut items, chapter, 2);
+        }
+        format!(
+            "// Generated from the site's URL paths; written anew by every scraper run.\n\
+             /** @type {{import('@docusaurus/plugin-content-docs').SidebarsConfig}} */\n\
+             module.exports = {{\n  docs: [\n{}  ],\n}};\n",
+            items
+        )
+    }
+
+    /// Adds a chapter to the sidebar: its doc, or a category of its sub-chapters linking to its doc.
+    ///
+    /// A chapter without a page of its own becomes a category without a link.
+    fn push_item(&self, items: &mut String, chapter: &Chapter, depth: usize) {
+        let indent = "  ".repeat(depth);
+        if chapter.children.is_empty() {
+            if let Some(index) = chapter.page {
+                items.push_str(&format!("{}{},\n", indent, js_string(&self.doc_id(index))));
+            }
+            return;
+        }
+        let (label, link) = match chapter.page {
+            Some(index) => (
+                self.pages[index].title.clone(),
+                format!("{}  link: {{ type: \"doc\", id: {} }},\n", indent, js_string(&self.doc_id(index))),
+            ),
+            None => (book::chapter_title(&chapter.name), String::new()),
+        };
+        items.push_str(&format!(
+            "{}{{\n{}  type: \"category\",\n{}  label: {},\n{}{}  items: [\n",
+            indent,
+            indent,
+            indent,
+            js_string(&label),
+            link,
+            indent
+        ));
+        for child in &chapter.children {
+            self.push_item(items, child, depth + 2);
+        }
+        items.push_str(&format!("{}  ],\n{}}},\n", indent, indent));
+    }
+}
+
+impl Exporter for DocusaurusExporter {
+    fn name(&self) -> &str {
+        "Docusaurus"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        self.pages.push(DocPage {
+            url: page.url.to_string(),
+            title: page.entry.title.clone().unwrap_or_else(|| page.url.to_string()),
+            file: page.entry.file.clone(),
+            markdown: page.markdown.to_string(),
+            media: page.entry.media.clone(),
+        });
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, _entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        self.pages.retain(|page| page.url != url);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.sort_by(|a, b| a.url.cmp(&b.url));
+        let urls: Vec<&str> = self.pages.iter().map(|page| page.url.as_str()).collect();
+        let by_host = book::several_hosts(&urls);
+        let root = book::chapters(&urls);
+        let mut positions = vec![1; self.pages.len()];
+        // The home page comes first, ahead of the top-level chapters
+        Self::positions(&root, if root.page.is_some() { 2 } else { 1 }, &mut positions);
+
+        // The pages of this run replace those written before, so removed pages don't linger
+        let docs = self.dir.join("docs");
+        if docs.exists() {
+            fs::remove_dir_all(&docs).map_err(|e| format!("Failed to clear {}: {}", docs.display(), e))?;
+        }
+        for (index, page) in self.pages.iter().enumerate() {
+            let path = docs.join(&page.file);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+            }
+            // The id is the file name only: Docusaurus prefixes it with the file's directory itself
+            let id = Path::new(&page.file)
+                .file_stem()
+                .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+            let content = format!(
+                "---\nid: \"{}\"\ntitle: \"{}\"\nslug: \"{}\"\nsidebar_position: {}\n---\n\n{}",
+                escape_yaml_string(&id),
+                escape_yaml_string(&page.title),
+                escape_yaml_string(&self.slug(index, by_host)),
+                positions[index],
+                page.markdown
+            );
+            fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        book::copy_media(&self.output_dir, &docs, self.pages.iter().flat_map(|page| &page.media));
+
+        let sidebars = self.dir.join("sidebars.js");
+        fs::write(&sidebars, self.sidebars(&root))
+            .map_err(|e| format!("Failed to write {}: {}", sidebars.display(), e))?;
+
+        info!("Wrote {} pages as Docusaurus docs to: {}", self.pages.len(), self.dir.display());
+        Ok(())
+    }
+}
+
+/// Quotes a value as a JavaScript string literal.
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "''".to_string())
+}
@@ -0,0 +1,262 @@
+//! Hugo exporter: the scraped pages as page bundles in a Hugo `content/` directory.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use percent_encoding::percent_decode_str;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::frontmatter::escape_yaml_string;
+use crate::manifest::ManifestEntry;
+use crate::media::MediaFile;
+
+use super::book::{self, Chapter};
+use super::{ExportPage, Exporter};
+
+/// Name of the directory inside the output directory holding `content/`.
+pub const HUGO_DIR_NAME: &str = "hugo";
+
+/// Characters left out of bundle directory names, as they don't belong in a path segment.
+const FORBIDDEN: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|', '#', '%'];
+
+/// A page kept until the bundles are written.
+struct BundlePage {
+    url: String,
+    title: String,
+    file: String,
+    scraped_at: DateTime<Utc>,
+    markdown: String,
+    media: Vec<MediaFile>,
+}
+
+/// Where a page is written in `content/`.
+struct Bundle {
+    /// Bundle directory relative to `content/`, empty for the home page
+    dir: String,
+    /// `_index.md` for a section with pages below it, `index.md` otherwise
+    file: &'static str,
+    /// Last path segment of the page's URL, empty for the home page
+    slug: String,
+    /// Position of the page among the pages of its section
+    weight: usize,
+}
+
+/// Writes the pages as Hugo page bundles in `hugo/content/`: a page with
+/// pages below it becomes a branch bundle (`_index.md`), every other page a
+/// leaf bundle (`index.md`), in directories following the site's URL paths,
+/// so the top-level directories are Hugo's sections.
+///
+/// Every page gets the Hugo frontmatter `title`, `date`, `slug` and `weight`,
+/// the screenshots and assets it shows are copied into its bundle as page
+/// resources, and links between scraped pages become `relref` shortcodes.
+/// The bundles need every page, so they are written when the run finishes;
+/// `content/` is written anew each time. An interrupted run leaves the
+/// previous content in place.
+pub struct HugoExporter {
+    output_dir: PathBuf,
+    dir: PathBuf,
+    pages: Vec<BundlePage>,
+}
+
+impl HugoExporter {
+    /// Creates an exporter writing the bundles into the output directory.
+    pub fn new(output_dir: &Path) -> Self {
+        HugoExporter {
+            output_dir: output_dir.to_path_buf(),
+            dir: output_dir.join(HUGO_DIR_NAME),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Places the pages of a chapter's sub-chapters in bundles below `dir`.
+    ///
+    /// Sections without a page of their own are collected in `sections`, so
+    /// they can be given an `_index.md` listing their pages.
+    fn place(chapter: &Chapter, dir: &str, bundles: &mut [Option<Bundle>], sections: &mut Vec<(String, String)>) {
+        let mut names = HashSet::new();
+        for (weight, child) in (1..).zip(&chapter.children) {
+            let segment = percent_decode_str(&child.name).decode_utf8_lossy().into_owned();
+            let child_dir = format!("{}{}", dir, unique_name(&segment, &mut names));
+            let branch = !child.children.is_empty();
+            match child.page {
+                Some(index) => {
+                    bundles[index] = Some(Bundle {
+                        dir: child_dir.clone(),
+                        file: if branch { "_index.md" } else { "index.md" },
+                        slug: segment,
+                        weight,
+                    });
+                }
+                None => sections.push((child_dir.clone(), book::chapter_title(&segment))),
+            }
+            Self::place(child, &format!("{}/", child_dir), bundles, sections);
+        }
+    }
+
+    /// Returns a page's markdown with links to scraped pages as `relref` shortcodes and its media as page resources.
+    fn rewrite_links(&self, index: usize, bundles: &[Bundle], pages_by_url: &HashMap<String, usize>) -> String {
+        let page = &self.pages[index];
+        let base = Url::parse(&page.url).ok();
+        book::rewrite_links(&page.markdown, |text, target, image| {
+            let prefix = if image { "!" } else { "" };
+            // Media is copied into the bundle under its path in the output directory
+            if let Some(media) = page.media.iter().find(|media| media.path_from(&page.file) == target) {
+                return Some(format!("{}[{}]({})", prefix, text, media.file));
+            }
+            if image {
+                return None;
+            }
+            let mut url = base.as_ref()?.join(target).ok()?;
+            let fragment = url.fragment().map(|fragment| format!("#{}", fragment)).unwrap_or_default();
+            url.set_fragment(None);
+            let linked = &bundles[book::lookup(pages_by_url, &url)?];
+            let path = if linked.dir.is_empty() {
+                format!("/{}", linked.file)
+            } else {
+                format!("/{}/{}", linked.dir, linked.file)
+            };
+            Some(format!("[{}]({{{{< relref \"{}{}\" >}}}})", text, path, fragment))
+        })
+    }
+}
+
+impl Exporter for HugoExporter {
+    fn name(&self) -> &str {
+        "Hugo"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        self.pages.push(BundlePage {
+            url: page.url.to_string(),
+            title: page.entry.title.clone().unwrap_or_else(|| page.url.to_string()),
+            file: page.entry.file.clone(),
+            scraped_at: page.entry.scraped_at,
+            markdown: page.markdown.to_string(),
+            media: page.entry.media.clone(),
+        });
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, _entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        self.pages.retain(|page| page.url != url);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.sort_by(|a, b| a.url.cmp(&b.url));
+        let urls: Vec<&str> = self.pages.iter().map(|page| page.url.as_str()).collect();
+        let root = book::chapters(&urls);
+
+        let mut placed: Vec<Option<Bundle>> = self.pages.iter().map(|_| None).collect();
+        let mut sections = Vec::new();
+        Self::place(&root, "", &mut placed, &mut sections);
+        let bundles: Vec<Bundle> = placed
+            .into_iter()
+            .map(|bundle| {
+                // Only the home page is left unplaced
+                bundle.unwrap_or(Bundle {
+                    dir: String::new(),
+                    file: "_index.md",
+                    slug: String::new(),
+                    weight: 0,
+                })
+            })
+            .collect();
+        let pages_by_url: HashMap<String, usize> = self
+            .pages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, page)| Some((Url::parse(&page.url).ok()?.to_string(), index)))
+            .collect();
+
+        // The bundles of this run replace those written before, so removed pages don't linger
+        let content = self.dir.join("content");
+        if content.exists() {
+            fs::remove_dir_all(&content).map_err(|e| format!("Failed to clear {}: {}", content.display(), e))?;
+        }
+        for (index, (page, bundle)) in self.pages.iter().zip(&bundles).enumerate() {
+            let dir = content.join(&bundle.dir);
+            fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+            let mut frontmatter = format!(
+                "---\ntitle: \"{}\"\ndate: {}\n",
+                escape_yaml_string(&page.title),
+                page.scraped_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+            );
+            if !bundle.dir.is_empty() {
+                frontmatter.push_str(&format!(
+                    "slug: \"{}\"\nweight: {}\n",
+                    escape_yaml_string(&bundle.slug),
+                    bundle.weight
+                ));
+            }
+            let path = dir.join(bundle.file);
+            let body = self.rewrite_links(index, &bundles, &pages_by_url);
+            fs::write(&path, format!("{}---\n\n{}", frontmatter, body))
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            book::copy_media(&self.output_dir, &dir, &page.media);
+        }
+        // A section needs an `_index.md` to list its pages, even when the site had no page at its path
+        for (dir, title) in &sections {
+            let path = content.join(dir).join("_index.md");
+            if let Err(e) = fs::write(&path, format!("---\ntitle: \"{}\"\n---\n", escape_yaml_string(title))) {
+                warn!("Failed to write {}: {}", path.display(), e);
+            }
+        }
+
+        info!("Wrote {} pages as Hugo page bundles to: {}", self.pages.len(), content.display());
+        Ok(())
+    }
+}
+
+/// Returns a bundle directory name for a path segment not yet used in its section, numbering repeated segments.
+fn unique_name(segment: &str, names: &mut HashSet<String>) -> String {
+    let base = segment.replace(FORBIDDEN, "-");
+    let base = match base.trim().trim_start_matches('.') {
+        "" => "page".to_string(),
+        trimmed => trimmed.to_string(),
+    };
+    let mut name = base.clone();
+    let mut count = 1;
+    // Names are compared without case, as file names are on macOS and Windows
+    while !names.insert(name.to_lowercase()) {
+        count += 1;
+        name = format!("{}-{}", base, count);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_leave_out_forbidden_characters() {
+        let mut names = HashSet::new();
+        assert_eq!(unique_name("a:b*c?d", &mut names), "a-b-c-d");
+        assert_eq!(unique_name("50%", &mut names), "50-");
+        assert_eq!(unique_name("what's new", &mut names), "what's new");
+    }
+
+    #[test]
+    fn empty_and_hidden_names_become_page() {
+        let mut names = HashSet::new();
+        assert_eq!(unique_name("", &mut names), "page");
+        assert_eq!(unique_name("..", &mut names), "page-2");
+        assert_eq!(unique_name(" ", &mut names), "page-3");
+        assert_eq!(unique_name(".config", &mut names), "config");
+    }
+
+    #[test]
+    fn repeated_names_are_numbered_ignoring_case() {
+        let mut names = HashSet::new();
+        assert_eq!(unique_name("Setup", &mut names), "Setup");
+        assert_eq!(unique_name("setup", &mut names), "setup-2");
+        assert_eq!(unique_name("SETUP", &mut names), "SETUP-3");
+        assert_eq!(unique_name("setup-2", &mut names), "setup-2-2");
+        // Each section has names of its own
+        assert_eq!(unique_name("setup", &mut HashSet::new()), "setup");
+    }
+}
@@ -0,0 +1,123 @@
+//! JSON Lines exporter: one JSON object per page.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::anchors::{SlugStyle, Slugger};
+use crate::markdown;
+use crate::media::MediaFile;
+
+use super::{ExportPage, Exporter};
+
+/// File name of the JSON Lines export inside the output directory.
+pub const JSONL_FILE_NAME: &str = "pages.jsonl";
+
+/// A single line of the JSON Lines export.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonlRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<&'a str>,
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    file: &'a str,
+    markdown: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headings: Vec<HeadingRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_code: Option<i32>,
+    scraped_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    media: &'a [MediaFile],
+}
+
+/// A heading of a page with its anchor id.
+#[derive(Debug, Serialize)]
+struct HeadingRecord {
+    level: usize,
+    text: String,
+    anchor: String,
+}
+
+/// Writes every page as one line of JSON to `pages.jsonl`.
+///
+/// Each line is flushed as soon as it is written, so the file is valid JSON
+/// Lines up to the last completed page even if the run is interrupted.
+pub struct JsonlExporter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    slug_style: SlugStyle,
+}
+
+impl JsonlExporter {
+    /// Creates (or truncates) `pages.jsonl` in the output directory.
+    ///
+    /// Heading anchors are generated with `slug_style`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created
+    pub fn create(output_dir: &Path, slug_style: SlugStyle) -> Result<Self, Box<dyn Error>> {
+        let path = output_dir.join(JSONL_FILE_NAME);
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        Ok(JsonlExporter {
+            path,
+            writer: BufWriter::new(file),
+            slug_style,
+        })
+    }
+}
+
+impl Exporter for JsonlExporter {
+    fn name(&self) -> &str {
+        "JSONL"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        let mut slugger = Slugger::new(self.slug_style);
+        let headings = markdown::headings(page.markdown)
+            .into_iter()
+            .map(|heading| HeadingRecord {
+                anchor: slugger.slug(&heading.text),
+                level: heading.level,
+                text: heading.text,
+            })
+            .collect();
+
+        let record = JsonlRecord {
+            id: page.entry.id.as_deref(),
+            url: page.url,
+            title: page.entry.title.as_deref(),
+            file: &page.entry.file,
+            markdown: page.markdown,
+            headings,
+            description: page.metadata.and_then(|m| m.description.as_deref()),
+            language: page.metadata.and_then(|m| m.language.as_deref()),
+            status_code: page.metadata.and_then(|m| m.status_code),
+            scraped_at: page.entry.scraped_at,
+            media: &page.entry.media,
+        };
+
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        info!("Exported pages to: {}", self.path.display());
+        Ok(())
+    }
+}
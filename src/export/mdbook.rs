@@ -0,0 +1,161 @@
+//! mdBook exporter: the scraped pages as a book project that `mdbook` can build.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use url::Url;
+
+use crate::manifest::ManifestEntry;
+use crate::media::MediaFile;
+
+use super::book::{self, Chapter};
+use super::single_file::escape_link_text;
+use super::{ExportPage, Exporter};
+
+/// Name of the book project directory inside the output directory.
+pub const MDBOOK_DIR_NAME: &str = "mdbook";
+
+/// A page kept until the book is written.
+struct BookPage {
+    url: String,
+    title: String,
+    file: String,
+    markdown: String,
+    media: Vec<MediaFile>,
+}
+
+/// Writes the pages as an mdBook project in `mdbook/`: `book.toml`, and `src/`
+/// with the pages, the screenshots and assets they show, and a `SUMMARY.md`
+/// nesting the pages like the site's URL paths.
+///
+/// Pages keep their paths relative to the output directory, so links between
+/// them and to their assets work in the book. The table of contents needs
+/// every page, so the book is written when the run finishes; `src/` is
+/// written anew each time, while an existing `book.toml` is left as it is so
+/// it can be customized. An interrupted run leaves the previous book in place.
+pub struct MdbookExporter {
+    output_dir: PathBuf,
+    dir: PathBuf,
+    pages: Vec<BookPage>,
+}
+
+impl MdbookExporter {
+    /// Creates an exporter writing the book project into the output directory.
+    pub fn new(output_dir: &Path) -> Self {
+        MdbookExporter {
+            output_dir: output_dir.to_path_buf(),
+            dir: output_dir.join(MDBOOK_DIR_NAME),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Returns the book's title: the title of the site's home page, or its hosts without one.
+    fn title(&self) -> String {
+        let home = self.pages.iter().find(|page| Url::parse(&page.url).is_ok_and(|url| url.path() == "/"));
+        if let Some(home) = home {
+            return home.title.clone();
+        }
+        let hosts: BTreeSet<String> = self
+            .pages
+            .iter()
+            .filter_map(|page| Url::parse(&page.url).ok()?.host_str().map(str::to_string))
+            .collect();
+        hosts.into_iter().collect::<Vec<_>>().join(", ")
+    }
+
+    /// Renders the table of contents, the home page first as a prefix chapter.
+    fn summary(&self) -> String {
+        let urls: Vec<&str> = self.pages.iter().map(|page| page.url.as_str()).collect();
+        let root = book::chapters(&urls);
+        let mut summary = String::from("# Summary\n\n");
+        if let Some(home) = root.page {
+            summary.push_str(&format!("{}\n\n", self.link(home)));
+        }
+        for chapter in &root.children {
+            self.push_chapter(&mut summary, chapter, 0);
+        }
+        summary
+    }
+
+    /// Adds a chapter and its sub-chapters to the table of contents.
+    ///
+    /// A chapter without a page of its own becomes a draft chapter, which mdBook lists without a link.
+    fn push_chapter(&self, summary: &mut String, chapter: &Chapter, depth: usize) {
+        let entry = match chapter.page {
+            Some(index) => self.link(index),
+            None => format!("[{}]()", escape_link_text(&book::chapter_title(&chapter.name))),
+        };
+        summary.push_str(&format!("{}- {}\n", "    ".repeat(depth), entry));
+        for child in &chapter.children {
+            self.push_chapter(summary, child, depth + 1);
+        }
+    }
+
+    /// Returns the table of contents link to a page.
+    fn link(&self, index: usize) -> String {
+        let page = &self.pages[index];
+        let target = if page.file.contains(' ') {
+            format!("<{}>", page.file)
+        } else {
+            page.file.clone()
+        };
+        format!("[{}]({})", escape_link_text(&page.title), target)
+    }
+}
+
+impl Exporter for MdbookExporter {
+    fn name(&self) -> &str {
+        "mdBook"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        self.pages.push(BookPage {
+            url: page.url.to_string(),
+            title: page.entry.title.clone().unwrap_or_else(|| page.url.to_string()),
+            file: page.entry.file.clone(),
+            markdown: page.markdown.to_string(),
+            media: page.entry.media.clone(),
+        });
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, _entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        self.pages.retain(|page| page.url != url);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.sort_by(|a, b| a.url.cmp(&b.url));
+
+        let book_toml = self.dir.join("book.toml");
+        if !book_toml.exists() {
+            fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to create directory {}: {}", self.dir.display(), e))?;
+            let title = self.title().replace('\\', "\\\\").replace('"', "\\\"");
+            fs::write(&book_toml, format!("[book]\ntitle = \"{}\"\nsrc = \"src\"\n\n[output.html]\n", title))
+                .map_err(|e| format!("Failed to write {}: {}", book_toml.display(), e))?;
+        }
+
+        // The pages of this run replace those of the previous book, so removed pages don't linger
+        let src = self.dir.join("src");
+        if src.exists() {
+            fs::remove_dir_all(&src).map_err(|e| format!("Failed to clear {}: {}", src.display(), e))?;
+        }
+        for page in &self.pages {
+            let path = src.join(&page.file);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+            }
+            fs::write(&path, &page.markdown).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        book::copy_media(&self.output_dir, &src, self.pages.iter().flat_map(|page| &page.media));
+        let summary = src.join("SUMMARY.md");
+        fs::write(&summary, self.summary()).map_err(|e| format!("Failed to write {}: {}", summary.display(), e))?;
+
+        info!("Wrote {} pages as an mdBook to: {}", self.pages.len(), self.dir.display());
+        Ok(())
+    }
+}
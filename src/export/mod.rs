@@ -0,0 +1,216 @@
+//! Exporters that turn scraped pages into other formats.
+//!
+//! Exporters are streaming: each page is handed to every active exporter as
+//! soon as it has been scraped, and exporters write it out immediately. A run
+//! that is interrupted part way through therefore still leaves usable partial
+//! exports behind.
+//!
+//! Exporters are also told how each page compares with the previous run and
+//! which pages were removed from the site, so exports feeding other systems can
+//! pass on only what changed.
+
+use clap::ValueEnum;
+use std::error::Error;
+use std::path::Path;
+use tracing::warn;
+
+use crate::anchors::SlugStyle;
+use crate::manifest::ManifestEntry;
+use crate::page::PageChange;
+use crate::Metadata;
+
+mod book;
+mod changes;
+mod docusaurus;
+mod hugo;
+mod jsonl;
+mod mdbook;
+mod obsidian;
+mod pdf;
+mod single_file;
+mod sqlite;
+
+pub use changes::ChangesExporter;
+pub use docusaurus::{DocusaurusExporter, DOCUSAURUS_DIR_NAME};
+pub use hugo::{HugoExporter, HUGO_DIR_NAME};
+pub use jsonl::JsonlExporter;
+pub use mdbook::{MdbookExporter, MDBOOK_DIR_NAME};
+pub use obsidian::{ObsidianExporter, OBSIDIAN_DIR_NAME};
+pub use pdf::PdfExporter;
+pub use single_file::SingleFileExporter;
+pub use sqlite::SqliteExporter;
+
+/// Export formats that can be selected with `--export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per page in `pages.jsonl`
+    Jsonl,
+    /// Only the pages added, changed or removed since the previous run, in `changes.jsonl`
+    Changes,
+    /// All pages combined into one markdown document with a table of contents, in `all-pages.md`
+    SingleFile,
+    /// Pages, their headings and every run in the SQLite database `pages.sqlite`
+    Sqlite,
+    /// All pages rendered into one paginated PDF with a cover page and table of contents, in `all-pages.pdf`
+    Pdf,
+    /// An mdBook project in `mdbook/` with the pages and a `SUMMARY.md` following the site's URL paths
+    Mdbook,
+    /// Docusaurus docs in `docusaurus/docs/` with Docusaurus frontmatter, and a `sidebars.js` following the
+    /// site's URL paths
+    Docusaurus,
+    /// An Obsidian vault in `obsidian/` with a note per page, links between pages as wikilinks and an index note
+    Obsidian,
+    /// Hugo page bundles in `hugo/content/` with Hugo frontmatter, in sections following the site's URL paths
+    Hugo,
+}
+
+impl ExportFormat {
+    /// Returns the heading anchor style of the renderer this format is usually consumed by.
+    pub fn default_slug_style(self) -> SlugStyle {
+        match self {
+            ExportFormat::Jsonl
+            | ExportFormat::Changes
+            | ExportFormat::SingleFile
+            | ExportFormat::Sqlite
+            | ExportFormat::Pdf
+            | ExportFormat::Obsidian
+            | ExportFormat::Hugo => SlugStyle::Github,
+            ExportFormat::Mdbook => SlugStyle::Mdbook,
+            ExportFormat::Docusaurus => SlugStyle::Docusaurus,
+        }
+    }
+}
+
+/// Parses `--slug-style` values of the form `FORMAT=STYLE`, e.g. `jsonl=mdbook`.
+///
+/// # Errors
+///
+/// Returns an error if the value has no `=` or names an unknown format or style
+pub fn parse_slug_style_override(value: &str) -> Result<(ExportFormat, SlugStyle), String> {
+    let (format, style) = value.split_once('=').ok_or("expected FORMAT=STYLE")?;
+    let format = ExportFormat::from_str(format.trim(), true).map_err(|e| format!("unknown format: {}", e))?;
+    let style = SlugStyle::from_str(style.trim(), true).map_err(|e| format!("unknown slug style: {}", e))?;
+    Ok((format, style))
+}
+
+/// A scraped page handed to exporters.
+#[derive(Debug)]
+pub struct ExportPage<'a> {
+    /// URL the page was scraped from
+    pub url: &'a str,
+    /// Manifest entry of the page (file name, title, scrape date)
+    pub entry: &'a ManifestEntry,
+    /// Markdown body of the page, without frontmatter
+    pub markdown: &'a str,
+    /// Metadata returned by the scrape; `None` when the page was unchanged and not downloaded again
+    pub metadata: Option<&'a Metadata>,
+    /// How the page compares with the previous run
+    pub change: PageChange,
+    /// File the page was saved to before this run, when it has been saved under a new name since
+    pub previous_file: Option<&'a str>,
+}
+
+/// A destination for scraped pages.
+pub trait Exporter: Send {
+    /// Short name of the exporter used in log messages.
+    fn name(&self) -> &str;
+
+    /// Writes a single page. Called once per page, as soon as it has been scraped.
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>>;
+
+    /// Records a page found removed from the site in this run. Pages still
+    /// removed since an earlier run are not passed again.
+    fn remove_page(&mut self, _url: &str, _entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Completes the export after the last page. Not called if the run is interrupted.
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// The set of exporters active for a run.
+#[derive(Default)]
+pub struct Exporters {
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl Exporters {
+    /// Opens an exporter for each requested format, writing into `output_dir`.
+    ///
+    /// Each exporter generates heading anchors in its format's default slug
+    /// style unless `slug_styles` overrides it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exporter cannot create its output
+    pub fn open(
+        formats: &[ExportFormat],
+        output_dir: &Path,
+        slug_styles: &[(ExportFormat, SlugStyle)],
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut exporters: Vec<Box<dyn Exporter>> = Vec::new();
+        for &format in formats {
+            let slug_style = slug_styles
+                .iter()
+                .rev()
+                .find(|(overridden, _)| *overridden == format)
+                .map_or(format.default_slug_style(), |(_, style)| *style);
+            match format {
+                ExportFormat::Jsonl => exporters.push(Box::new(JsonlExporter::create(output_dir, slug_style)?)),
+                ExportFormat::Changes => exporters.push(Box::new(ChangesExporter::create(output_dir)?)),
+                ExportFormat::SingleFile => exporters.push(Box::new(SingleFileExporter::new(output_dir, slug_style))),
+                ExportFormat::Sqlite => exporters.push(Box::new(SqliteExporter::open(output_dir, slug_style)?)),
+                ExportFormat::Pdf => exporters.push(Box::new(PdfExporter::new(output_dir))),
+                ExportFormat::Mdbook => exporters.push(Box::new(MdbookExporter::new(output_dir))),
+                ExportFormat::Docusaurus => exporters.push(Box::new(DocusaurusExporter::new(output_dir))),
+                ExportFormat::Obsidian => exporters.push(Box::new(ObsidianExporter::new(output_dir))),
+                ExportFormat::Hugo => exporters.push(Box::new(HugoExporter::new(output_dir))),
+            }
+        }
+        Ok(Exporters { exporters })
+    }
+
+    /// Hands a page to every exporter.
+    ///
+    /// A failing exporter is reported, in the span of the page being processed,
+    /// but does not prevent the others from receiving the page.
+    pub fn export_page(&mut self, page: &ExportPage) {
+        for exporter in &mut self.exporters {
+            if let Err(e) = exporter.export_page(page) {
+                warn!("{} export failed: {}", exporter.name(), e);
+            }
+        }
+    }
+
+    /// Tells every exporter about a page removed from the site.
+    ///
+    /// Failing exporters are reported like in `export_page`.
+    pub fn remove_page(&mut self, url: &str, entry: &ManifestEntry) {
+        for exporter in &mut self.exporters {
+            if let Err(e) = exporter.remove_page(url, entry) {
+                warn!("{} export failed: {}", exporter.name(), e);
+            }
+        }
+    }
+
+    /// Finishes every exporter.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered; all exporters are finished regardless
+    pub fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut first_error = None;
+        for exporter in &mut self.exporters {
+            if let Err(e) = exporter.finish() {
+                let message = format!("{} export failed to finish: {}", exporter.name(), e);
+                first_error.get_or_insert(message);
+            }
+        }
+        match first_error {
+            Some(message) => Err(message.into()),
+            None => Ok(()),
+        }
+    }
+}
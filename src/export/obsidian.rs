@@ -0,0 +1,290 @@
+//! Obsidian exporter: the scraped pages as a vault of notes linked with wikilinks.
+
+use percent_encoding::percent_decode_str;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use url::Url;
+
+use crate::anchors::{SlugStyle, Slugger};
+use crate::frontmatter::escape_yaml_string;
+use crate::manifest::ManifestEntry;
+use crate::markdown;
+use crate::media::MediaFile;
+
+use super::book::{self, Chapter};
+use super::{ExportPage, Exporter};
+
+/// Name of the vault directory inside the output directory.
+pub const OBSIDIAN_DIR_NAME: &str = "obsidian";
+
+/// Name of the vault's index note, listing every page.
+const INDEX_NOTE: &str = "Index";
+
+/// Characters Obsidian doesn't allow in note names or that break wikilinks.
+const FORBIDDEN: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|', '#', '^', '[', ']'];
+
+/// A page kept until the vault is written.
+struct VaultPage {
+    url: String,
+    title: String,
+    file: String,
+    markdown: String,
+    media: Vec<MediaFile>,
+}
+
+/// Where a page is written in the vault.
+struct Note {
+    /// Folder of the note relative to the vault, empty for the vault's root
+    folder: String,
+    /// Note name, unique across the vault, so a wikilink needs nothing else
+    name: String,
+}
+
+/// Writes the pages as an Obsidian vault in `obsidian/`: a note per page,
+/// named after its title, in folders following the site's sections, with
+/// links between scraped pages turned into `[[wikilinks]]` and an `Index`
+/// note listing every page.
+///
+/// Links to pages that weren't scraped stay as they are, and images saved
+/// with the pages become embeds. The wikilinks need the names of all pages,
+/// so the vault is written when the run finishes; notes are written anew
+/// each time, while the `.obsidian` settings folder is left alone. An
+/// interrupted run leaves the previous vault in place.
+pub struct ObsidianExporter {
+    output_dir: PathBuf,
+    dir: PathBuf,
+    pages: Vec<VaultPage>,
+}
+
+impl ObsidianExporter {
+    /// Creates an exporter writing the vault into the output directory.
+    pub fn new(output_dir: &Path) -> Self {
+        ObsidianExporter {
+            output_dir: output_dir.to_path_buf(),
+            dir: output_dir.join(OBSIDIAN_DIR_NAME),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Places every page in the vault: a section with pages below it becomes a
+    /// folder holding them and the section's own page.
+    fn place(&self, chapter: &Chapter, folder: &str, names: &mut HashSet<String>, notes: &mut [Option<Note>]) {
+        if let Some(index) = chapter.page {
+            notes[index] = Some(Note {
+                folder: folder.to_string(),
+                name: unique_name(&self.pages[index].title, names),
+            });
+        }
+        for child in &chapter.children {
+            let folder = if child.children.is_empty() {
+                folder.to_string()
+            } else {
+                let name = note_name(&percent_decode_str(&child.name).decode_utf8_lossy());
+                format!("{}{}/", folder, name)
+            };
+            self.place(child, &folder, names, notes);
+        }
+    }
+
+    /// Returns a page's markdown with links to scraped pages as wikilinks and its images as embeds.
+    ///
+    /// Links inside fenced code blocks are left alone.
+    fn rewrite_links(
+        &self,
+        index: usize,
+        notes: &[Note],
+        pages_by_url: &HashMap<String, usize>,
+        headings: &[HashMap<String, String>],
+    ) -> String {
+        let page = &self.pages[index];
+        let base = Url::parse(&page.url).ok();
+        book::rewrite_links(&page.markdown, |text, target, image| {
+            if image {
+                return page
+                    .media
+                    .iter()
+                    .find(|media| media.path_from(&page.file) == target)
+                    .map(|media| embed(&media.file, text));
+            }
+            let mut url = base.as_ref()?.join(target).ok()?;
+            let fragment = url.fragment().map(|fragment| percent_decode_str(fragment).decode_utf8_lossy().into_owned());
+            url.set_fragment(None);
+            let linked = book::lookup(pages_by_url, &url)?;
+            let heading = fragment.and_then(|fragment| headings[linked].get(&fragment).cloned());
+            Some(wikilink(&notes[linked].name, heading.as_deref(), text))
+        })
+    }
+
+    /// Renders the index note, listing the pages nested like the site's URL paths.
+    fn index_note(&self, root: &Chapter, notes: &[Note]) -> String {
+        let mut index = format!("# {}\n\n", INDEX_NOTE);
+        if let Some(home) = root.page {
+            index.push_str(&format!("{}\n\n", wikilink(&notes[home].name, None, "")));
+        }
+        for chapter in &root.children {
+            push_entry(&mut index, chapter, notes, 0);
+        }
+        index
+    }
+}
+
+impl Exporter for ObsidianExporter {
+    fn name(&self) -> &str {
+        "Obsidian"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        self.pages.push(VaultPage {
+            url: page.url.to_string(),
+            title: page.entry.title.clone().unwrap_or_else(|| page.url.to_string()),
+            file: page.entry.file.clone(),
+            markdown: page.markdown.to_string(),
+            media: page.entry.media.clone(),
+        });
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, _entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        self.pages.retain(|page| page.url != url);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.sort_by(|a, b| a.url.cmp(&b.url));
+        let urls: Vec<&str> = self.pages.iter().map(|page| page.url.as_str()).collect();
+        let root = book::chapters(&urls);
+
+        let mut names = HashSet::from([INDEX_NOTE.to_lowercase()]);
+        let mut placed: Vec<Option<Note>> = self.pages.iter().map(|_| None).collect();
+        self.place(&root, "", &mut names, &mut placed);
+        let notes: Vec<Note> = placed
+            .into_iter()
+            .zip(&self.pages)
+            .map(|(note, page)| {
+                note.unwrap_or_else(|| Note {
+                    folder: String::new(),
+                    name: unique_name(&page.title, &mut names),
+                })
+            })
+            .collect();
+
+        let pages_by_url: HashMap<String, usize> = self
+            .pages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, page)| Some((Url::parse(&page.url).ok()?.to_string(), index)))
+            .collect();
+        // Fragments of links are matched against the anchors the site most likely gave the headings
+        let headings: Vec<HashMap<String, String>> = self
+            .pages
+            .iter()
+            .map(|page| {
+                let mut slugger = Slugger::new(SlugStyle::Github);
+                markdown::headings(&page.markdown)
+                    .into_iter()
+                    .filter(|heading| !heading.text.contains(FORBIDDEN))
+                    .map(|heading| (slugger.slug(&heading.text), heading.text))
+                    .collect()
+            })
+            .collect();
+
+        // The notes of this run replace those written before, so removed pages don't linger
+        if self.dir.exists() {
+            for entry in fs::read_dir(&self.dir)? {
+                let path = entry?.path();
+                if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+                    continue;
+                }
+                let removed = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+                removed.map_err(|e| format!("Failed to clear {}: {}", path.display(), e))?;
+            }
+        }
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create directory {}: {}", self.dir.display(), e))?;
+
+        for (index, (page, note)) in self.pages.iter().zip(&notes).enumerate() {
+            let folder = self.dir.join(&note.folder);
+            fs::create_dir_all(&folder)
+                .map_err(|e| format!("Failed to create directory {}: {}", folder.display(), e))?;
+            let path = folder.join(format!("{}.md", note.name));
+            let content = format!(
+                "---\ntitle: \"{}\"\nurl: \"{}\"\n---\n\n{}",
+                escape_yaml_string(&page.title),
+                escape_yaml_string(&page.url),
+                self.rewrite_links(index, &notes, &pages_by_url, &headings)
+            );
+            fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        book::copy_media(&self.output_dir, &self.dir, self.pages.iter().flat_map(|page| &page.media));
+
+        let index = self.dir.join(format!("{}.md", INDEX_NOTE));
+        fs::write(&index, self.index_note(&root, &notes))
+            .map_err(|e| format!("Failed to write {}: {}", index.display(), e))?;
+
+        info!("Wrote {} pages as an Obsidian vault to: {}", self.pages.len(), self.dir.display());
+        Ok(())
+    }
+}
+
+/// Adds a chapter and its sub-chapters to the index note; a chapter without a page is listed by name.
+fn push_entry(index: &mut String, chapter: &Chapter, notes: &[Note], depth: usize) {
+    let entry = match chapter.page {
+        Some(page) => wikilink(&notes[page].name, None, ""),
+        None => book::chapter_title(&percent_decode_str(&chapter.name).decode_utf8_lossy()),
+    };
+    index.push_str(&format!("{}- {}\n", "    ".repeat(depth), entry));
+    for child in &chapter.children {
+        push_entry(index, child, notes, depth + 1);
+    }
+}
+
+/// Returns a note name for a title: the characters Obsidian doesn't allow replaced with `-`.
+fn note_name(title: &str) -> String {
+    let name = title.replace(FORBIDDEN, "-");
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns a note name for a title not yet used in the vault, numbering repeated titles.
+fn unique_name(title: &str, names: &mut HashSet<String>) -> String {
+    let base = note_name(title);
+    let mut name = base.clone();
+    let mut count = 1;
+    // Names are compared without case, as file names are on macOS and Windows
+    while !names.insert(name.to_lowercase()) {
+        count += 1;
+        name = format!("{} {}", base, count);
+    }
+    name
+}
+
+/// Returns a wikilink to a note, or to one of its headings, shown as `text` unless that is the note's name.
+fn wikilink(name: &str, heading: Option<&str>, text: &str) -> String {
+    let target = match heading {
+        Some(heading) => format!("{}#{}", name, heading),
+        None => name.to_string(),
+    };
+    let text = text.trim().replace("\\[", "[").replace("\\]", "]").replace(['|', '[', ']'], "");
+    if text.is_empty() || text == target {
+        format!("[[{}]]", target)
+    } else {
+        format!("[[{}|{}]]", target, text)
+    }
+}
+
+/// Returns the embed of a file saved with the pages, by its path in the vault.
+fn embed(file: &str, alt: &str) -> String {
+    let alt = alt.replace(['|', '[', ']'], "");
+    if alt.trim().is_empty() {
+        format!("![[{}]]", file)
+    } else {
+        format!("![[{}|{}]]", file, alt.trim())
+    }
+}
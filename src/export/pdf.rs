@@ -0,0 +1,593 @@
+//! PDF exporter: every page rendered into one paginated document.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::manifest::ManifestEntry;
+use crate::markdown::{self, Fence};
+use crate::pdf::{Document, Font, Page, Style, PAGE_HEIGHT, PAGE_WIDTH};
+
+use super::{ExportPage, Exporter};
+
+/// File name of the PDF inside the output directory.
+pub const PDF_FILE_NAME: &str = "all-pages.pdf";
+
+/// Left and right page margin, in points.
+const MARGIN: f32 = 56.0;
+
+/// Baseline of the first line of text on a page.
+const TOP: f32 = PAGE_HEIGHT - 80.0;
+
+/// Lowest baseline text is set on.
+const BOTTOM: f32 = 64.0;
+
+/// Width of the text column.
+const TEXT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+/// Height of a line of the table of contents.
+const CONTENTS_LINE: f32 = 16.0;
+
+/// Space the "Contents" heading takes on the first page of the table of contents.
+const CONTENTS_HEADING: f32 = 44.0;
+
+const BODY: Style = Style {
+    font: Font::Regular,
+    size: 10.5,
+    gray: 0.0,
+};
+
+const CODE: Style = Style {
+    font: Font::Mono,
+    size: 8.5,
+    gray: 0.15,
+};
+
+const SMALL: Style = Style {
+    font: Font::Regular,
+    size: 8.0,
+    gray: 0.45,
+};
+
+/// Image references, kept as their alt text.
+static IMAGE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!\[([^\]]*)\]\([^)]*\)").unwrap());
+
+/// Links, kept as their text.
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+
+/// Autolinks, kept as their URL.
+static AUTOLINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<((?:https?|mailto):[^>\s]+)>").unwrap());
+
+/// Inline HTML tags, dropped.
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"</?[A-Za-z][^>]*>").unwrap());
+
+/// Strong and emphasized text and inline code, kept without their markers.
+static EMPHASIS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\*\*([^*]+)\*\*|__([^_]+)__|\*([^*\s][^*]*)\*|`([^`]+)`").unwrap());
+
+/// Backslash escapes, kept as the escaped character.
+static ESCAPE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\([\\`*_{}\[\]()#+\-.!|>])").unwrap());
+
+/// List items, with their indentation and marker.
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*)([-*+]|\d+[.)])\s+(.*)$").unwrap());
+
+/// Thematic breaks.
+static RULE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:(?:\*\s*){3,}|(?:-\s*){3,}|(?:_\s*){3,})$").unwrap());
+
+/// A page kept until the document is written.
+struct PdfPage {
+    url: String,
+    title: String,
+    markdown: String,
+    scraped_at: DateTime<Utc>,
+}
+
+/// Renders every page into `all-pages.pdf`: a cover page, a table of contents
+/// linking to each page, and the pages themselves, each starting on a new PDF
+/// page headed by its source URL.
+///
+/// Like the single file export, the document needs every page, so it is
+/// written when the run finishes, with pages in URL order. Markdown is laid out
+/// as plain text: headings, lists, quotes, tables and code blocks keep their
+/// shape, while links and images are reduced to their text.
+pub struct PdfExporter {
+    path: PathBuf,
+    pages: Vec<PdfPage>,
+}
+
+impl PdfExporter {
+    /// Creates an exporter writing `all-pages.pdf` in the output directory.
+    pub fn new(output_dir: &Path) -> Self {
+        PdfExporter {
+            path: output_dir.join(PDF_FILE_NAME),
+            pages: Vec::new(),
+        }
+    }
+}
+
+impl Exporter for PdfExporter {
+    fn name(&self) -> &str {
+        "PDF"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        self.pages.push(PdfPage {
+            url: page.url.to_string(),
+            title: page.entry.title.clone().unwrap_or_else(|| page.url.to_string()),
+            markdown: page.markdown.to_string(),
+            scraped_at: page.entry.scraped_at,
+        });
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, _entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        self.pages.retain(|page| page.url != url);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.sort_by(|a, b| a.url.cmp(&b.url));
+
+        // The pages are laid out first, so the table of contents knows where each one starts
+        let mut layout = Layout::default();
+        let mut starts = Vec::with_capacity(self.pages.len());
+        for page in &self.pages {
+            starts.push(layout.pages.len());
+            layout.start_page(&page.url);
+            layout.wrapped(&page.title, 0.0, heading_style(0), heading_style(0).size * 1.3);
+            layout.wrapped(&format!("Source: {}", page.url), 0.0, SMALL, 14.0);
+            layout.gap(10.0);
+            layout.markdown(&page.markdown, &page.title);
+        }
+
+        // Characters the standard fonts lack are set as `?`; each page says how many it lost
+        let ends = starts.iter().skip(1).copied().chain([layout.pages.len()]);
+        for (page, (start, end)) in self.pages.iter().zip(starts.iter().copied().zip(ends)) {
+            let substituted: usize = layout.pages[start..end].iter().map(Page::substituted).sum();
+            if substituted > 0 {
+                warn!("{} characters of {} aren't in the PDF's fonts and were replaced with ?", substituted, page.url);
+            }
+        }
+
+        let first_content = 1 + contents_page_count(self.pages.len());
+        let hosts = self.hosts();
+        let mut pdf_pages = vec![self.cover(&hosts)];
+        pdf_pages.extend(self.contents(&starts, first_content));
+        for (mut page, source) in layout.pages.into_iter().zip(layout.sources) {
+            header(&mut page, &source);
+            pdf_pages.push(page);
+        }
+
+        let total = pdf_pages.len();
+        let mut document = Document::new(&format!("{} documentation", hosts));
+        for (index, mut page) in pdf_pages.into_iter().enumerate() {
+            // The cover has no page number
+            if index > 0 {
+                footer(&mut page, index + 1, total);
+            }
+            document.push(page);
+        }
+        for (page, start) in self.pages.iter().zip(&starts) {
+            document.bookmark(&page.title, first_content + start);
+        }
+
+        fs::write(&self.path, document.to_bytes(Utc::now()))
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        info!("Rendered {} pages into {} PDF pages: {}", self.pages.len(), total, self.path.display());
+        Ok(())
+    }
+}
+
+impl PdfExporter {
+    /// Returns the hosts the pages come from, for the title.
+    fn hosts(&self) -> String {
+        let mut hosts: Vec<String> = self
+            .pages
+            .iter()
+            .filter_map(|page| Url::parse(&page.url).ok()?.host_str().map(str::to_string))
+            .collect();
+        hosts.dedup();
+        if hosts.is_empty() {
+            "Scraped".to_string()
+        } else {
+            hosts.join(", ")
+        }
+    }
+
+    /// Lays out the cover page: the site, the number of pages and when they were scraped.
+    fn cover(&self, hosts: &str) -> Page {
+        let mut page = Page::new();
+        let mut y = PAGE_HEIGHT * 0.62;
+        page.text(MARGIN, y, Style { font: Font::Regular, size: 14.0, gray: 0.45 }, "Documentation snapshot");
+        y -= 40.0;
+        let title = Style { font: Font::Bold, size: 28.0, gray: 0.0 };
+        for line in wrap(hosts, title, TEXT_WIDTH) {
+            page.text(MARGIN, y, title, &line);
+            y -= 34.0;
+        }
+        page.line((MARGIN, y + 10.0), (PAGE_WIDTH - MARGIN, y + 10.0), 1.0, 0.0);
+        y -= 16.0;
+        let details = Style { font: Font::Regular, size: 12.0, gray: 0.2 };
+        page.text(MARGIN, y, details, &format!("{} pages", self.pages.len()));
+        if let Some(latest) = self.pages.iter().map(|page| page.scraped_at).max() {
+            y -= 18.0;
+            page.text(MARGIN, y, details, &format!("Scraped {}", latest.format("%Y-%m-%d %H:%M UTC")));
+        }
+        page
+    }
+
+    /// Lays out the table of contents, one line per page linking to where it starts.
+    ///
+    /// Takes as many pages as `contents_page_count` says, so the pages after it can be numbered beforehand.
+    fn contents(&self, starts: &[usize], first_content: usize) -> Vec<Page> {
+        let mut pages = vec![Page::new()];
+        let heading = heading_style(0);
+        pages[0].text(MARGIN, TOP, heading, "Contents");
+        let mut y = TOP - CONTENTS_HEADING;
+        for (index, (page, start)) in self.pages.iter().zip(starts).enumerate() {
+            if y < BOTTOM {
+                pages.push(Page::new());
+                y = TOP;
+            }
+            let target = first_content + start;
+            let number = (target + 1).to_string();
+            let number_width = Font::Regular.width(&number, BODY.size);
+            let title = fit(&format!("{}. {}", index + 1, plain_text(&page.title)), BODY, TEXT_WIDTH - number_width - 16.0);
+            let current = pages.last_mut().expect("at least one page");
+            current.text(MARGIN, y, BODY, &title);
+            current.text(PAGE_WIDTH - MARGIN - number_width, y, BODY, &number);
+            current.link(MARGIN, y - 4.0, PAGE_WIDTH - MARGIN, y + BODY.size, target);
+            y -= CONTENTS_LINE;
+        }
+        pages
+    }
+}
+
+/// Returns how many pages a table of contents of `entries` lines takes.
+fn contents_page_count(entries: usize) -> usize {
+    let per_page = ((TOP - BOTTOM) / CONTENTS_LINE) as usize + 1;
+    let on_first = ((TOP - CONTENTS_HEADING - BOTTOM) / CONTENTS_LINE) as usize + 1;
+    if entries <= on_first {
+        1
+    } else {
+        1 + (entries - on_first).div_ceil(per_page)
+    }
+}
+
+/// Sets the source URL of the page and a rule at the top of a PDF page.
+fn header(page: &mut Page, source: &str) {
+    let y = PAGE_HEIGHT - 40.0;
+    page.text(MARGIN, y, SMALL, &fit(source, SMALL, TEXT_WIDTH));
+    page.line((MARGIN, y - 6.0), (PAGE_WIDTH - MARGIN, y - 6.0), 0.5, 0.7);
+}
+
+/// Sets the page number at the bottom of a PDF page.
+fn footer(page: &mut Page, number: usize, total: usize) {
+    let text = format!("{} / {}", number, total);
+    let x = (PAGE_WIDTH - Font::Regular.width(&text, SMALL.size)) / 2.0;
+    page.text(x, 32.0, SMALL, &text);
+}
+
+/// Returns the style of a heading of `level`, 0 being a page's title.
+fn heading_style(level: usize) -> Style {
+    let size = match level {
+        0 => 20.0,
+        1 => 17.0,
+        2 => 14.5,
+        3 => 12.5,
+        _ => 11.0,
+    };
+    Style { font: Font::Bold, size, gray: 0.0 }
+}
+
+/// PDF pages being filled with text from the top down.
+#[derive(Default)]
+struct Layout {
+    pages: Vec<Page>,
+    /// Source URL of the scraped page shown on each PDF page
+    sources: Vec<String>,
+    y: f32,
+}
+
+impl Layout {
+    /// Starts a new PDF page for the scraped page at `source`.
+    fn start_page(&mut self, source: &str) {
+        self.pages.push(Page::new());
+        self.sources.push(source.to_string());
+        self.y = TOP;
+    }
+
+    /// Moves to a new PDF page if fewer than `height` points are left on this one.
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < BOTTOM {
+            let source = self.sources.last().cloned().unwrap_or_default();
+            self.start_page(&source);
+        }
+    }
+
+    /// Leaves `height` points of space, unless at the top of a page.
+    fn gap(&mut self, height: f32) {
+        if self.y < TOP {
+            self.y -= height;
+        }
+    }
+
+    /// Sets one line of text `indent` points from the margin.
+    fn line(&mut self, indent: f32, style: Style, text: &str, line_height: f32) {
+        self.ensure(line_height);
+        let page = self.pages.last_mut().expect("a page was started");
+        page.text(MARGIN + indent, self.y - style.size, style, text);
+        self.y -= line_height;
+    }
+
+    /// Sets text wrapped to the column, `indent` points from the margin.
+    fn wrapped(&mut self, text: &str, indent: f32, style: Style, line_height: f32) {
+        for line in wrap(text, style, TEXT_WIDTH - indent) {
+            self.line(indent, style, &line, line_height);
+        }
+    }
+
+    /// Draws a horizontal rule across the column.
+    fn rule(&mut self) {
+        self.ensure(12.0);
+        let y = self.y - 6.0;
+        let page = self.pages.last_mut().expect("a page was started");
+        page.line((MARGIN, y), (PAGE_WIDTH - MARGIN, y), 0.5, 0.6);
+        self.y -= 12.0;
+    }
+
+    /// Lays out a markdown body block by block.
+    ///
+    /// A top-level heading repeating `title` at the start is left out, as the
+    /// page already starts with its title.
+    fn markdown(&mut self, markdown: &str, title: &str) {
+        let mut paragraph: Vec<&str> = Vec::new();
+        let mut at_start = true;
+        let mut lines = markdown.lines().peekable();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim();
+            let other_block = trimmed.is_empty()
+                || Fence::open(trimmed).is_some()
+                || trimmed.starts_with('|')
+                || trimmed.starts_with('>')
+                || RULE.is_match(trimmed)
+                || LIST_ITEM.is_match(line);
+            // A single line underlined with `===` or `---` is a setext heading
+            let underline = lines.peek().map(|next| next.trim()).filter(|next| {
+                !other_block
+                    && paragraph.is_empty()
+                    && !next.is_empty()
+                    && (next.chars().all(|c| c == '=') || next.chars().all(|c| c == '-'))
+            });
+            let heading = match underline {
+                Some(underline) => {
+                    let level = if underline.starts_with('=') { 1 } else { 2 };
+                    lines.next();
+                    Some(markdown::Heading { level, text: trimmed.to_string() })
+                }
+                None if trimmed.starts_with('#') => markdown::headings(trimmed).into_iter().next(),
+                None => None,
+            };
+            let starts_block = other_block || heading.is_some();
+            if !starts_block {
+                paragraph.push(trimmed);
+                at_start = false;
+                continue;
+            }
+            self.paragraph(&mut paragraph);
+
+            if trimmed.is_empty() {
+                continue;
+            }
+            let repeats_title = at_start;
+            at_start = false;
+            if let Some(heading) = heading {
+                let text = plain_text(&heading.text);
+                if repeats_title && heading.level == 1 && text == title {
+                    continue;
+                }
+                let style = heading_style(heading.level);
+                self.gap(style.size * 0.6);
+                // A heading is kept with at least two lines of what follows it
+                self.ensure(style.size * 1.3 + 2.0 * BODY.size * 1.4);
+                self.wrapped(&text, 0.0, style, style.size * 1.3);
+                self.y -= 2.0;
+            } else if let Some(fence) = Fence::open(trimmed) {
+                let mut code = Vec::new();
+                for line in lines.by_ref() {
+                    if fence.closes(line) {
+                        break;
+                    }
+                    code.push(line);
+                }
+                self.code(&code);
+            } else if RULE.is_match(trimmed) {
+                self.rule();
+            } else if trimmed.starts_with('|') {
+                if !markdown::is_table_delimiter(trimmed) {
+                    let is_header = lines.peek().is_some_and(|next| markdown::is_table_delimiter(next.trim()));
+                    let cells: Vec<String> =
+                        trimmed.trim_matches('|').split('|').map(|cell| plain_text(cell.trim())).collect();
+                    let style = if is_header { Style { font: Font::Bold, ..BODY } } else { BODY };
+                    self.wrapped(&cells.join("   |   "), 8.0, style, BODY.size * 1.4);
+                }
+            } else if trimmed.starts_with('>') {
+                let quote = trimmed.trim_start_matches('>').trim();
+                self.wrapped(&plain_text(quote), 16.0, Style { font: Font::Italic, gray: 0.3, ..BODY }, BODY.size * 1.4);
+            } else if let Some(item) = LIST_ITEM.captures(line) {
+                let depth = item[1].replace('\t', "    ").len() / 2;
+                let marker = if item[2].chars().all(|c| c.is_ascii_punctuation()) { "•".to_string() } else { item[2].to_string() };
+                self.list_item(depth, &marker, &plain_text(&item[3]));
+            }
+        }
+        self.paragraph(&mut paragraph);
+    }
+
+    /// Sets the lines collected for a paragraph, and clears them.
+    fn paragraph(&mut self, lines: &mut Vec<&str>) {
+        if lines.is_empty() {
+            return;
+        }
+        self.wrapped(&plain_text(&lines.join(" ")), 0.0, BODY, BODY.size * 1.4);
+        self.y -= BODY.size * 0.6;
+        lines.clear();
+    }
+
+    /// Sets a list item with its marker hanging left of the text.
+    fn list_item(&mut self, depth: usize, marker: &str, text: &str) {
+        let indent = 14.0 + depth.min(6) as f32 * 14.0;
+        let lines = wrap(text, BODY, TEXT_WIDTH - indent);
+        for (index, line) in lines.iter().enumerate() {
+            self.ensure(BODY.size * 1.4);
+            if index == 0 {
+                let page = self.pages.last_mut().expect("a page was started");
+                let x = MARGIN + indent - 4.0 - Font::Regular.width(marker, BODY.size);
+                page.text(x, self.y - BODY.size, BODY, marker);
+            }
+            self.line(indent, BODY, line, BODY.size * 1.4);
+        }
+    }
+
+    /// Sets a code block line by line, breaking lines too long for the column.
+    fn code(&mut self, lines: &[&str]) {
+        let columns = ((TEXT_WIDTH - 12.0) / (0.6 * CODE.size)) as usize;
+        self.gap(4.0);
+        for line in lines {
+            let chars: Vec<char> = line.replace('\t', "    ").chars().collect();
+            if chars.is_empty() {
+                self.y -= CODE.size * 1.35;
+                continue;
+            }
+            for chunk in chars.chunks(columns.max(1)) {
+                self.line(12.0, CODE, &chunk.iter().collect::<String>(), CODE.size * 1.35);
+            }
+        }
+        self.y -= CODE.size;
+    }
+}
+
+/// Reduces inline markdown to the text it displays.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(plain_text("See **[the guide](/guide)** and `run()`"), "See the guide and run()");
+/// ```
+fn plain_text(markdown: &str) -> String {
+    let text = IMAGE.replace_all(markdown, |captures: &regex::Captures| match captures[1].trim() {
+        "" => "[Image]".to_string(),
+        alt => format!("[Image: {}]", alt),
+    });
+    let text = LINK.replace_all(&text, "$1");
+    let text = AUTOLINK.replace_all(&text, "$1");
+    let text = HTML_TAG.replace_all(&text, "");
+    let text = EMPHASIS.replace_all(&text, "$1$2$3$4");
+    ESCAPE.replace_all(&text, "$1").into_owned()
+}
+
+/// Breaks text into lines no wider than `width`, breaking words that don't fit on a line of their own.
+fn wrap(text: &str, style: Style, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+        if style.font.width(&candidate, style.size) <= width {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            current.push(c);
+            if style.font.width(&current, style.size) > width && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Shortens text to fit `width`, ending it with an ellipsis if it was cut.
+fn fit(text: &str, style: Style, width: f32) -> String {
+    if style.font.width(text, style.size) <= width {
+        return text.to_string();
+    }
+    let mut fitted: String = text.to_string();
+    while !fitted.is_empty() && style.font.width(&format!("{}…", fitted), style.size) > width {
+        fitted.pop();
+    }
+    format!("{}…", fitted.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_wraps_at_word_boundaries() {
+        let width = Font::Regular.width("aaaa bbbb", BODY.size);
+        assert_eq!(wrap("aaaa bbbb cccc dddd e", BODY, width), ["aaaa bbbb", "cccc dddd", "e"]);
+        assert_eq!(wrap("  spaced \n  out  ", BODY, TEXT_WIDTH), ["spaced out"]);
+        assert!(wrap("", BODY, width).is_empty());
+    }
+
+    #[test]
+    fn words_too_long_for_a_line_are_broken() {
+        let width = Font::Mono.width("abcd", CODE.size);
+        assert_eq!(wrap("ab abcdefghij", CODE, width), ["ab", "abcd", "efgh", "ij"]);
+        // A character wider than the line still gets a line of its own
+        assert_eq!(wrap("MM", BODY, 1.0), ["M", "M"]);
+    }
+
+    #[test]
+    fn text_is_cut_to_fit_with_an_ellipsis() {
+        let width = Font::Regular.width("Getting started", BODY.size);
+        assert_eq!(fit("Getting started", BODY, width), "Getting started");
+        let fitted = fit("Getting started with the API", BODY, width);
+        assert!(fitted.ends_with('…') && fitted.starts_with("Getting"), "{}", fitted);
+        assert!(Font::Regular.width(&fitted, BODY.size) <= width);
+        // Trailing spaces aren't kept before the ellipsis
+        assert!(!fitted.contains(" …"));
+    }
+
+    #[test]
+    fn contents_take_as_many_pages_as_their_lines_need() {
+        let on_first = ((TOP - CONTENTS_HEADING - BOTTOM) / CONTENTS_LINE) as usize + 1;
+        let per_page = ((TOP - BOTTOM) / CONTENTS_LINE) as usize + 1;
+        assert_eq!(contents_page_count(0), 1);
+        assert_eq!(contents_page_count(on_first), 1);
+        assert_eq!(contents_page_count(on_first + 1), 2);
+        assert_eq!(contents_page_count(on_first + per_page), 2);
+        assert_eq!(contents_page_count(on_first + per_page + 1), 3);
+    }
+
+    #[test]
+    fn contents_fill_the_pages_counted_for_them() {
+        // The table of contents must take exactly the pages counted before the pages after it are numbered
+        for count in [1, 10, 45, 46, 47, 100, 250] {
+            let exporter = PdfExporter {
+                path: PathBuf::new(),
+                pages: (0..count)
+                    .map(|index| PdfPage {
+                        url: format!("https://docs.example.com/{}", index),
+                        title: format!("Page {}", index),
+                        markdown: String::new(),
+                        scraped_at: DateTime::UNIX_EPOCH,
+                    })
+                    .collect(),
+            };
+            let starts: Vec<usize> = (0..count).collect();
+            assert_eq!(exporter.contents(&starts, 2).len(), contents_page_count(count), "{} entries", count);
+        }
+    }
+}
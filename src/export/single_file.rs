@@ -0,0 +1,146 @@
+//! Single file exporter: every page combined into one markdown document.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::anchors::{SlugStyle, Slugger};
+use crate::manifest::ManifestEntry;
+use crate::markdown::{self, Fence};
+
+use super::{ExportPage, Exporter};
+
+/// File name of the combined document inside the output directory.
+pub const SINGLE_FILE_NAME: &str = "all-pages.md";
+
+/// A page kept until the document is written.
+struct CombinedPage {
+    url: String,
+    title: String,
+    markdown: String,
+}
+
+/// Combines every page into `all-pages.md`, with a table of contents linking
+/// to each page and the page's source URL under its heading.
+///
+/// The table of contents needs every page, so unlike the other exports the
+/// document is written when the run finishes; pages are listed in URL order,
+/// which keeps the sections of a site together. An interrupted run leaves the
+/// previous document in place.
+pub struct SingleFileExporter {
+    path: PathBuf,
+    slug_style: SlugStyle,
+    pages: Vec<CombinedPage>,
+}
+
+impl SingleFileExporter {
+    /// Creates an exporter writing `all-pages.md` in the output directory.
+    ///
+    /// Page anchors are generated with `slug_style`.
+    pub fn new(output_dir: &Path, slug_style: SlugStyle) -> Self {
+        SingleFileExporter {
+            path: output_dir.join(SINGLE_FILE_NAME),
+            slug_style,
+            pages: Vec::new(),
+        }
+    }
+}
+
+impl Exporter for SingleFileExporter {
+    fn name(&self) -> &str {
+        "Single file"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        self.pages.push(CombinedPage {
+            url: page.url.to_string(),
+            title: page.entry.title.clone().unwrap_or_else(|| page.url.to_string()),
+            markdown: page.markdown.to_string(),
+        });
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, _entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        self.pages.retain(|page| page.url != url);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.sort_by(|a, b| a.url.cmp(&b.url));
+
+        // Anchors are generated in document order, so repeated headings get the suffix the renderer gives them
+        let mut slugger = Slugger::new(self.slug_style);
+        slugger.slug("Contents");
+        let mut contents = String::from("# Contents\n\n");
+        let mut sections = String::new();
+        for (index, page) in self.pages.iter().enumerate() {
+            let body = demote_headings(without_title_heading(&page.markdown, &page.title));
+            let anchor = slugger.slug(&page.title);
+            for heading in markdown::headings(&body) {
+                slugger.slug(&heading.text);
+            }
+            contents.push_str(&format!("{}. [{}](#{})\n", index + 1, escape_link_text(&page.title), anchor));
+            sections.push_str(&format!(
+                "\n---\n\n# {}\n\nSource: <{}>\n\n{}\n",
+                page.title,
+                page.url,
+                body.trim()
+            ));
+        }
+
+        fs::write(&self.path, contents + &sections)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        info!("Combined {} pages into: {}", self.pages.len(), self.path.display());
+        Ok(())
+    }
+}
+
+/// Removes the page's own top-level heading when it repeats the title the section is headed with.
+fn without_title_heading<'a>(markdown: &'a str, title: &str) -> &'a str {
+    let trimmed = markdown.trim_start();
+    let (first_line, rest) = trimmed.split_once('\n').unwrap_or((trimmed, ""));
+    let repeats_title = first_line.starts_with("# ")
+        && markdown::headings(first_line).first().is_some_and(|heading| heading.text == title);
+    if repeats_title {
+        rest
+    } else {
+        markdown
+    }
+}
+
+/// Moves every ATX heading outside code blocks one level down, so pages nest under their section heading.
+///
+/// Level 6 headings stay at level 6, the deepest markdown has.
+fn demote_headings(markdown: &str) -> String {
+    let mut demoted = String::with_capacity(markdown.len() + 64);
+    let mut fence: Option<Fence> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if open.closes(trimmed) {
+                fence = None;
+            }
+        } else if let Some(open) = Fence::open(trimmed) {
+            fence = Some(open);
+        } else {
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            let is_heading = (1..6).contains(&level) && trimmed[level..].starts_with([' ', '\t']);
+            if is_heading {
+                demoted.push_str(&line[..line.len() - trimmed.len()]);
+                demoted.push('#');
+                demoted.push_str(trimmed);
+                demoted.push('\n');
+                continue;
+            }
+        }
+        demoted.push_str(line);
+        demoted.push('\n');
+    }
+    demoted
+}
+
+/// Escapes the characters that would end the text of a markdown link early.
+pub(super) fn escape_link_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
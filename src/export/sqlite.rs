@@ -0,0 +1,195 @@
+//! SQLite exporter: pages, their headings and every run in one database.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::anchors::{SlugStyle, Slugger};
+use crate::manifest::ManifestEntry;
+use crate::markdown;
+use crate::page::PageChange;
+
+use super::{ExportPage, Exporter};
+
+/// File name of the SQLite database inside the output directory.
+pub const SQLITE_FILE_NAME: &str = "pages.sqlite";
+
+/// Tables of the database, created when missing. The schema is documented in
+/// the README; columns are only ever added, so queries keep working.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at TEXT NOT NULL,
+    finished_at TEXT
+);
+CREATE TABLE IF NOT EXISTS pages (
+    url TEXT PRIMARY KEY,
+    title TEXT,
+    file TEXT NOT NULL,
+    markdown TEXT NOT NULL,
+    description TEXT,
+    language TEXT,
+    status_code INTEGER,
+    page_type TEXT,
+    quality REAL,
+    content_hash TEXT,
+    scraped_at TEXT NOT NULL,
+    changed_run INTEGER NOT NULL REFERENCES runs (id),
+    removed_at TEXT,
+    id TEXT
+);
+CREATE TABLE IF NOT EXISTS headings (
+    url TEXT NOT NULL REFERENCES pages (url),
+    position INTEGER NOT NULL,
+    level INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    anchor TEXT NOT NULL,
+    PRIMARY KEY (url, position)
+);
+CREATE TABLE IF NOT EXISTS run_pages (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    url TEXT NOT NULL,
+    change TEXT NOT NULL,
+    PRIMARY KEY (run_id, url)
+);
+";
+
+/// Writes every page into `pages.sqlite`, keeping the pages of earlier runs.
+///
+/// Each run adds a row to `runs` and records what happened to every page it
+/// scraped in `run_pages`, so the history of the mirror can be queried. Pages
+/// are written as they are scraped, each in its own transaction, so an
+/// interrupted run leaves the database consistent, with the run unfinished.
+pub struct SqliteExporter {
+    path: PathBuf,
+    connection: Connection,
+    run_id: i64,
+    slug_style: SlugStyle,
+    pages: usize,
+}
+
+impl SqliteExporter {
+    /// Opens (or creates) `pages.sqlite` in the output directory and records a new run in it.
+    ///
+    /// Heading anchors are generated with `slug_style`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or its tables created
+    pub fn open(output_dir: &Path, slug_style: SlugStyle) -> Result<Self, Box<dyn Error>> {
+        let path = output_dir.join(SQLITE_FILE_NAME);
+        let connection = Connection::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to create the tables of {}: {}", path.display(), e))?;
+        // Databases created before pages had identifiers get the column added
+        if connection.prepare("SELECT id FROM pages LIMIT 0").is_err() {
+            connection.execute("ALTER TABLE pages ADD COLUMN id TEXT", [])?;
+        }
+        connection.execute("INSERT INTO runs (started_at) VALUES (?1)", params![timestamp(Utc::now())])?;
+        let run_id = connection.last_insert_rowid();
+        Ok(SqliteExporter {
+            path,
+            connection,
+            run_id,
+            slug_style,
+            pages: 0,
+        })
+    }
+}
+
+impl Exporter for SqliteExporter {
+    fn name(&self) -> &str {
+        "SQLite"
+    }
+
+    fn export_page(&mut self, page: &ExportPage) -> Result<(), Box<dyn Error>> {
+        let change = match page.change {
+            PageChange::Added => "added",
+            PageChange::Changed => "changed",
+            PageChange::Unchanged => "unchanged",
+        };
+        let transaction = self.connection.transaction()?;
+        // Unchanged pages that were not downloaded again keep the metadata of their last download
+        transaction.execute(
+            "INSERT INTO pages (url, title, file, markdown, description, language, status_code, page_type, quality,
+                content_hash, scraped_at, changed_run, id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?14)
+            ON CONFLICT (url) DO UPDATE SET
+                title = excluded.title,
+                file = excluded.file,
+                markdown = excluded.markdown,
+                description = coalesce(excluded.description, description),
+                language = coalesce(excluded.language, language),
+                status_code = coalesce(excluded.status_code, status_code),
+                page_type = excluded.page_type,
+                quality = excluded.quality,
+                content_hash = excluded.content_hash,
+                scraped_at = excluded.scraped_at,
+                changed_run = CASE WHEN ?13 = 'unchanged' THEN changed_run ELSE excluded.changed_run END,
+                removed_at = NULL,
+                id = excluded.id",
+            params![
+                page.url,
+                page.entry.title,
+                page.entry.file,
+                page.markdown,
+                page.metadata.and_then(|metadata| metadata.description.as_deref()),
+                page.metadata.and_then(|metadata| metadata.language.as_deref()),
+                page.metadata.and_then(|metadata| metadata.status_code),
+                page.entry.page_type.map(|page_type| page_type.to_string()),
+                page.entry.quality.as_ref().map(|quality| quality.score),
+                page.entry.content_hash,
+                timestamp(page.entry.scraped_at),
+                self.run_id,
+                change,
+                page.entry.id,
+            ],
+        )?;
+
+        transaction.execute("DELETE FROM headings WHERE url = ?1", params![page.url])?;
+        let mut slugger = Slugger::new(self.slug_style);
+        for (position, heading) in markdown::headings(page.markdown).into_iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO headings (url, position, level, text, anchor) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![page.url, position, heading.level, heading.text, slugger.slug(&heading.text)],
+            )?;
+        }
+
+        transaction.execute(
+            "INSERT OR REPLACE INTO run_pages (run_id, url, change) VALUES (?1, ?2, ?3)",
+            params![self.run_id, page.url, change],
+        )?;
+        transaction.commit()?;
+        self.pages += 1;
+        Ok(())
+    }
+
+    fn remove_page(&mut self, url: &str, entry: &ManifestEntry) -> Result<(), Box<dyn Error>> {
+        let removed_at = entry.removed.as_ref().map_or_else(Utc::now, |removal| removal.removed_at);
+        let transaction = self.connection.transaction()?;
+        transaction.execute("UPDATE pages SET removed_at = ?1 WHERE url = ?2", params![timestamp(removed_at), url])?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO run_pages (run_id, url, change) VALUES (?1, ?2, 'removed')",
+            params![self.run_id, url],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "UPDATE runs SET finished_at = ?1 WHERE id = ?2",
+            params![timestamp(Utc::now()), self.run_id],
+        )?;
+        info!("Exported {} pages to: {}", self.pages, self.path.display());
+        Ok(())
+    }
+}
+
+/// Formats a date the way SQLite's date functions read it, e.g. `2026-10-18T06:00:04Z`.
+fn timestamp(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
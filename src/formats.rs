@@ -0,0 +1,196 @@
+//! Additional page formats saved next to the markdown file.
+//!
+//! Besides markdown, FireCrawl can return a page's cleaned HTML, its original
+//! HTML, the links found on it and a screenshot. `--formats` requests them in
+//! the same scrape call, and each one is saved next to the page's markdown
+//! file under the same name: `guide.md` gets `guide.html`, `guide.raw.html`,
+//! `guide.links.txt` and `guide.png`. Screenshots are also listed with their
+//! dimensions and hash in the page's frontmatter and manifest entry.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::ValueEnum;
+use reqwest::Client;
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::bandwidth::{self, Bandwidth, ResponseTooLarge};
+use crate::media::{MediaFile, MediaKind};
+use crate::redact::{self, ContentRule};
+use crate::ScrapeData;
+
+/// Page formats that can be saved in addition to markdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageFormat {
+    /// HTML with navigation, scripts and other clutter removed, saved as `.html`
+    Html,
+    /// The page's original HTML, saved as `.raw.html`
+    #[value(alias = "rawHtml")]
+    #[serde(alias = "rawHtml")]
+    RawHtml,
+    /// The links found on the page, one per line, saved as `.links.txt`
+    Links,
+    /// A screenshot of the visible part of the page, saved as `.png` or `.jpg`
+    Screenshot,
+}
+
+impl PageFormat {
+    /// Returns the name of the format in FireCrawl scrape requests.
+    pub fn api_name(self) -> &'static str {
+        match self {
+            PageFormat::Html => "html",
+            PageFormat::RawHtml => "rawHtml",
+            PageFormat::Links => "links",
+            PageFormat::Screenshot => "screenshot",
+        }
+    }
+}
+
+/// Saves the requested formats of a page next to its markdown file.
+///
+/// Text formats have the content redaction rules applied like the markdown.
+/// A format missing from the response (for example cleaned HTML with the direct
+/// backend) is skipped and recorded as a warning of the page.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client, used to download screenshots returned as a URL
+/// * `output_dir` - The output directory
+/// * `file` - File the page is saved to, relative to the output directory
+/// * `data` - The scraped page
+/// * `formats` - The formats to save
+/// * `rules` - Content redaction rules
+/// * `bandwidth` - Meter downloaded screenshots are counted by; none are downloaded once its cap is reached
+/// * `warnings` - Warnings of the page, extended with the formats that could not be saved
+///
+/// # Returns
+///
+/// A `Result` containing the number of redactions made and the screenshot saved, if one was
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be written or a screenshot cannot be decoded or downloaded
+#[allow(clippy::too_many_arguments)]
+pub async fn save_formats(
+    client: &Client,
+    output_dir: &Path,
+    file: &str,
+    data: &ScrapeData,
+    formats: &[PageFormat],
+    rules: &[ContentRule],
+    bandwidth: &Bandwidth,
+    warnings: &mut Vec<String>,
+) -> Result<(usize, Option<MediaFile>), Box<dyn Error>> {
+    let markdown_path = output_dir.join(file);
+    let mut redactions = 0;
+    let mut screenshot_file = None;
+
+    for &format in formats {
+        let text = match format {
+            PageFormat::Html => data.html.clone(),
+            PageFormat::RawHtml => data.raw_html.clone(),
+            PageFormat::Links => data.links.as_ref().map(|links| links.iter().map(|link| format!("{}\n", link)).collect()),
+            PageFormat::Screenshot => None,
+        };
+
+        let (path, content) = match (format, text) {
+            (PageFormat::Screenshot, _) => {
+                let Some(screenshot) = &data.screenshot else {
+                    warn!("No screenshot received");
+                    warnings.push("No screenshot received".to_string());
+                    continue;
+                };
+                let (extension, bytes) = match screenshot_bytes(client, screenshot, bandwidth).await? {
+                    Ok(screenshot) => screenshot,
+                    Err(reason) => {
+                        warn!("Screenshot not downloaded: {}", reason);
+                        warnings.push(format!("Screenshot not downloaded: {}", reason));
+                        continue;
+                    }
+                };
+                let relative = sibling_path(Path::new(file), extension).to_string_lossy().into_owned();
+                screenshot_file = Some(MediaFile::new(&relative, MediaKind::Screenshot, &bytes));
+                (output_dir.join(relative), bytes)
+            }
+            (_, Some(text)) => {
+                let (text, count) = redact::redact_content(rules, &text);
+                redactions += count;
+                (sibling_path(&markdown_path, extension(format)), text.into_bytes())
+            }
+            (_, None) => {
+                warn!("No {} received", format.api_name());
+                warnings.push(format!("No {} received", format.api_name()));
+                continue;
+            }
+        };
+
+        fs::write(&path, content).map_err(|e| format!("Failed to write file {}: {}", path.display(), e))?;
+        info!("Saved: {}", path.display());
+    }
+
+    Ok((redactions, screenshot_file))
+}
+
+/// Returns the file extension a text format is saved with.
+fn extension(format: PageFormat) -> &'static str {
+    match format {
+        PageFormat::Html => "html",
+        PageFormat::RawHtml => "raw.html",
+        PageFormat::Links => "links.txt",
+        PageFormat::Screenshot => "png",
+    }
+}
+
+/// Returns the path of a file saved next to a markdown file, e.g. `guide.md` becomes `guide.html`.
+fn sibling_path(markdown_path: &Path, extension: &str) -> PathBuf {
+    let stem = markdown_path.file_stem().unwrap_or_default().to_string_lossy();
+    markdown_path.with_file_name(format!("{}.{}", stem, extension))
+}
+
+/// Returns the image format and bytes of a screenshot.
+///
+/// FireCrawl returns screenshots either as a URL to download them from or as
+/// base64 data, optionally as a `data:` URL. A screenshot to download is
+/// counted as an asset, and is not downloaded once the cap of `bandwidth` is
+/// reached or if it is larger than the largest response size; the reason is
+/// returned instead.
+async fn screenshot_bytes(
+    client: &Client,
+    screenshot: &str,
+    bandwidth: &Bandwidth,
+) -> Result<Result<(&'static str, Vec<u8>), String>, Box<dyn Error>> {
+    if screenshot.starts_with("http://") || screenshot.starts_with("https://") {
+        if bandwidth.cap_reached() {
+            return Ok(Err("--max-bytes reached".to_string()));
+        }
+        let response = client.get(screenshot).send().await?.error_for_status()?;
+        let jpeg = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("image/jpeg"));
+        let bytes = match bandwidth::read_capped(response, bandwidth.max_response()).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.is::<ResponseTooLarge>() => return Ok(Err(format!("larger than {}", bandwidth::format_bytes(bandwidth.max_response())))),
+            Err(e) => return Err(e),
+        };
+        bandwidth.record_asset(bytes.len() as u64);
+        return Ok(Ok((if jpeg { "jpg" } else { "png" }, bytes)));
+    }
+
+    let (jpeg, data) = match screenshot.strip_prefix("data:") {
+        Some(rest) => {
+            let (mime, data) = rest.split_once(',').ok_or("Malformed screenshot data URL")?;
+            (mime.starts_with("image/jpeg"), data)
+        }
+        None => (false, screenshot),
+    };
+    let bytes = STANDARD
+        .decode(data.trim())
+        .map_err(|e| format!("Failed to decode screenshot: {}", e))?;
+    Ok(Ok((if jpeg { "jpg" } else { "png" }, bytes)))
+}
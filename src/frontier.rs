@@ -0,0 +1,123 @@
+//! Persistent frontier of pages still to be scraped.
+//!
+//! When the API becomes unreachable mid-run, the pages not yet scraped are
+//! parked in `.scraper/frontier.json` while the scraper waits for the API to
+//! come back. If the run ends before that happens (it gives up, or is
+//! interrupted), the next run in the same output directory picks the parked
+//! pages up again. A run stopped by `--stop-at-max-bytes` parks its remaining
+//! pages the same way.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// File name of the frontier inside the state directory.
+pub const FRONTIER_FILE_NAME: &str = "frontier.json";
+
+/// Delay before the first probe after the API became unreachable.
+pub const INITIAL_PROBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Longest delay between two probes.
+pub const MAX_PROBE_DELAY: Duration = Duration::from_secs(300);
+
+/// A page waiting to be scraped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingPage {
+    /// URL of the page
+    pub url: String,
+
+    /// Fixed file name relative to the output directory, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+/// Pages parked while the API was unreachable.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Frontier {
+    /// When the pages were parked
+    pub parked_at: Option<DateTime<Utc>>,
+
+    /// Why the pages were parked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Pages still to be scraped, in order
+    pub pending: Vec<PendingPage>,
+}
+
+impl Frontier {
+    /// Returns the frontier path for the given output directory.
+    pub fn path(output_dir: &Path) -> PathBuf {
+        crate::state_dir(output_dir).join(FRONTIER_FILE_NAME)
+    }
+
+    /// Loads the parked frontier of an output directory, returning an empty frontier if none exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frontier exists but cannot be read or parsed
+    pub fn load(output_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        if !path.exists() {
+            return Ok(Frontier::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read frontier {}: {}", path.display(), e))?;
+        let frontier = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse frontier {}: {}", path.display(), e))?;
+        Ok(frontier)
+    }
+
+    /// Parks the given pages, replacing any previously parked frontier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state directory cannot be created or the file cannot be written
+    pub fn park(output_dir: &Path, pending: Vec<PendingPage>, reason: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let frontier = Frontier {
+            parked_at: Some(Utc::now()),
+            reason: Some(reason.to_string()),
+            pending,
+        };
+
+        let path = Self::path(output_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&frontier)?)
+            .map_err(|e| format!("Failed to write frontier {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Removes the parked frontier of an output directory, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be removed
+    pub fn clear(output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove frontier {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the delay before the next probe, doubling the previous one up to `MAX_PROBE_DELAY`.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(next_probe_delay(Duration::from_secs(5)), Duration::from_secs(10));
+/// assert_eq!(next_probe_delay(Duration::from_secs(200)), MAX_PROBE_DELAY);
+/// ```
+pub fn next_probe_delay(delay: Duration) -> Duration {
+    (delay * 2).min(MAX_PROBE_DELAY)
+}
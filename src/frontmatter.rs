@@ -0,0 +1,467 @@
+//! Creation and parsing of the YAML frontmatter at the top of saved pages.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::cli::Backend;
+use crate::encoding;
+use crate::media::MediaFile;
+use crate::page_type::PageType;
+use crate::preset::Preset;
+use crate::template::FrontmatterTemplate;
+use crate::translate::Translation;
+use crate::{ChangeStatus, Metadata};
+
+/// How dates are written in the frontmatter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateFormat {
+    /// RFC 3339 timestamp, e.g. `2024-01-01T12:00:00+00:00`
+    #[default]
+    Rfc3339,
+    /// Calendar date only, e.g. `2024-01-01`
+    Date,
+    /// Seconds since the Unix epoch, e.g. `1704110400`
+    Epoch,
+}
+
+/// Time zone dates are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateZone {
+    /// Coordinated Universal Time
+    #[default]
+    Utc,
+    /// The time zone of the machine running the scrape
+    Local,
+    /// A fixed offset from UTC
+    Fixed(FixedOffset),
+}
+
+/// Parses a time zone given as `utc`, `local` or an offset such as `+02:00`.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(parse_timezone("UTC"), Ok(DateZone::Utc));
+/// assert!(parse_timezone("-05:30").is_ok());
+/// ```
+pub fn parse_timezone(value: &str) -> Result<DateZone, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "utc" | "z" => Ok(DateZone::Utc),
+        "local" => Ok(DateZone::Local),
+        _ => value
+            .parse::<FixedOffset>()
+            .map(DateZone::Fixed)
+            .map_err(|_| format!("invalid time zone {:?}; expected utc, local or an offset such as +02:00", value)),
+    }
+}
+
+/// Format and time zone of the dates written in the frontmatter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DateStyle {
+    /// How dates are written
+    pub format: DateFormat,
+    /// Time zone dates are written in
+    pub zone: DateZone,
+}
+
+impl DateStyle {
+    /// Renders a date as a frontmatter value.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let style = DateStyle { format: DateFormat::Date, zone: parse_timezone("+09:00")? };
+    /// assert_eq!(style.render(Utc.with_ymd_and_hms(2024, 1, 1, 20, 0, 0).unwrap()), "2024-01-02");
+    /// ```
+    pub fn render(&self, date: DateTime<Utc>) -> String {
+        let date = match self.zone {
+            DateZone::Utc => date.fixed_offset(),
+            DateZone::Local => date.with_timezone(&Local).fixed_offset(),
+            DateZone::Fixed(offset) => date.with_timezone(&offset),
+        };
+        match self.format {
+            DateFormat::Rfc3339 => date.to_rfc3339(),
+            DateFormat::Date => date.format("%Y-%m-%d").to_string(),
+            DateFormat::Epoch => date.timestamp().to_string(),
+        }
+    }
+
+    /// Parses a date written by `render` in any format.
+    ///
+    /// Date-only values are read as midnight in this style's time zone, so
+    /// rendering them again gives the same day.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let style = DateStyle::default();
+    /// assert_eq!(style.parse("1704067200"), style.parse("2024-01-01T00:00:00Z"));
+    /// assert_eq!(style.parse("2024-01-01"), style.parse("2024-01-01T00:00:00Z"));
+    /// ```
+    pub fn parse(&self, value: &str) -> Option<DateTime<Utc>> {
+        if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+            return Some(date.with_timezone(&Utc));
+        }
+        if let Ok(seconds) = value.parse::<i64>() {
+            return Utc.timestamp_opt(seconds, 0).single();
+        }
+        let midnight = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0)?;
+        let date = match self.zone {
+            DateZone::Utc => midnight.and_utc(),
+            DateZone::Local => Local.from_local_datetime(&midnight).earliest()?.with_timezone(&Utc),
+            DateZone::Fixed(offset) => offset.from_local_datetime(&midnight).single()?.with_timezone(&Utc),
+        };
+        Some(date)
+    }
+}
+
+/// How a page was crawled, as frontmatter templates see it under `crawl`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Crawl<'a> {
+    /// URL the page was requested at
+    pub url: &'a str,
+    /// Path of the page's file, relative to the output directory
+    pub file: &'a str,
+    /// Backend the page was fetched with
+    pub backend: Backend,
+    /// Extraction preset of the site's documentation generator, if one was used
+    pub preset: Option<Preset>,
+}
+
+/// Creates YAML frontmatter from metadata and adds a timestamp.
+/// 
+/// Renders the frontmatter template, the built-in one unless
+/// `--frontmatter-template` replaces it, with the page's values. The built-in
+/// template writes the page title, source URL, the page's stable identifier,
+/// page type, the scrape date in the configured format and the full heading
+/// path of every heading on the page. Translated pages also record their
+/// language, the language of the original and its hash.
+/// 
+/// # Arguments
+/// 
+/// * `template` - The template to render, e.g. `FrontmatterTemplate::built_in()`
+/// * `metadata` - The metadata containing title and source URL
+/// * `id` - The page's stable identifier, as returned by `manifest::page_id`
+/// * `page_type` - The kind of page, as returned by `page_type::classify`
+/// * `headings` - Heading paths of the page, as returned by `markdown::heading_paths`
+/// * `scraped_at` - Time the page's current content was scraped
+/// * `last_scraped_at` - Time of the latest scrape, when it found the content unchanged
+/// * `change_status` - Whether FireCrawl found the page changed, with `--change-tracking`
+/// * `dates` - How dates are written
+/// * `translation` - How the page was translated, if it was
+/// * `crawl` - How the page was crawled
+/// 
+/// # Returns
+/// 
+/// A string containing the YAML frontmatter block
+/// 
+/// # Errors
+/// 
+/// Returns an error if the template fails to render or doesn't render a block between `---` lines
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let metadata = Metadata {
+///     title: Some("Page Title".to_string()),
+///     source_url: Some("https://example.com".to_string()),
+///     ..Default::default()
+/// };
+/// 
+/// let headings = vec!["Install".to_string(), "Install > Linux".to_string()];
+/// let crawl = Crawl { url: "https://example.com", file: "Page_Title.md", backend: Backend::Firecrawl, preset: None };
+/// let frontmatter = create_frontmatter(FrontmatterTemplate::built_in(), &metadata, "100680ad546ce6a5", PageType::Guide, &headings, chrono::Utc::now(), None, None, &DateStyle::default(), None, &crawl)?;
+/// // Results in:
+/// // ---
+/// // title: "Page Title"
+/// // url: "https://example.com"
+/// // id: "100680ad546ce6a5"
+/// // pageType: guide
+/// // scrapeDate: 2024-01-01T12:00:00+00:00
+/// // headings:
+/// //   - "Install"
+/// //   - "Install > Linux"
+/// // ---
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn create_frontmatter(
+    template: &FrontmatterTemplate,
+    metadata: &Metadata,
+    id: &str,
+    page_type: PageType,
+    headings: &[String],
+    scraped_at: DateTime<Utc>,
+    last_scraped_at: Option<DateTime<Utc>>,
+    change_status: Option<ChangeStatus>,
+    dates: &DateStyle,
+    translation: Option<&Translation>,
+    crawl: &Crawl,
+) -> Result<String, Box<dyn Error>> {
+    let translation = translation.map(|translation| {
+        json!({
+            "language": translation.language,
+            "originalLanguage": translation.original_language,
+            "originalHash": translation.original_hash,
+        })
+    });
+    let context = json!({
+        "title": metadata.title,
+        "url": metadata.source_url,
+        "id": id,
+        "description": metadata.description,
+        "language": metadata.language,
+        "metadata": metadata,
+        "pageType": page_type.to_string(),
+        "scrapeDate": dates.render(scraped_at),
+        "lastScrapeDate": last_scraped_at.map(|date| dates.render(date)),
+        "changeStatus": change_status.map(|status| status.to_string()),
+        "headings": headings,
+        "translation": translation,
+        "crawl": crawl,
+    });
+    template.render(&context)
+}
+
+/// Adds fields to the end of a frontmatter block created by `create_frontmatter`.
+/// 
+/// Values are written as double-quoted strings. Fields the block already has
+/// and names that aren't plain keys (letters, digits, `_` and `-`) are left out.
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let fields = BTreeMap::from([("team".to_string(), "Payments".to_string())]);
+/// let frontmatter = add_fields("---\ntitle: \"Intro\"\n---\n\n", &fields);
+/// assert_eq!(frontmatter, "---\ntitle: \"Intro\"\nteam: \"Payments\"\n---\n\n");
+/// ```
+pub fn add_fields(frontmatter: &str, fields: &BTreeMap<String, String>) -> String {
+    let Some(end) = frontmatter.rfind("---\n") else {
+        return frontmatter.to_string();
+    };
+
+    let mut added = String::new();
+    for (name, value) in fields {
+        let plain = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        let exists = frontmatter[..end].lines().any(|line| line.split_once(':').is_some_and(|(key, _)| key == name));
+        if plain && !exists {
+            added.push_str(&format!("{}: \"{}\"\n", name, escape_yaml_string(value)));
+        }
+    }
+    format!("{}{}{}", &frontmatter[..end], added, &frontmatter[end..])
+}
+
+/// Flags a page saved from an error response by adding its status to the end of a frontmatter block as `error`.
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let frontmatter = add_error_status("---\ntitle: \"Not Found\"\n---\n\n", 404);
+/// assert_eq!(frontmatter, "---\ntitle: \"Not Found\"\nerror: 404\n---\n\n");
+/// ```
+pub fn add_error_status(frontmatter: &str, status: u16) -> String {
+    let Some(end) = frontmatter.rfind("---\n") else {
+        return frontmatter.to_string();
+    };
+    format!("{}error: {}\n{}", &frontmatter[..end], status, &frontmatter[end..])
+}
+
+/// Adds the media files saved with a page to the end of a frontmatter block, as a `media` list.
+/// 
+/// Paths are relative to the page, like the links in its body.
+/// 
+/// # Arguments
+/// 
+/// * `frontmatter` - A frontmatter block created by `create_frontmatter`
+/// * `page_file` - File the page is saved to, relative to the output directory
+/// * `media` - The screenshots and assets saved with the page
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let frontmatter = add_media("---\ntitle: \"Intro\"\n---\n\n", "guides/intro.md", &[screenshot]);
+/// // ---
+/// // title: "Intro"
+/// // media:
+/// //   - path: "intro.png"
+/// //     kind: screenshot
+/// //     width: 1280
+/// //     height: 800
+/// //     size: 183204
+/// //     hash: "9f86d0…"
+/// // ---
+/// ```
+pub fn add_media(frontmatter: &str, page_file: &str, media: &[MediaFile]) -> String {
+    let Some(end) = frontmatter.rfind("---\n").filter(|_| !media.is_empty()) else {
+        return frontmatter.to_string();
+    };
+
+    let mut added = String::from("media:\n");
+    for file in media {
+        added.push_str(&format!("  - path: \"{}\"\n", escape_yaml_string(&file.path_from(page_file))));
+        added.push_str(&format!("    kind: {}\n", file.kind));
+        if let (Some(width), Some(height)) = (file.width, file.height) {
+            added.push_str(&format!("    width: {}\n    height: {}\n", width, height));
+        }
+        added.push_str(&format!("    size: {}\n    hash: \"{}\"\n", file.size, file.hash));
+    }
+    format!("{}{}{}", &frontmatter[..end], added, &frontmatter[end..])
+}
+
+/// Escapes backslashes and double quotes so a value can be placed in a double-quoted YAML string.
+/// 
+/// The value is cleaned first (see `encoding::clean_field`), so it always stays on one line.
+pub fn escape_yaml_string(value: &str) -> String {
+    encoding::clean_field(value).replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Parses the top-level scalar fields of a page's YAML frontmatter.
+/// 
+/// Only the subset of YAML written by this tool is understood: `key: value`
+/// lines with plain, single-quoted or double-quoted values. Nested values and
+/// lists are skipped.
+/// 
+/// # Arguments
+/// 
+/// * `content` - The full content of a saved page
+/// 
+/// # Returns
+/// 
+/// The fields by name, or `None` if the content doesn't start with a frontmatter block
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let fields = parse_frontmatter("---\ntitle: \"Intro\"\nurl: \"https://example.com\"\n---\n\n# Intro").unwrap();
+/// assert_eq!(fields["url"], "https://example.com");
+/// ```
+pub fn parse_frontmatter(content: &str) -> Option<BTreeMap<String, String>> {
+    let mut lines = content.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+
+    let mut fields = BTreeMap::new();
+    for line in lines {
+        if line.trim_end() == "---" {
+            return Some(fields);
+        }
+        // Indented lines and list items belong to nested values
+        if line.starts_with([' ', '\t', '-']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        fields.insert(key.trim().to_string(), unquote_yaml_string(value));
+    }
+
+    // No closing delimiter: not a frontmatter block
+    None
+}
+
+/// Converts a frontmatter block written by this tool into a JSON object.
+/// 
+/// Quoted values become strings; plain values become booleans or integers
+/// where they read as such (e.g. `--date-format epoch` dates), and strings
+/// otherwise. `- ` items below a key without a value become an array of
+/// strings. This is the shape frontmatter schemas are checked against.
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let value = to_json("---\ntitle: \"Intro\"\nscrapeDate: 1704110400\nheadings:\n  - \"Install\"\n---\n\n");
+/// assert_eq!(value, serde_json::json!({ "title": "Intro", "scrapeDate": 1704110400, "headings": ["Install"] }));
+/// ```
+pub fn to_json(frontmatter: &str) -> Value {
+    let mut object = Map::new();
+    let mut list: Option<String> = None;
+    for line in frontmatter.lines() {
+        let line = line.trim_end();
+        if line == "---" || line.is_empty() {
+            continue;
+        }
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some(Value::Array(items)) = list.as_ref().and_then(|key| object.get_mut(key)) {
+                items.push(Value::String(unquote_yaml_string(item.trim())));
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_string();
+        let value = value.trim();
+        if value.is_empty() {
+            object.insert(key.clone(), Value::Array(Vec::new()));
+            list = Some(key);
+            continue;
+        }
+        list = None;
+        let value = if value.starts_with(['"', '\'']) {
+            Value::String(unquote_yaml_string(value))
+        } else if let Ok(flag) = value.parse::<bool>() {
+            Value::Bool(flag)
+        } else if let Ok(number) = value.parse::<i64>() {
+            Value::from(number)
+        } else {
+            Value::String(value.to_string())
+        };
+        object.insert(key, value);
+    }
+    Value::Object(object)
+}
+
+/// Returns the content of a saved page with its frontmatter block removed.
+/// 
+/// Content without a frontmatter block is returned unchanged.
+/// 
+/// # Examples
+/// 
+/// ```text
+/// assert_eq!(strip_frontmatter("---\ntitle: \"Intro\"\n---\n\n# Intro"), "# Intro");
+/// ```
+pub fn strip_frontmatter(content: &str) -> &str {
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return content;
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return rest[offset..].trim_start_matches(['\r', '\n']);
+        }
+    }
+    content
+}
+
+/// Removes the quotes around a YAML scalar, undoing `escape_yaml_string` for double-quoted values.
+fn unquote_yaml_string(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        let mut result = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    result.push(escaped);
+                }
+            } else {
+                result.push(c);
+            }
+        }
+        return result;
+    }
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return inner.replace("''", "'");
+    }
+    value.to_string()
+}
@@ -0,0 +1,565 @@
+//! Mock FireCrawl API for end-to-end runs without network access or credits.
+//!
+//! Only built with the `test-harness` feature. A `MockFirecrawl` serves the
+//! scrape and map endpoints from fixtures, so the whole pipeline (discovery,
+//! scraping, writing files, manifest, report and exports) runs exactly as
+//! against the real API. The `harness` command uses it to try out a
+//! configuration on fixture pages before pointing it at a real site, and the
+//! tests below use it to check the pipeline end to end.
+//!
+//! With `--chaos`, the mock also misbehaves like a struggling API: page
+//! scrapes get random extra latency, `429 Too Many Requests`, `503 Service
+//! Unavailable` and malformed responses, so retry, outage and resume handling
+//! can be exercised without touching the real API.
+//!
+//! ```toml
+//! start_url = "https://docs.example.com/"
+//!
+//! [[page]]
+//! url = "https://docs.example.com/"
+//! title = "Home"
+//! markdown = "# Home\n\nWelcome."
+//! links = ["https://docs.example.com/guide"]
+//!
+//! [[page]]
+//! url = "https://docs.example.com/guide"
+//! title = "Guide"
+//! markdown = "# Guide\n\nStep one."
+//! ```
+
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+use crate::api::Api;
+use crate::cli::{Backend, ScrapeOptions};
+use crate::manifest::Manifest;
+use crate::throttle::CreditPacer;
+
+/// API key sent to the mock server.
+const MOCK_API_KEY: &str = "fc-harness";
+
+/// Pages served by the mock API.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixtures {
+    /// The URL the scrape starts from
+    pub start_url: String,
+
+    /// The pages of the fixture site
+    #[serde(default, rename = "page")]
+    pub pages: Vec<FixturePage>,
+}
+
+/// One page of the fixture site.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixturePage {
+    /// URL of the page
+    pub url: String,
+    /// Page title
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Markdown content; the API answers without markdown if not set
+    #[serde(default)]
+    pub markdown: Option<String>,
+    /// HTML content, returned when the `html` format is requested
+    #[serde(default)]
+    pub html: Option<String>,
+    /// Language of the page
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Links returned when the `links` format is requested
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// HTTP status the page is reported to have been served with
+    #[serde(default = "default_status")]
+    pub status_code: u16,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl Fixtures {
+    /// Loads fixtures from a TOML file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read fixtures {}: {}", path.display(), e))?;
+        let fixtures = toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse fixtures {}: {}", path.display(), e))?;
+        Ok(fixtures)
+    }
+
+    fn page(&self, url: &str) -> Option<&FixturePage> {
+        self.pages.iter().find(|page| page.url == url)
+    }
+}
+
+/// Faults injected into page scrapes by `--chaos`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chaos {
+    /// Upper bound of the random latency added to every page scrape
+    pub latency: Duration,
+    /// Share of page scrapes answered with `429 Too Many Requests`
+    pub rate_limited: f64,
+    /// Share of page scrapes answered with `503 Service Unavailable`
+    pub unavailable: f64,
+    /// Share of page scrapes answered with a truncated JSON body
+    pub malformed: f64,
+    /// Seed of the random choices, so a run can be repeated exactly
+    pub seed: u64,
+}
+
+/// Parses a `--chaos` specification such as `latency=500,429=0.2,malformed=0.1,seed=7`.
+///
+/// `latency` is in milliseconds; `429`, `503` and `malformed` are the shares of
+/// page scrapes given that fault, between 0 and 1. Unset values keep their
+/// defaults (up to 200 ms latency, 10% `429`, 5% `503`, 5% malformed), and the
+/// seed is random unless given.
+///
+/// # Examples
+///
+/// ```text
+/// let chaos = parse_chaos("429=0.5,seed=3")?;
+/// assert_eq!(chaos.rate_limited, 0.5);
+/// ```
+pub fn parse_chaos(spec: &str) -> Result<Chaos, String> {
+    let mut chaos = Chaos {
+        latency: Duration::from_millis(200),
+        rate_limited: 0.1,
+        unavailable: 0.05,
+        malformed: 0.05,
+        seed: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64),
+    };
+
+    for setting in spec.split(',').map(str::trim).filter(|setting| !setting.is_empty()) {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, got {:?}", setting))?;
+        let rate = || match value.parse::<f64>() {
+            Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+            _ => Err(format!("{} must be a share between 0 and 1, got {:?}", key, value)),
+        };
+        match key {
+            "latency" => {
+                let millis = value.parse().map_err(|_| format!("latency must be milliseconds, got {:?}", value))?;
+                chaos.latency = Duration::from_millis(millis);
+            }
+            "429" => chaos.rate_limited = rate()?,
+            "503" => chaos.unavailable = rate()?,
+            "malformed" => chaos.malformed = rate()?,
+            "seed" => chaos.seed = value.parse().map_err(|_| format!("seed must be a number, got {:?}", value))?,
+            _ => return Err(format!("unknown chaos setting {:?}; expected latency, 429, 503, malformed or seed", key)),
+        }
+    }
+    if chaos.rate_limited + chaos.unavailable + chaos.malformed > 1.0 {
+        return Err("the shares of 429, 503 and malformed responses add up to more than 1".to_string());
+    }
+    Ok(chaos)
+}
+
+/// Faults injected by the mock API so far.
+#[derive(Debug, Default)]
+pub struct ChaosStats {
+    /// Page scrapes answered with `429`
+    pub rate_limited: AtomicUsize,
+    /// Page scrapes answered with `503`
+    pub unavailable: AtomicUsize,
+    /// Page scrapes answered with a malformed body
+    pub malformed: AtomicUsize,
+}
+
+/// Injects the faults of a `Chaos` into responses, drawing from a seeded generator.
+struct ChaosInjector {
+    chaos: Chaos,
+    state: AtomicU64,
+    stats: Arc<ChaosStats>,
+}
+
+impl ChaosInjector {
+    /// Returns a pseudo-random number in `[0, 1)` (SplitMix64).
+    fn next(&self) -> f64 {
+        let mut z = self.state.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Replaces or delays the response to a page scrape.
+    fn apply(&self, response: ResponseTemplate) -> ResponseTemplate {
+        let delay = self.chaos.latency.mul_f64(self.next());
+        let roll = self.next();
+        let chaos = &self.chaos;
+
+        let response = if roll < chaos.rate_limited {
+            self.stats.rate_limited.fetch_add(1, Ordering::Relaxed);
+            ResponseTemplate::new(429)
+                .insert_header("Retry-After", "1")
+                .set_body_json(json!({ "success": false, "error": "Rate limit exceeded" }))
+        } else if roll < chaos.rate_limited + chaos.unavailable {
+            self.stats.unavailable.fetch_add(1, Ordering::Relaxed);
+            ResponseTemplate::new(503).set_body_string("Service Unavailable")
+        } else if roll < chaos.rate_limited + chaos.unavailable + chaos.malformed {
+            self.stats.malformed.fetch_add(1, Ordering::Relaxed);
+            ResponseTemplate::new(200).set_body_raw(r##"{"success": true, "data": {"markdown": "# Trunc"##, "application/json")
+        } else {
+            response
+        };
+        response.set_delay(delay)
+    }
+}
+
+/// A running mock of the FireCrawl API.
+pub struct MockFirecrawl {
+    server: MockServer,
+    stats: Arc<ChaosStats>,
+}
+
+impl MockFirecrawl {
+    /// Starts a mock API serving the given fixtures on a random local port.
+    ///
+    /// Scrape requests for URLs without a fixture are answered with `404`. With
+    /// `chaos`, page scrapes get its faults injected; link discovery and map
+    /// requests are always answered normally, so a run gets as far as scraping
+    /// pages.
+    pub async fn start(fixtures: &Fixtures, chaos: Option<&Chaos>) -> Self {
+        let server = MockServer::start().await;
+        let fixtures = Arc::new(fixtures.clone());
+        let stats = Arc::new(ChaosStats::default());
+        let injector = chaos.map(|chaos| ChaosInjector {
+            chaos: chaos.clone(),
+            state: AtomicU64::new(chaos.seed),
+            stats: Arc::clone(&stats),
+        });
+
+        let scrape_fixtures = Arc::clone(&fixtures);
+        Mock::given(method("POST"))
+            .and(path("/v1/scrape"))
+            .respond_with(move |request: &Request| {
+                let response = scrape_response(&scrape_fixtures, request);
+                match &injector {
+                    Some(injector) if !is_discovery(request) => injector.apply(response),
+                    _ => response,
+                }
+            })
+            .mount(&server)
+            .await;
+
+        let links: Vec<String> = fixtures.pages.iter().map(|page| page.url.clone()).collect();
+        Mock::given(method("POST"))
+            .and(path("/v1/map"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "links": links })))
+            .mount(&server)
+            .await;
+
+        MockFirecrawl { server, stats }
+    }
+
+    /// Returns the faults injected so far.
+    pub fn chaos_stats(&self) -> &ChaosStats {
+        &self.stats
+    }
+
+    /// Returns the scrape endpoint of the mock API.
+    pub fn api_url(&self) -> String {
+        format!("{}/v1/scrape", self.server.uri())
+    }
+
+    /// Returns the number of requests the mock API has received.
+    pub async fn request_count(&self) -> usize {
+        self.server.received_requests().await.map_or(0, |requests| requests.len())
+    }
+}
+
+/// Returns whether a scrape request only asks for links, as link discovery does.
+fn is_discovery(request: &Request) -> bool {
+    let body: Value = request.body_json().unwrap_or_default();
+    body["formats"] == json!(["links"])
+}
+
+/// Answers a scrape request from the fixtures.
+fn scrape_response(fixtures: &Fixtures, request: &Request) -> ResponseTemplate {
+    let body: Value = request.body_json().unwrap_or_default();
+    let url = body["url"].as_str().unwrap_or_default();
+    let formats: Vec<&str> = body["formats"]
+        .as_array()
+        .map(|formats| formats.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let Some(page) = fixtures.page(url) else {
+        return ResponseTemplate::new(404)
+            .set_body_json(json!({ "success": false, "error": format!("No fixture for {}", url) }));
+    };
+
+    let mut data = json!({
+        "metadata": {
+            "title": page.title,
+            "language": page.language,
+            "sourceURL": page.url,
+            "statusCode": page.status_code,
+        }
+    });
+    if formats.contains(&"markdown") {
+        if let Some(markdown) = &page.markdown {
+            data["markdown"] = json!(markdown);
+        }
+    }
+    if formats.contains(&"html") {
+        if let Some(html) = &page.html {
+            data["html"] = json!(html);
+        }
+    }
+    if formats.contains(&"links") {
+        data["links"] = json!(page.links);
+    }
+    ResponseTemplate::new(200).set_body_json(json!({ "success": true, "data": data }))
+}
+
+/// Outcome of a harness run.
+#[derive(Debug, Default)]
+pub struct HarnessSummary {
+    /// Fixture pages found in the manifest after the run, with their files
+    pub saved: Vec<(String, String)>,
+    /// Fixture pages not in the manifest after the run
+    pub not_saved: Vec<String>,
+    /// Requests made to the mock API
+    pub requests: usize,
+}
+
+/// Scrapes the fixture site through a mock API into `output_dir`.
+///
+/// The run uses `options` exactly like a real scrape, so the output shows what
+/// a configuration would produce for the fixture pages. With `chaos`, the mock
+/// injects faults into page scrapes.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The fixtures cannot be loaded
+/// - The options select the direct backend, which doesn't use the API
+/// - The scrape itself fails
+pub async fn run(
+    fixtures_path: &Path,
+    output_dir: &Path,
+    options: &ScrapeOptions,
+    chaos: Option<&Chaos>,
+) -> Result<HarnessSummary, Box<dyn Error>> {
+    let fixtures = Fixtures::load(fixtures_path)?;
+    run_fixtures(&fixtures, output_dir, options, chaos).await
+}
+
+/// Scrapes already loaded fixtures through a mock API into `output_dir`; see `run`.
+///
+/// # Errors
+///
+/// Returns an error if the options select the direct backend or the scrape fails
+pub async fn run_fixtures(
+    fixtures: &Fixtures,
+    output_dir: &Path,
+    options: &ScrapeOptions,
+    chaos: Option<&Chaos>,
+) -> Result<HarnessSummary, Box<dyn Error>> {
+    if options.backend == Backend::Direct {
+        return Err("The harness mocks the FireCrawl API and cannot be used with --backend direct".into());
+    }
+
+    let mock = MockFirecrawl::start(fixtures, chaos).await;
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory {}: {}", output_dir.display(), e))?;
+
+    let client = Client::new();
+    let api_url = mock.api_url();
+    let api = Api { client: &client, url: &api_url, key: MOCK_API_KEY };
+    let pacer = CreditPacer::new(options.credits_per_minute);
+    let result = crate::site::scrape_site(&api, &fixtures.start_url, None, output_dir, options, &pacer).await;
+    let stats = mock.chaos_stats();
+    if chaos.is_some() {
+        info!(
+            "Chaos injected {} rate limits, {} outages and {} malformed responses",
+            stats.rate_limited.load(Ordering::Relaxed),
+            stats.unavailable.load(Ordering::Relaxed),
+            stats.malformed.load(Ordering::Relaxed)
+        );
+    }
+    result?;
+
+    let manifest = Manifest::load(output_dir)?;
+    let mut summary = HarnessSummary {
+        requests: mock.request_count().await,
+        ..Default::default()
+    };
+    for page in &fixtures.pages {
+        match manifest.pages.get(&page.url) {
+            Some(entry) => summary.saved.push((page.url.clone(), entry.file.clone())),
+            None => summary.not_saved.push(page.url.clone()),
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter;
+    use crate::pause::PauseConditions;
+    use crate::workspace::PageCounts;
+    use std::path::PathBuf;
+
+    fn fixtures() -> Fixtures {
+        toml::from_str(
+            r##"
+            start_url = "https://docs.example.com/"
+
+            [[page]]
+            url = "https://docs.example.com/"
+            title = "Home"
+            markdown = "# Home"
+            links = ["https://docs.example.com/guide", "https://docs.example.com/empty", "https://other.example.com/"]
+
+            [[page]]
+            url = "https://docs.example.com/guide"
+            title = "Guide"
+            markdown = "# Guide\n\nStep one.\n\n[Next](https://docs.example.com/home)"
+
+            [[page]]
+            url = "https://docs.example.com/empty"
+            title = "Empty"
+            "##,
+        )
+        .unwrap()
+    }
+
+    fn output_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scraper-harness-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn round_trip_writes_pages_manifest_and_report() {
+        let dir = output_dir("round-trip");
+        let options = ScrapeOptions::parse_args(["--export", "jsonl"]).unwrap();
+
+        let summary = run_fixtures(&fixtures(), &dir, &options, None).await.unwrap();
+
+        // The start page itself isn't linked from any page, so only the linked pages are scraped
+        assert_eq!(summary.saved, vec![("https://docs.example.com/guide".to_string(), "Guide.md".to_string())]);
+        assert_eq!(summary.requests, 3);
+
+        let content = fs::read_to_string(dir.join("Guide.md")).unwrap();
+        let fields = frontmatter::parse_frontmatter(&content).unwrap();
+        assert_eq!(fields["url"], "https://docs.example.com/guide");
+        assert!(frontmatter::strip_frontmatter(&content).starts_with("# Guide\n\nStep one."));
+
+        let manifest = Manifest::load(&dir).unwrap();
+        assert!(manifest.empty_content.contains_key("https://docs.example.com/empty"));
+
+        let report: Value = serde_json::from_str(&fs::read_to_string(dir.join(".scraper/report.json")).unwrap()).unwrap();
+        assert_eq!(report["pagesFound"], 2);
+        assert_eq!(report["pagesProcessed"], 1);
+        assert_eq!(report["pagesEmpty"], 1);
+
+        let export = fs::read_to_string(dir.join("pages.jsonl")).unwrap();
+        assert_eq!(export.lines().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn filters_and_map_discovery_apply_to_fixture_site() {
+        let dir = output_dir("map");
+        let options = ScrapeOptions::parse_args(["--discover", "map", "--exclude", "/empty$"]).unwrap();
+
+        let summary = run_fixtures(&fixtures(), &dir, &options, None).await.unwrap();
+
+        assert_eq!(summary.saved.len(), 2);
+        assert_eq!(summary.not_saved, vec!["https://docs.example.com/empty".to_string()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn direct_backend_is_rejected() {
+        let options = ScrapeOptions::parse_args(["--backend", "direct"]).unwrap();
+        assert!(run_fixtures(&fixtures(), &output_dir("direct"), &options, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn chaos_faults_fail_pages_without_aborting_run() {
+        let dir = output_dir("chaos");
+        let options = ScrapeOptions::parse_args(["--discover", "map", "--retries", "0"]).unwrap();
+        let chaos = parse_chaos("latency=0,429=0.5,503=0,malformed=0.5,seed=7").unwrap();
+
+        let summary = run_fixtures(&fixtures(), &dir, &options, Some(&chaos)).await.unwrap();
+
+        // Every page scrape got a fault, failing the page but not the run
+        assert!(summary.saved.is_empty());
+        let report: Value = serde_json::from_str(&fs::read_to_string(dir.join(".scraper/report.json")).unwrap()).unwrap();
+        assert_eq!(report["pagesFailed"], 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn transient_faults_are_retried() {
+        let dir = output_dir("retries");
+        let options = ScrapeOptions::parse_args(["--discover", "map", "--retries", "5", "--retry-delay-ms", "1"]).unwrap();
+        let chaos = parse_chaos("latency=0,429=0,503=0.5,malformed=0,seed=7").unwrap();
+
+        let summary = run_fixtures(&fixtures(), &dir, &options, Some(&chaos)).await.unwrap();
+
+        // Both pages with content are saved despite the 503 answers, and the retries are reported
+        assert_eq!(summary.saved.len(), 2);
+        let report: Value = serde_json::from_str(&fs::read_to_string(dir.join(".scraper/report.json")).unwrap()).unwrap();
+        assert_eq!(report["pagesFailed"], 0);
+        assert!(report["retries"].as_object().is_some_and(|retries| !retries.is_empty()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unauthorized_page_pauses_site_with_auth_broken() {
+        let dir = output_dir("unauthorized");
+        let mut fixtures = fixtures();
+        fixtures.pages[1].status_code = 401;
+        let options = ScrapeOptions::parse_args(["--discover", "map", "--error-pages", "retry"]).unwrap();
+
+        let summary = run_fixtures(&fixtures, &dir, &options, None).await.unwrap();
+
+        // `--error-pages retry` fails the page, and it still counts as refused
+        assert!(summary.not_saved.contains(&"https://docs.example.com/guide".to_string()));
+        let counts: PageCounts = serde_json::from_str(&fs::read_to_string(dir.join(".scraper/report.json")).unwrap()).unwrap();
+        assert_eq!(counts.pages_failed, 1);
+        assert_eq!(counts.pages_unauthorized, 1);
+        let conditions = PauseConditions {
+            auth_broken: true,
+            ..Default::default()
+        };
+        assert!(conditions.check(None, &counts).is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn chaos_spec_is_validated() {
+        assert!(parse_chaos("").is_ok());
+        assert!(parse_chaos("429=1.5").is_err());
+        assert!(parse_chaos("429=0.6,503=0.6").is_err());
+        assert!(parse_chaos("jitter=3").is_err());
+    }
+}
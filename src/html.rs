@@ -0,0 +1,199 @@
+//! Lightweight HTML inspection helpers.
+//!
+//! These helpers are intentionally simple string scanners: they are used to
+//! pick a few well-known tags and attributes out of pages, not to build a DOM.
+
+/// Returns the raw text of every tag with one of the given names, in document order.
+///
+/// # Examples
+///
+/// ```text
+/// let tags = html::tags("<html><meta name=\"a\"><p>x</p></html>", &["meta"]);
+/// assert_eq!(tags, vec!["<meta name=\"a\">"]);
+/// ```
+pub fn tags<'a>(html: &'a str, names: &[&str]) -> Vec<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let mut tags = Vec::new();
+    let mut position = 0;
+
+    while let Some(start) = lower[position..].find('<').map(|i| i + position) {
+        let Some(end) = lower[start..].find('>').map(|i| i + start + 1) else {
+            break;
+        };
+        let name = lower[start + 1..end]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or("");
+        if names.contains(&name) {
+            tags.push(&html[start..end]);
+        }
+        position = end;
+    }
+
+    tags
+}
+
+/// Returns the decoded value of an attribute of a single tag.
+///
+/// Handles double-quoted, single-quoted and unquoted values.
+///
+/// # Examples
+///
+/// ```text
+/// let value = html::attribute("<a href='/docs'>", "href");
+/// assert_eq!(value.as_deref(), Some("/docs"));
+/// ```
+pub fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search_from = 0;
+
+    while let Some(found) = lower[search_from..].find(name).map(|i| i + search_from) {
+        search_from = found + name.len();
+
+        // The name must be a whole attribute name, not a suffix of another one
+        let preceded_by_space = lower[..found]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_whitespace());
+        if !preceded_by_space {
+            continue;
+        }
+
+        let rest = lower[search_from..].trim_start();
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let value_start = tag.len() - after_eq.trim_start().len();
+        let raw = &tag[value_start..];
+
+        let value = match raw.chars().next() {
+            Some(quote @ ('"' | '\'')) => raw[1..].split(quote).next().unwrap_or(""),
+            _ => raw
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or(""),
+        };
+        return Some(decode_entities(value));
+    }
+
+    None
+}
+
+/// Returns the decoded text of the first element with the given name.
+///
+/// # Examples
+///
+/// ```text
+/// let title = html::element_text("<title>A &amp; B</title>", "title");
+/// assert_eq!(title.as_deref(), Some("A & B"));
+/// ```
+pub fn element_text(html: &str, name: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find(&format!("<{}", name))?;
+    let content_start = open + lower[open..].find('>')? + 1;
+    let content_end = content_start + lower[content_start..].find(&format!("</{}", name))?;
+
+    let text = strip_tags(&html[content_start..content_end]);
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        None
+    } else {
+        Some(decode_entities(&text))
+    }
+}
+
+/// Returns the outer HTML of every outermost element with the given name, in document order.
+///
+/// Elements of the same name nested inside a returned element are part of it
+/// and are not returned separately.
+///
+/// # Examples
+///
+/// ```text
+/// let tables = html::elements("<table><tr><td><table></table></td></tr></table><p>x</p>", "table");
+/// assert_eq!(tables.len(), 1);
+/// ```
+pub fn elements<'a>(html: &'a str, name: &str) -> Vec<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let open_tag = format!("<{}", name);
+    let close_tag = format!("</{}", name);
+    let mut elements = Vec::new();
+    let mut position = 0;
+
+    while let Some(start) = find_tag(&lower, &open_tag, position) {
+        let mut depth = 0;
+        let mut cursor = start;
+        let mut end = None;
+
+        loop {
+            let next_open = find_tag(&lower, &open_tag, cursor);
+            let next_close = find_tag(&lower, &close_tag, cursor);
+            match (next_open, next_close) {
+                (Some(open), Some(close)) if open < close => {
+                    depth += 1;
+                    cursor = open + open_tag.len();
+                }
+                (_, Some(close)) => {
+                    depth -= 1;
+                    cursor = close + close_tag.len();
+                    if depth == 0 {
+                        end = lower[cursor..].find('>').map(|i| cursor + i + 1);
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        let Some(end) = end else {
+            break;
+        };
+        elements.push(&html[start..end]);
+        position = end;
+    }
+
+    elements
+}
+
+/// Finds the next occurrence of a tag opening such as `<table` that is not a prefix of a longer tag name.
+fn find_tag(lower: &str, tag: &str, from: usize) -> Option<usize> {
+    let mut position = from;
+    while let Some(found) = lower[position..].find(tag).map(|i| i + position) {
+        let after = lower[found + tag.len()..].chars().next();
+        if after.is_none_or(|c| c.is_whitespace() || c == '>' || c == '/') {
+            return Some(found);
+        }
+        position = found + tag.len();
+    }
+    None
+}
+
+/// Removes HTML tags, keeping only the text content.
+pub fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Decodes the handful of character entities commonly found in attributes and titles.
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
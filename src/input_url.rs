@@ -0,0 +1,77 @@
+//! Start URLs given without a scheme, such as `docs.example.com` or `example.com/docs`.
+//!
+//! Typing a site's address the way a browser's address bar accepts it is
+//! common enough that failing on it is unhelpful. Such an input is tried
+//! over HTTPS first and HTTP second, and the URL the site ends up answering
+//! at, after following its redirects, becomes the start URL, so the output
+//! directory and link filtering see the same URL a full address would have
+//! given.
+
+use reqwest::Client;
+use std::error::Error;
+use std::time::Duration;
+use tracing::{debug, info};
+use url::Url;
+
+use crate::cli::ScrapeOptions;
+
+/// Schemes tried for an input without one, in order.
+const SCHEMES: [&str; 2] = ["https", "http"];
+
+/// How long to wait for a site to answer over one scheme before trying the next.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Returns the start URL for an input, adding a scheme when it has none.
+///
+/// Inputs with a scheme are returned as they are. Others are requested over
+/// HTTPS, then HTTP, and the first scheme the site answers on, whatever the
+/// status, is kept; the URL is the one the site's redirects end at. The
+/// credentials configured for the host are sent with the probe.
+///
+/// # Errors
+///
+/// Returns an error if the input isn't a valid address with either scheme,
+/// or if the site answers over neither
+///
+/// # Examples
+///
+/// ```text
+/// let url = resolve(&client, "docs.example.com", &options).await?;
+/// assert_eq!(url, "https://docs.example.com/");
+/// ```
+pub async fn resolve(client: &Client, input: &str, options: &ScrapeOptions) -> Result<String, Box<dyn Error>> {
+    let input = input.trim();
+    if input.contains("://") {
+        return Ok(input.to_string());
+    }
+
+    let mut last_error = String::new();
+    for scheme in SCHEMES {
+        let candidate = format!("{}://{}", scheme, input);
+        let url = Url::parse(&candidate).map_err(|e| format!("Invalid URL {}: {}", input, e))?;
+        if url.host_str().is_none_or(str::is_empty) {
+            return Err(format!("Invalid URL {}: expected an address such as docs.example.com", input).into());
+        }
+
+        let mut request = client.get(url.as_str()).timeout(PROBE_TIMEOUT);
+        if let Some(auth) = options.credentials_for(url.as_str()) {
+            request = request.header(reqwest::header::AUTHORIZATION, auth.header_value());
+        }
+        match request.send().await {
+            Ok(response) => {
+                let resolved = response.url().to_string();
+                if resolved == url.as_str() {
+                    info!("Using {} for {}", resolved, input);
+                } else {
+                    info!("Using {} for {} (redirected from {})", resolved, input, url);
+                }
+                return Ok(resolved);
+            }
+            Err(e) => {
+                debug!("{} didn't answer: {}", url, e);
+                last_error = e.to_string();
+            }
+        }
+    }
+    Err(format!("{} answered neither over HTTPS nor over HTTP: {}", input, last_error).into())
+}
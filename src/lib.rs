@@ -0,0 +1,440 @@
+//! A documentation scraper that saves sites as markdown files with YAML frontmatter.
+//!
+//! The `scraper` binary runs the command line tool (`run`). Applications can
+//! also scrape sites themselves with `scrape`, changing, skipping or enriching
+//! pages through hooks registered on the options (see `middleware`), and find
+//! pages in a mirror without scraping them: the canonical form pages are
+//! keyed by, and where a page's file is written.
+//!
+//! A page is saved at `<output dir>/<site dir>/<page file>`, where the site
+//! directory follows `--dir-naming` (or is left out with `--no-domain-dir`):
+//!
+//! ```
+//! use scraper::output::{self, DirNaming, PageVariables};
+//! use scraper::page_type::PageType;
+//! use scraper::urls;
+//! use std::path::Path;
+//!
+//! let url = urls::canonical_url("https://docs.example.com/guide/install.html#linux")?;
+//! let page = PageVariables {
+//!     url: &url,
+//!     title: None,
+//!     language: None,
+//!     page_type: PageType::Guide,
+//!     scraped_at: chrono::Utc::now(),
+//! };
+//! let path = Path::new("docs")
+//!     .join(output::domain_dir_name(&url, DirNaming::Flat, None)?)
+//!     .join(output::page_file_name(&page, Some("{path}"))?);
+//! assert_eq!(path, Path::new("docs/docs_example_com/guide/install.md"));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Without `--path-template`, page files are named after the page title, which
+//! is only known once the page is scraped. A path template that uses only URL
+//! variables makes every path predictable up front; otherwise the `file` of a
+//! page's entry in the mirror's manifest, keyed by its canonical URL, tells
+//! where it was saved.
+
+use reqwest::Client;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use dotenv::dotenv;
+use std::path::Path;
+use std::io;
+use std::process::ExitCode;
+use clap::Parser;
+use tracing::{error, info, warn};
+
+mod accessibility;
+mod anchors;
+mod api;
+mod assets;
+mod backlog;
+mod auth;
+mod bandwidth;
+mod checkpoint;
+mod cli;
+mod compare;
+mod completions;
+mod compliance;
+mod config;
+mod crawl;
+mod debug_bundle;
+mod direct;
+mod discovery;
+mod domain_profile;
+mod encoding;
+mod export;
+mod formats;
+mod frontier;
+mod frontmatter;
+#[cfg(feature = "test-harness")]
+mod harness;
+mod html;
+mod input_url;
+mod lock;
+mod logging;
+mod manifest;
+pub mod markdown;
+mod media;
+mod metrics;
+pub mod middleware;
+mod navigation;
+mod mirror;
+mod notify;
+pub mod output;
+mod page;
+pub mod page_type;
+mod pause;
+mod pdf;
+mod platform;
+mod preset;
+mod quality;
+mod redact;
+mod replace;
+mod report;
+mod retry;
+mod robots;
+mod scaffold;
+mod scheduler;
+mod schema;
+mod shutdown;
+mod site;
+mod status;
+mod template;
+mod throttle;
+mod tombstone;
+mod translate;
+pub mod urls;
+mod wizard;
+mod workspace;
+
+pub use api::{Action, ChangeStatus, ChangeTracking, JsonOptions, Location, Metadata, ScrapeData, ScrapeRequest, ScrapeResponse};
+pub use cli::ScrapeOptions;
+
+use api::{api_key, api_url};
+use cli::{Cli, Command};
+use checkpoint::Checkpoint;
+use config::Config;
+use manifest::Manifest;
+use report::PageCounts;
+use site::{checkpoint_options, credit_pacer, map_site, refresh_mirror, resume_run, retrier, scrape_documentation, scrape_url_list};
+
+/// Name of the directory inside the output directory that holds run state such as the report.
+const STATE_DIR_NAME: &str = ".scraper";
+
+/// Returns the directory holding run state for the given output directory.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # use scraper::state_dir;
+/// # use std::path::{Path, PathBuf};
+/// let dir = state_dir(Path::new("docs_example_com"));
+/// assert_eq!(dir, PathBuf::from("docs_example_com/.scraper"));
+/// ```
+pub fn state_dir(output_dir: &Path) -> PathBuf {
+    output_dir.join(STATE_DIR_NAME)
+}
+
+/// Scrapes a site into markdown files, for applications embedding the scraper.
+/// 
+/// Works like `scraper <url>` with the given options, calling the middleware
+/// registered on them for every page. The FireCrawl API URL and key are read
+/// from the environment as by the command line tool; the configuration file
+/// and `.env` are not read.
+/// 
+/// # Arguments
+/// 
+/// * `start_url` - The URL to start scraping from; without a scheme, HTTPS and then HTTP are tried
+/// * `options` - Options controlling how pages are scraped and saved, e.g. from `ScrapeOptions::parse_args`
+/// 
+/// # Errors
+/// 
+/// Returns an error if `FIRECRAWL_API_KEY` is not set for the FireCrawl
+/// backend, or in the same cases as a scrape from the command line. Pages
+/// that fail don't fail the scrape; they are listed in the run report.
+/// 
+/// # Examples
+/// 
+/// ```no_run
+/// # use scraper::ScrapeOptions;
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let options = ScrapeOptions::parse_args(["--output-dir", "mirrors"])?;
+/// scraper::scrape("https://docs.example.com", &options).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn scrape(start_url: &str, options: &ScrapeOptions) -> Result<(), Box<dyn Error>> {
+    let api_key = api_key(options.backend)?;
+    scrape_documentation(&Client::new(), &api_url(), &api_key, start_url, options).await?;
+    Ok(())
+}
+
+/// Runs the `scraper` command line tool.
+/// 
+/// Parses the command line, loads `.env` and the configuration file, and runs
+/// the requested command.
+/// 
+/// # Returns
+/// 
+/// The exit status of a run that didn't fail as a whole: 130 if Ctrl-C or
+/// SIGTERM stopped it, `EXIT_PAGES_FAILED` if pages failed, `EXIT_PAGES_WARNED`
+/// if pages were saved with warnings and `--fail-on-warning` was given, and
+/// success otherwise
+/// 
+/// Environment variables:
+/// - FIRECRAWL_API_URL: Optional. Defaults to "https://api.firecrawl.dev"
+/// - FIRECRAWL_API_KEY: Required. Your API authentication key
+/// 
+/// Usage: cargo run -- <url> [options]
+///        cargo run -- --from-file <file> [options]
+///        cargo run -- refresh <dir> [options]
+///        cargo run -- resume <dir>
+///        cargo run -- map <url> [--save <file>]
+///        cargo run -- compare <url> [--sample <n>] [--out <dir>]
+///        cargo run -- import <dir>
+/// Example: cargo run -- https://docs.example.com --citation-footer
+pub async fn run() -> Result<ExitCode, Box<dyn Error>> {
+    // Parse command line arguments
+    let cli = Cli::parse();
+    logging::init(cli.verbose, cli.quiet, cli.debug_bundle.is_some());
+    shutdown::install();
+    let Some(bundle_path) = cli.debug_bundle.clone() else {
+        return run_command(cli).await;
+    };
+
+    debug_bundle::enable();
+    let result = run_command(cli).await;
+    let ended = match &result {
+        Ok(_) => "finished".to_string(),
+        Err(e) => format!("failed: {}", e),
+    };
+    match debug_bundle::write(&bundle_path, &ended) {
+        Ok(()) => info!("Debug bundle written to: {}", bundle_path.display()),
+        Err(e) => error!("Failed to write debug bundle {}: {}", bundle_path.display(), e),
+    }
+    result
+}
+
+/// Runs the command parsed from the command line.
+/// 
+/// # Returns
+/// 
+/// The exit status of the command, as for `run`
+/// 
+/// # Errors
+/// 
+/// Returns an error if the command fails as a whole
+async fn run_command(cli: Cli) -> Result<ExitCode, Box<dyn Error>> {
+    // A resumed run keeps the command line of the run it continues
+    if !matches!(cli.command, Some(Command::Resume { .. })) {
+        checkpoint::record_command(std::env::args().skip(1).collect());
+    }
+
+    // Load environment variables
+    dotenv().ok();
+
+    // Initialize HTTP client
+    let client = Client::new();
+
+    // Get API configuration
+    let api_url = api_url();
+    
+    match cli.command {
+        Some(Command::Refresh { dir, mut options }) => {
+            Config::load(options.config.as_deref())?.apply(&mut options)?;
+            let api_key = api_key(options.backend)?;
+            let pages = refresh_mirror(&client, &api_url, &api_key, &dir, &options).await?;
+            return Ok(exit_status(pages, options.fail_on_warning));
+        }
+        Some(Command::Resume { dir }) => {
+            let checkpoint = Checkpoint::load(&dir)?.ok_or_else(|| format!("No interrupted run to resume in {}", dir.display()))?;
+            let options = checkpoint_options(&checkpoint.command)?;
+            checkpoint::record_command(checkpoint.command);
+            let api_key = api_key(options.backend)?;
+            let pages = resume_run(&client, &api_url, &api_key, &dir, &options).await?;
+            return Ok(exit_status(pages, options.fail_on_warning));
+        }
+        Some(Command::Map { url, save, mut options }) => {
+            Config::load(options.config.as_deref())?.apply(&mut options)?;
+            let api_key = api_key(options.backend)?;
+            let url = input_url::resolve(&client, &url, &options).await?;
+            let urls = map_site(&client, &api_url, &api_key, &url, &options).await?;
+            match save {
+                Some(path) => {
+                    let list: String = urls.iter().map(|url| format!("{}\n", url)).collect();
+                    fs::write(&path, list).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                    println!("Saved {} URLs to {}", urls.len(), path.display());
+                }
+                None => {
+                    for url in &urls {
+                        println!("{}", url);
+                    }
+                }
+            }
+        }
+        Some(Command::Compare { url, sample, out, mut options }) => {
+            Config::load(options.config.as_deref())?.apply(&mut options)?;
+            let api_key = api_key(options.backend)?;
+            let url = input_url::resolve(&client, &url, &options).await?;
+            let mut urls = map_site(&client, &api_url, &api_key, &url, &options).await?;
+            if urls.is_empty() {
+                urls.push(url);
+            }
+            let pages = compare::sample(&urls, sample);
+            let pacer = credit_pacer(&options, &api_key)?;
+            let (comparisons, report) =
+                compare::compare_pages(&client, &api_url, &api_key, &pages, &out, &options, &pacer, &retrier(&options))
+                    .await?;
+            println!("Compared {} of {} sampled pages; report written to {}", comparisons.len(), pages.len(), report.display());
+        }
+        Some(Command::Run { name, args }) => {
+            let (url, options) = config::site_options(&name, &args)?;
+            let api_key = api_key(options.backend)?;
+            let pages = scrape_documentation(&client, &api_url, &api_key, &url, &options).await?;
+            return Ok(exit_status(pages, options.fail_on_warning));
+        }
+        Some(Command::RunAll { file, sites, force }) => {
+            let report = workspace::run_all(&client, &api_url, &file, &sites, force).await?;
+            let skipped = report
+                .sites
+                .iter()
+                .filter(|site| matches!(site.status, workspace::SiteStatus::Skipped | workspace::SiteStatus::Paused))
+                .count();
+            println!("Ran {} sites ({} skipped or paused)", report.sites.len() - skipped, skipped);
+        }
+        Some(Command::Daemon { file, sites, status_addr }) => {
+            workspace::run_daemon(&client, &api_url, &file, &sites, status_addr).await?;
+            return Ok(ExitCode::from(shutdown::EXIT_INTERRUPTED));
+        }
+        Some(Command::Unpause { sites, file }) => {
+            let unpaused = workspace::unpause(&file, &sites)?;
+            for site in &sites {
+                if unpaused.contains(site) {
+                    println!("Unpaused: {}", site);
+                } else {
+                    println!("Not paused: {}", site);
+                }
+            }
+        }
+        Some(Command::Init { template: None, dir, .. }) => {
+            let written = wizard::run(&dir, &mut io::stdin().lock(), &mut io::stderr())?;
+            for path in &written {
+                println!("Wrote: {}", path.display());
+            }
+            if !written.is_empty() {
+                println!("Run `scraper <url>` here to scrape a site with these settings");
+            }
+        }
+        Some(Command::Init { template: Some(template), dir, url, name }) => {
+            let written = scaffold::init(template, &dir, url.as_deref(), name.as_deref())?;
+            for path in &written {
+                println!("Created: {}", path.display());
+            }
+            println!("Edit {} and run `scraper run-all` there to mirror the site", dir.join(workspace::DEFAULT_WORKSPACE_FILE).display());
+        }
+        #[cfg(feature = "test-harness")]
+        Some(Command::Harness { fixtures, out, chaos, mut options }) => {
+            Config::load(options.config.as_deref())?.apply(&mut options)?;
+            // Fixture sites must not leave profiles behind for the real ones
+            options.no_domain_profiles = true;
+            if let Some(chaos) = &chaos {
+                info!("Chaos mode, seed {}", chaos.seed);
+            }
+            let summary = harness::run(&fixtures, &out, &options, chaos.as_ref()).await?;
+            for (url, file) in &summary.saved {
+                println!("Saved {} as {}", url, file);
+            }
+            for url in &summary.not_saved {
+                println!("Not saved: {}", url);
+            }
+            println!(
+                "{} of {} fixture pages saved to {} ({} API requests)",
+                summary.saved.len(),
+                summary.saved.len() + summary.not_saved.len(),
+                out.display(),
+                summary.requests
+            );
+        }
+        Some(Command::Completions { shell }) => {
+            completions::generate(shell, &mut io::stdout().lock())?;
+        }
+        Some(Command::Import { dir }) => {
+            let summary = mirror::import_mirror(&dir)?;
+            println!(
+                "Imported {} pages into {} ({} already tracked)",
+                summary.imported,
+                Manifest::path(&dir).display(),
+                summary.already_tracked
+            );
+        }
+        Some(Command::PreviewReplacements { dir, config }) => {
+            let mut options = ScrapeOptions::default();
+            Config::load(config.as_deref())?.apply(&mut options)?;
+            let preview = replace::preview(&dir, &options.replace_rules)?;
+            for page in &preview.pages {
+                println!("{} ({}): {} replacements", page.file, page.url, page.replacements);
+                for (before, after) in page.lines.iter().take(replace::PREVIEW_LINES) {
+                    println!("  - {}", before.trim());
+                    println!("  + {}", after.trim());
+                }
+                if page.lines.len() > replace::PREVIEW_LINES {
+                    println!("  ... and {} more changed lines", page.lines.len() - replace::PREVIEW_LINES);
+                }
+            }
+            println!(
+                "{} of {} pages would change ({} replacements); nothing was written",
+                preview.pages.len(),
+                preview.pages_checked,
+                preview.pages.iter().map(|page| page.replacements).sum::<usize>()
+            );
+        }
+        None => {
+            let mut options = cli.options;
+            Config::load(options.config.as_deref())?.apply(&mut options)?;
+            let api_key = api_key(options.backend)?;
+            let pages = match cli.from_file {
+                Some(list) => scrape_url_list(&client, &api_url, &api_key, &list, &options).await?,
+                None => {
+                    let url = cli.url.ok_or("A URL to scrape is required")?;
+                    scrape_documentation(&client, &api_url, &api_key, &url, &options).await?
+                }
+            };
+            return Ok(exit_status(pages, options.fail_on_warning));
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Exit status of a run in which pages failed.
+pub const EXIT_PAGES_FAILED: u8 = 2;
+
+/// Exit status of a run in which pages were saved with warnings, with `--fail-on-warning`.
+pub const EXIT_PAGES_WARNED: u8 = 3;
+
+/// Returns the exit status of a run from its pages that failed or were saved with warnings.
+/// 
+/// Failed pages always make the run fail; pages saved with warnings only
+/// with `--fail-on-warning`. An interrupted run exits as interrupted whatever
+/// its pages.
+fn exit_status(pages: PageCounts, fail_on_warning: bool) -> ExitCode {
+    if pages.interrupted {
+        return ExitCode::from(shutdown::EXIT_INTERRUPTED);
+    }
+    if pages.failed > 0 {
+        error!("{} pages failed; see the run report", pages.failed);
+        return ExitCode::from(EXIT_PAGES_FAILED);
+    }
+    if pages.with_warnings > 0 {
+        if fail_on_warning {
+            error!("{} pages were saved with warnings (--fail-on-warning); see the manifest", pages.with_warnings);
+            return ExitCode::from(EXIT_PAGES_WARNED);
+        }
+        warn!("{} pages were saved with warnings; see the manifest", pages.with_warnings);
+    }
+    ExitCode::SUCCESS
+}
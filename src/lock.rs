@@ -0,0 +1,207 @@
+//! Exclusive use of an output directory by one run at a time.
+//!
+//! Two runs writing to the same output directory would interleave their page
+//! files and overwrite each other's manifest. Every run that writes takes a
+//! lock on `.scraper/run.lock` for as long as it runs, recording which process
+//! holds it. The lock is held by the operating system, so it is released even
+//! when a run crashes or is killed.
+//!
+//! A second run against a locked directory fails by default. With
+//! `--if-locked wait` it waits for the first run to finish and then runs
+//! itself; with `--if-locked observe` it follows the first run without
+//! writing anything and ends with that run's outcome.
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::report::{PageCounts, REPORT_FILE_NAME};
+
+/// Name of the lock file inside the state directory.
+pub const LOCK_FILE_NAME: &str = "run.lock";
+
+/// How often a waiting run checks whether the lock was released.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a waiting run logs that it is still waiting.
+const STATUS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What a run does when another run holds the lock on its output directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum IfLocked {
+    /// Fail without touching the directory
+    #[default]
+    Fail,
+    /// Wait for the other run to finish, then run
+    Wait,
+    /// Follow the other run until it finishes and report its outcome, without running
+    Observe,
+}
+
+/// The run holding a lock, as recorded in the lock file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockHolder {
+    /// Process ID of the run
+    pid: u32,
+
+    /// When the run took the lock
+    started_at: DateTime<Utc>,
+
+    /// The URL or directory the run started from
+    start_url: String,
+}
+
+/// Outcome of taking the lock on an output directory.
+#[derive(Debug)]
+pub enum Acquired {
+    /// The lock was taken; the run goes ahead while it holds it
+    Locked(RunLock),
+    /// Another run was observed to its end; its page counts decide the exit status
+    Observed(PageCounts),
+}
+
+/// The lock on an output directory, released when dropped.
+#[derive(Debug)]
+pub struct RunLock {
+    /// The open lock file; closing it releases the lock
+    _file: File,
+}
+
+impl RunLock {
+    /// Takes the lock on `output_dir` for a run starting from `start_url`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the lock, or with `IfLocked::Observe` and another
+    /// run holding the lock, that run's page counts once it has finished
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock file cannot be opened or written, or with
+    /// `IfLocked::Fail` if another run holds the lock
+    pub async fn acquire(
+        output_dir: &Path,
+        start_url: &str,
+        if_locked: IfLocked,
+    ) -> Result<Acquired, Box<dyn Error>> {
+        let dir = crate::state_dir(output_dir);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let path = dir.join(LOCK_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| format!("Failed to open lock file {}: {}", path.display(), e))?;
+
+        if !try_lock(&file)? {
+            let holder = describe_holder(&mut file);
+            match if_locked {
+                IfLocked::Fail => {
+                    return Err(format!(
+                        "{} is in use by another run ({}); pass --if-locked wait to run after it or --if-locked observe to follow it",
+                        output_dir.display(),
+                        holder
+                    )
+                    .into());
+                }
+                IfLocked::Wait => {
+                    info!("{} is in use by another run ({}); waiting for it to finish", output_dir.display(), holder);
+                    wait_for_release(&file).await?;
+                }
+                IfLocked::Observe => {
+                    info!("Observing the run using {} ({})", output_dir.display(), holder);
+                    wait_for_release(&file).await?;
+                    // The lock is released with the file; this run doesn't write anything
+                    return Ok(Acquired::Observed(observed_outcome(output_dir)));
+                }
+            }
+        }
+
+        let holder = LockHolder {
+            pid: std::process::id(),
+            started_at: Utc::now(),
+            start_url: start_url.to_string(),
+        };
+        let holder = serde_json::to_string(&holder)?;
+        file.set_len(0)
+            .and_then(|()| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| file.write_all(holder.as_bytes()))
+            .map_err(|e| format!("Failed to write lock file {}: {}", path.display(), e))?;
+        Ok(Acquired::Locked(RunLock { _file: file }))
+    }
+}
+
+/// Tries to take the lock on an open lock file, returning whether it was taken.
+fn try_lock(file: &File) -> Result<bool, Box<dyn Error>> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(e)) => Err(format!("Failed to lock the output directory: {}", e).into()),
+    }
+}
+
+/// Waits until the lock can be taken, logging every `STATUS_INTERVAL` that the other run is still going.
+async fn wait_for_release(file: &File) -> Result<(), Box<dyn Error>> {
+    let started = Instant::now();
+    let mut last_status = Instant::now();
+    while !try_lock(file)? {
+        if last_status.elapsed() >= STATUS_INTERVAL {
+            info!("The other run is still going after {} seconds", started.elapsed().as_secs());
+            last_status = Instant::now();
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Ok(())
+}
+
+/// Describes the run recorded in a lock file, for messages.
+fn describe_holder(file: &mut File) -> String {
+    let mut content = String::new();
+    let holder = file
+        .read_to_string(&mut content)
+        .ok()
+        .and_then(|_| serde_json::from_str::<LockHolder>(&content).ok());
+    match holder {
+        Some(holder) => format!(
+            "process {} scraping {} since {}",
+            holder.pid,
+            holder.start_url,
+            holder.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        None => "unknown process".to_string(),
+    }
+}
+
+/// Logs the outcome of an observed run from its report and returns its page counts.
+fn observed_outcome(output_dir: &Path) -> PageCounts {
+    let report = fs::read_to_string(crate::state_dir(output_dir).join(REPORT_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .unwrap_or_default();
+    let count = |field: &str| report[field].as_u64().unwrap_or_default() as usize;
+
+    if report["finishedAt"].is_null() {
+        warn!("The observed run stopped before finishing; see its log for the reason");
+    }
+    info!(
+        "The observed run processed {} pages; {} failed and {} came back empty",
+        count("pagesProcessed"),
+        count("pagesFailed"),
+        count("pagesEmpty")
+    );
+    PageCounts {
+        failed: count("pagesFailed") + count("pagesEmpty"),
+        with_warnings: count("pagesWithWarnings"),
+        interrupted: report["interrupted"].as_bool().unwrap_or_default(),
+    }
+}
@@ -0,0 +1,53 @@
+//! Log output.
+//!
+//! Progress and problems are logged with `tracing` to standard error, while the
+//! results a command produces (a dry run's page list, the harness summary) go to
+//! standard output. Every page is processed inside a `page` span carrying its
+//! URL, and every site of a workspace run inside a `site` span carrying its
+//! name, so the lines of a large crawl can be attributed and filtered.
+
+use std::io::IsTerminal;
+use tracing::Level;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::debug_bundle;
+
+/// Sets up logging for the given verbosity.
+///
+/// By default the scraper logs at info level. Each `-v` raises it one step, to
+/// debug and then trace, and `--quiet` lowers it to warnings and errors.
+/// Messages of dependencies are only shown from warn level. A `RUST_LOG`
+/// filter replaces all of this, e.g. `RUST_LOG=scraper=debug,reqwest=trace`.
+///
+/// # Arguments
+///
+/// * `verbose` - Number of times `-v` was given
+/// * `quiet` - Whether `--quiet` was given
+/// * `debug_bundle` - Whether to also capture the log at debug level for `--debug-bundle`
+pub fn init(verbose: u8, quiet: bool, debug_bundle: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => Level::WARN,
+        (false, 0) => Level::INFO,
+        (false, 1) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,{}={}", env!("CARGO_CRATE_NAME"), level)));
+
+    let console = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_target(false)
+        .with_filter(filter);
+    // The bundle gets the details a bug report needs, whatever the console shows
+    let bundle = debug_bundle.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(|| debug_bundle::LogWriter)
+            .with_ansi(false)
+            .with_target(false)
+            .with_filter(EnvFilter::new(format!("warn,{}=debug", env!("CARGO_CRATE_NAME"))))
+    });
+    tracing_subscriber::registry().with(console).with(bundle).init();
+}
@@ -0,0 +1,355 @@
+//! Manifest of scraped pages.
+//!
+//! The manifest lives in the state directory of an output directory and maps
+//! every scraped URL to the file it was saved to, along with information
+//! needed by later runs (such as HTTP cache validators).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::media::MediaFile;
+use crate::metrics::CallMetrics;
+use crate::page_type::PageType;
+use crate::quality::Quality;
+use crate::tombstone::Removal;
+use crate::ChangeStatus;
+
+/// File name of the manifest inside the state directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Record of a single scraped page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    /// Stable identifier of the page, derived from its URL (see `page_id`), that stays the same
+    /// when the page's title and file name change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Path of the saved file, relative to the output directory
+    pub file: String,
+
+    /// Title of the page, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// When the page content was last downloaded
+    pub scraped_at: DateTime<Utc>,
+
+    /// Size in bytes of the saved file when it was written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+
+    /// Hash of the saved file when it was written, as returned by `file_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_hash: Option<String>,
+
+    /// Hash of the saved content, leaving out the date of the latest scrape; a
+    /// later scrape producing the same hash leaves the file untouched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+
+    /// Whether FireCrawl found the page changed at the last scrape, with `--change-tracking`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_status: Option<ChangeStatus>,
+
+    /// `ETag` response header from the last direct fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    /// `Last-Modified` response header from the last direct fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+
+    /// `Cache-Control` response header from the last direct fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+
+    /// Latency, size and status of the API call that last scraped the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_call: Option<CallMetrics>,
+
+    /// URL of the page linked as "Previous" from this page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+
+    /// URL of the page linked as "Next" from this page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+
+    /// Kind of page, as classified when it was scraped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_type: Option<PageType>,
+
+    /// Quality score of the page's extraction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<Quality>,
+
+    /// Set when the page was removed from the site and its file replaced by a tombstone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed: Option<Removal>,
+
+    /// Problems with the page's metadata or content found when it was last saved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+
+    /// Formats left out of the last scrape because the response was larger
+    /// than `--max-response-size`, with `--drop-oversized-formats`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dropped_formats: Vec<String>,
+
+    /// Screenshots and downloaded assets saved with the page, also listed in its frontmatter
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<MediaFile>,
+}
+
+impl ManifestEntry {
+    /// Returns whether the saved file still has the size and hash recorded when it was written.
+    ///
+    /// The size is compared first, so most changed files are detected without
+    /// reading them. Entries written before sizes and hashes were recorded
+    /// cannot be verified and are trusted as long as their file exists.
+    pub fn file_intact(&self, output_dir: &Path) -> bool {
+        let path = output_dir.join(&self.file);
+        let Ok(metadata) = fs::metadata(&path) else {
+            return false;
+        };
+        if self.size.is_some_and(|size| size != metadata.len()) {
+            return false;
+        }
+        match &self.file_hash {
+            Some(hash) => fs::read(&path).is_ok_and(|content| file_hash(&content) == *hash),
+            None => true,
+        }
+    }
+}
+
+/// Returns the stable identifier of the page at a canonical URL: the first 16 hex digits of its SHA-256.
+///
+/// The identifier depends on the URL only, so it stays the same when the
+/// page's title, and with it the name of its file, changes between runs.
+///
+/// # Examples
+///
+/// ```text
+/// let id = manifest::page_id("https://docs.example.com/guide/setup");
+/// assert_eq!(id.len(), 16);
+/// ```
+pub fn page_id(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))[..16].to_string()
+}
+
+/// Returns the hash of a saved file's content, as `sha256:<hex>`.
+pub fn file_hash(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+/// Record of a page the API answered successfully but without any markdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmptyContentEntry {
+    /// When the empty response was received
+    pub detected_at: DateTime<Utc>,
+
+    /// HTTP status code the page was served with, as reported by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<i32>,
+
+    /// Path of the cached raw API response, relative to the output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_file: Option<String>,
+
+    /// Latency, size and status of the API call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_call: Option<CallMetrics>,
+}
+
+/// Record of a page whose last scrape failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedEntry {
+    /// When the scrape failed
+    pub failed_at: DateTime<Utc>,
+
+    /// Why it failed
+    pub error: String,
+
+    /// Runs in a row the page has failed in
+    #[serde(default = "first_failure")]
+    pub attempts: u32,
+
+    /// Earliest time a later run retries the page; not set once it is given up on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<DateTime<Utc>>,
+}
+
+/// Number of failed runs of an entry recorded before failed runs were counted.
+fn first_failure() -> u32 {
+    1
+}
+
+/// All pages scraped into an output directory, keyed by URL.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    /// Entries keyed by page URL
+    pub pages: BTreeMap<String, ManifestEntry>,
+
+    /// Pages whose last scrape returned no markdown, keyed by page URL
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub empty_content: BTreeMap<String, EmptyContentEntry>,
+
+    /// Pages whose last scrape failed, keyed by page URL; a previously saved file is kept
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub failed: BTreeMap<String, FailedEntry>,
+
+    /// Pages given up on after failing in `--max-failures` runs in a row, keyed by page URL
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub permanently_failing: BTreeMap<String, FailedEntry>,
+
+    /// Page URLs in reading order, following "Previous"/"Next" navigation links
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reading_order: Vec<String>,
+}
+
+impl Manifest {
+    /// Returns the manifest path for the given output directory.
+    pub fn path(output_dir: &Path) -> PathBuf {
+        crate::state_dir(output_dir).join(MANIFEST_FILE_NAME)
+    }
+
+    /// Loads the manifest of an output directory, returning an empty manifest if none exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest exists but cannot be read or parsed
+    pub fn load(output_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        if !path.exists() {
+            return Ok(Manifest::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+        let mut manifest: Manifest = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse manifest {}: {}", path.display(), e))?;
+        // Manifests written before pages had identifiers get them on loading
+        for (url, entry) in &mut manifest.pages {
+            entry.id.get_or_insert_with(|| page_id(url));
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest into the state directory of `output_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state directory cannot be created or the file cannot be written
+    pub fn save(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| format!("Failed to write manifest {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// Checks every saved file against the size and hash recorded when it was written.
+    ///
+    /// Files are checked in parallel. Cache validators are cleared for pages
+    /// whose file was edited or corrupted since, so the direct backend
+    /// downloads them again instead of trusting the file as unchanged. Missing
+    /// files are not reported, as they are never trusted.
+    ///
+    /// # Returns
+    ///
+    /// The files found modified, relative to `output_dir`, in sorted order
+    pub fn verify_files(&mut self, output_dir: &Path) -> Vec<String> {
+        let entries: Vec<(&String, &ManifestEntry)> = self
+            .pages
+            .iter()
+            .filter(|(_, entry)| entry.removed.is_none() && output_dir.join(&entry.file).exists())
+            .collect();
+        let workers = thread::available_parallelism().map_or(4, |n| n.get());
+        let chunk_size = entries.len().div_ceil(workers).max(1);
+
+        let modified: Vec<String> = thread::scope(|scope| {
+            let handles: Vec<_> = entries
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .filter(|(_, entry)| !entry.file_intact(output_dir))
+                            .map(|(url, _)| (*url).clone())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_default())
+                .collect()
+        });
+
+        let mut files = Vec::new();
+        for url in modified {
+            if let Some(entry) = self.pages.get_mut(&url) {
+                entry.etag = None;
+                entry.last_modified = None;
+                entry.cache_control = None;
+                entry.content_hash = None;
+                files.push(entry.file.clone());
+            }
+        }
+        files.sort();
+        files
+    }
+
+    /// Recomputes `reading_order` from the navigation links of the pages.
+    ///
+    /// Pages linked by "Next" (or, failing that, by the following page's
+    /// "Previous") are chained together. Each chain is placed where its first
+    /// page falls in URL order; pages outside any chain keep their URL order.
+    /// Links to pages that are not in the manifest are ignored.
+    pub fn update_reading_order(&mut self) {
+        let mut next: HashMap<&str, &str> = HashMap::new();
+        for (url, entry) in &self.pages {
+            if let Some(target) = entry.next.as_deref().filter(|t| self.pages.contains_key(*t)) {
+                next.insert(url, target);
+            }
+        }
+        for (url, entry) in &self.pages {
+            if let Some(source) = entry.previous.as_deref().filter(|s| self.pages.contains_key(*s)) {
+                next.entry(source).or_insert(url);
+            }
+        }
+
+        let targets: HashSet<&str> = next.values().copied().collect();
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut order = Vec::with_capacity(self.pages.len());
+
+        // Chain heads first, then whatever is left (pages in navigation cycles)
+        let heads = self.pages.keys().filter(|url| !targets.contains(url.as_str()));
+        for start in heads.chain(self.pages.keys()) {
+            let mut current = Some(start.as_str());
+            while let Some(url) = current {
+                if !visited.insert(url) {
+                    break;
+                }
+                order.push(url.to_string());
+                current = next.get(url).copied();
+            }
+        }
+
+        self.reading_order = order;
+    }
+}
@@ -0,0 +1,386 @@
+//! Helpers for inspecting and augmenting the markdown returned by the FireCrawl API.
+
+use chrono::{DateTime, Utc};
+
+/// Separator placed between the parts of a heading path.
+pub const HEADING_PATH_SEPARATOR: &str = " > ";
+
+/// A heading of a markdown document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Heading level, 1 for `#` through 6 for `######`
+    pub level: usize,
+    /// Heading text with inline markup removed
+    pub text: String,
+}
+
+/// The opening line of a fenced code block.
+///
+/// The block ends at a line of at least as many of the same character and
+/// nothing else, so a ```` ```` ```` block can show a ```` ``` ```` fence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fence {
+    /// `` ` `` or `~`
+    marker: char,
+    /// Number of markers opening the block, at least 3
+    length: usize,
+}
+
+impl Fence {
+    /// Returns the fence a line opens, if it starts a fenced code block.
+    pub(crate) fn open(line: &str) -> Option<Fence> {
+        let trimmed = line.trim_start();
+        let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let length = trimmed.chars().take_while(|c| *c == marker).count();
+        // After backticks, another backtick makes the line inline code rather than a fence
+        if length < 3 || (marker == '`' && trimmed[length..].contains('`')) {
+            return None;
+        }
+        Some(Fence { marker, length })
+    }
+
+    /// Returns whether a line inside the block closes it.
+    pub(crate) fn closes(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        let length = trimmed.chars().take_while(|c| *c == self.marker).count();
+        length >= self.length && length == trimmed.len()
+    }
+}
+
+/// Returns every heading of a markdown document, in document order.
+///
+/// Both ATX (`## Title`) and setext (`Title` underlined with `===` or `---`)
+/// headings are recognized; headings inside fenced code blocks are ignored.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::markdown::{headings, Heading};
+/// let headings = headings("# Install\n\nUsage\n-----\n");
+/// assert_eq!(headings[1], Heading { level: 2, text: "Usage".to_string() });
+/// ```
+pub fn headings(markdown: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut fence: Option<Fence> = None;
+
+    let lines: Vec<&str> = markdown.lines().collect();
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+
+        if let Some(open) = fence {
+            if open.closes(trimmed) {
+                fence = None;
+            }
+            continue;
+        }
+        fence = Fence::open(trimmed);
+        if fence.is_some() {
+            continue;
+        }
+
+        let heading = parse_atx_heading(trimmed)
+            .or_else(|| parse_setext_heading(trimmed, lines.get(index + 1).copied()));
+        if let Some((level, text)) = heading {
+            headings.push(Heading { level, text });
+        }
+    }
+
+    headings
+}
+
+/// Returns the full heading path of every heading in a markdown document.
+///
+/// Headings are found as described for `headings`. The path of a heading is made of
+/// the nearest preceding heading at each shallower level, so skipped levels
+/// (e.g. `#` followed directly by `###`) do not produce empty path segments.
+///
+/// # Arguments
+///
+/// * `markdown` - The markdown document to scan
+///
+/// # Returns
+///
+/// The heading paths in document order
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::markdown::heading_paths;
+/// let paths = heading_paths("# Installation\n## Linux\n### apt\n");
+/// assert_eq!(paths, vec!["Installation", "Installation > Linux", "Installation > Linux > apt"]);
+/// ```
+pub fn heading_paths(markdown: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+
+    for Heading { level, text } in headings(markdown) {
+        while stack.last().is_some_and(|(l, _)| *l >= level) {
+            stack.pop();
+        }
+        stack.push((level, text));
+
+        paths.push(
+            stack
+                .iter()
+                .map(|(_, t)| t.as_str())
+                .collect::<Vec<_>>()
+                .join(HEADING_PATH_SEPARATOR),
+        );
+    }
+
+    paths
+}
+
+/// Parses a single line as an ATX heading, returning its level and cleaned text.
+fn parse_atx_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') && !rest.starts_with('\t') {
+        return None;
+    }
+
+    // A closing sequence (`## Title ##`) is not part of the heading text, but a `#` ending a word (`C#`) is
+    let rest = rest.trim();
+    let unclosed = rest.trim_end_matches('#');
+    let rest = if unclosed.is_empty() || unclosed.ends_with([' ', '\t']) { unclosed.trim_end() } else { rest };
+    let text = strip_inline_markup(rest);
+    if text.is_empty() {
+        return None;
+    }
+
+    Some((level, text))
+}
+
+/// Parses a line followed by a `===` or `---` underline as a setext heading.
+fn parse_setext_heading(line: &str, next: Option<&str>) -> Option<(usize, String)> {
+    let underline = next?.trim();
+    let level = if !underline.is_empty() && underline.chars().all(|c| c == '=') {
+        1
+    } else if underline.len() >= 2 && underline.chars().all(|c| c == '-') {
+        2
+    } else {
+        return None;
+    };
+
+    // List items and block quotes followed by a rule are not headings
+    if line.starts_with(['-', '*', '+', '>', '|']) {
+        return None;
+    }
+
+    let text = strip_inline_markup(line.trim());
+    if text.is_empty() {
+        return None;
+    }
+
+    Some((level, text))
+}
+
+/// Removes links, emphasis markers and zero-width characters from heading text.
+///
+/// Documentation generators frequently append permalink anchors to headings
+/// (e.g. `## Usage [​](#usage)`), which would otherwise end up in the path.
+fn strip_inline_markup(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '[' => {
+                // Keep the label of `[label](target)` and drop the target
+                if let Some(close) = text[i..].find("](") {
+                    let label_end = i + close;
+                    if let Some(target_len) = text[label_end + 2..].find(')') {
+                        result.push_str(&strip_inline_markup(&text[i + 1..label_end]));
+                        let skip_to = label_end + 2 + target_len + 1;
+                        while chars.peek().is_some_and(|(j, _)| *j < skip_to) {
+                            chars.next();
+                        }
+                        continue;
+                    }
+                }
+                result.push(c);
+            }
+            '*' | '`' => {}
+            '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{feff}' => {}
+            _ => result.push(c),
+        }
+    }
+
+    result.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Builds the provenance footer appended to saved pages.
+///
+/// The footer is separated from the page content by a horizontal rule and lists the
+/// canonical source URL, the scrape date and, when given, a license note.
+///
+/// # Arguments
+///
+/// * `source_url` - Canonical URL the content was scraped from
+/// * `scraped_at` - Time the page was scraped
+/// * `license_note` - Optional license or usage statement
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::markdown::citation_footer;
+/// let footer = citation_footer("https://docs.example.com/page", chrono::Utc::now(), Some("CC BY 4.0"));
+/// // Results in:
+/// //
+/// // ---
+/// //
+/// // > Source: <https://docs.example.com/page>
+/// // > Scraped: 2024-01-01T12:00:00+00:00
+/// // > License: CC BY 4.0
+/// ```
+pub fn citation_footer(
+    source_url: &str,
+    scraped_at: DateTime<Utc>,
+    license_note: Option<&str>,
+) -> String {
+    let mut footer = String::from("\n\n---\n\n");
+    footer.push_str(&format!("> Source: <{}>  \n", source_url));
+    footer.push_str(&format!("> Scraped: {}", scraped_at.to_rfc3339()));
+    if let Some(note) = license_note {
+        footer.push_str(&format!("  \n> License: {}", note));
+    }
+    footer.push('\n');
+    footer
+}
+
+/// Returns a saved page body without the citation footer added by `citation_footer`.
+///
+/// Bodies without a footer are returned unchanged.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::markdown::{citation_footer, strip_citation_footer};
+/// let body = format!("# Intro{}", citation_footer("https://docs.example.com/", chrono::Utc::now(), None));
+/// assert_eq!(strip_citation_footer(&body), "# Intro");
+/// ```
+pub fn strip_citation_footer(body: &str) -> &str {
+    match body.rfind("\n\n---\n\n> Source: <") {
+        Some(start) => &body[..start],
+        None => body,
+    }
+}
+
+/// Replaces the markdown tables of a document with the given HTML tables, in order.
+///
+/// A markdown table is a run of lines starting with `|` that contains a
+/// delimiter row; tables inside fenced code blocks are left alone. Tables are
+/// only replaced when the number of markdown tables matches the number of HTML
+/// tables, since otherwise they cannot be paired reliably.
+///
+/// # Returns
+///
+/// The document with its tables replaced, or `None` if the counts don't match
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::markdown::replace_tables;
+/// let markdown = "Intro\n\n| a |\n|---|\n| 1 |\n\nEnd";
+/// let replaced = replace_tables(markdown, &["<table><tr><td>1</td></tr></table>"]).unwrap();
+/// assert_eq!(replaced, "Intro\n\n<table><tr><td>1</td></tr></table>\n\nEnd");
+/// ```
+pub fn replace_tables(markdown: &str, html_tables: &[&str]) -> Option<String> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut tables = Vec::new();
+    let mut fence: Option<Fence> = None;
+    let mut index = 0;
+
+    while index < lines.len() {
+        let trimmed = lines[index].trim_start();
+        if let Some(open) = fence {
+            if open.closes(trimmed) {
+                fence = None;
+            }
+            index += 1;
+            continue;
+        }
+        fence = Fence::open(trimmed);
+        if fence.is_some() {
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        while index < lines.len() && lines[index].trim_start().starts_with('|') {
+            index += 1;
+        }
+        if index == start {
+            index += 1;
+            continue;
+        }
+        if lines[start..index].iter().any(|line| is_table_delimiter(line)) {
+            tables.push(start..index);
+        }
+    }
+
+    if tables.is_empty() || tables.len() != html_tables.len() {
+        return None;
+    }
+
+    let mut result: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut next_line = 0;
+    for (range, html) in tables.iter().zip(html_tables) {
+        result.extend(&lines[next_line..range.start]);
+        result.push(html);
+        next_line = range.end;
+    }
+    result.extend(&lines[next_line..]);
+    Some(result.join("\n"))
+}
+
+/// Returns whether a line is a markdown table delimiter row such as `|---|:--:|`.
+pub fn is_table_delimiter(line: &str) -> bool {
+    let line = line.trim();
+    line.contains("---") && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closing_sequences_are_stripped_but_trailing_hashes_of_words_kept() {
+        assert_eq!(heading_paths("# Guide\n\n## C#"), ["Guide", "Guide > C#"]);
+        assert_eq!(heading_paths("## F# and C# ##"), ["F# and C#"]);
+        assert_eq!(heading_paths("## Title ###   \n### Notes#"), ["Title", "Title > Notes#"]);
+        assert!(heading_paths("## ##\n#hashtag").is_empty());
+    }
+
+    #[test]
+    fn longer_fences_are_not_closed_by_shorter_ones() {
+        let markdown = "# Guide\n\n````\n```\n## inside\n````\n\n## After\n~~~\n## tilde\n~~~\n## Done";
+        assert_eq!(heading_paths(markdown), ["Guide", "Guide > After", "Guide > Done"]);
+    }
+
+    #[test]
+    fn fences_close_only_on_the_same_marker_without_an_info_string() {
+        let markdown = "~~~~ text\n~~~\n```\n# not a heading\n```rust\n~~~~~\n# Heading\n";
+        assert_eq!(heading_paths(markdown), ["Heading"]);
+        // A fence left open hides the rest of the document
+        assert!(heading_paths("```\n# Install\n").is_empty());
+    }
+
+    #[test]
+    fn inline_code_is_not_a_fence() {
+        assert_eq!(Fence::open("``` `code` ```"), None);
+        assert_eq!(Fence::open("``"), None);
+        assert!(Fence::open("   ```rust").is_some());
+        assert!(Fence::open("~~~ `backticks` are fine").is_some());
+        assert_eq!(heading_paths("``` inline ```\n# Heading"), ["Heading"]);
+    }
+
+    #[test]
+    fn setext_headings_are_recognized() {
+        assert_eq!(heading_paths("Guide\n=====\n\nInstall\n---\n\n- item\n---\n"), ["Guide", "Guide > Install"]);
+    }
+}
@@ -0,0 +1,134 @@
+//! Descriptions of the media files saved with a page.
+//!
+//! Screenshots saved with `--formats screenshot` and the images and PDFs
+//! downloaded with `--download-assets` are recorded with their path, pixel
+//! dimensions and content hash, in the page's frontmatter and manifest entry,
+//! so whatever displays the page can show them without scanning directories.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Kinds of media files saved with a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MediaKind {
+    /// A screenshot of the page, saved next to its markdown file
+    Screenshot,
+    /// An image shown on the page, downloaded into `assets/`
+    Image,
+    /// A PDF document the page links to, downloaded into `assets/`
+    Pdf,
+}
+
+impl fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MediaKind::Screenshot => "screenshot",
+            MediaKind::Image => "image",
+            MediaKind::Pdf => "pdf",
+        })
+    }
+}
+
+/// A media file saved with a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaFile {
+    /// Path of the file, relative to the output directory
+    pub file: String,
+
+    /// What the file is to the page
+    pub kind: MediaKind,
+
+    /// Width in pixels, for images in a format whose size could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+
+    /// Height in pixels, for images in a format whose size could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+
+    /// Size of the file in bytes
+    pub size: u64,
+
+    /// SHA-256 hash of the file's content, in hex
+    pub hash: String,
+}
+
+impl MediaFile {
+    /// Describes the file `file`, relative to the output directory, from its content.
+    pub fn new(file: &str, kind: MediaKind, bytes: &[u8]) -> Self {
+        let dimensions = (kind != MediaKind::Pdf).then(|| dimensions(bytes)).flatten();
+        MediaFile {
+            file: file.to_string(),
+            kind,
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            size: bytes.len() as u64,
+            hash: format!("{:x}", Sha256::digest(bytes)),
+        }
+    }
+
+    /// Returns the path of the file relative to the page saved as `page_file`, as links in the page use it.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let image = MediaFile::new("assets/1f2e.png", MediaKind::Image, &bytes);
+    /// assert_eq!(image.path_from("guides/intro.md"), "../assets/1f2e.png");
+    /// ```
+    pub fn path_from(&self, page_file: &str) -> String {
+        let page_dir = page_file.rsplit_once('/').map_or("", |(dir, _)| dir);
+        match self.file.strip_prefix(page_dir).and_then(|rest| rest.strip_prefix('/')) {
+            Some(rest) => rest.to_string(),
+            _ => format!("{}{}", "../".repeat(page_file.matches('/').count()), self.file),
+        }
+    }
+}
+
+/// Reads the pixel dimensions of a PNG, GIF, JPEG or WebP image from its header.
+///
+/// # Returns
+///
+/// The width and height, or `None` for other formats and truncated files
+fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le24 = |at: usize| {
+        let b = bytes.get(at..at + 3)?;
+        Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+    };
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return match bytes.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+    if bytes.starts_with(b"\xff\xd8") {
+        // The size is in the first start-of-frame segment; every other segment is skipped by its length
+        let mut at = 2;
+        while *bytes.get(at)? == 0xff {
+            let marker = *bytes.get(at + 1)?;
+            let start_of_frame = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+            if start_of_frame {
+                return Some((be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
@@ -0,0 +1,97 @@
+//! Response metrics of FireCrawl API calls.
+//!
+//! Every scrape request records its latency, response size and HTTP status.
+//! The per-page values are kept in the manifest and summarized as percentiles
+//! in the run report, which shows which pages are slow or bloated before
+//! tuning wait times and timeouts.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Latency, size and status of a single API call.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallMetrics {
+    /// Time from sending the request to receiving the full response body, in milliseconds
+    pub latency_ms: u64,
+
+    /// Size of the response body in bytes
+    pub response_bytes: u64,
+
+    /// HTTP status code of the response
+    pub status: u16,
+}
+
+impl CallMetrics {
+    /// Creates metrics for a call that took `elapsed` and returned `response_bytes` bytes.
+    pub fn new(elapsed: Duration, response_bytes: usize, status: u16) -> Self {
+        CallMetrics {
+            latency_ms: u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+            response_bytes: response_bytes as u64,
+            status,
+        }
+    }
+}
+
+/// Percentile summary of the API calls made during a run.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSummary {
+    /// Number of calls summarized
+    pub calls: usize,
+
+    /// Median latency in milliseconds
+    pub latency_p50_ms: u64,
+
+    /// 95th percentile latency in milliseconds
+    pub latency_p95_ms: u64,
+
+    /// Median response size in bytes
+    pub response_bytes_p50: u64,
+
+    /// 95th percentile response size in bytes
+    pub response_bytes_p95: u64,
+}
+
+impl MetricsSummary {
+    /// Summarizes a set of calls, or returns `None` if there were none.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let calls = [CallMetrics::new(Duration::from_millis(120), 2048, 200)];
+    /// let summary = MetricsSummary::from_calls(&calls).unwrap();
+    /// assert_eq!(summary.latency_p95_ms, 120);
+    /// ```
+    pub fn from_calls(calls: &[CallMetrics]) -> Option<Self> {
+        if calls.is_empty() {
+            return None;
+        }
+
+        let mut latencies: Vec<u64> = calls.iter().map(|call| call.latency_ms).collect();
+        let mut sizes: Vec<u64> = calls.iter().map(|call| call.response_bytes).collect();
+        latencies.sort_unstable();
+        sizes.sort_unstable();
+
+        Some(MetricsSummary {
+            calls: calls.len(),
+            latency_p50_ms: percentile(&latencies, 50),
+            latency_p95_ms: percentile(&latencies, 95),
+            response_bytes_p50: percentile(&sizes, 50),
+            response_bytes_p95: percentile(&sizes, 95),
+        })
+    }
+}
+
+/// Returns the nearest-rank percentile of a sorted, non-empty slice.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(percentile(&[1, 2, 3, 4], 50), 2);
+/// assert_eq!(percentile(&[1, 2, 3, 4], 95), 4);
+/// ```
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
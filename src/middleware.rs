@@ -0,0 +1,275 @@
+//! Hooks into the scrape pipeline for applications embedding the scraper.
+//!
+//! A `Middleware` sees every page at three points: before it is requested,
+//! after its content came back, and before its file is written. Hooks can
+//! change the request (URL, headers, main-content extraction), skip the page,
+//! change the content that is saved, and add frontmatter fields. Middlewares
+//! are registered on `ScrapeOptions::middleware` and run in the order they
+//! were added; the first one that skips a page stops the others.
+//!
+//! ```no_run
+//! use scraper::middleware::{HookFuture, Middleware, PageResponse, Verdict};
+//! use scraper::ScrapeOptions;
+//!
+//! struct Internal;
+//!
+//! impl Middleware for Internal {
+//!     fn after_response<'a>(&'a self, response: &'a mut PageResponse<'_>) -> HookFuture<'a> {
+//!         Box::pin(async move {
+//!             Ok(if response.markdown.contains("INTERNAL ONLY") { Verdict::Skip } else { Verdict::Continue })
+//!         })
+//!     }
+//! }
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut options = ScrapeOptions::parse_args(["--output-dir", "docs"])?;
+//! options.middleware.add(Internal);
+//! scraper::scrape("https://docs.example.com", &options).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Future returned by the hooks of a `Middleware`.
+pub type HookFuture<'a> = Pin<Box<dyn Future<Output = Result<Verdict, Box<dyn Error>>> + Send + 'a>>;
+
+/// What a hook decided about a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Go on processing the page
+    Continue,
+    /// Leave the page out of the mirror; it is counted as skipped in the run report
+    Skip,
+}
+
+/// A page about to be requested.
+///
+/// Not seen for pages of a crawl job, which requests them itself.
+#[derive(Debug)]
+pub struct PageRequest<'a> {
+    /// URL of the page, as it is keyed in the manifest
+    pub page_url: &'a str,
+
+    /// URL to request; the page keeps its key when this is changed
+    pub url: String,
+
+    /// Additional HTTP headers sent when fetching the page
+    pub headers: BTreeMap<String, String>,
+
+    /// Main-content extraction setting sent to FireCrawl; ignored by the direct backend
+    pub only_main_content: Option<bool>,
+}
+
+/// A page whose content came back.
+#[derive(Debug)]
+pub struct PageResponse<'a> {
+    /// URL of the page
+    pub url: &'a str,
+
+    /// HTTP status code of the page, if known
+    pub status_code: Option<i32>,
+
+    /// HTML of the page, if it was requested or fetched
+    pub html: Option<&'a str>,
+
+    /// Markdown content of the page
+    pub markdown: String,
+
+    /// Page title
+    pub title: Option<String>,
+
+    /// Page description
+    pub description: Option<String>,
+
+    /// Page language
+    pub language: Option<String>,
+}
+
+/// A page about to be written, after redaction, alt text and translation.
+#[derive(Debug)]
+pub struct PageDocument<'a> {
+    /// URL of the page
+    pub url: &'a str,
+
+    /// Path of the file, relative to the output directory
+    pub file: &'a str,
+
+    /// Markdown written below the frontmatter
+    pub body: String,
+
+    /// Fields added to the frontmatter, written as strings
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Hooks called for every page; each defaults to doing nothing.
+///
+/// Middlewares are shared by the pages scraped at the same time, and a scrape
+/// can run on any thread of a multi-threaded runtime, so they are `Send + Sync`.
+pub trait Middleware: Send + Sync {
+    /// Called before a page is requested.
+    fn before_request<'a>(&'a self, _request: &'a mut PageRequest<'_>) -> HookFuture<'a> {
+        Box::pin(async { Ok(Verdict::Continue) })
+    }
+
+    /// Called once a page's content came back, before it is processed.
+    fn after_response<'a>(&'a self, _response: &'a mut PageResponse<'_>) -> HookFuture<'a> {
+        Box::pin(async { Ok(Verdict::Continue) })
+    }
+
+    /// Called before a page's file is written.
+    fn before_write<'a>(&'a self, _document: &'a mut PageDocument<'_>) -> HookFuture<'a> {
+        Box::pin(async { Ok(Verdict::Continue) })
+    }
+}
+
+/// The middlewares registered for a run.
+#[derive(Default)]
+pub struct Pipeline {
+    middlewares: Vec<Box<dyn Middleware>>,
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pipeline({} middlewares)", self.middlewares.len())
+    }
+}
+
+impl Pipeline {
+    /// Registers a middleware, called after those registered before it.
+    pub fn add(&mut self, middleware: impl Middleware + 'static) {
+        self.middlewares.push(Box::new(middleware));
+    }
+
+    /// Runs the `before_request` hooks.
+    pub(crate) async fn before_request(&self, request: &mut PageRequest<'_>) -> Result<Verdict, Box<dyn Error>> {
+        for middleware in &self.middlewares {
+            if middleware.before_request(request).await? == Verdict::Skip {
+                return Ok(Verdict::Skip);
+            }
+        }
+        Ok(Verdict::Continue)
+    }
+
+    /// Runs the `after_response` hooks.
+    pub(crate) async fn after_response(&self, response: &mut PageResponse<'_>) -> Result<Verdict, Box<dyn Error>> {
+        for middleware in &self.middlewares {
+            if middleware.after_response(response).await? == Verdict::Skip {
+                return Ok(Verdict::Skip);
+            }
+        }
+        Ok(Verdict::Continue)
+    }
+
+    /// Runs the `before_write` hooks.
+    pub(crate) async fn before_write(&self, document: &mut PageDocument<'_>) -> Result<Verdict, Box<dyn Error>> {
+        for middleware in &self.middlewares {
+            if middleware.before_write(document).await? == Verdict::Skip {
+                return Ok(Verdict::Skip);
+            }
+        }
+        Ok(Verdict::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ScrapeOptions;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the hooks it was called for, skipping pages at `skip_at`.
+    struct Recorder {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+        skip_at: Option<&'static str>,
+    }
+
+    impl Recorder {
+        fn record(&self, hook: &'static str) -> Verdict {
+            self.calls.lock().unwrap().push(format!("{} {}", self.name, hook));
+            if self.skip_at == Some(hook) {
+                Verdict::Skip
+            } else {
+                Verdict::Continue
+            }
+        }
+    }
+
+    impl Middleware for Recorder {
+        fn before_request<'a>(&'a self, request: &'a mut PageRequest<'_>) -> HookFuture<'a> {
+            Box::pin(async move {
+                request.headers.insert("X-Seen-By".to_string(), self.name.to_string());
+                Ok(self.record("before_request"))
+            })
+        }
+
+        fn before_write<'a>(&'a self, _document: &'a mut PageDocument<'_>) -> HookFuture<'a> {
+            Box::pin(async move { Ok(self.record("before_write")) })
+        }
+    }
+
+    fn request() -> PageRequest<'static> {
+        PageRequest {
+            page_url: "https://docs.example.com/",
+            url: "https://docs.example.com/".to_string(),
+            headers: BTreeMap::new(),
+            only_main_content: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn middlewares_run_in_order_until_one_skips() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut pipeline = Pipeline::default();
+        for (name, skip_at) in [("first", None), ("second", Some("before_write")), ("third", None)] {
+            pipeline.add(Recorder { name, calls: calls.clone(), skip_at });
+        }
+
+        let mut request = request();
+        assert_eq!(pipeline.before_request(&mut request).await.unwrap(), Verdict::Continue);
+        assert_eq!(request.headers["X-Seen-By"], "third");
+        let mut document = PageDocument {
+            url: "https://docs.example.com/",
+            file: "index.md",
+            body: String::new(),
+            fields: BTreeMap::new(),
+        };
+        assert_eq!(pipeline.before_write(&mut document).await.unwrap(), Verdict::Skip);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "first before_request",
+                "second before_request",
+                "third before_request",
+                "first before_write",
+                "second before_write"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn hooks_default_to_continuing() {
+        struct Nothing;
+        impl Middleware for Nothing {}
+
+        let mut pipeline = Pipeline::default();
+        pipeline.add(Nothing);
+        assert_eq!(pipeline.before_request(&mut request()).await.unwrap(), Verdict::Continue);
+    }
+
+    #[test]
+    fn a_scrape_can_be_spawned() {
+        // Checked when compiling: an embedding application can run a scrape on a multi-threaded runtime
+        fn assert_send<T: Send>(_: &T) {}
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<ScrapeOptions>();
+        let options = ScrapeOptions::parse_args(["--output-dir", "docs"]).unwrap();
+        let scrape = crate::scrape("https://docs.example.com", &options);
+        assert_send(&scrape);
+    }
+}
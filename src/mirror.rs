@@ -0,0 +1,176 @@
+//! Operations on an existing mirror directory.
+
+use chrono::{DateTime, Utc};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::export::{DOCUSAURUS_DIR_NAME, HUGO_DIR_NAME, MDBOOK_DIR_NAME, OBSIDIAN_DIR_NAME};
+use crate::frontmatter::{self, DateStyle};
+use crate::manifest::{page_id, Manifest, ManifestEntry};
+use crate::tombstone::REMOVED_DIR_NAME;
+
+/// A page already saved in a mirror directory.
+#[derive(Debug)]
+pub struct MirroredPage {
+    /// Path of the file relative to the mirror directory
+    pub file: String,
+    /// Source URL recorded in the file's frontmatter
+    pub url: String,
+    /// Title recorded in the file's frontmatter
+    pub title: Option<String>,
+    /// Scrape date recorded in the file's frontmatter
+    pub scraped_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of importing a mirror directory into its manifest.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Pages added to the manifest
+    pub imported: usize,
+    /// Pages skipped because the manifest already had an entry for their URL
+    pub already_tracked: usize,
+}
+
+/// Recursively lists the markdown files in a directory, in sorted order.
+///
+/// Hidden directories (such as the `.scraper` state directory), the
+/// `_removed` directory of archived pages and the `mdbook`, `docusaurus` and
+/// `obsidian` exports are skipped.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read
+pub fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    let skipped =
+        [REMOVED_DIR_NAME, MDBOOK_DIR_NAME, DOCUSAURUS_DIR_NAME, OBSIDIAN_DIR_NAME, HUGO_DIR_NAME].map(|name| dir.join(name));
+
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|e| format!("Failed to read directory {}: {}", current.display(), e))?;
+        for entry in entries {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+
+            if path.is_dir() {
+                if !hidden && !skipped.contains(&path) {
+                    pending.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "md") {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Lists the pages of a mirror directory whose frontmatter records a source URL.
+///
+/// Files without frontmatter or without a `url` field are skipped with a warning.
+///
+/// # Errors
+///
+/// Returns an error if the directory or one of its files cannot be read
+///
+/// # Examples
+///
+/// ```text
+/// for page in mirrored_pages(Path::new("docs_example_com"))? {
+///     println!("{} <- {}", page.file, page.url);
+/// }
+/// ```
+pub fn mirrored_pages(dir: &Path) -> Result<Vec<MirroredPage>, Box<dyn Error>> {
+    let mut pages = Vec::new();
+
+    for path in markdown_files(dir)? {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut fields = frontmatter::parse_frontmatter(&content).unwrap_or_default();
+
+        let Some(url) = fields.remove("url") else {
+            warn!("Skipping {}: no url in frontmatter", path.display());
+            continue;
+        };
+        let scraped_at = fields
+            .get("scrapeDate")
+            .and_then(|date| DateStyle::default().parse(date));
+
+        let file = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        pages.push(MirroredPage {
+            file,
+            url,
+            title: fields.remove("title"),
+            scraped_at,
+        });
+    }
+
+    Ok(pages)
+}
+
+/// Builds or extends the manifest of a directory from the frontmatter of its files.
+///
+/// Used to adopt mirrors created by older versions of this tool, which did not
+/// write a manifest. URLs already present in the manifest are left untouched.
+/// When a file has no `scrapeDate`, its modification time is used instead.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The directory or its files cannot be read
+/// - An existing manifest cannot be parsed
+/// - The manifest cannot be written
+///
+/// # Examples
+///
+/// ```text
+/// let summary = import_mirror(Path::new("docs_example_com"))?;
+/// println!("Imported {} pages", summary.imported);
+/// ```
+pub fn import_mirror(dir: &Path) -> Result<ImportSummary, Box<dyn Error>> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()).into());
+    }
+
+    let mut manifest = Manifest::load(dir)?;
+    let mut summary = ImportSummary::default();
+
+    for page in mirrored_pages(dir)? {
+        if manifest.pages.contains_key(&page.url) {
+            summary.already_tracked += 1;
+            continue;
+        }
+
+        let scraped_at = match page.scraped_at {
+            Some(date) => date,
+            None => fs::metadata(dir.join(&page.file))?.modified()?.into(),
+        };
+
+        let id = page_id(&page.url);
+        manifest.pages.insert(
+            page.url,
+            ManifestEntry {
+                id: Some(id),
+                file: page.file,
+                title: page.title,
+                scraped_at,
+                ..Default::default()
+            },
+        );
+        summary.imported += 1;
+    }
+
+    manifest.save(dir)?;
+    Ok(summary)
+}
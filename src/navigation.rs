@@ -0,0 +1,103 @@
+//! Detection of "Previous"/"Next" navigation links.
+//!
+//! Tutorials and guides link each page to the one before and after it. The
+//! links found here are recorded in the manifest, and the manifest stitches
+//! them into a reading order that exporters use instead of sorting by URL.
+
+use regex::Regex;
+use std::sync::LazyLock;
+use url::Url;
+
+/// A markdown inline link: `[text](target "optional title")`.
+pub static MARKDOWN_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\[([^\]]*)\]\(\s*<?([^)\s>]+)>?(?:\s+"[^"]*")?\s*\)"#).unwrap());
+
+/// Words that mark a link to the previous page when they start the link text.
+const PREVIOUS_WORDS: &[&str] = &["previous", "prev"];
+
+/// Words that mark a link to the next page when they start the link text.
+const NEXT_WORDS: &[&str] = &["next"];
+
+/// The previous and next pages a page links to.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct NavigationLinks {
+    /// Absolute URL of the previous page
+    pub previous: Option<String>,
+    /// Absolute URL of the next page
+    pub next: Option<String>,
+}
+
+/// Which way a navigation link points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Previous,
+    Next,
+}
+
+/// Finds the "Previous" and "Next" links of a page.
+///
+/// A link counts as navigation when its text starts with "Next", "Previous" or
+/// "Prev", ignoring arrows and punctuation (`« Previous`, `Next: Installation →`).
+/// Navigation usually sits at the bottom of a page, so the last matching link
+/// of each kind wins. Relative targets are resolved against `page_url`, and
+/// links back to the page itself are ignored.
+///
+/// # Arguments
+///
+/// * `markdown` - The markdown content of the page
+/// * `page_url` - The URL of the page, used to resolve relative links
+///
+/// # Returns
+///
+/// The previous and next page URLs, without fragments
+///
+/// # Examples
+///
+/// ```text
+/// let links = navigation_links("[« Previous](intro) [Next: Setup »](/guide/setup)", "https://example.com/guide/basics");
+/// assert_eq!(links.previous.as_deref(), Some("https://example.com/guide/intro"));
+/// assert_eq!(links.next.as_deref(), Some("https://example.com/guide/setup"));
+/// ```
+pub fn navigation_links(markdown: &str, page_url: &str) -> NavigationLinks {
+    let mut links = NavigationLinks::default();
+    let Ok(base) = Url::parse(page_url) else {
+        return links;
+    };
+
+    for capture in MARKDOWN_LINK.captures_iter(markdown) {
+        let Some(direction) = direction(&capture[1]) else {
+            continue;
+        };
+        let Ok(mut target) = base.join(&capture[2]) else {
+            continue;
+        };
+        target.set_fragment(None);
+        if target.as_str() == base.as_str() {
+            continue;
+        }
+
+        match direction {
+            Direction::Previous => links.previous = Some(target.to_string()),
+            Direction::Next => links.next = Some(target.to_string()),
+        }
+    }
+
+    links
+}
+
+/// Classifies link text as previous or next navigation.
+fn direction(text: &str) -> Option<Direction> {
+    let normalized = text
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+        .collect::<String>();
+    let first_word = normalized.split_whitespace().next()?;
+
+    if PREVIOUS_WORDS.contains(&first_word) {
+        Some(Direction::Previous)
+    } else if NEXT_WORDS.contains(&first_word) {
+        Some(Direction::Next)
+    } else {
+        None
+    }
+}
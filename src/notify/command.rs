@@ -0,0 +1,56 @@
+//! Notifier running a command.
+
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::{Notification, Notifier, NotifyFuture};
+
+/// Runs a program for every notification, e.g. a paging tool or a script
+/// forwarding it to a channel there is no notifier for.
+///
+/// The notification is passed in the environment variables `SCRAPER_EVENT`,
+/// `SCRAPER_SITE`, `SCRAPER_URL`, `SCRAPER_DETAIL` and `SCRAPER_TEXT`. The
+/// program is run directly, without a shell, and fails the notification by
+/// exiting with a non-zero status.
+pub struct CommandNotifier {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandNotifier {
+    /// Creates a notifier running `program` with `args`.
+    pub fn new(program: &str, args: &[String]) -> Self {
+        CommandNotifier {
+            program: program.to_string(),
+            args: args.to_vec(),
+        }
+    }
+}
+
+impl Notifier for CommandNotifier {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let output = Command::new(&self.program)
+                .args(&self.args)
+                .env("SCRAPER_EVENT", notification.event.to_string())
+                .env("SCRAPER_SITE", &notification.site)
+                .env("SCRAPER_URL", &notification.url)
+                .env("SCRAPER_DETAIL", &notification.detail)
+                .env("SCRAPER_TEXT", notification.text())
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .output()
+                .await
+                .map_err(|e| format!("Failed to run {}: {}", self.program, e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("{} exited with {}: {}", self.program, output.status, stderr.trim()).into());
+            }
+            Ok(())
+        })
+    }
+}
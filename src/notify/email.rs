@@ -0,0 +1,327 @@
+//! Notifier sending email through an SMTP server.
+//!
+//! Only what sending a plain text notification needs is spoken: `EHLO`,
+//! `STARTTLS`, `AUTH PLAIN` and one message per connection.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use serde::Deserialize;
+use std::error::Error;
+use std::net::IpAddr;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_native_tls::{native_tls, TlsConnector};
+
+use super::{Notification, Notifier, NotifyFuture};
+
+/// Name the client introduces itself with in `EHLO`.
+const CLIENT_NAME: &str = "scraper";
+
+/// How the connection to the SMTP server is encrypted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum SmtpTls {
+    /// Connect in plain text and upgrade with `STARTTLS`, usually on port 587
+    #[default]
+    #[serde(rename = "starttls")]
+    StartTls,
+    /// Connect over TLS from the start, usually on port 465
+    #[serde(rename = "implicit")]
+    Implicit,
+    /// No encryption, for a relay on the local network
+    #[serde(rename = "none")]
+    Plain,
+}
+
+/// Sends each notification as a plain text email.
+pub struct EmailNotifier {
+    host: String,
+    port: u16,
+    tls: SmtpTls,
+    login: Option<(String, String)>,
+    from: String,
+    to: Vec<String>,
+}
+
+impl EmailNotifier {
+    /// Creates a notifier sending from `from` to `to` through the SMTP server at `host`.
+    ///
+    /// `port` defaults to 465 with implicit TLS and 587 otherwise; `login` is a
+    /// user name and password.
+    pub fn new(
+        host: &str,
+        port: Option<u16>,
+        tls: SmtpTls,
+        login: Option<(String, String)>,
+        from: &str,
+        to: &[String],
+    ) -> Self {
+        let default_port = if tls == SmtpTls::Implicit { 465 } else { 587 };
+        EmailNotifier {
+            host: host.to_string(),
+            port: port.unwrap_or(default_port),
+            tls,
+            login,
+            from: from.to_string(),
+            to: to.to_vec(),
+        }
+    }
+
+    /// Sends one message, opening a connection for it.
+    async fn deliver(&self, message: &str) -> Result<(), Box<dyn Error>> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", self.host, self.port, e))?;
+        match self.tls {
+            SmtpTls::Implicit => {
+                let stream = self.connector()?.connect(&self.host, stream).await?;
+                let mut session = Session::new(stream);
+                session.greet().await?;
+                self.transact(&mut session, message).await
+            }
+            SmtpTls::StartTls => {
+                let mut session = Session::new(stream);
+                session.greet().await?;
+                session.command("STARTTLS", 220).await?;
+                let stream = self.connector()?.connect(&self.host, session.into_inner()).await?;
+                let mut session = Session::new(stream);
+                session.command(&format!("EHLO {}", CLIENT_NAME), 250).await?;
+                self.transact(&mut session, message).await
+            }
+            SmtpTls::Plain => {
+                let mut session = Session::new(stream);
+                session.greet().await?;
+                self.transact(&mut session, message).await
+            }
+        }
+    }
+
+    fn connector(&self) -> Result<TlsConnector, Box<dyn Error>> {
+        Ok(TlsConnector::from(native_tls::TlsConnector::new()?))
+    }
+
+    /// Logs in if configured and sends the message over an established session.
+    async fn transact<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        session: &mut Session<S>,
+        message: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some((username, password)) = &self.login {
+            let credentials = STANDARD.encode(format!("\0{}\0{}", username, password));
+            session.command(&format!("AUTH PLAIN {}", credentials), 235).await.map_err(|_| "SMTP login failed")?;
+        }
+        session.command(&format!("MAIL FROM:<{}>", self.from), 250).await?;
+        for recipient in &self.to {
+            session.command(&format!("RCPT TO:<{}>", recipient), 250).await?;
+        }
+        session.command("DATA", 354).await?;
+        session.command(&format!("{}\r\n.", dot_stuff(message)), 250).await?;
+        // The message is accepted; a server hanging up without answering QUIT doesn't matter
+        let _ = session.command("QUIT", 221).await;
+        Ok(())
+    }
+
+    /// Formats a notification as an RFC 5322 message.
+    fn message(&self, notification: &Notification) -> String {
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n",
+            self.from,
+            self.to.join(", "),
+            encode_header(&notification.subject()),
+            Utc::now().to_rfc2822(),
+            notification.text().replace('\n', "\r\n")
+        )
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move { self.deliver(&self.message(notification)).await })
+    }
+}
+
+/// One SMTP conversation.
+struct Session<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S) -> Self {
+        Session {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    /// Returns the underlying stream, to upgrade it to TLS; the server has nothing more to say before that.
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Reads the server's greeting and introduces the client.
+    async fn greet(&mut self) -> Result<(), Box<dyn Error>> {
+        self.expect(220).await?;
+        self.command(&format!("EHLO {}", CLIENT_NAME), 250).await
+    }
+
+    /// Sends a command and checks the reply code.
+    async fn command(&mut self, line: &str, code: u16) -> Result<(), Box<dyn Error>> {
+        let stream = self.stream.get_mut();
+        stream.write_all(line.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+        self.expect(code).await
+    }
+
+    /// Reads a reply, of one or more lines, and checks its code.
+    async fn expect(&mut self, code: u16) -> Result<(), Box<dyn Error>> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err("The SMTP server closed the connection".into());
+            }
+            reply.push_str(line.trim_end());
+            // The last line of a reply has a space after the code, the others a hyphen
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+            reply.push(' ');
+        }
+        if reply.get(..3).and_then(|start| start.parse::<u16>().ok()) != Some(code) {
+            return Err(format!("Unexpected SMTP reply: {}", reply).into());
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `host` is this machine, where a login without encryption doesn't leave it.
+pub fn is_loopback(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Doubles the dots starting lines of a message, which would otherwise end it.
+fn dot_stuff(message: &str) -> String {
+    let stuffed = message.replace("\r\n.", "\r\n..");
+    let stuffed = stuffed.trim_end_matches("\r\n");
+    if stuffed.starts_with('.') {
+        format!(".{}", stuffed)
+    } else {
+        stuffed.to_string()
+    }
+}
+
+/// Encodes a header value as an RFC 2047 encoded word if it isn't plain ASCII.
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?utf-8?B?{}?=", STANDARD.encode(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, DuplexStream};
+
+    /// Greets the client with the first of `replies` and answers each of its commands with the next, returning
+    /// everything the client sent.
+    async fn serve(server: DuplexStream, replies: &[&str]) -> String {
+        let mut server = BufReader::new(server);
+        let mut received = String::new();
+        let (greeting, answers) = replies.split_first().unwrap();
+        server.get_mut().write_all(greeting.as_bytes()).await.unwrap();
+        let mut in_data = false;
+        for answer in answers {
+            // A command is one line, the message after `DATA` everything up to the line with a single dot
+            loop {
+                let mut line = String::new();
+                if server.read_line(&mut line).await.unwrap() == 0 {
+                    return received;
+                }
+                received.push_str(&line);
+                if !in_data || line == ".\r\n" {
+                    break;
+                }
+            }
+            in_data = answer.starts_with("354");
+            server.get_mut().write_all(answer.as_bytes()).await.unwrap();
+        }
+        received
+    }
+
+    fn notifier(login: Option<(String, String)>) -> EmailNotifier {
+        EmailNotifier::new("localhost", None, SmtpTls::Plain, login, "scraper@example.com", &["team@example.com".to_string()])
+    }
+
+    #[tokio::test]
+    async fn multi_line_replies_are_read_to_their_last_line() {
+        let (client, mut server) = duplex(1024);
+        server.write_all(b"250-smtp.example.com\r\n250-SIZE 1000\r\n250 AUTH PLAIN\r\n220 ready\r\n").await.unwrap();
+        let mut session = Session::new(client);
+        session.expect(250).await.unwrap();
+        // The next reply starts after the last line of the previous one
+        session.expect(220).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unexpected_reply_codes_and_hang_ups_are_errors() {
+        let (client, mut server) = duplex(1024);
+        server.write_all(b"550-mailbox\r\n550 unavailable\r\n").await.unwrap();
+        let mut session = Session::new(client);
+        let error = session.expect(250).await.unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected SMTP reply: 550-mailbox 550 unavailable");
+
+        drop(server);
+        assert!(session.expect(250).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn message_is_sent_dot_stuffed_after_logging_in() {
+        let (client, server) = duplex(4096);
+        let replies = ["220 ready\r\n", "250 hello\r\n", "235 ok\r\n", "250 ok\r\n", "250 ok\r\n", "354 go\r\n", "250 queued\r\n", "221 bye\r\n"];
+        let server = tokio::spawn(async move { serve(server, &replies).await });
+        let notifier = notifier(Some(("user".to_string(), "secret".to_string())));
+        let mut session = Session::new(client);
+        session.greet().await.unwrap();
+        notifier.transact(&mut session, "Subject: x\r\n\r\n.hidden\r\nend\r\n").await.unwrap();
+
+        let credentials = STANDARD.encode("\0user\0secret");
+        assert_eq!(
+            server.await.unwrap(),
+            format!(
+                "EHLO scraper\r\nAUTH PLAIN {}\r\nMAIL FROM:<scraper@example.com>\r\nRCPT TO:<team@example.com>\r\nDATA\r\n\
+                 Subject: x\r\n\r\n..hidden\r\nend\r\n.\r\nQUIT\r\n",
+                credentials
+            )
+        );
+    }
+
+    #[test]
+    fn dot_stuffing_doubles_leading_dots() {
+        assert_eq!(dot_stuff(".first\r\nplain\r\n.\r\n..two\r\n"), "..first\r\nplain\r\n..\r\n...two");
+        assert_eq!(dot_stuff("no dots. here\r\n"), "no dots. here");
+    }
+
+    #[test]
+    fn headers_are_encoded_only_when_not_ascii() {
+        assert_eq!(encode_header("Site docs failed"), "Site docs failed");
+        assert_eq!(encode_header("Échec"), format!("=?utf-8?B?{}?=", STANDARD.encode("Échec")));
+    }
+
+    #[test]
+    fn loopback_hosts_are_recognized() {
+        for host in ["localhost", "LOCALHOST", "127.0.0.1", "127.0.0.2", "::1", "[::1]"] {
+            assert!(is_loopback(host), "{}", host);
+        }
+        for host in ["smtp.example.com", "10.0.0.1", "localhost.example.com"] {
+            assert!(!is_loopback(host), "{}", host);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Notifications about workspace runs.
+//!
+//! `run-all` and `daemon` tell the team when a site's run failed, when a site
+//! was paused, or when a run changed the mirror. Each channel is a `Notifier`;
+//! the `[[workspace.notify]]` entries of a workspace file configure any number
+//! of them, each with the events it is sent:
+//!
+//! ```toml
+//! [[workspace.notify]]
+//! type = "slack"
+//! url = "https://hooks.slack.com/services/..."
+//! events = ["failure", "pause"]
+//!
+//! [[workspace.notify]]
+//! type = "email"
+//! host = "smtp.example.com"
+//! username = "scraper@example.com"
+//! password_env = "SMTP_PASSWORD"
+//! from = "scraper@example.com"
+//! to = ["docs-team@example.com"]
+//! events = ["changes"]
+//! ```
+//!
+//! A notification that cannot be sent is logged; it never fails the run.
+
+mod command;
+mod email;
+mod webhook;
+
+use reqwest::Client;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::warn;
+
+use command::CommandNotifier;
+use email::{EmailNotifier, SmtpTls};
+use webhook::{DiscordNotifier, SlackNotifier, WebhookNotifier};
+
+/// Longest a notifier may take to send one notification.
+const SEND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Future returned by `Notifier::send`.
+pub type NotifyFuture<'a> = Pin<Box<dyn Future<Output = Result<(), Box<dyn Error>>> + 'a>>;
+
+/// Something that happened to a site in a workspace run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    /// The site's run failed
+    Failure,
+    /// The site's run tripped a `pause_when` condition and the site was paused
+    Pause,
+    /// The site's run added, changed or removed pages
+    Changes,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Event::Failure => "failure",
+            Event::Pause => "pause",
+            Event::Changes => "changes",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A message about one site, sent to every notifier that wants its event.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// What happened
+    pub event: Event,
+    /// Name of the site
+    pub site: String,
+    /// The URL the site is scraped from
+    pub url: String,
+    /// The error, the pause reason, or a summary of the changed pages
+    pub detail: String,
+}
+
+impl Notification {
+    /// Creates a notification that a site's run failed with `error`.
+    pub fn failure(site: &str, url: &str, error: &str) -> Self {
+        Notification::new(Event::Failure, site, url, error)
+    }
+
+    /// Creates a notification that a site was paused for `reason`.
+    pub fn pause(site: &str, url: &str, reason: &str) -> Self {
+        Notification::new(Event::Pause, site, url, reason)
+    }
+
+    /// Creates a notification that a site's run changed its mirror, or `None` if it didn't.
+    pub fn changes(site: &str, url: &str, added: usize, changed: usize, removed: usize) -> Option<Self> {
+        if added + changed + removed == 0 {
+            return None;
+        }
+        let detail = format!("{} pages added, {} changed, {} removed", added, changed, removed);
+        Some(Notification::new(Event::Changes, site, url, &detail))
+    }
+
+    fn new(event: Event, site: &str, url: &str, detail: &str) -> Self {
+        Notification {
+            event,
+            site: site.to_string(),
+            url: url.to_string(),
+            detail: detail.to_string(),
+        }
+    }
+
+    /// Returns a one-line subject, e.g. for an email.
+    pub fn subject(&self) -> String {
+        match self.event {
+            Event::Failure => format!("Scraping {} failed", self.site),
+            Event::Pause => format!("Paused scraping {}", self.site),
+            Event::Changes => format!("{} changed", self.site),
+        }
+    }
+
+    /// Returns the message as a sentence or two of plain text.
+    pub fn text(&self) -> String {
+        match self.event {
+            Event::Failure => format!("Scraping {} ({}) failed: {}", self.site, self.url, self.detail),
+            Event::Pause => format!(
+                "Paused scraping {} ({}): {}. Run `scraper unpause {}` once it is fixed.",
+                self.site, self.url, self.detail, self.site
+            ),
+            Event::Changes => format!("Scraped {} ({}): {}", self.site, self.url, self.detail),
+        }
+    }
+}
+
+/// A channel notifications are sent through.
+///
+/// A new channel implements it and gets a `NotifierConfig` variant building it.
+pub trait Notifier {
+    /// Returns a short description of the channel for log messages, e.g. `Slack`.
+    fn name(&self) -> &str;
+
+    /// Sends one notification.
+    fn send<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a>;
+}
+
+/// A notification channel as written in the workspace file.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum NotifierConfig {
+    /// Posts a JSON object with the event, text, site, URL and detail
+    Webhook {
+        /// URL the message is posted to
+        url: String,
+        /// Events sent; all of them when empty
+        #[serde(default)]
+        events: Vec<Event>,
+    },
+
+    /// Posts the text to a Slack (or Mattermost) incoming webhook
+    Slack {
+        /// The incoming webhook URL
+        url: String,
+        /// Events sent; all of them when empty
+        #[serde(default)]
+        events: Vec<Event>,
+    },
+
+    /// Posts the text to a Discord webhook
+    Discord {
+        /// The webhook URL
+        url: String,
+        /// Events sent; all of them when empty
+        #[serde(default)]
+        events: Vec<Event>,
+    },
+
+    /// Sends an email through an SMTP server
+    Email {
+        /// Host name of the SMTP server
+        host: String,
+        /// Port of the SMTP server; 465 with `tls = "implicit"`, 587 otherwise
+        port: Option<u16>,
+        /// How the connection is encrypted, `starttls` by default
+        #[serde(default)]
+        tls: SmtpTls,
+        /// User name to log in with; no login without it
+        username: Option<String>,
+        /// Environment variable holding the password
+        password_env: Option<String>,
+        /// Sender address
+        from: String,
+        /// Recipient addresses
+        to: Vec<String>,
+        /// Events sent; all of them when empty
+        #[serde(default)]
+        events: Vec<Event>,
+    },
+
+    /// Runs a command with the notification in `SCRAPER_*` environment variables
+    Command {
+        /// The program and its arguments
+        command: Vec<String>,
+        /// Events sent; all of them when empty
+        #[serde(default)]
+        events: Vec<Event>,
+    },
+}
+
+impl NotifierConfig {
+    /// Returns the events the channel is sent, all of them when empty.
+    pub fn events(&self) -> &[Event] {
+        match self {
+            NotifierConfig::Webhook { events, .. }
+            | NotifierConfig::Slack { events, .. }
+            | NotifierConfig::Discord { events, .. }
+            | NotifierConfig::Email { events, .. }
+            | NotifierConfig::Command { events, .. } => events,
+        }
+    }
+
+    /// Builds the configured notifier, sending HTTP requests with `client`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an email channel has no recipients, its password
+    /// variable is not set or it would log in without encryption to another
+    /// machine, or a command channel has no command
+    pub fn build(&self, client: &Client) -> Result<Box<dyn Notifier>, Box<dyn Error>> {
+        let notifier: Box<dyn Notifier> = match self {
+            NotifierConfig::Webhook { url, .. } => Box::new(WebhookNotifier::new(client.clone(), url)),
+            NotifierConfig::Slack { url, .. } => Box::new(SlackNotifier::new(client.clone(), url)),
+            NotifierConfig::Discord { url, .. } => Box::new(DiscordNotifier::new(client.clone(), url)),
+            NotifierConfig::Email {
+                host,
+                port,
+                tls,
+                username,
+                password_env,
+                from,
+                to,
+                ..
+            } => {
+                if to.is_empty() {
+                    return Err("Email notifications need at least one address in `to`".into());
+                }
+                let password = match password_env {
+                    Some(name) => Some(std::env::var(name).map_err(|_| {
+                        format!("Environment variable {} (password for SMTP server {}) is not set", name, host)
+                    })?),
+                    None => None,
+                };
+                let login = match (username, password) {
+                    (Some(username), Some(password)) => Some((username.clone(), password)),
+                    (None, None) => None,
+                    _ => return Err("Email notifications need both username and password_env, or neither".into()),
+                };
+                // Without encryption the password would cross the network readable by anyone on the way
+                if login.is_some() && *tls == SmtpTls::Plain && !email::is_loopback(host) {
+                    return Err(format!(
+                        "Email notifications with tls = \"none\" only log in to a server on this machine, not {}; \
+                         use starttls or implicit",
+                        host
+                    )
+                    .into());
+                }
+                Box::new(EmailNotifier::new(host, *port, *tls, login, from, to))
+            }
+            NotifierConfig::Command { command, .. } => {
+                let (program, args) = command.split_first().ok_or("Command notifications need a command")?;
+                Box::new(CommandNotifier::new(program, args))
+            }
+        };
+        Ok(notifier)
+    }
+}
+
+/// The notifiers of a workspace, each with the events it is sent.
+#[derive(Default)]
+pub struct Notifiers {
+    notifiers: Vec<(Box<dyn Notifier>, Vec<Event>)>,
+}
+
+impl fmt::Debug for Notifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Notifiers({} notifiers)", self.notifiers.len())
+    }
+}
+
+impl Notifiers {
+    /// Builds the configured notifiers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a notifier is misconfigured, see `NotifierConfig`
+    pub fn from_config(client: &Client, configs: &[NotifierConfig]) -> Result<Self, Box<dyn Error>> {
+        let mut notifiers = Notifiers::default();
+        for config in configs {
+            notifiers.add(config.build(client)?, config.events().to_vec());
+        }
+        Ok(notifiers)
+    }
+
+    /// Registers a notifier sent the given events, or all of them when empty.
+    pub fn add(&mut self, notifier: Box<dyn Notifier>, events: Vec<Event>) {
+        self.notifiers.push((notifier, events));
+    }
+
+    /// Sends a notification to every notifier that wants its event, one after another.
+    ///
+    /// Failures and timeouts are logged; the others are still sent.
+    pub async fn send(&self, notification: &Notification) {
+        for (notifier, events) in &self.notifiers {
+            if !events.is_empty() && !events.contains(&notification.event) {
+                continue;
+            }
+            match tokio::time::timeout(SEND_TIMEOUT, notifier.send(notification)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!("Failed to send the {} notification to {}: {}", notification.event, notifier.name(), e),
+                Err(_) => warn!("Timed out sending the {} notification to {}", notification.event, notifier.name()),
+            }
+        }
+    }
+}
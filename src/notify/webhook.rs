@@ -0,0 +1,110 @@
+//! Notifiers posting to HTTP webhooks: plain JSON, Slack and Discord.
+
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::error::Error;
+
+use super::{Notification, Notifier, NotifyFuture};
+
+/// Longest message Discord accepts in `content`, in characters.
+const DISCORD_MAX_LENGTH: usize = 2000;
+
+/// Posts `body` to `url`, failing on an error status.
+async fn post(client: &Client, url: &str, body: &Value) -> Result<(), Box<dyn Error>> {
+    client.post(url).json(body).send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// Posts a JSON object with `event`, `text`, `site`, `url` and `detail`.
+///
+/// The `text` summary is displayed as is by Slack, Mattermost and Teams
+/// incoming webhooks; other receivers can use the separate fields.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    /// Creates a notifier posting to `url`.
+    pub fn new(client: Client, url: &str) -> Self {
+        WebhookNotifier {
+            client,
+            url: url.to_string(),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let body = json!({
+                "event": notification.event.to_string(),
+                "text": notification.text(),
+                "site": notification.site,
+                "url": notification.url,
+                "detail": notification.detail,
+            });
+            post(&self.client, &self.url, &body).await
+        })
+    }
+}
+
+/// Posts the text of a notification to a Slack or Mattermost incoming webhook.
+pub struct SlackNotifier {
+    client: Client,
+    url: String,
+}
+
+impl SlackNotifier {
+    /// Creates a notifier posting to the incoming webhook `url`.
+    pub fn new(client: Client, url: &str) -> Self {
+        SlackNotifier {
+            client,
+            url: url.to_string(),
+        }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "Slack"
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move { post(&self.client, &self.url, &json!({ "text": notification.text() })).await })
+    }
+}
+
+/// Posts the text of a notification to a Discord webhook.
+pub struct DiscordNotifier {
+    client: Client,
+    url: String,
+}
+
+impl DiscordNotifier {
+    /// Creates a notifier posting to the webhook `url`.
+    pub fn new(client: Client, url: &str) -> Self {
+        DiscordNotifier {
+            client,
+            url: url.to_string(),
+        }
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            // Discord rejects longer messages outright, and a long error is still worth the start of it
+            let content: String = notification.text().chars().take(DISCORD_MAX_LENGTH).collect();
+            post(&self.client, &self.url, &json!({ "content": content })).await
+        })
+    }
+}
@@ -0,0 +1,669 @@
+//! Naming of output directories and page files.
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use tokio::sync::Notify;
+use url::Url;
+
+use crate::manifest::Manifest;
+use crate::page_type::PageType;
+use crate::quality;
+use crate::urls;
+
+/// Second-level labels that are commonly part of a public suffix (as in `example.co.uk`).
+const SECOND_LEVEL_SUFFIXES: &[&str] = &["ac", "co", "com", "edu", "gov", "net", "org"];
+
+/// How scraped pages are written to the output directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// One markdown file with frontmatter per page
+    #[default]
+    Markdown,
+    /// Every page as one line of `pages.jsonl`, without page files
+    Jsonl,
+    /// Every page as a row of the SQLite database `pages.sqlite`, without page files
+    Sqlite,
+}
+
+/// Strategy used to name the directory a site is saved into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DirNaming {
+    /// Sanitized host name with dots replaced, e.g. `docs_example_com`
+    #[default]
+    Flat,
+    /// Registrable domain with subdomains nested below it, e.g. `example.com/docs`
+    Domain,
+    /// Host labels in reverse order, e.g. `com.example.docs`
+    ReverseDomain,
+    /// Full host name, e.g. `docs.example.com`
+    HostPort,
+    /// Name built from `--dir-template`
+    CustomTemplate,
+}
+
+/// Parts of a URL's host used to build directory names.
+struct HostParts {
+    /// Full host name, e.g. `docs.example.com`
+    host: String,
+    /// Registrable domain, e.g. `example.com`
+    domain: String,
+    /// Labels in front of the registrable domain, e.g. `docs`
+    subdomain: Option<String>,
+    /// Explicit non-default port, if any
+    port: Option<u16>,
+}
+
+impl HostParts {
+    fn from_url(url: &Url) -> Result<Self, Box<dyn Error>> {
+        // Directory names use the readable Unicode form of internationalized hosts
+        let host = urls::display_host(url)
+            .ok_or_else(|| format!("URL has no host: {}", url))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+
+        let (domain, subdomain) = split_registrable_domain(&host);
+        Ok(HostParts {
+            domain,
+            subdomain,
+            port: url.port(),
+            host,
+        })
+    }
+
+    /// Returns `name` with the port appended when the URL uses a non-default port,
+    /// so sites served on different ports of one host never share a directory.
+    fn with_port(&self, name: String) -> String {
+        match self.port {
+            Some(port) => format!("{}_{}", name, port),
+            None => name,
+        }
+    }
+}
+
+/// Builds the relative directory path a site is saved into.
+///
+/// Every path component is sanitized, so the result is always a relative path
+/// without `..` components regardless of the URL or template.
+///
+/// # Arguments
+///
+/// * `url` - Any URL of the site
+/// * `naming` - The naming strategy
+/// * `template` - Template used by `DirNaming::CustomTemplate`; supports `{host}`,
+///   `{domain}`, `{subdomain}`, `{port}` and `{reversed}`, and may contain `/`
+///   to create nested directories
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - URL parsing fails or the URL has no host
+/// - `DirNaming::CustomTemplate` is used without a template
+/// - The template produces an empty path
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::output::{domain_dir_name, DirNaming};
+/// # use std::path::PathBuf;
+/// let dir = domain_dir_name("https://docs.example.com/intro", DirNaming::Domain, None)?;
+/// assert_eq!(dir, PathBuf::from("example.com/docs"));
+///
+/// let dir = domain_dir_name("https://docs.example.com/intro", DirNaming::ReverseDomain, None)?;
+/// assert_eq!(dir, PathBuf::from("com.example.docs"));
+///
+/// let dir = domain_dir_name("http://localhost:8080/", DirNaming::HostPort, None)?;
+/// assert_eq!(dir, PathBuf::from("localhost_8080"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn domain_dir_name(
+    url: &str,
+    naming: DirNaming,
+    template: Option<&str>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let parsed_url = Url::parse(url)?;
+
+    if naming == DirNaming::Flat {
+        let domain = urls::display_host(&parsed_url).unwrap_or_else(|| "unknown".to_string());
+        return Ok(PathBuf::from(sanitize_filename(&domain)));
+    }
+
+    let parts = HostParts::from_url(&parsed_url)?;
+    let reversed = reverse_host(&parts.host);
+
+    let path = match naming {
+        DirNaming::Flat => unreachable!("handled above"),
+        DirNaming::Domain => {
+            let mut path = PathBuf::from(sanitize_component(&parts.with_port(parts.domain.clone())));
+            if let Some(subdomain) = &parts.subdomain {
+                path.push(sanitize_component(subdomain));
+            }
+            path
+        }
+        DirNaming::ReverseDomain => PathBuf::from(sanitize_component(&parts.with_port(reversed))),
+        DirNaming::HostPort => PathBuf::from(sanitize_component(&parts.with_port(parts.host.clone()))),
+        DirNaming::CustomTemplate => {
+            let template = template.ok_or("--dir-naming custom-template requires --dir-template")?;
+            let rendered = template
+                .replace("{host}", &parts.host)
+                .replace("{domain}", &parts.domain)
+                .replace("{subdomain}", parts.subdomain.as_deref().unwrap_or(""))
+                .replace("{port}", &parts.port.map(|p| p.to_string()).unwrap_or_default())
+                .replace("{reversed}", &reversed);
+
+            rendered
+                .split('/')
+                .map(sanitize_component)
+                .filter(|component| !component.is_empty())
+                .collect()
+        }
+    };
+
+    if path.as_os_str().is_empty() {
+        return Err(format!("Directory template produced an empty path for {}", url).into());
+    }
+
+    Ok(path)
+}
+
+/// Information about a scraped page available to `--path-template`.
+pub struct PageVariables<'a> {
+    /// URL of the page
+    pub url: &'a str,
+    /// Page title, if known
+    pub title: Option<&'a str>,
+    /// Page language, if known
+    pub language: Option<&'a str>,
+    /// Kind of page
+    pub page_type: PageType,
+    /// When the page was scraped
+    pub scraped_at: DateTime<Utc>,
+}
+
+/// Builds the path of a page file, relative to the output directory, from a template.
+///
+/// Supported variables:
+///
+/// * `{host}`, `{domain}`, `{subdomain}`, `{port}`, `{reversed}` - as for directory templates
+/// * `{path}` - the URL path without its extension, e.g. `docs/guide/intro`
+/// * `{path_dir}` - the directories of the URL path, e.g. `docs/guide`
+/// * `{slug}` - the last URL path segment without its extension, `index` for directory URLs
+/// * `{title}` - the page title, falling back to the slug
+/// * `{yyyy}`, `{mm}`, `{dd}` - the scrape date
+/// * `{lang}` - the page language in lowercase, `und` when unknown
+/// * `{type}` - the page type, e.g. `reference`
+///
+/// Every path component is sanitized and empty components are dropped, so the
+/// result is always a relative path without `..` components. `.md` is appended
+/// when the template doesn't end with it.
+///
+/// # Errors
+///
+/// Returns an error if URL parsing fails or the template produces an empty path
+///
+/// # Examples
+///
+/// ```
+/// # use chrono::Utc;
+/// # use scraper::output::{page_path, PageVariables};
+/// # use scraper::page_type::PageType;
+/// let page = PageVariables {
+///     url: "https://docs.example.com/guide/install.html",
+///     title: Some("Installing"),
+///     language: Some("en-US"),
+///     page_type: PageType::Guide,
+///     scraped_at: Utc::now(),
+/// };
+/// assert_eq!(page_path("{lang}/{path_dir}/{slug}.md", &page)?, "en-us/guide/install.md");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn page_path(template: &str, page: &PageVariables) -> Result<String, Box<dyn Error>> {
+    let parsed_url = Url::parse(page.url)?;
+    let parts = HostParts::from_url(&parsed_url)?;
+
+    let segments: Vec<&str> = parsed_url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+    let directory_url = parsed_url.path().ends_with('/');
+    let (dirs, slug) = match segments.split_last() {
+        Some((last, dirs)) if !directory_url => (dirs.to_vec(), strip_extension(last).to_string()),
+        _ => (segments.clone(), "index".to_string()),
+    };
+    let path_dir = dirs.join("/");
+    let path = if path_dir.is_empty() {
+        slug.clone()
+    } else {
+        format!("{}/{}", path_dir, slug)
+    };
+
+    let rendered = template
+        .replace("{host}", &parts.host)
+        .replace("{domain}", &parts.domain)
+        .replace("{subdomain}", parts.subdomain.as_deref().unwrap_or(""))
+        .replace("{port}", &parts.port.map(|p| p.to_string()).unwrap_or_default())
+        .replace("{reversed}", &reverse_host(&parts.host))
+        .replace("{path_dir}", &path_dir)
+        .replace("{path}", &path)
+        .replace("{slug}", &slug)
+        .replace("{title}", &page.title.unwrap_or(&slug).replace('/', "_"))
+        .replace("{yyyy}", &page.scraped_at.format("%Y").to_string())
+        .replace("{mm}", &page.scraped_at.format("%m").to_string())
+        .replace("{dd}", &page.scraped_at.format("%d").to_string())
+        .replace("{lang}", &page.language.unwrap_or("und").to_lowercase())
+        .replace("{type}", &page.page_type.to_string());
+
+    let mut file = rendered
+        .split('/')
+        .map(sanitize_component)
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if file.is_empty() {
+        return Err(format!("Path template produced an empty path for {}", page.url).into());
+    }
+    if !file.ends_with(".md") {
+        file.push_str(".md");
+    }
+    Ok(file)
+}
+
+/// Builds the path of a page file, relative to the site's directory.
+///
+/// This is where a page is saved: the rendered `--path-template` if one is
+/// given, otherwise the sanitized title, or the name derived from the URL for
+/// pages without a title. Pages saved for review are moved into `_review/`
+/// below this path.
+///
+/// # Errors
+///
+/// Returns an error if the path template cannot be rendered for the page
+///
+/// # Examples
+///
+/// ```
+/// # use chrono::Utc;
+/// # use scraper::output::{page_file_name, PageVariables};
+/// # use scraper::page_type::PageType;
+/// let page = PageVariables {
+///     url: "https://docs.example.com/guide/install.html",
+///     title: None,
+///     language: None,
+///     page_type: PageType::Guide,
+///     scraped_at: Utc::now(),
+/// };
+/// assert_eq!(page_file_name(&page, None)?, "install.md");
+/// assert_eq!(page_file_name(&page, Some("{path}"))?, "guide/install.md");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn page_file_name(page: &PageVariables, template: Option<&str>) -> Result<String, Box<dyn Error>> {
+    match (template, page.title) {
+        (Some(template), _) => page_path(template, page),
+        (None, Some(title)) => Ok(format!("{}.md", sanitize_filename(title))),
+        (None, None) => Ok(fallback_file_name(page.url)),
+    }
+}
+
+/// A file a page named after its title can be saved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitledFile {
+    /// Path of the file, relative to the site's directory
+    pub file: String,
+    /// Sections the file name is prefixed with, e.g. `api / auth`; `None` for the title alone
+    pub section: Option<String>,
+}
+
+impl TitledFile {
+    /// Returns the title the page is listed under in the manifest and exports.
+    pub fn listed_title(&self, title: &str) -> String {
+        match &self.section {
+            Some(section) => format!("{} / {}", section, title),
+            None => title.to_string(),
+        }
+    }
+}
+
+/// Returns the files a page named after its title can be saved to, in order of preference.
+///
+/// The first is the title alone. Pages sharing a title, like the "Overview" of
+/// every section, are told apart by prefixing it with the sections the page is
+/// in, taken from the URL path nearest first. The last resort appends a short
+/// hash of the URL.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::output::titled_files;
+/// let files = titled_files("https://docs.example.com/api/auth/overview", "Overview");
+/// assert_eq!(files[0].file, "Overview.md");
+/// assert_eq!(files[1].file, "auth_Overview.md");
+/// assert_eq!(files[1].listed_title("Overview"), "auth / Overview");
+/// assert_eq!(files[2].file, "api_auth_Overview.md");
+/// ```
+pub fn titled_files(url: &str, title: &str) -> Vec<TitledFile> {
+    let title_name = sanitize_filename(title);
+    let parsed_url = Url::parse(url).ok();
+    let segments: Vec<String> = parsed_url
+        .as_ref()
+        .and_then(|url| url.path_segments())
+        .map(|segments| {
+            segments
+                .filter(|segment| !segment.is_empty())
+                .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    // A page's sections are the directories of its URL, or all of its path for a directory URL
+    let directory_url = parsed_url.as_ref().is_none_or(|url| url.path().ends_with('/'));
+    let sections = match segments.split_last() {
+        Some((_, dirs)) if !directory_url => dirs,
+        _ => &segments[..],
+    };
+
+    let mut files = vec![TitledFile {
+        file: format!("{}.md", title_name),
+        section: None,
+    }];
+    for start in (0..sections.len()).rev() {
+        let sections = &sections[start..];
+        let prefix: Vec<String> = sections.iter().map(|section| sanitize_filename(section)).collect();
+        files.push(TitledFile {
+            file: format!("{}_{}.md", prefix.join("_"), title_name),
+            section: Some(sections.join(" / ")),
+        });
+    }
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    files.push(TitledFile {
+        file: format!("{}-{}.md", title_name, &hash[..8]),
+        section: None,
+    });
+    files
+}
+
+/// The files the pages of a run are saved to, so pages sharing a title each get a file of their own.
+///
+/// Pages are scraped concurrently, so a file is claimed before the page is
+/// written. Starting from the manifest keeps the files of earlier runs with the
+/// pages they belong to. Files are claimed in the order the pages were
+/// started, not the order they finish in, so which of several pages sharing a
+/// title gets the title alone doesn't depend on `--concurrency` or timing.
+pub struct FileClaims {
+    /// URL of the page saved to each file, by path outside the review directory
+    owners: Mutex<HashMap<String, String>>,
+
+    /// Turns handed out so far and those not yet used up
+    turns: Mutex<Turns>,
+
+    /// Woken whenever a turn is used up
+    turn_ended: Notify,
+}
+
+/// Turns of the pages of a run to claim a file.
+#[derive(Default)]
+struct Turns {
+    /// Turn of the next page started
+    next: usize,
+
+    /// Turns of the pages started that haven't claimed a file or finished yet
+    open: BTreeSet<usize>,
+}
+
+/// A started page's turn to claim a file, ending when it claims one or is dropped.
+pub struct ClaimTicket<'a> {
+    claims: &'a FileClaims,
+    turn: usize,
+}
+
+impl FileClaims {
+    /// Starts from the files of the pages in the manifest that haven't been removed.
+    pub fn new(manifest: &Manifest) -> Self {
+        let owners = manifest
+            .pages
+            .iter()
+            .filter(|(_, entry)| entry.removed.is_none())
+            .map(|(url, entry)| (quality::review_path(&entry.file, false), url.clone()))
+            .collect();
+        FileClaims {
+            owners: Mutex::new(owners),
+            turns: Mutex::new(Turns::default()),
+            turn_ended: Notify::new(),
+        }
+    }
+
+    /// Hands out the turn of the page started next, to be taken in the order pages are started.
+    pub fn ticket(&self) -> ClaimTicket<'_> {
+        let mut turns = self.turns.lock().unwrap_or_else(PoisonError::into_inner);
+        let turn = turns.next;
+        turns.next += 1;
+        turns.open.insert(turn);
+        ClaimTicket { claims: self, turn }
+    }
+
+    /// Ends a turn, letting the pages started after it claim their files.
+    fn end_turn(&self, turn: usize) {
+        let ended = self.turns.lock().unwrap_or_else(PoisonError::into_inner).open.remove(&turn);
+        if ended {
+            self.turn_ended.notify_waiters();
+        }
+    }
+}
+
+impl ClaimTicket<'_> {
+    /// Claims one of `files` for the page at `url` and returns it, once the
+    /// pages started before this one have claimed their files or finished.
+    ///
+    /// The file the page was saved to before (`previous`) is kept if it is one of
+    /// them and still its own, so a page doesn't move once another page with its
+    /// title goes away; otherwise the first file no other page is saved to is taken.
+    pub async fn claim(&self, url: &str, files: Vec<TitledFile>, previous: Option<&str>) -> TitledFile {
+        let claims = self.claims;
+        loop {
+            // Registered before checking, so a turn ending in between isn't missed
+            let turn_ended = claims.turn_ended.notified();
+            let first = claims.turns.lock().unwrap_or_else(PoisonError::into_inner).open.first().copied();
+            if first.is_none_or(|first| first >= self.turn) {
+                break;
+            }
+            turn_ended.await;
+        }
+
+        let mut owners = claims.owners.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = previous.map(|previous| quality::review_path(previous, false));
+        let free = |file: &TitledFile| owners.get(&file.file).is_none_or(|owner| owner == url);
+        let position = files
+            .iter()
+            .position(|file| previous.as_deref() == Some(file.file.as_str()) && free(file))
+            .or_else(|| files.iter().position(free))
+            .unwrap_or(files.len() - 1);
+        let file = files.into_iter().nth(position).expect("position is within the files");
+        owners.insert(file.file.clone(), url.to_string());
+        drop(owners);
+        claims.end_turn(self.turn);
+        file
+    }
+}
+
+impl Drop for ClaimTicket<'_> {
+    fn drop(&mut self) {
+        self.claims.end_turn(self.turn);
+    }
+}
+
+/// Sanitizes a string for use as a filename by replacing invalid characters with underscores.
+///
+/// # Arguments
+///
+/// * `filename` - The string to sanitize
+///
+/// # Returns
+///
+/// A new string with invalid filename characters replaced by underscores and
+/// control characters removed
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::output::sanitize_filename;
+/// let safe_name = sanitize_filename("hello/world.txt");
+/// assert_eq!(safe_name, "hello_world_txt");
+///
+/// let safe_name = sanitize_filename("file<with>invalid*chars");
+/// assert_eq!(safe_name, "file_with_invalid_chars");
+/// ```
+pub fn sanitize_filename(filename: &str) -> String {
+    let invalid_chars: &[char] = &['/', '\\', '?', '%', '*', ':', '|', '"', '<', '>', '.', ' '];
+    let mut sanitized: String = filename.chars().filter(|c| !c.is_control()).collect();
+    for c in invalid_chars {
+        sanitized = sanitized.replace(*c, "_");
+    }
+    sanitized
+}
+
+/// Builds the file name of a page that has no title and no `--path-template`.
+///
+/// The name is the percent-decoded last path segment without its extension, or
+/// `index` for the site root. URLs with a query string get a short hash of the
+/// query appended, so `?page=1` and `?page=2` are saved to different files.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::output::fallback_file_name;
+/// assert_eq!(fallback_file_name("https://docs.example.com/guide/caf%C3%A9%20menu.html"), "café_menu.md");
+/// assert_eq!(fallback_file_name("https://docs.example.com/"), "index.md");
+/// assert!(fallback_file_name("https://docs.example.com/list?page=2").starts_with("list-"));
+/// ```
+pub fn fallback_file_name(url: &str) -> String {
+    let parsed_url = Url::parse(url).ok();
+    let segment = parsed_url
+        .as_ref()
+        .and_then(|url| url.path_segments())
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .unwrap_or_default();
+    let decoded = percent_decode_str(segment).decode_utf8_lossy();
+
+    let mut name = sanitize_component(strip_extension(&decoded));
+    if name.is_empty() {
+        name = "index".to_string();
+    }
+    if let Some(query) = parsed_url.as_ref().and_then(|url| url.query()).filter(|query| !query.is_empty()) {
+        let hash = format!("{:x}", Sha256::digest(query.as_bytes()));
+        name = format!("{}-{}", name, &hash[..8]);
+    }
+    format!("{}.md", name)
+}
+
+/// Removes the extension of a URL path segment, e.g. `install.html` becomes `install`.
+fn strip_extension(segment: &str) -> &str {
+    match segment.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => segment,
+    }
+}
+
+/// Splits a host into its registrable domain and the subdomain labels in front of it.
+///
+/// Without a public suffix list this is a heuristic: the last two labels form the
+/// domain, or the last three when the second-to-last label is a common second-level
+/// suffix under a two-letter country code (`example.co.uk`). IP addresses and single
+/// label hosts are returned unchanged.
+pub(crate) fn split_registrable_domain(host: &str) -> (String, Option<String>) {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return (host.to_string(), None);
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let suffix_len = match labels.as_slice() {
+        [.., second, tld] if tld.len() == 2 && SECOND_LEVEL_SUFFIXES.contains(second) => 3,
+        _ => 2,
+    };
+
+    if labels.len() <= suffix_len {
+        return (host.to_string(), None);
+    }
+
+    let split = labels.len() - suffix_len;
+    (labels[split..].join("."), Some(labels[..split].join(".")))
+}
+
+/// Reverses the labels of a host name, e.g. `docs.example.com` becomes `com.example.docs`.
+fn reverse_host(host: &str) -> String {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return host.to_string();
+    }
+    host.split('.').rev().collect::<Vec<_>>().join(".")
+}
+
+/// Makes a single path component safe: only alphanumerics (including non-ASCII
+/// letters of internationalized hosts), `.`, `-` and `_` are kept, and components
+/// consisting only of dots are rejected.
+fn sanitize_component(component: &str) -> String {
+    let sanitized: String = component
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if sanitized.chars().all(|c| c == '.') {
+        String::new()
+    } else {
+        sanitized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = "https://docs.example.com/a/overview";
+    const SECOND: &str = "https://docs.example.com/b/overview";
+
+    #[tokio::test]
+    async fn pages_sharing_a_title_claim_files_in_the_order_they_were_started() {
+        let claims = FileClaims::new(&Manifest::default());
+        let first = claims.ticket();
+        let second = claims.ticket();
+
+        // The second page finishes first, but waits for the first to claim its file
+        let (second_file, first_file) = tokio::join!(second.claim(SECOND, titled_files(SECOND, "Overview"), None), async {
+            tokio::task::yield_now().await;
+            first.claim(FIRST, titled_files(FIRST, "Overview"), None).await
+        });
+        assert_eq!(first_file.file, "Overview.md");
+        assert_eq!(second_file.file, "b_Overview.md");
+        assert_eq!(second_file.section.as_deref(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn pages_ending_without_a_claim_let_later_pages_claim() {
+        let claims = FileClaims::new(&Manifest::default());
+        let failed = claims.ticket();
+        let second = claims.ticket();
+        drop(failed);
+        assert_eq!(second.claim(SECOND, titled_files(SECOND, "Overview"), None).await.file, "Overview.md");
+    }
+
+    #[tokio::test]
+    async fn pages_keep_the_file_they_were_saved_to_before() {
+        let claims = FileClaims::new(&Manifest::default());
+        let first = claims.ticket();
+        let second = claims.ticket();
+        let file = first.claim(FIRST, titled_files(FIRST, "Overview"), Some("a_Overview.md")).await;
+        assert_eq!(file.file, "a_Overview.md");
+        assert_eq!(second.claim(SECOND, titled_files(SECOND, "Overview"), None).await.file, "Overview.md");
+    }
+}
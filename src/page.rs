@@ -0,0 +1,731 @@
+//! Scraping a single page and saving it as markdown.
+//!
+//! A page is fetched through FireCrawl or directly from the site, passed
+//! through the middleware hooks, redaction and replacement rules, translated
+//! and given its assets, and written with its frontmatter unless its content
+//! is unchanged. What became of it is returned as a `PageOutcome` for the
+//! scheduler to record.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use tracing::{debug, info, warn};
+
+use crate::accessibility::{self, AltTextGenerator};
+use crate::api::{make_api_request, Api, Metadata, ScrapeData, ScrapeRequest};
+use crate::bandwidth::{self, ResponseTooLarge};
+use crate::cli::{Backend, ErrorPages, SchemaViolations, ScrapeOptions};
+use crate::direct::{self, FetchOutcome};
+use crate::encoding::{self, LineEndings};
+use crate::manifest::{self, EmptyContentEntry, ManifestEntry};
+use crate::middleware::{PageDocument, PageRequest, PageResponse, Verdict};
+use crate::output::{self, sanitize_filename, ClaimTicket, PageVariables, TitledFile};
+use crate::page_type::{self, PageType};
+use crate::preset::Preset;
+use crate::retry::Retrier;
+use crate::scheduler::Run;
+use crate::template::FrontmatterTemplate;
+use crate::translate::{self, Translator};
+use crate::{debug_bundle, formats, frontmatter, html, markdown, navigation, platform, quality, redact, tombstone, urls};
+use crate::STATE_DIR_NAME;
+
+/// Name of the directory inside the state directory that holds raw API responses without markdown.
+const EMPTY_RESPONSES_DIR_NAME: &str = "empty-responses";
+
+/// Returns the main-content extraction setting to request for a page.
+/// 
+/// A host whose pages came back empty with main-content extraction is scraped
+/// without it; otherwise the default of the page type guessed from the URL
+/// applies, if any.
+pub fn only_main_content(url: &str, options: &ScrapeOptions, learned: Option<bool>) -> Option<bool> {
+    learned.filter(|only| !only).or_else(|| {
+        page_type::classify_url(url)
+            .filter(|_| !options.no_page_type_defaults)
+            .map(|page_type| {
+                page_type.defaults().only_main_content || (options.landing_main_content && page_type == PageType::Landing)
+            })
+    })
+}
+
+/// Returns the extraction preset to apply to a host's pages.
+///
+/// A preset chosen with `--preset` applies to every host; otherwise the preset
+/// of the generator detected on the host, if one was recognized.
+pub fn extraction_preset(options: &ScrapeOptions, detected: Option<Preset>) -> Option<Preset> {
+    match options.preset.unwrap_or_default() {
+        Preset::Auto => detected.filter(|preset| *preset != Preset::None),
+        Preset::None => None,
+        chosen => Some(chosen),
+    }
+}
+
+/// Result of `process_page` for a page that was scraped without error.
+pub enum PageOutcome {
+    /// The page was saved (or was unchanged and kept)
+    Saved(Box<ProcessedPage>),
+
+    /// The API reported success but returned no markdown, so nothing was saved
+    EmptyContent(EmptyContentEntry),
+
+    /// The page was removed from the site and its file is a tombstone
+    Removed(Box<ManifestEntry>),
+
+    /// A middleware hook skipped the page, so nothing was saved
+    Skipped,
+
+    /// The site answered with a 4xx or 5xx status and `--error-pages` doesn't save such pages
+    ErrorStatus(u16),
+}
+
+/// How a saved page compares with the file saved for it by an earlier run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageChange {
+    /// No file was saved for the page before
+    Added,
+
+    /// The file was rewritten with changed content
+    Changed,
+
+    /// The content was unchanged and the file left untouched
+    Unchanged,
+}
+
+/// A page saved by `process_page`.
+pub struct ProcessedPage {
+    /// Manifest entry recording where and when the page was saved
+    pub entry: ManifestEntry,
+
+    /// How the page compares with its previously saved file
+    pub change: PageChange,
+
+    /// Markdown saved for the page, without frontmatter
+    pub body: String,
+
+    /// Metadata of the scrape; `None` if the page was unchanged and not downloaded again
+    pub metadata: Option<Metadata>,
+
+    /// Number of replacements made by content redaction rules
+    pub redactions: usize,
+
+    /// Number of replacements made by search-and-replace rules
+    pub replacements: usize,
+
+    /// Images without alt text and lost captions found on the page
+    pub images: Vec<accessibility::ImageFinding>,
+}
+
+/// A page to scrape, with what the scheduler has found out about it.
+pub struct PageJob<'a> {
+    /// The URL to process
+    pub url: &'a str,
+    /// Main-content extraction setting to request from FireCrawl
+    pub only_main_content: Option<bool>,
+    /// Extraction preset of the site's documentation generator, if any
+    pub preset: Option<Preset>,
+    /// Manifest entry from a previous run, if its file still exists
+    pub previous: Option<&'a ManifestEntry>,
+    /// File to write relative to the output directory; derived from `--path-template` or the page title when `None`
+    pub file_name: Option<&'a str>,
+    /// The page as a crawl job scraped it; the page is fetched when `None`
+    pub crawled: Option<ScrapeData>,
+}
+
+/// Processes a single documentation page and saves it as markdown.
+/// 
+/// With the direct backend, cache validators from the previous manifest entry are
+/// used to avoid downloading unchanged pages, and the response's caching headers
+/// are recorded in a `.meta.json` sidecar next to the saved file.
+/// 
+/// # Arguments
+/// 
+/// * `run` - The API, output directory, options and limits of the run
+/// * `job` - The page to process
+/// * `file_claim` - The page's turn to claim a file, so pages sharing a title get files of their own
+/// * `retrier` - Retrier of the page's FireCrawl request, counting its retries
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the processed page, or the empty-content record if
+/// the response held no markdown; the raw response is then cached in the state
+/// directory so the page can be investigated
+/// 
+/// # Errors
+/// 
+/// Returns an error if:
+/// - The API request fails
+/// - File writing fails
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let job = PageJob { url: "https://docs.example.com/page", only_main_content: None, preset: None, previous: None, file_name: None, crawled: None };
+/// let page = process_page(&run, job, &file_claims.ticket(), &retrier).await?;
+/// ```
+pub async fn process_page(
+    run: &Run<'_>,
+    job: PageJob<'_>,
+    file_claim: &ClaimTicket<'_>,
+    retrier: &Retrier,
+) -> Result<PageOutcome, Box<dyn Error>> {
+    let Run { api, output_dir, options, bandwidth, assets, .. } = *run;
+    let Api { client, url: api_url, key: api_key } = api;
+    let PageJob { url, only_main_content, preset, previous, file_name, crawled } = job;
+    let type_defaults = page_type::classify_url(url)
+        .filter(|_| !options.no_page_type_defaults)
+        .map(PageType::defaults);
+    let raw_html_tables = type_defaults.is_some_and(|defaults| defaults.raw_html_tables);
+    let auth = options.credentials_for(url);
+
+    let mut request = PageRequest {
+        page_url: url,
+        url: url.to_string(),
+        headers: BTreeMap::new(),
+        only_main_content,
+    };
+    if crawled.is_none() && options.middleware.before_request(&mut request).await? == Verdict::Skip {
+        info!("Skipped by middleware before the request");
+        return Ok(PageOutcome::Skipped);
+    }
+
+    let mut dropped_formats = Vec::new();
+    let (mut data, cache, api_call, raw_response) = match (crawled, options.backend) {
+        (Some(data), _) => (data, None, None, None),
+        (None, Backend::Firecrawl) => {
+            let mut formats = vec!["markdown".to_string()];
+            if raw_html_tables {
+                formats.push("html".to_string());
+            }
+            for format in &options.formats {
+                let name = format.api_name().to_string();
+                if !formats.contains(&name) {
+                    formats.push(name);
+                }
+            }
+            if options.change_tracking {
+                formats.push("changeTracking".to_string());
+            }
+            let mut headers = serde_json::Map::new();
+            if let Some(auth) = auth {
+                headers.insert("Authorization".to_string(), auth.header_value().into());
+            }
+            for (name, value) in &request.headers {
+                headers.insert(name.clone(), value.clone().into());
+            }
+            let extraction = preset.and_then(Preset::extraction);
+            let mut request = ScrapeRequest {
+                url: request.url,
+                formats,
+                only_main_content: request.only_main_content,
+                include_tags: extraction.and_then(|extraction| extraction.include_tags()),
+                exclude_tags: extraction.and_then(|extraction| extraction.exclude_tags()),
+                headers: (!headers.is_empty()).then_some(serde_json::Value::Object(headers)),
+                wait_for: extraction.and_then(|extraction| extraction.wait_for),
+                actions: extraction.and_then(|extraction| extraction.actions()),
+                ..Default::default()
+            };
+            debug_bundle::record_request(url, &request);
+            let max_size = bandwidth.max_response();
+            // The error isn't kept while the request is made again, so a future running this stays `Send`
+            let answered = match make_api_request(client, api_url, api_key, request.clone(), retrier, max_size).await {
+                Err(e) if options.drop_oversized_formats && e.is::<ResponseTooLarge>() => {
+                    // Markdown is what the page is saved as, so it is the one format never dropped
+                    dropped_formats = request
+                        .formats
+                        .iter()
+                        .filter(|format| !matches!(format.as_str(), "markdown" | "changeTracking"))
+                        .cloned()
+                        .collect();
+                    if dropped_formats.is_empty() {
+                        return Err(e);
+                    }
+                    warn!("{}; requesting markdown only, without {}", e, dropped_formats.join(", "));
+                    request.formats.retain(|format| !dropped_formats.contains(format));
+                    None
+                }
+                result => Some(result?),
+            };
+            let (response, call, body) = match answered {
+                Some(answered) => answered,
+                None => make_api_request(client, api_url, api_key, request, retrier, max_size).await?,
+            };
+            bandwidth.record_payload(call.response_bytes);
+            (response.data, None, Some(call), Some(body))
+        }
+        (None, Backend::Direct) => {
+            debug_bundle::record_request(url, &serde_json::json!({ "backend": "direct", "url": &request.url, "headers": &request.headers }));
+            // Without a saved file there is nothing to reuse, so no conditional request is made
+            let cached = previous.filter(|_| options.writes_page_files());
+            match direct::fetch_page(client, &request.url, cached, auth, &request.headers, bandwidth.max_response()).await? {
+                FetchOutcome::Fetched { data, cache } => {
+                    bandwidth.record_payload(data.raw_html.as_ref().map_or(0, |html| html.len() as u64));
+                    (*data, Some(cache), None, None)
+                }
+                FetchOutcome::Fresh | FetchOutcome::NotModified => {
+                    info!("Unchanged");
+                    return unchanged_page(output_dir, previous);
+                }
+            }
+        }
+    };
+    debug_bundle::record_response(url, &data.metadata, api_call);
+    
+    let status = data.metadata.status_code.and_then(|status| u16::try_from(status).ok());
+    if let (true, Some(status), Some(previous)) = (options.tombstones, status, previous) {
+        if tombstone::is_gone(status) {
+            return removed_page(output_dir, url, previous, status);
+        }
+    }
+    // The site's error page is only saved when asked for; otherwise the caller decides what the status means
+    let error_status = status.filter(|status| *status >= 400);
+    if let Some(status) = error_status.filter(|_| options.error_pages.unwrap_or_default() != ErrorPages::Flag) {
+        return Ok(PageOutcome::ErrorStatus(status));
+    }
+
+    let Some(mut markdown) = data.markdown.clone() else {
+        let response_file = match &raw_response {
+            Some(body) => {
+                let (body, _) = redact::redact_content(&options.content_rules, &String::from_utf8_lossy(body));
+                Some(cache_empty_response(output_dir, url, body.as_bytes())?)
+            }
+            None => None,
+        };
+        match &response_file {
+            Some(file) => warn!("No markdown content received; response saved to {}", file),
+            None => warn!("No markdown content received"),
+        }
+        return Ok(PageOutcome::EmptyContent(EmptyContentEntry {
+            detected_at: Utc::now(),
+            status_code: data.metadata.status_code,
+            response_file,
+            api_call,
+        }));
+    };
+
+    if let (true, Some(html)) = (raw_html_tables, &data.html) {
+        if let Some(replaced) = markdown::replace_tables(&markdown, &html::elements(html, "table")) {
+            markdown = replaced;
+        }
+    }
+
+    let mut response = PageResponse {
+        url,
+        status_code: data.metadata.status_code,
+        html: data.raw_html.as_deref().or(data.html.as_deref()),
+        markdown,
+        title: data.metadata.title.clone(),
+        description: data.metadata.description.clone(),
+        language: data.metadata.language.clone(),
+    };
+    let verdict = options.middleware.after_response(&mut response).await?;
+    let PageResponse { markdown: hooked, title, description, language, .. } = response;
+    if verdict == Verdict::Skip {
+        info!("Skipped by middleware after the response");
+        return Ok(PageOutcome::Skipped);
+    }
+    let mut markdown = hooked;
+    data.metadata.title = title;
+    data.metadata.description = description;
+    data.metadata.language = language;
+
+    let page_html = data.raw_html.as_deref().or(data.html.as_deref());
+    let mut images = accessibility::check_images(&markdown, page_html, url);
+    if let Some(generator) = AltTextGenerator::from_options(options).filter(|_| !images.is_empty()) {
+        markdown = accessibility::fill_alt_text(client, &generator, &markdown, url, &mut images).await;
+    }
+
+    // Redaction rules apply to everything saved from the page, including the file name
+    let (markdown, mut redactions) = redact::redact_content(&options.content_rules, &markdown);
+    for field in [&mut data.metadata.title, &mut data.metadata.description].into_iter().flatten() {
+        let (redacted, count) = redact::redact_content(&options.content_rules, field);
+        *field = redacted;
+        redactions += count;
+    }
+    // Search-and-replace rules only rewrite the page body, and see it already redacted
+    let (mut markdown, replacements) = redact::redact_content(&options.replace_rules, &markdown);
+
+    let page_type = page_type::classify(url, &markdown);
+    let scraped_at = Utc::now();
+    let quality = quality::score(&markdown, page_html, data.warning.is_some());
+    let quarantined = options.min_quality.is_some_and(|min_quality| quality.score < min_quality);
+
+    // Problems that don't stop the page from being saved, recorded in the manifest
+    let mut warnings = Vec::new();
+    if let Some(warning) = &data.warning {
+        warn!("Warning from the API: {}", warning);
+        warnings.push(format!("Warning from the API: {}", warning));
+    }
+    if !dropped_formats.is_empty() {
+        warnings.push(format!(
+            "Dropped {}: the response was larger than {}",
+            dropped_formats.join(", "),
+            bandwidth::format_bytes(bandwidth.max_response())
+        ));
+    }
+    if let Some(status) = error_status {
+        warn!("Saved with status {}", status);
+        warnings.push(format!("Saved with status {}", status));
+    }
+    if data.metadata.title.is_none() {
+        warn!("No title in the page metadata");
+        warnings.push("No title in the page metadata".to_string());
+    }
+
+    // Pages on a domain allowed with --allow-domain are kept apart from the site's own
+    let domain_dir = match file_name {
+        Some(_) => None,
+        None => platform::allowed_domain_dir(url, options)?,
+    };
+    let in_domain_dir = |name: String| match &domain_dir {
+        Some(dir) => format!("{}/{}", dir, name),
+        None => name,
+    };
+    let mut titled_file = None;
+    let filename = match (file_name, &options.path_template, data.metadata.title.as_deref()) {
+        (Some(name), _, _) => name.to_string(),
+        // Pages sharing a title are told apart by the sections of their URL
+        (None, None, Some(title)) => {
+            let files = output::titled_files(url, title)
+                .into_iter()
+                .map(|file| TitledFile {
+                    file: in_domain_dir(file.file),
+                    section: file.section,
+                })
+                .collect();
+            let file = file_claim.claim(url, files, previous.map(|previous| previous.file.as_str())).await;
+            if file.section.is_some() {
+                debug!("Another page is titled {:?}; saving as {}", title, file.file);
+            }
+            titled_file.insert(file).file.clone()
+        }
+        (None, _, _) => in_domain_dir(output::page_file_name(
+            &PageVariables {
+                url,
+                title: data.metadata.title.as_deref(),
+                language: data.metadata.language.as_deref(),
+                page_type,
+                scraped_at,
+            },
+            options.path_template.as_deref(),
+        )?),
+    };
+    let filename = quality::review_path(&filename, quarantined);
+    if let (true, Some(min_quality)) = (quarantined, options.min_quality) {
+        warn!(
+            "Quality score {} is below --min-quality {} ({:?}); saving for review",
+            quality.score, min_quality, quality.issues
+        );
+        warnings.push(format!("Quality score {} is below --min-quality {}", quality.score, min_quality));
+    }
+    // A page moving into or out of the review directory leaves no copy behind
+    if let Some(previous) = previous.filter(|previous| previous.file != filename) {
+        if quality::review_path(&previous.file, quarantined) == filename {
+            let _ = fs::remove_file(output_dir.join(&previous.file));
+        }
+    }
+
+    let file_path = output_dir.join(&filename);
+    let writes_file = options.writes_page_files();
+    if let (true, Some(parent)) = (writes_file, file_path.parent()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    // Saved with --line-endings crlf, the file is compared and merged with LF line endings
+    let existing = fs::read_to_string(&file_path)
+        .ok()
+        .filter(|_| writes_file)
+        .map(|existing| existing.replace("\r\n", "\n"));
+
+    // Pages are translated after redaction, and named after their original title so file names stay stable
+    let translator = Translator::from_options(options)
+        .filter(|translator| translator.needs_translation(data.metadata.language.as_deref()));
+    let translation = match translator {
+        Some(translator) => {
+            info!("Translating into {}", translator.target());
+            let (translated, translation) =
+                translate::translate_page(client, &translator, &markdown, &mut data.metadata, existing.as_deref())
+                    .await?;
+            markdown = translated;
+            Some(translation)
+        }
+        None => None,
+    };
+
+    // Screenshots and downloaded assets, listed in the frontmatter and the manifest
+    let mut media = Vec::new();
+    if let Some(assets) = assets {
+        let (localized, assets, failed) = assets.localize(&markdown, url, &filename, bandwidth).await;
+        markdown = localized;
+        media = assets;
+        if failed > 0 {
+            warnings.push(format!("{} assets not downloaded", failed));
+        }
+    }
+
+    let mut document = PageDocument {
+        url,
+        file: &filename,
+        body: markdown,
+        fields: BTreeMap::new(),
+    };
+    if options.middleware.before_write(&mut document).await? == Verdict::Skip {
+        info!("Skipped by middleware before writing");
+        return Ok(PageOutcome::Skipped);
+    }
+    let PageDocument { body: markdown, fields, .. } = document;
+    let markdown = encoding::normalize_text(&markdown, LineEndings::Lf);
+
+    // Other formats are saved first, so the screenshot can be listed in the frontmatter
+    let saved_formats: Vec<formats::PageFormat> = options
+        .formats
+        .iter()
+        .copied()
+        .filter(|format| !dropped_formats.iter().any(|dropped| dropped == format.api_name()))
+        .collect();
+    let (format_redactions, screenshot) = formats::save_formats(
+        client,
+        output_dir,
+        &filename,
+        &data,
+        &saved_formats,
+        &options.content_rules,
+        bandwidth,
+        &mut warnings,
+    )
+    .await?;
+    redactions += format_redactions;
+    if let Some(screenshot) = screenshot {
+        media.insert(0, screenshot);
+    }
+
+    let headings = markdown::heading_paths(&markdown);
+    let navigation = navigation::navigation_links(&markdown, url);
+
+    // A page whose content hasn't changed keeps the scrape date of the saved file,
+    // so site generators don't report it as updated
+    let dates = options.date_style();
+    let content_scraped_at = existing.as_deref().and_then(|existing| {
+        let saved_at = frontmatter::parse_frontmatter(existing)?
+            .get("scrapeDate")
+            .and_then(|date| dates.parse(date))?;
+        let saved_body = markdown::strip_citation_footer(frontmatter::strip_frontmatter(existing));
+        (saved_body == markdown).then_some(saved_at)
+    });
+    let last_scraped_at = content_scraped_at.map(|_| scraped_at);
+    let content_scraped_at = content_scraped_at.unwrap_or(scraped_at);
+
+    let mut body = markdown;
+    if options.citation_footer {
+        let source_url = data.metadata.source_url.as_deref().unwrap_or(url);
+        body.push_str(&markdown::citation_footer(
+            source_url,
+            content_scraped_at,
+            options.license_note.as_deref(),
+        ));
+    }
+
+    let change_status = data.change_tracking.as_ref().map(|tracking| tracking.change_status);
+    let id = manifest::page_id(url);
+    let template = options.frontmatter_template.as_ref().unwrap_or(FrontmatterTemplate::built_in());
+    let crawl = frontmatter::Crawl {
+        url,
+        file: &filename,
+        backend: options.backend,
+        preset,
+    };
+    let render_frontmatter = |content_scraped_at, last_scraped_at, change_status| -> Result<String, Box<dyn Error>> {
+        let frontmatter = frontmatter::create_frontmatter(
+            template,
+            &data.metadata,
+            &id,
+            page_type,
+            &headings,
+            content_scraped_at,
+            last_scraped_at,
+            change_status,
+            &dates,
+            translation.as_ref(),
+            &crawl,
+        )?;
+        let frontmatter = frontmatter::add_media(&frontmatter, &filename, &media);
+        let frontmatter = match error_status {
+            Some(status) => frontmatter::add_error_status(&frontmatter, status),
+            None => frontmatter,
+        };
+        Ok(frontmatter::add_fields(&frontmatter, &fields))
+    };
+    let frontmatter = render_frontmatter(content_scraped_at, last_scraped_at, change_status)?;
+    if let Some(schema) = &options.frontmatter_schema {
+        let violations = schema.violations(&frontmatter);
+        if !violations.is_empty() && options.schema_violations == SchemaViolations::Fail {
+            return Err(format!(
+                "Frontmatter doesn't match schema {}: {}",
+                schema.path().display(),
+                violations.join("; ")
+            )
+            .into());
+        }
+        for violation in violations {
+            warn!("Frontmatter doesn't match the schema: {}", violation);
+            warnings.push(format!("Frontmatter doesn't match the schema: {}", violation));
+        }
+    }
+    let line_endings = options.line_endings.unwrap_or_default();
+    let content = encoding::normalize_text(&format!("{}{}", frontmatter, body), line_endings);
+
+    // The date and change status of the latest scrape change from run to run, so they are left out of the content hash
+    let content_hash = match (writes_file, last_scraped_at, change_status) {
+        // Without a saved file the date the content was first scraped isn't known, so no date is hashed
+        (false, _, _) => manifest::file_hash(
+            encoding::normalize_text(
+                &format!(
+                    "{}{}",
+                    render_frontmatter(DateTime::UNIX_EPOCH, None, None)?,
+                    markdown::strip_citation_footer(&body)
+                ),
+                line_endings,
+            )
+            .as_bytes(),
+        ),
+        (true, None, None) => manifest::file_hash(content.as_bytes()),
+        (true, _, _) => manifest::file_hash(
+            encoding::normalize_text(&format!("{}{}", render_frontmatter(content_scraped_at, None, None)?, body), line_endings)
+                .as_bytes(),
+        ),
+    };
+    let unchanged = previous.filter(|previous| {
+        previous.file == filename && previous.removed.is_none() && previous.content_hash.as_ref() == Some(&content_hash)
+    });
+    let (change, size, file_hash) = match unchanged {
+        Some(previous) => {
+            info!("Unchanged: {}", file_path.display());
+            (PageChange::Unchanged, previous.size, previous.file_hash.clone())
+        }
+        None if !writes_file => {
+            info!("Scraped: {}", filename);
+            let change = if previous.is_some() { PageChange::Changed } else { PageChange::Added };
+            (change, None, None)
+        }
+        None => {
+            fs::write(&file_path, &content)
+                .map_err(|e| format!("Failed to write file {}: {}", file_path.display(), e))?;
+            info!("Saved: {}", file_path.display());
+            let change = if previous.is_some() { PageChange::Changed } else { PageChange::Added };
+            (change, Some(content.len() as u64), Some(manifest::file_hash(content.as_bytes())))
+        }
+    };
+    if let (true, Some(cache)) = (writes_file, &cache) {
+        direct::write_sidecar(&file_path, url, data.metadata.status_code, cache, scraped_at)?;
+    }
+
+    let cache = cache.unwrap_or_default();
+    Ok(PageOutcome::Saved(Box::new(ProcessedPage {
+        entry: ManifestEntry {
+            id: Some(id),
+            file: filename,
+            title: match &titled_file {
+                Some(file) => data.metadata.title.as_deref().map(|title| file.listed_title(title)),
+                None => data.metadata.title.clone(),
+            },
+            scraped_at,
+            size,
+            file_hash,
+            content_hash: Some(content_hash),
+            change_status,
+            etag: cache.etag,
+            last_modified: cache.last_modified,
+            cache_control: cache.cache_control,
+            api_call,
+            previous: navigation.previous,
+            next: navigation.next,
+            page_type: Some(page_type),
+            quality: Some(quality),
+            removed: None,
+            warnings,
+            dropped_formats,
+            media,
+        },
+        change,
+        body,
+        metadata: Some(data.metadata),
+        redactions,
+        replacements,
+        images,
+    })))
+}
+
+/// Saves the raw API response of a page that came back without markdown.
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the path of the saved response, relative to `output_dir`
+/// 
+/// # Errors
+/// 
+/// Returns an error if the directory cannot be created or the file cannot be written
+fn cache_empty_response(output_dir: &Path, url: &str, body: &[u8]) -> Result<String, Box<dyn Error>> {
+    let relative = Path::new(STATE_DIR_NAME)
+        .join(EMPTY_RESPONSES_DIR_NAME)
+        .join(format!("{}.json", sanitize_filename(&urls::display_url(url))));
+    let path = output_dir.join(&relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, body).map_err(|e| format!("Failed to write response {}: {}", path.display(), e))?;
+    Ok(relative.to_string_lossy().into_owned())
+}
+
+/// Replaces the saved file of a page that was removed from the site with a tombstone.
+/// 
+/// Pages that already are tombstones are left as they are, so the removal
+/// date and archived copy stay those of the original removal.
+/// 
+/// # Errors
+/// 
+/// Returns an error if the saved file cannot be archived or replaced
+fn removed_page(
+    output_dir: &Path,
+    url: &str,
+    previous: &ManifestEntry,
+    status: u16,
+) -> Result<PageOutcome, Box<dyn Error>> {
+    if previous.removed.is_some() {
+        info!("Still removed");
+        return Ok(PageOutcome::Removed(Box::new(previous.clone())));
+    }
+
+    let entry = tombstone::bury(output_dir, url, previous, status)?;
+    if let Some(removal) = &entry.removed {
+        info!("Removed (status {}); last version kept in {}", status, removal.archived_file);
+    }
+    Ok(PageOutcome::Removed(Box::new(entry)))
+}
+
+/// Builds the processed page for a page that was not downloaded again because it is unchanged.
+/// 
+/// The body is read back from the previously saved file so exporters still
+/// receive the full page.
+/// 
+/// # Errors
+/// 
+/// Returns an error if there is no previously saved file or it cannot be read
+fn unchanged_page(
+    output_dir: &Path,
+    previous: Option<&ManifestEntry>,
+) -> Result<PageOutcome, Box<dyn Error>> {
+    let entry = previous.ok_or("Page reported unchanged but no previous file exists")?;
+    let file_path = output_dir.join(&entry.file);
+    let content = fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read file {}: {}", file_path.display(), e))?;
+
+    Ok(PageOutcome::Saved(Box::new(ProcessedPage {
+        entry: entry.clone(),
+        change: PageChange::Unchanged,
+        body: frontmatter::strip_frontmatter(&content).to_string(),
+        metadata: None,
+        redactions: 0,
+        replacements: 0,
+        images: Vec::new(),
+    })))
+}
@@ -0,0 +1,196 @@
+//! Page type heuristics.
+//!
+//! Documentation sites mix very different kinds of pages. Pages are classified
+//! from their URL before they are scraped, so extraction options suited to the
+//! type can be applied to the request, and the classification is refined from
+//! the page structure afterwards and recorded with the page.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use url::Url;
+
+use crate::markdown;
+
+/// The kind of content a page holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageType {
+    /// API, CLI or configuration reference
+    Reference,
+    /// Tutorials, how-tos and conceptual guides
+    Guide,
+    /// Changelogs and release notes
+    Changelog,
+    /// Blog posts and announcements
+    Blog,
+    /// Site and section landing pages
+    Landing,
+}
+
+/// Extraction options applied to pages of a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeDefaults {
+    /// Extract only the main content, dropping navigation, headers and footers
+    pub only_main_content: bool,
+    /// Replace markdown tables with the page's original HTML tables
+    pub raw_html_tables: bool,
+}
+
+/// Path segments that identify a page type, checked in this order.
+const SEGMENT_HINTS: &[(PageType, &[&str])] = &[
+    (PageType::Changelog, &["changelog", "changes", "release-notes", "releases", "whats-new", "history"]),
+    (PageType::Blog, &["blog", "news", "posts", "announcements"]),
+    (PageType::Reference, &["api", "reference", "ref", "sdk", "cli", "commands", "config", "configuration"]),
+    (PageType::Guide, &["guide", "guides", "tutorial", "tutorials", "how-to", "howto", "learn", "getting-started", "quickstart"]),
+];
+
+/// Minimum number of version headings for structure-based changelog detection.
+const CHANGELOG_MIN_VERSIONS: usize = 3;
+
+/// Minimum number of tables for structure-based reference detection.
+const REFERENCE_MIN_TABLES: usize = 2;
+
+impl PageType {
+    /// Returns the extraction options used for pages of this type.
+    ///
+    /// Reference pages keep their tables as raw HTML, since parameter tables
+    /// often contain lists and code that markdown tables cannot hold. Landing
+    /// pages keep all content because it is rarely inside a main element.
+    pub fn defaults(self) -> TypeDefaults {
+        match self {
+            PageType::Reference => TypeDefaults {
+                only_main_content: true,
+                raw_html_tables: true,
+            },
+            PageType::Guide | PageType::Changelog | PageType::Blog => TypeDefaults {
+                only_main_content: true,
+                raw_html_tables: false,
+            },
+            PageType::Landing => TypeDefaults {
+                only_main_content: false,
+                raw_html_tables: false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for PageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PageType::Reference => "reference",
+            PageType::Guide => "guide",
+            PageType::Changelog => "changelog",
+            PageType::Blog => "blog",
+            PageType::Landing => "landing",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Classifies a page from its URL alone.
+///
+/// The site root (optionally followed by a language segment such as `/en/`)
+/// is a landing page; otherwise the path segments are matched against known
+/// names such as `api`, `blog` or `changelog`, and a date in the path marks a
+/// blog post.
+///
+/// # Returns
+///
+/// The page type, or `None` if the URL gives no hint
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::page_type::{classify_url, PageType};
+/// assert_eq!(classify_url("https://example.com/docs/api/client"), Some(PageType::Reference));
+/// assert_eq!(classify_url("https://example.com/en/"), Some(PageType::Landing));
+/// assert_eq!(classify_url("https://example.com/docs/widgets"), None);
+/// ```
+pub fn classify_url(url: &str) -> Option<PageType> {
+    let parsed = Url::parse(url).ok()?;
+    let segments: Vec<String> = parsed
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|segment| !segment.is_empty())
+                .map(|segment| segment.trim_end_matches(".html").to_ascii_lowercase())
+                .filter(|segment| segment != "index")
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if segments.is_empty() || (segments.len() == 1 && is_language_code(&segments[0])) {
+        return Some(PageType::Landing);
+    }
+
+    for (page_type, hints) in SEGMENT_HINTS {
+        if segments.iter().any(|segment| hints.contains(&segment.as_str())) {
+            return Some(*page_type);
+        }
+    }
+
+    // Blog posts usually carry their date in the path: /2024/05/title
+    let has_year = segments
+        .iter()
+        .any(|segment| segment.len() == 4 && segment.starts_with("20") && segment.chars().all(|c| c.is_ascii_digit()));
+    has_year.then_some(PageType::Blog)
+}
+
+/// Classifies a scraped page from its URL and the structure of its markdown.
+///
+/// The URL wins when it gives a hint. Otherwise a page with several version
+/// headings (`## 1.4.0`, `## v2.0`) is a changelog, a page with several tables
+/// is a reference page, and anything else is a guide.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::page_type::{classify, PageType};
+/// let markdown = "# Changes\n## 1.2.0\n- fix\n## 1.1.0\n- add\n## 1.0.0\n- initial\n";
+/// assert_eq!(classify("https://example.com/docs/project", markdown), PageType::Changelog);
+/// ```
+pub fn classify(url: &str, markdown: &str) -> PageType {
+    if let Some(page_type) = classify_url(url) {
+        return page_type;
+    }
+
+    let version_headings = markdown
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix('#'))
+        .map(|heading| heading.trim_start_matches('#').trim())
+        .filter(|heading| is_version(heading))
+        .count();
+    if version_headings >= CHANGELOG_MIN_VERSIONS {
+        return PageType::Changelog;
+    }
+
+    // Every table has exactly one delimiter row such as |---|:--:|
+    let tables = markdown
+        .lines()
+        .filter(|line| line.trim_start().starts_with('|') && markdown::is_table_delimiter(line))
+        .count();
+    if tables >= REFERENCE_MIN_TABLES {
+        return PageType::Reference;
+    }
+
+    PageType::Guide
+}
+
+/// Returns whether a path segment looks like a language code (`en`, `pt-br`, `zh_CN`).
+fn is_language_code(segment: &str) -> bool {
+    let mut parts = segment.split(['-', '_']);
+    let primary = parts.next().unwrap_or("");
+    primary.len() == 2
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| (2..=4).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Returns whether a heading starts with a version number such as `1.2.0` or `v2.0`.
+fn is_version(heading: &str) -> bool {
+    let heading = heading.trim_start_matches(['[', 'v', 'V']);
+    let version: String = heading
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    version.contains('.') && version.starts_with(|c: char| c.is_ascii_digit())
+}
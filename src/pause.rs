@@ -0,0 +1,156 @@
+//! Pausing workspace sites whose run looks broken.
+//!
+//! A scheduled site whose credentials expired or whose docs moved keeps
+//! "succeeding": the login page gets saved over every page, or most pages fail
+//! and the rest of the mirror goes stale. With `pause_when` conditions in the
+//! workspace file, `run-all` and `daemon` check each site's run against them.
+//! A site that trips one is paused: later rounds skip it, instead of saving a
+//! broken mirror over the good one again and again, until `scraper unpause`
+//! clears it. Pausing is logged, shown on the dashboard and status files, and
+//! sent to the workspace's notifiers as a `pause` event.
+//!
+//! ```toml
+//! [workspace]
+//! notify_url = "https://hooks.slack.com/services/..."
+//!
+//! [workspace.pause_when]
+//! failed_share = 0.3
+//! shrink = 0.5
+//! auth_broken = true
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::workspace::PageCounts;
+
+/// File name of a paused site's pause record inside its state directory.
+pub const PAUSE_FILE_NAME: &str = "paused.json";
+
+/// Conditions under which a site is paused after a run.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PauseConditions {
+    /// Pause when more than this share of the pages attempted failed or came back empty, from 0 to 1
+    pub failed_share: Option<f64>,
+
+    /// Pause when discovery found fewer pages than the previous run by more than this share, from 0 to 1
+    pub shrink: Option<f64>,
+
+    /// Pause when any page was answered with `401 Unauthorized` or `403 Forbidden`
+    pub auth_broken: bool,
+}
+
+impl PauseConditions {
+    /// Returns why a run with `current` counts should pause its site, or `None` if it shouldn't.
+    ///
+    /// `previous` holds the counts of the site's run before, if there was one.
+    pub fn check(&self, previous: Option<&PageCounts>, current: &PageCounts) -> Option<String> {
+        if self.auth_broken && current.pages_unauthorized > 0 {
+            return Some(format!(
+                "authentication appears broken: {} pages were answered with 401 or 403",
+                current.pages_unauthorized
+            ));
+        }
+        let attempted = current.pages_processed + current.pages_failed + current.pages_empty;
+        if let Some(limit) = self.failed_share.filter(|_| attempted > 0) {
+            let failed = current.pages_failed + current.pages_empty;
+            let share = failed as f64 / attempted as f64;
+            if share > limit {
+                return Some(format!(
+                    "{:.0}% of pages failed or came back empty ({} of {}), more than {:.0}%",
+                    share * 100.0,
+                    failed,
+                    attempted,
+                    limit * 100.0
+                ));
+            }
+        }
+        let before = previous.map_or(0, |previous| previous.pages_found);
+        if let Some(limit) = self.shrink.filter(|_| before > 0) {
+            let shrink = 1.0 - current.pages_found as f64 / before as f64;
+            if shrink > limit {
+                return Some(format!(
+                    "the site shrank by {:.0}%, from {} to {} pages, more than {:.0}%",
+                    shrink * 100.0,
+                    before,
+                    current.pages_found,
+                    limit * 100.0
+                ));
+            }
+        }
+        None
+    }
+
+    /// Checks that the shares are between 0 and 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first share out of range
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, share) in [("failed_share", self.failed_share), ("shrink", self.shrink)] {
+            if share.is_some_and(|share| !(0.0..=1.0).contains(&share)) {
+                return Err(format!("pause_when.{} must be between 0 and 1", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether a status code means the request was refused for lack of valid credentials.
+pub fn is_unauthorized(status: i32) -> bool {
+    status == 401 || status == 403
+}
+
+/// Record of a paused site, kept in its state directory until it is unpaused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pause {
+    /// When the site was paused
+    pub paused_at: DateTime<Utc>,
+
+    /// The condition the run tripped
+    pub reason: String,
+}
+
+impl Pause {
+    /// Returns the pause record path for the given output directory.
+    fn path(output_dir: &Path) -> PathBuf {
+        crate::state_dir(output_dir).join(PAUSE_FILE_NAME)
+    }
+
+    /// Loads the pause record of a site's output directory, if the site is paused.
+    pub fn load(output_dir: &Path) -> Option<Pause> {
+        let content = fs::read_to_string(Self::path(output_dir)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Saves the pause record into a site's output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written
+    pub fn save(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// Removes the pause record of a site's output directory, returning whether the site was paused.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record exists but cannot be removed
+    pub fn clear(output_dir: &Path) -> Result<bool, Box<dyn Error>> {
+        let path = Self::path(output_dir);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        Ok(true)
+    }
+}
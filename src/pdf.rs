@@ -0,0 +1,453 @@
+//! Minimal PDF writer: pages of text, rules and internal links.
+//!
+//! Only what the PDF export needs is supported. Text is set in the standard
+//! Helvetica and Courier fonts every PDF reader has, so no font is embedded;
+//! they cover the Windows-1252 character set, and other characters are
+//! written as `?` and counted per page. Content streams are compressed.
+
+use chrono::{DateTime, Utc};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+/// Width of an A4 page in points.
+pub const PAGE_WIDTH: f32 = 595.0;
+
+/// Height of an A4 page in points.
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of the font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556,
+    556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334,
+    260, 334, 584,
+];
+
+/// Widths of the printable ASCII characters in Helvetica Bold, in thousandths of the font size.
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611,
+    556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389,
+    280, 389, 584,
+];
+
+/// Characters of Windows-1252 outside Latin-1, with their codes.
+const WIN_ANSI_EXTRAS: [(char, u8); 27] = [
+    ('€', 0x80),
+    ('‚', 0x82),
+    ('ƒ', 0x83),
+    ('„', 0x84),
+    ('…', 0x85),
+    ('†', 0x86),
+    ('‡', 0x87),
+    ('ˆ', 0x88),
+    ('‰', 0x89),
+    ('Š', 0x8a),
+    ('‹', 0x8b),
+    ('Œ', 0x8c),
+    ('Ž', 0x8e),
+    ('‘', 0x91),
+    ('’', 0x92),
+    ('“', 0x93),
+    ('”', 0x94),
+    ('•', 0x95),
+    ('–', 0x96),
+    ('—', 0x97),
+    ('˜', 0x98),
+    ('™', 0x99),
+    ('š', 0x9a),
+    ('›', 0x9b),
+    ('œ', 0x9c),
+    ('ž', 0x9e),
+    ('Ÿ', 0x9f),
+];
+
+/// One of the standard fonts text is set in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    /// Helvetica
+    Regular,
+    /// Helvetica Bold
+    Bold,
+    /// Helvetica Oblique
+    Italic,
+    /// Courier, for code
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 4] = [Font::Regular, Font::Bold, Font::Italic, Font::Mono];
+
+    /// Name of the font in page resources.
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Italic => "F3",
+            Font::Mono => "F4",
+        }
+    }
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Italic => "Helvetica-Oblique",
+            Font::Mono => "Courier",
+        }
+    }
+
+    /// Returns the width of `text` set in this font at `size` points.
+    pub fn width(self, text: &str, size: f32) -> f32 {
+        let widths = match self {
+            Font::Regular | Font::Italic => &HELVETICA_WIDTHS,
+            Font::Bold => &HELVETICA_BOLD_WIDTHS,
+            Font::Mono => return text.chars().count() as f32 * 0.6 * size,
+        };
+        let units: u32 = text
+            .chars()
+            .map(|c| match c as u32 {
+                code @ 32..=126 => u32::from(widths[code as usize - 32]),
+                // Accented letters and punctuation are about as wide as an average letter
+                _ => 556,
+            })
+            .sum();
+        units as f32 * size / 1000.0
+    }
+}
+
+/// How a piece of text is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    /// The font
+    pub font: Font,
+    /// Font size in points
+    pub size: f32,
+    /// Gray level of the text, from 0 (black) to 1 (white)
+    pub gray: f32,
+}
+
+/// A link from a rectangle of a page to another page of the document.
+#[derive(Debug, Clone)]
+struct Link {
+    rect: [f32; 4],
+    page: usize,
+}
+
+/// One page of a document, drawn in points from the bottom left corner.
+#[derive(Debug, Default)]
+pub struct Page {
+    content: String,
+    links: Vec<Link>,
+    /// Characters of the page's text the fonts lack, set as `?`
+    substituted: usize,
+}
+
+impl Page {
+    /// Creates an empty page.
+    pub fn new() -> Self {
+        Page::default()
+    }
+
+    /// Sets `text` on one line with its baseline starting at `x`, `y`.
+    ///
+    /// Characters outside Windows-1252 are set as `?` and counted, see `substituted`.
+    pub fn text(&mut self, x: f32, y: f32, style: Style, text: &str) {
+        self.substituted += text.chars().filter(|&c| win_ansi_code(c).is_none()).count();
+        let _ = writeln!(
+            self.content,
+            "BT {:.2} g /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET",
+            style.gray,
+            style.font.resource(),
+            style.size,
+            x,
+            y,
+            encode_text(text)
+        );
+    }
+
+    /// Draws a straight line `width` points wide.
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, gray: f32) {
+        let _ = writeln!(
+            self.content,
+            "{:.2} G {:.2} w {:.2} {:.2} m {:.2} {:.2} l S",
+            gray, width, from.0, from.1, to.0, to.1
+        );
+    }
+
+    /// Returns how many characters of the text set on the page were replaced with `?`.
+    pub fn substituted(&self) -> usize {
+        self.substituted
+    }
+
+    /// Makes the rectangle from `x1`, `y1` to `x2`, `y2` a link to the page at index `page`.
+    pub fn link(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, page: usize) {
+        self.links.push(Link {
+            rect: [x1, y1, x2, y2],
+            page,
+        });
+    }
+}
+
+/// A PDF document being assembled.
+#[derive(Debug)]
+pub struct Document {
+    title: String,
+    pages: Vec<Page>,
+    bookmarks: Vec<(String, usize)>,
+}
+
+impl Document {
+    /// Creates an empty document with a title for its metadata.
+    pub fn new(title: &str) -> Self {
+        Document {
+            title: title.to_string(),
+            pages: Vec::new(),
+            bookmarks: Vec::new(),
+        }
+    }
+
+    /// Appends a page.
+    pub fn push(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    /// Adds an entry to the document outline readers show next to the pages, pointing at the page at index `page`.
+    pub fn bookmark(&mut self, title: &str, page: usize) {
+        self.bookmarks.push((title.to_string(), page));
+    }
+
+    /// Returns the document as the bytes of a PDF file.
+    pub fn to_bytes(&self, created: DateTime<Utc>) -> Vec<u8> {
+        // Objects 1 to 8 are fixed, followed by each page, its content, its links and then the bookmarks
+        const CATALOG: usize = 1;
+        const PAGES: usize = 2;
+        const FIRST_FONT: usize = 3;
+        const INFO: usize = 7;
+        const OUTLINES: usize = 8;
+        let mut next_id = 9;
+        let mut page_ids = Vec::with_capacity(self.pages.len());
+        for page in &self.pages {
+            page_ids.push(next_id);
+            next_id += 2 + page.links.len();
+        }
+        let first_bookmark = next_id;
+        let mut objects: Vec<Vec<u8>> = vec![Vec::new(); first_bookmark + self.bookmarks.len()];
+
+        let outline_mode = if self.bookmarks.is_empty() { "" } else { " /PageMode /UseOutlines" };
+        objects[CATALOG] =
+            format!("<< /Type /Catalog /Pages {} 0 R /Outlines {} 0 R{} >>", PAGES, OUTLINES, outline_mode).into_bytes();
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+        objects[PAGES] =
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), self.pages.len()).into_bytes();
+        for (index, font) in Font::ALL.iter().enumerate() {
+            objects[FIRST_FONT + index] = format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font.base_font()
+            )
+            .into_bytes();
+        }
+        objects[INFO] = format!(
+            "<< /Title ({}) /Producer (scraper) /CreationDate (D:{}Z) >>",
+            encode_text(&self.title),
+            created.format("%Y%m%d%H%M%S")
+        )
+        .into_bytes();
+        objects[OUTLINES] = match self.bookmarks.len() {
+            0 => b"<< /Type /Outlines /Count 0 >>".to_vec(),
+            count => format!(
+                "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                first_bookmark,
+                first_bookmark + count - 1,
+                count
+            )
+            .into_bytes(),
+        };
+
+        let fonts: String = (0..Font::ALL.len())
+            .map(|index| format!("/{} {} 0 R", Font::ALL[index].resource(), FIRST_FONT + index))
+            .collect::<Vec<_>>()
+            .join(" ");
+        for (page, &id) in self.pages.iter().zip(&page_ids) {
+            let link_ids: Vec<usize> = (0..page.links.len()).map(|index| id + 2 + index).collect();
+            let annotations = if link_ids.is_empty() {
+                String::new()
+            } else {
+                let refs: Vec<String> = link_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+                format!(" /Annots [{}]", refs.join(" "))
+            };
+            objects[id] = format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {} >> >> /Contents {} 0 R{} >>",
+                PAGES,
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                fonts,
+                id + 1,
+                annotations
+            )
+            .into_bytes();
+            objects[id + 1] = compressed_stream(page.content.as_bytes());
+            for (link, link_id) in page.links.iter().zip(link_ids) {
+                let target = page_ids.get(link.page).copied().unwrap_or(id);
+                objects[link_id] = format!(
+                    "<< /Type /Annot /Subtype /Link /Rect [{:.2} {:.2} {:.2} {:.2}] /Border [0 0 0] /Dest [{} 0 R /Fit] >>",
+                    link.rect[0], link.rect[1], link.rect[2], link.rect[3], target
+                )
+                .into_bytes();
+            }
+        }
+
+        for (index, (title, page)) in self.bookmarks.iter().enumerate() {
+            let id = first_bookmark + index;
+            let mut item = format!("<< /Title ({}) /Parent {} 0 R", encode_text(title), OUTLINES);
+            if index > 0 {
+                let _ = write!(item, " /Prev {} 0 R", id - 1);
+            }
+            if index + 1 < self.bookmarks.len() {
+                let _ = write!(item, " /Next {} 0 R", id + 1);
+            }
+            let target = page_ids.get(*page).or(page_ids.first()).copied().unwrap_or(PAGES);
+            let _ = write!(item, " /Dest [{} 0 R /Fit] >>", target);
+            objects[id] = item.into_bytes();
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (id, object) in objects.iter().enumerate().skip(1) {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", id).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len());
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len(),
+            CATALOG,
+            INFO,
+            xref
+        );
+        pdf.extend_from_slice(table.as_bytes());
+        pdf
+    }
+}
+
+/// Returns a stream object holding `data` compressed.
+fn compressed_stream(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing to a vector cannot fail
+    let _ = encoder.write_all(data);
+    let compressed = encoder.finish().unwrap_or_default();
+    let mut stream = format!("<< /Length {} /Filter /FlateDecode >>\nstream\n", compressed.len()).into_bytes();
+    stream.extend_from_slice(&compressed);
+    stream.extend_from_slice(b"\nendstream");
+    stream
+}
+
+/// Encodes text as the inside of a PDF string literal in Windows-1252.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(encode_text("(a) – b"), "\\(a\\) \\226 b");
+/// ```
+fn encode_text(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for c in text.chars() {
+        let code = win_ansi_code(c).unwrap_or(b'?');
+        match code {
+            b'(' | b')' | b'\\' => {
+                encoded.push('\\');
+                encoded.push(code as char);
+            }
+            32..=126 => encoded.push(code as char),
+            _ => {
+                let _ = write!(encoded, "\\{:03o}", code);
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns the Windows-1252 code of a character, or `None` if the fonts lack it; tabs are set as spaces.
+fn win_ansi_code(c: char) -> Option<u8> {
+    match c {
+        '\t' => Some(b' '),
+        ' '..='~' => Some(c as u8),
+        '\u{a0}'..='\u{ff}' => Some(c as u32 as u8),
+        _ => WIN_ANSI_EXTRAS.iter().find(|(extra, _)| *extra == c).map(|(_, code)| *code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_encoded_in_windows_1252() {
+        assert_eq!(encode_text("plain text"), "plain text");
+        assert_eq!(encode_text("(a) \\ b"), "\\(a\\) \\\\ b");
+        assert_eq!(encode_text("café\tß"), "caf\\351 \\337");
+        assert_eq!(encode_text("“quoted” – €5"), "\\223quoted\\224 \\226 \\2005");
+        assert_eq!(encode_text("a → b 日本"), "a ? b ??");
+    }
+
+    #[test]
+    fn pages_count_the_characters_set_as_question_marks() {
+        let style = Style { font: Font::Regular, size: 10.0, gray: 0.0 };
+        let mut page = Page::new();
+        page.text(0.0, 0.0, style, "naïve – “fine”");
+        assert_eq!(page.substituted(), 0);
+        page.text(0.0, 0.0, style, "a → b");
+        page.text(0.0, 0.0, style, "日本");
+        assert_eq!(page.substituted(), 3);
+    }
+
+    #[test]
+    fn documents_have_a_valid_cross_reference_table() {
+        let mut document = Document::new("Docs (draft)");
+        let mut first = Page::new();
+        first.text(56.0, 700.0, Style { font: Font::Bold, size: 20.0, gray: 0.0 }, "Contents");
+        first.link(56.0, 690.0, 539.0, 710.0, 1);
+        document.push(first);
+        document.push(Page::new());
+        document.bookmark("Second", 1);
+        let pdf = document.to_bytes(DateTime::UNIX_EPOCH);
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+
+        // Fixed objects 1 to 8, a page object and content stream per page, one link and one bookmark
+        let objects = 9 + 2 * 2 + 1 + 1;
+        let text = String::from_utf8_lossy(&pdf);
+        let tail = text.rsplit_once("startxref\n").unwrap().1;
+        assert_eq!(tail.lines().nth(1), Some("%%EOF"));
+        let xref: usize = tail.lines().next().unwrap().parse().unwrap();
+        let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+        let mut lines = table.lines();
+        assert_eq!(lines.next(), Some("xref"));
+        assert_eq!(lines.next(), Some(format!("0 {}", objects).as_str()));
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for id in 1..objects {
+            let entry = lines.next().unwrap();
+            assert!(entry.ends_with(" 00000 n "), "{}", entry);
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", id).as_bytes()), "object {}", id);
+        }
+        assert_eq!(lines.next(), Some("trailer"));
+        assert_eq!(lines.next(), Some(format!("<< /Size {} /Root 1 0 R /Info 7 0 R >>", objects).as_str()));
+
+        // The link on the first page and the bookmark point at the second page's object
+        assert!(text.contains("/Kids [9 0 R 12 0 R] /Count 2"));
+        assert!(text.contains("/Subtype /Link /Rect [56.00 690.00 539.00 710.00] /Border [0 0 0] /Dest [12 0 R /Fit]"));
+        assert!(text.contains("<< /Title (Second) /Parent 8 0 R /Dest [12 0 R /Fit] >>"));
+        assert!(text.contains("/Title (Docs \\(draft\\))"));
+    }
+}
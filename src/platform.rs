@@ -0,0 +1,406 @@
+//! Documentation platforms that serve one site from several hosts.
+//!
+//! Hosted documentation platforms often spread a site over several hosts: a
+//! GitBook space answers on `example.gitbook.io` and the older
+//! `example.gitbook.com`, a ReadMe project on `example.readme.io`, and every
+//! Netlify deploy preview (`deploy-preview-42--example.netlify.app`) is a copy
+//! of `example.netlify.app`. Links between them would otherwise be dropped as
+//! leading off the site.
+//!
+//! The hosts of one platform site are grouped into one logical site: URLs on
+//! any of them are moved onto the start URL's host before they are filtered
+//! and deduplicated, so the pages end up in one output tree and a page linked
+//! through two hosts is scraped once. Hosts that can't be recognized from their
+//! name, such as custom domains, are added with `--site-host`.
+//!
+//! Subdomains of the start URL's domain are a different matter: `api.example.com`
+//! serves other pages than `docs.example.com`, so with `--include-subdomains`, or
+//! `--subdomain` for a chosen few, their URLs belong to the site but keep their
+//! own host. So do URLs on unrelated domains allowed with `--allow-domain`, such
+//! as the `example.github.io` half of documentation split across two domains;
+//! their pages are saved in a subdirectory per domain.
+//!
+//! The domain whose subdomains are followed comes from the Public Suffix List,
+//! including its section of hosting services, so `example.github.io` is a
+//! domain of its own rather than a subdomain of `github.io`. Domains shared by
+//! unrelated sites, public suffixes and the platforms above, have no
+//! subdomains to follow.
+
+use clap::ValueEnum;
+use publicsuffix::{List, Psl};
+use serde::Deserialize;
+use std::error::Error;
+use std::net::IpAddr;
+use std::sync::LazyLock;
+use url::Url;
+
+use crate::cli::ScrapeOptions;
+use crate::output;
+use crate::urls;
+
+/// Platforms whose hosts are grouped, as chosen with `--platform`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Platform {
+    /// Recognize the hosts of every known platform
+    #[default]
+    Auto,
+    /// GitBook spaces on `*.gitbook.io` and `*.gitbook.com`
+    #[value(name = "gitbook")]
+    #[serde(rename = "gitbook")]
+    GitBook,
+    /// ReadMe projects on `*.readme.io` and `*.readme.com`
+    #[value(name = "readme")]
+    #[serde(rename = "readme")]
+    ReadMe,
+    /// Netlify sites and their deploy previews on `*.netlify.app` and `*.netlify.com`
+    Netlify,
+    /// Don't group hosts; only the start URL's host and `--site-host` hosts belong to the site
+    None,
+}
+
+/// Platforms recognized with `Platform::Auto`.
+const PRESETS: &[Platform] = &[Platform::GitBook, Platform::ReadMe, Platform::Netlify];
+
+/// The Public Suffix List, with the domains of hosting services in its private section.
+static PUBLIC_SUFFIXES: LazyLock<List> = LazyLock::new(|| {
+    include_str!("public_suffix_list.dat").parse().expect("the bundled Public Suffix List is valid")
+});
+
+impl Platform {
+    /// Returns the host suffixes of the platform and the labels in front of
+    /// them that belong to the platform itself rather than to a site.
+    fn hosts(self) -> (&'static [&'static str], &'static [&'static str]) {
+        match self {
+            Platform::GitBook => (&[".gitbook.io", ".gitbook.com"], &["app", "api", "files", "www"]),
+            Platform::ReadMe => (&[".readme.io", ".readme.com"], &["dash", "www"]),
+            Platform::Netlify => (&[".netlify.app", ".netlify.com"], &["app", "www"]),
+            Platform::Auto | Platform::None => (&[], &[]),
+        }
+    }
+
+    /// Returns the name identifying the site a host serves on this platform, if it is one of its hosts.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// assert_eq!(Platform::Netlify.site_key("deploy-preview-42--example.netlify.app").as_deref(), Some("netlify:example"));
+    /// assert_eq!(Platform::GitBook.site_key("example.gitbook.com").as_deref(), Some("gitbook:example"));
+    /// assert_eq!(Platform::GitBook.site_key("docs.example.com"), None);
+    /// ```
+    fn site_key(self, host: &str) -> Option<String> {
+        let (suffixes, reserved) = self.hosts();
+        let label = suffixes.iter().find_map(|suffix| host.strip_suffix(suffix))?;
+        // Deploy previews and branch deploys are named `<prefix>--<site>`
+        let label = match self {
+            Platform::Netlify => label.rsplit_once("--").map_or(label, |(_, site)| site),
+            _ => label,
+        };
+        if label.is_empty() || label.contains('.') || reserved.contains(&label) {
+            return None;
+        }
+        Some(format!("{:?}:{}", self, label).to_ascii_lowercase())
+    }
+
+    /// Returns the platforms whose hosts are recognized.
+    fn presets(self) -> &'static [Platform] {
+        match self {
+            Platform::Auto => PRESETS,
+            Platform::GitBook => &[Platform::GitBook],
+            Platform::ReadMe => &[Platform::ReadMe],
+            Platform::Netlify => &[Platform::Netlify],
+            Platform::None => &[],
+        }
+    }
+}
+
+/// The hosts serving one site.
+#[derive(Debug, Clone)]
+pub struct SiteHosts {
+    /// The start URL, whose scheme, host and port every URL of the site is moved onto
+    start: Url,
+
+    /// Host of the start URL, in its ASCII form
+    host: String,
+
+    /// Further hosts given with `--site-host`, in their ASCII form
+    extra_hosts: Vec<String>,
+
+    /// Platforms whose hosts are recognized
+    platform: Platform,
+
+    /// Names of the platform sites served by the start URL's host and the further hosts
+    site_keys: Vec<String>,
+
+    /// Registrable domain of the start URL's host, if subdomains are followed
+    domain: Option<String>,
+
+    /// Whether every subdomain of `domain` belongs to the site, from `--include-subdomains`
+    all_subdomains: bool,
+
+    /// Subdomains of `domain` that belong to the site, from `--subdomain`, as full hosts
+    subdomains: Vec<String>,
+
+    /// Further domains whose hosts belong to the site, from `--allow-domain`
+    allowed_domains: Vec<String>,
+}
+
+impl SiteHosts {
+    /// Creates the host group of a site starting at `start_url`.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_url` - The URL the run starts from
+    /// * `options` - Options choosing the platforms whose hosts are recognized
+    ///   (`--platform`), further hosts serving the same pages (`--site-host`) and
+    ///   the subdomains that belong to the site (`--include-subdomains`, `--subdomain`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the start URL cannot be parsed or has no host, or
+    /// if subdomains are to be followed on a domain shared by unrelated sites
+    pub fn new(start_url: &str, options: &ScrapeOptions) -> Result<Self, Box<dyn Error>> {
+        let start = Url::parse(start_url).map_err(|e| format!("Failed to parse start URL: {}", e))?;
+        let host = urls::ascii_host(&start).ok_or("Invalid base domain")?;
+        let platform = options.platform.unwrap_or_default();
+        let extra_hosts: Vec<String> = options.site_hosts.iter().map(|host| normalize_host(host)).collect();
+
+        // A bare label names a subdomain of the start URL's domain
+        let names: Vec<String> = options.subdomains.iter().map(|name| normalize_host(name)).collect();
+        let domain = if options.include_subdomains || names.iter().any(|name| !name.contains('.')) {
+            Some(subdomain_parent(&host)?)
+        } else {
+            None
+        };
+        let subdomains = names
+            .into_iter()
+            .map(|name| match &domain {
+                Some(domain) if !name.contains('.') => format!("{}.{}", name, domain),
+                _ => name,
+            })
+            .collect();
+
+        let site_keys = std::iter::once(&host)
+            .chain(&extra_hosts)
+            .flat_map(|host| platform.presets().iter().filter_map(|preset| preset.site_key(host)))
+            .collect();
+        Ok(SiteHosts {
+            start,
+            host,
+            extra_hosts,
+            platform,
+            site_keys,
+            domain,
+            all_subdomains: options.include_subdomains,
+            subdomains,
+            allowed_domains: options.allowed_domains.iter().map(|domain| normalize_host(domain)).collect(),
+        })
+    }
+
+    /// Returns the URL the site starts from.
+    pub fn start_url(&self) -> &str {
+        self.start.as_str()
+    }
+
+    /// Returns whether more hosts than the start URL's belong to the site.
+    pub fn is_grouped(&self) -> bool {
+        !self.extra_hosts.is_empty() || !self.site_keys.is_empty()
+    }
+
+    /// Returns whether subdomains of the start URL's domain belong to the site.
+    pub fn has_subdomains(&self) -> bool {
+        self.all_subdomains || !self.subdomains.is_empty()
+    }
+
+    /// Returns whether a host is one of the site's subdomains, kept on its own host.
+    fn is_subdomain(&self, host: &str) -> bool {
+        if self.subdomains.iter().any(|subdomain| subdomain == host) {
+            return true;
+        }
+        self.all_subdomains && self.domain.as_ref().is_some_and(|domain| on_domains(host, std::slice::from_ref(domain)))
+    }
+
+    /// Returns whether a host serves the site.
+    fn contains(&self, host: &str) -> bool {
+        host == self.host
+            || self.extra_hosts.iter().any(|extra| extra == host)
+            || self.platform.presets().iter().any(|preset| {
+                preset.site_key(host).is_some_and(|key| self.site_keys.contains(&key))
+            })
+    }
+
+    /// Moves a URL of the site onto the start URL's host.
+    ///
+    /// URLs on the start URL's host, on the site's subdomains and on allowed
+    /// domains are returned unchanged.
+    ///
+    /// # Returns
+    ///
+    /// The URL on the start URL's host, or `None` if its host doesn't serve the site
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let site = SiteHosts::new("https://example.netlify.app/", &ScrapeOptions::default())?;
+    /// let url = Url::parse("https://deploy-preview-42--example.netlify.app/guide").unwrap();
+    /// assert_eq!(site.unify(url).map(|url| url.to_string()).as_deref(), Some("https://example.netlify.app/guide"));
+    /// ```
+    pub fn unify(&self, mut url: Url) -> Option<Url> {
+        let host = urls::ascii_host(&url)?;
+        if host == self.host || self.is_subdomain(&host) || on_domains(&host, &self.allowed_domains) {
+            return Some(url);
+        }
+        if !self.contains(&host) {
+            return None;
+        }
+        url.set_host(self.start.host_str()).ok()?;
+        url.set_scheme(self.start.scheme()).ok()?;
+        url.set_port(self.start.port()).ok()?;
+        Some(url)
+    }
+}
+
+/// Returns the subdirectory the pages of a URL on a domain allowed with `--allow-domain` are saved in.
+///
+/// The subdirectory is named after the URL's host like the site directories
+/// of the output directory, following `--dir-naming`.
+///
+/// # Returns
+///
+/// The subdirectory, or `None` if the URL isn't on an allowed domain
+///
+/// # Errors
+///
+/// Returns an error if the directory name cannot be built from the URL
+pub fn allowed_domain_dir(url: &str, options: &ScrapeOptions) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(host) = Url::parse(url).ok().and_then(|url| urls::ascii_host(&url)) else {
+        return Ok(None);
+    };
+    let domains: Vec<String> = options.allowed_domains.iter().map(|domain| normalize_host(domain)).collect();
+    if !on_domains(&host, &domains) {
+        return Ok(None);
+    }
+    let dir = output::domain_dir_name(url, options.dir_naming, options.dir_template.as_deref())?;
+    Ok(Some(dir.to_string_lossy().into_owned()))
+}
+
+/// Returns the registrable domain of a host, whose subdomains belong to a site on it.
+///
+/// The domain follows the Public Suffix List: `docs.example.co.uk` gives
+/// `example.co.uk` and `docs.example.github.io` gives `example.github.io`.
+/// IP addresses and hosts the list doesn't know, such as `localhost`, are
+/// their own domain.
+///
+/// # Errors
+///
+/// Returns an error if the domain is shared by unrelated sites: the host is a
+/// public suffix itself, such as `github.io`, or its domain is one of the
+/// documentation platforms, such as `gitbook.io`
+fn subdomain_parent(host: &str) -> Result<String, Box<dyn Error>> {
+    if host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().is_ok() {
+        return Ok(host.to_string());
+    }
+    let shared = |domain: &str| {
+        format!(
+            "Can't follow subdomains of {}: {} is shared by unrelated sites; give the hosts with --subdomain instead",
+            host, domain
+        )
+    };
+    let domain = match PUBLIC_SUFFIXES.domain(host.as_bytes()) {
+        Some(domain) => String::from_utf8_lossy(domain.as_bytes()).into_owned(),
+        None if PUBLIC_SUFFIXES.suffix(host.as_bytes()).is_some_and(|suffix| suffix.is_known()) => {
+            return Err(shared(host).into());
+        }
+        None => host.to_string(),
+    };
+    let platform = PRESETS.iter().flat_map(|preset| preset.hosts().0).any(|suffix| suffix[1..] == domain);
+    if platform {
+        return Err(shared(&domain).into());
+    }
+    Ok(domain)
+}
+
+/// Returns whether a host is one of the domains or a subdomain of one.
+fn on_domains(host: &str, domains: &[String]) -> bool {
+    domains.iter().any(|domain| {
+        host == domain || host.strip_suffix(domain.as_str()).is_some_and(|label| label.ends_with('.'))
+    })
+}
+
+/// Lowercases a host given on the command line and removes its trailing dot.
+fn normalize_host(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(start_url: &str, args: &[&str]) -> Result<SiteHosts, Box<dyn Error>> {
+        SiteHosts::new(start_url, &ScrapeOptions::parse_args(args.iter().copied())?)
+    }
+
+    fn follows(site: &SiteHosts, url: &str) -> bool {
+        site.unify(Url::parse(url).unwrap()).is_some()
+    }
+
+    #[test]
+    fn subdomains_are_those_of_the_registrable_domain() {
+        let site = site("https://docs.example.co.uk/", &["--include-subdomains"]).unwrap();
+        assert!(follows(&site, "https://api.example.co.uk/"));
+        assert!(follows(&site, "https://example.co.uk/"));
+        assert!(!follows(&site, "https://other.co.uk/"));
+        assert!(!follows(&site, "https://example.com/"));
+    }
+
+    #[test]
+    fn other_tenants_of_hosting_services_are_not_subdomains() {
+        for (start, tenant, other) in [
+            ("https://proj.github.io/", "https://api.proj.github.io/", "https://other.github.io/"),
+            ("https://proj.readthedocs.io/", "https://v2.proj.readthedocs.io/", "https://other.readthedocs.io/"),
+            ("https://x.netlify.app/", "https://api.x.netlify.app/", "https://y.netlify.app/"),
+        ] {
+            let site = site(start, &["--include-subdomains"]).unwrap();
+            assert!(follows(&site, tenant), "{}", tenant);
+            assert!(!follows(&site, other), "{}", other);
+        }
+    }
+
+    #[test]
+    fn subdomains_of_shared_domains_are_refused() {
+        assert!(site("https://proj.gitbook.io/", &["--include-subdomains"]).is_err());
+        assert!(site("https://proj.readme.io/", &["--subdomain", "api"]).is_err());
+        assert!(site("https://github.io/", &["--include-subdomains"]).is_err());
+        // Full hosts don't need the domain
+        let site = site("https://proj.gitbook.io/", &["--subdomain", "api.example.com"]).unwrap();
+        assert!(follows(&site, "https://api.example.com/"));
+        assert!(!follows(&site, "https://other.gitbook.io/"));
+    }
+
+    #[test]
+    fn bare_labels_name_subdomains_of_the_domain() {
+        let docs = site("https://docs.example.com/", &["--subdomain", "api"]).unwrap();
+        assert!(follows(&docs, "https://api.example.com/"));
+        assert!(!follows(&docs, "https://blog.example.com/"));
+        let pages = site("https://proj.github.io/", &["--subdomain", "api"]).unwrap();
+        assert!(follows(&pages, "https://api.proj.github.io/"));
+        assert!(!follows(&pages, "https://api.github.io/"));
+    }
+
+    #[test]
+    fn local_hosts_are_their_own_domain() {
+        assert_eq!(subdomain_parent("localhost").unwrap(), "localhost");
+        assert_eq!(subdomain_parent("127.0.0.1").unwrap(), "127.0.0.1");
+        assert_eq!(subdomain_parent("[::1]").unwrap(), "[::1]");
+        let site = site("http://localhost:8765/", &["--include-subdomains"]).unwrap();
+        assert!(follows(&site, "http://api.localhost:8765/"));
+    }
+
+    #[test]
+    fn platform_hosts_are_grouped() {
+        let site = site("https://example.netlify.app/", &[]).unwrap();
+        let url = Url::parse("https://deploy-preview-42--example.netlify.app/guide").unwrap();
+        assert_eq!(site.unify(url).unwrap().as_str(), "https://example.netlify.app/guide");
+        assert!(!follows(&site, "https://other.netlify.app/"));
+    }
+}
@@ -0,0 +1,219 @@
+//! Extraction presets for documentation generators.
+//!
+//! Sites built with the same documentation generator share their markup: the
+//! article sits in the same element, and the sidebar, table of contents,
+//! breadcrumbs and "edit this page" links carry the same classes. A preset
+//! names the elements FireCrawl should keep (`includeTags`) and drop
+//! (`excludeTags`) on such a site, and how long to wait for pages rendered in
+//! the browser, so the first run of a site already gives clean pages.
+//!
+//! The generator is detected from the markup of the first page scraped on a
+//! host, mostly from its `<meta name="generator">` tag, and remembered in the
+//! host's domain profile. `--preset` picks a preset instead, or none.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{html, Action};
+
+/// Extraction presets, as chosen with `--preset`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// Detect the generator from the markup of each host's first page
+    #[default]
+    Auto,
+    /// Docusaurus sites
+    Docusaurus,
+    /// MkDocs sites with the Material theme
+    #[value(name = "mkdocs-material")]
+    #[serde(rename = "mkdocs-material")]
+    MkDocsMaterial,
+    /// Sphinx sites, including those hosted on Read the Docs
+    Sphinx,
+    /// GitBook spaces
+    #[value(name = "gitbook")]
+    #[serde(rename = "gitbook")]
+    GitBook,
+    /// Mintlify sites
+    Mintlify,
+    /// Don't apply a preset
+    None,
+}
+
+/// What a preset asks of FireCrawl.
+#[derive(Debug)]
+pub struct Extraction {
+    /// Elements whose content is kept, as tag names, `.class` or `#id` selectors
+    pub include_tags: &'static [&'static str],
+
+    /// Elements that are dropped, such as navigation and tables of contents
+    pub exclude_tags: &'static [&'static str],
+
+    /// Milliseconds to wait before extracting, for pages rendered in the browser
+    pub wait_for: Option<i32>,
+
+    /// Element to wait for before extracting
+    pub wait_for_selector: Option<&'static str>,
+}
+
+const DOCUSAURUS: Extraction = Extraction {
+    include_tags: &["article"],
+    exclude_tags: &[
+        ".theme-doc-breadcrumbs",
+        ".theme-doc-toc-mobile",
+        ".theme-doc-toc-desktop",
+        ".theme-doc-footer",
+        ".theme-edit-this-page",
+        ".pagination-nav",
+        ".hash-link",
+    ],
+    wait_for: None,
+    wait_for_selector: None,
+};
+
+const MKDOCS_MATERIAL: Extraction = Extraction {
+    include_tags: &[".md-content"],
+    exclude_tags: &[".md-header", ".md-sidebar", ".md-footer", ".md-content__button", ".md-source-file", ".headerlink"],
+    wait_for: None,
+    wait_for_selector: None,
+};
+
+const SPHINX: Extraction = Extraction {
+    include_tags: &[],
+    exclude_tags: &[
+        ".sphinxsidebar",
+        ".wy-nav-side",
+        ".wy-breadcrumbs",
+        ".rst-versions",
+        ".rst-footer-buttons",
+        ".related",
+        ".headerlink",
+        ".footer",
+    ],
+    wait_for: None,
+    wait_for_selector: None,
+};
+
+const GITBOOK: Extraction = Extraction {
+    include_tags: &["main"],
+    exclude_tags: &["header", "aside", "footer", "nav"],
+    wait_for: Some(1000),
+    wait_for_selector: None,
+};
+
+const MINTLIFY: Extraction = Extraction {
+    include_tags: &["#content-area"],
+    exclude_tags: &["#navbar", "#sidebar", "#table-of-contents", "#pagination", "footer"],
+    wait_for: None,
+    wait_for_selector: Some("#content-area"),
+};
+
+/// Presets recognized by `detect`, in the order they are tried.
+const DETECTED: &[Preset] = &[Preset::Docusaurus, Preset::MkDocsMaterial, Preset::Sphinx, Preset::GitBook, Preset::Mintlify];
+
+impl Preset {
+    /// Returns what the preset asks of FireCrawl, or `None` for `Auto` and `None`.
+    pub fn extraction(self) -> Option<&'static Extraction> {
+        match self {
+            Preset::Docusaurus => Some(&DOCUSAURUS),
+            Preset::MkDocsMaterial => Some(&MKDOCS_MATERIAL),
+            Preset::Sphinx => Some(&SPHINX),
+            Preset::GitBook => Some(&GITBOOK),
+            Preset::Mintlify => Some(&MINTLIFY),
+            Preset::Auto | Preset::None => None,
+        }
+    }
+
+    /// Returns the lowercase prefixes of `<meta name="generator">` values
+    /// naming the generator, and markup only its pages contain.
+    fn markers(self) -> (&'static [&'static str], &'static [&'static str]) {
+        match self {
+            Preset::Docusaurus => (&["docusaurus"], &["id=\"__docusaurus\""]),
+            // Material's generator tag reads "mkdocs-1.6.0, mkdocs-material-9.5.0"
+            Preset::MkDocsMaterial => (&[], &["mkdocs-material", "class=\"md-header"]),
+            Preset::Sphinx => (&["sphinx", "docutils"], &["_static/documentation_options.js", "readthedocs-data"]),
+            Preset::GitBook => (&["gitbook"], &[]),
+            Preset::Mintlify => (&["mintlify"], &["mintcdn.com", "id=\"content-area\""]),
+            Preset::Auto | Preset::None => (&[], &[]),
+        }
+    }
+
+    /// Returns the name of the preset, as given to `--preset`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Auto => "auto",
+            Preset::Docusaurus => "docusaurus",
+            Preset::MkDocsMaterial => "mkdocs-material",
+            Preset::Sphinx => "sphinx",
+            Preset::GitBook => "gitbook",
+            Preset::Mintlify => "mintlify",
+            Preset::None => "none",
+        }
+    }
+}
+
+impl Extraction {
+    /// Returns the `includeTags` of a FireCrawl request.
+    pub fn include_tags(&self) -> Option<Vec<String>> {
+        tag_list(self.include_tags)
+    }
+
+    /// Returns the `excludeTags` of a FireCrawl request.
+    pub fn exclude_tags(&self) -> Option<Vec<String>> {
+        tag_list(self.exclude_tags)
+    }
+
+    /// Returns the `actions` of a FireCrawl request.
+    pub fn actions(&self) -> Option<Vec<Action>> {
+        self.wait_for_selector.map(|selector| {
+            vec![Action::Wait {
+                milliseconds: None,
+                selector: Some(selector.to_string()),
+            }]
+        })
+    }
+}
+
+/// Returns a list of selectors for a request, or `None` when it is empty.
+fn tag_list(tags: &[&str]) -> Option<Vec<String>> {
+    (!tags.is_empty()).then(|| tags.iter().map(|tag| tag.to_string()).collect())
+}
+
+/// Detects the documentation generator a page was built with.
+///
+/// The `<meta name="generator">` tag is checked first, then markup typical of
+/// each generator.
+///
+/// # Returns
+///
+/// The preset of the generator, or `Preset::None` if none was recognized
+///
+/// # Examples
+///
+/// ```text
+/// let html = r#"<head><meta name="generator" content="Docusaurus v3.5.2"></head>"#;
+/// assert_eq!(detect(html), Preset::Docusaurus);
+/// assert_eq!(detect("<html><body>Hello</body></html>"), Preset::None);
+/// ```
+pub fn detect(html: &str) -> Preset {
+    let generators: Vec<String> = html::tags(html, &["meta"])
+        .into_iter()
+        .filter(|tag| html::attribute(tag, "name").is_some_and(|name| name.eq_ignore_ascii_case("generator")))
+        .filter_map(|tag| html::attribute(tag, "content"))
+        .map(|content| content.trim().to_ascii_lowercase())
+        .collect();
+    let lower = html.to_ascii_lowercase();
+
+    let by_generator = DETECTED.iter().find(|preset| {
+        let (prefixes, _) = preset.markers();
+        generators.iter().any(|generator| prefixes.iter().any(|prefix| generator.starts_with(prefix)))
+    });
+    let by_markup = || {
+        DETECTED.iter().find(|preset| {
+            let (_, markup) = preset.markers();
+            markup.iter().any(|marker| lower.contains(marker))
+        })
+    };
+    by_generator.or_else(by_markup).copied().unwrap_or(Preset::None)
+}
@@ -0,0 +1,172 @@
+//! Quality scoring of scraped pages.
+//!
+//! Extraction doesn't always work: a page may come back as little more than
+//! its navigation, a cookie banner or a list of links. Every saved page gets a
+//! score between 0 and 1 recorded in the manifest, so bad extractions can be
+//! found, and with `--min-quality` pages scoring below the threshold are saved
+//! in a `_review/` directory instead of among the good output.
+//!
+//! The score is a weighted average of four signals:
+//!
+//! | Signal | Weight | Full marks when |
+//! |--------|--------|-----------------|
+//! | Content ratio | 0.3 | the text is at least 5% of the page's HTML; skipped without HTML |
+//! | Headings | 0.2 | the page has at least one heading |
+//! | Link density | 0.3 | at most 30% of the text is link text; none left at 80% |
+//! | API warning | 0.2 | the API returned no warning |
+
+use serde::{Deserialize, Serialize};
+
+use crate::markdown;
+use crate::navigation::MARKDOWN_LINK;
+
+/// Directory inside the output directory that pages below `--min-quality` are saved in.
+pub const REVIEW_DIR_NAME: &str = "_review";
+
+/// Share of the HTML the text must make up for full marks.
+const GOOD_CONTENT_RATIO: f64 = 0.05;
+
+/// Link density up to which a page gets full marks.
+const LOW_LINK_DENSITY: f64 = 0.3;
+
+/// Link density at which a page gets no marks.
+const HIGH_LINK_DENSITY: f64 = 0.8;
+
+/// Quality score of a saved page.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Quality {
+    /// Score between 0 (certainly a bad extraction) and 1, rounded to two decimals
+    pub score: f64,
+
+    /// What lowered the score
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<QualityIssue>,
+}
+
+/// A problem lowering a page's quality score.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum QualityIssue {
+    /// The text is less than half the good content ratio of the HTML
+    LowContentRatio,
+    /// The page has no headings
+    NoHeadings,
+    /// More than half of the text is link text
+    HighLinkDensity,
+    /// The API returned a warning with the page
+    ApiWarning,
+}
+
+/// Scores a scraped page.
+///
+/// # Arguments
+///
+/// * `markdown` - The page's markdown, without frontmatter
+/// * `html` - The page's HTML, if the response included it
+/// * `warning` - Whether the API returned a warning with the page
+///
+/// # Examples
+///
+/// ```text
+/// let quality = score("# Setup\n\nInstall the package and run it.", None, false);
+/// assert_eq!(quality.score, 1.0);
+///
+/// let quality = score("[Home](/) [Docs](/docs) [Blog](/blog)", None, false);
+/// assert_eq!(quality.issues, vec![QualityIssue::NoHeadings, QualityIssue::HighLinkDensity]);
+/// ```
+pub fn score(markdown: &str, html: Option<&str>, warning: bool) -> Quality {
+    let mut issues = Vec::new();
+    let mut total = 0.0;
+    let mut weights = 0.0;
+
+    // Links count as their text only
+    let link_chars: usize = MARKDOWN_LINK
+        .captures_iter(markdown)
+        .map(|captures| text_chars(&captures[1]))
+        .sum();
+    let text_chars = text_chars(&MARKDOWN_LINK.replace_all(markdown, "$1"));
+
+    if let Some(html) = html.filter(|html| !html.is_empty()) {
+        let ratio = text_chars as f64 / html.len() as f64;
+        let content = (ratio / GOOD_CONTENT_RATIO).min(1.0);
+        if content < 0.5 {
+            issues.push(QualityIssue::LowContentRatio);
+        }
+        total += 0.3 * content;
+        weights += 0.3;
+    }
+
+    let has_headings = !markdown::headings(markdown).is_empty();
+    if !has_headings {
+        issues.push(QualityIssue::NoHeadings);
+    }
+    total += if has_headings { 0.2 } else { 0.0 };
+    weights += 0.2;
+
+    let density = if text_chars == 0 { 1.0 } else { link_chars as f64 / text_chars as f64 };
+    if density > 0.5 {
+        issues.push(QualityIssue::HighLinkDensity);
+    }
+    total += 0.3 * ((HIGH_LINK_DENSITY - density) / (HIGH_LINK_DENSITY - LOW_LINK_DENSITY)).clamp(0.0, 1.0);
+    weights += 0.3;
+
+    if warning {
+        issues.push(QualityIssue::ApiWarning);
+    } else {
+        total += 0.2;
+    }
+    weights += 0.2;
+
+    Quality {
+        score: (total / weights * 100.0).round() / 100.0,
+        issues,
+    }
+}
+
+/// Parses a `--min-quality` threshold.
+///
+/// # Errors
+///
+/// Returns an error if the value is not a number between 0 and 1
+pub fn parse_min_quality(value: &str) -> Result<f64, String> {
+    let threshold: f64 = value.parse().map_err(|_| format!("{} is not a number", value))?;
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(format!("{} is not between 0 and 1", value));
+    }
+    Ok(threshold)
+}
+
+/// Returns the path a page is saved at, inside or outside the review directory.
+///
+/// A page that was quarantined by an earlier run moves back out once it
+/// scores well enough, and the other way around.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(review_path("guide/setup.md", true), "_review/guide/setup.md");
+/// assert_eq!(review_path("_review/guide/setup.md", false), "guide/setup.md");
+/// ```
+pub fn review_path(file: &str, quarantined: bool) -> String {
+    let file = file
+        .strip_prefix(REVIEW_DIR_NAME)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(file);
+    if quarantined {
+        format!("{}/{}", REVIEW_DIR_NAME, file)
+    } else {
+        file.to_string()
+    }
+}
+
+/// Returns whether a file path relative to the output directory is in the review directory.
+pub fn is_quarantined(file: &str) -> bool {
+    file.strip_prefix(REVIEW_DIR_NAME).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Counts the characters of text that aren't whitespace or markdown punctuation.
+fn text_chars(text: &str) -> usize {
+    text.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '#' | '*' | '_' | '`' | '|' | '-' | '>' | '[' | ']'))
+        .count()
+}
@@ -0,0 +1,139 @@
+//! Redaction of secrets from logged text and of sensitive content from saved pages.
+//!
+//! Response bodies and error messages end up in terminal output and in bug
+//! reports. Before they are printed they pass through `preview`, which removes
+//! credentials and shortens them to a readable length.
+//!
+//! Two kinds of secrets are removed: values registered at startup with
+//! `register_secret` (the API key and any configured credentials), and
+//! anything that looks like an `Authorization` or `Cookie` header or a bearer
+//! token, in case a proxy echoes the request back.
+//!
+//! Saved pages are redacted separately, with the `ContentRule`s configured in
+//! the configuration file (API keys, email addresses, internal host names and
+//! so on), before anything is written to disk.
+
+use regex::Regex;
+use std::sync::{LazyLock, Mutex};
+
+/// Text substituted for every redacted value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Number of characters of a response body kept by `preview`.
+const PREVIEW_LENGTH: usize = 300;
+
+/// Secrets registered with `register_secret`.
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Header lines carrying credentials, as `Name: value` or `name=value`.
+static CREDENTIAL_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(proxy-authorization|authorization|set-cookie|cookie)(\s*[:=]\s*)[^\r\n]+").unwrap()
+});
+
+/// Bearer and basic credentials appearing outside of header lines.
+static CREDENTIAL_TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(bearer|basic)(\s+)[A-Za-z0-9._~+/=-]{8,}").unwrap());
+
+/// Registers a value that must never appear in logged text.
+///
+/// Empty values are ignored.
+pub fn register_secret(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.lock().unwrap_or_else(|e| e.into_inner());
+    if !secrets.iter().any(|known| known == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// Removes registered secrets and credential headers from text.
+///
+/// # Examples
+///
+/// ```text
+/// register_secret("fc-123456");
+/// assert_eq!(redact("key fc-123456"), "key [REDACTED]");
+/// assert_eq!(redact("Cookie: session=abc"), "Cookie: [REDACTED]");
+/// ```
+pub fn redact(text: &str) -> String {
+    let mut text = CREDENTIAL_HEADER
+        .replace_all(text, format!("${{1}}${{2}}{}", REDACTED))
+        .into_owned();
+    text = CREDENTIAL_TOKEN
+        .replace_all(&text, format!("${{1}}${{2}}{}", REDACTED))
+        .into_owned();
+
+    let secrets = SECRETS.lock().unwrap_or_else(|e| e.into_inner());
+    for secret in secrets.iter() {
+        text = text.replace(secret.as_str(), REDACTED);
+    }
+    text
+}
+
+/// A redaction rule applied to page content before it is saved.
+#[derive(Debug, Clone)]
+pub struct ContentRule {
+    regex: Regex,
+    replacement: String,
+}
+
+impl ContentRule {
+    /// Creates a rule replacing every match of `regex` with `replacement`.
+    ///
+    /// The replacement may reference capture groups as `$1` or `${name}`.
+    pub fn new(regex: Regex, replacement: String) -> Self {
+        ContentRule { regex, replacement }
+    }
+
+    /// Creates a rule replacing every occurrence of `text` with `replacement`.
+    pub fn literal(text: &str, replacement: String) -> Self {
+        let regex = Regex::new(&regex::escape(text)).expect("escaped literal is a valid regex");
+        ContentRule { regex, replacement }
+    }
+}
+
+/// Applies content rules to text, in order, each to the output of the previous one.
+///
+/// # Returns
+///
+/// The redacted text and the number of replacements made
+///
+/// # Examples
+///
+/// ```text
+/// let rules = vec![ContentRule::literal("wiki.corp.example.com", REDACTED.to_string())];
+/// let (text, count) = redact_content(&rules, "See https://wiki.corp.example.com/x");
+/// assert_eq!((text.as_str(), count), ("See https://[REDACTED]/x", 1));
+/// ```
+pub fn redact_content(rules: &[ContentRule], text: &str) -> (String, usize) {
+    let mut count = 0;
+    let mut text = text.to_string();
+    for rule in rules {
+        let matches = rule.regex.find_iter(&text).count();
+        if matches > 0 {
+            count += matches;
+            text = rule.regex.replace_all(&text, rule.replacement.as_str()).into_owned();
+        }
+    }
+    (text, count)
+}
+
+/// Prepares a response body for inclusion in a log or error message.
+///
+/// Secrets are redacted, whitespace is collapsed and the result is cut to a
+/// few hundred characters.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(preview("  short\n body "), "short body");
+/// ```
+pub fn preview(body: &str) -> String {
+    let collapsed = redact(body).split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() > PREVIEW_LENGTH {
+        format!("{}...", collapsed.chars().take(PREVIEW_LENGTH).collect::<String>())
+    } else {
+        collapsed
+    }
+}
@@ -0,0 +1,85 @@
+//! Previewing search-and-replace rules on a saved mirror.
+//!
+//! `[[replace]]` rules in the configuration file rewrite the markdown of pages
+//! before they are saved: pointing absolute CDN URLs at the docs site, fixing a
+//! vendor's broken relative links, normalizing a product name. A pattern that
+//! matches more than intended rewrites every page it touches on the next run,
+//! so `scraper preview-replacements <dir>` runs the configured rules over the
+//! pages already saved in a mirror and shows which would change, and how,
+//! without writing anything.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::frontmatter;
+use crate::markdown;
+use crate::mirror;
+use crate::redact::{self, ContentRule};
+
+/// Number of changed lines shown for each page by the preview.
+pub const PREVIEW_LINES: usize = 3;
+
+/// What the rules would change in one saved page.
+#[derive(Debug)]
+pub struct PagePreview {
+    /// Path of the file relative to the mirror directory
+    pub file: String,
+    /// Source URL recorded in the file's frontmatter
+    pub url: String,
+    /// Number of replacements the rules would make
+    pub replacements: usize,
+    /// Lines the rules would change, before and after
+    pub lines: Vec<(String, String)>,
+}
+
+/// Outcome of previewing the rules on a mirror.
+#[derive(Debug, Default)]
+pub struct Preview {
+    /// Number of saved pages the rules were run over
+    pub pages_checked: usize,
+    /// Pages the rules would change, in file order
+    pub pages: Vec<PagePreview>,
+}
+
+/// Runs `rules` over the body of every saved page of a mirror, without writing anything.
+///
+/// Bodies are checked without their frontmatter and citation footer, which
+/// the rules don't apply to. Changed lines are found by running the rules over
+/// each line on its own, so replacements spanning lines are counted but not
+/// listed.
+///
+/// # Errors
+///
+/// Returns an error if there are no rules, or the mirror or one of its files cannot be read
+pub fn preview(dir: &Path, rules: &[ContentRule]) -> Result<Preview, Box<dyn Error>> {
+    if rules.is_empty() {
+        return Err("The configuration file has no [[replace]] rules".into());
+    }
+    let mut preview = Preview::default();
+    for page in mirror::mirrored_pages(dir)? {
+        let path = dir.join(&page.file);
+        let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let body = markdown::strip_citation_footer(frontmatter::strip_frontmatter(&content));
+        preview.pages_checked += 1;
+
+        let (_, replacements) = redact::redact_content(rules, body);
+        if replacements == 0 {
+            continue;
+        }
+        let lines = body
+            .lines()
+            .filter_map(|line| {
+                let (replaced, count) = redact::redact_content(rules, line);
+                (count > 0 && replaced != line).then(|| (line.to_string(), replaced))
+            })
+            .collect();
+        preview.pages.push(PagePreview {
+            file: page.file,
+            url: page.url,
+            replacements,
+            lines,
+        });
+    }
+    Ok(preview)
+}
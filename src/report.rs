@@ -0,0 +1,261 @@
+//! Run report written at the end of every scrape.
+//!
+//! The report summarizes what a run did and records decisions made along the
+//! way (such as the outcome of the compliance pre-check), so they can be
+//! reviewed after the fact.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::assets::AssetSummary;
+use crate::bandwidth::BandwidthSummary;
+use crate::compliance::ComplianceReport;
+use crate::metrics::MetricsSummary;
+use crate::ChangeStatus;
+
+/// File name of the run report inside the state directory.
+pub const REPORT_FILE_NAME: &str = "report.json";
+
+/// Summary of a single scraper run.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunReport {
+    /// The URL the run started from
+    pub start_url: String,
+
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+
+    /// When the run finished, if it ran to completion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// Number of pages found during link discovery
+    pub pages_found: usize,
+
+    /// Number of the pages found that were picked to be scraped, with `--sample`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_sampled: usize,
+
+    /// Number of pages processed without error
+    pub pages_processed: usize,
+
+    /// Number of pages that failed to process
+    pub pages_failed: usize,
+
+    /// Number of processed pages saved for the first time
+    pub pages_added: usize,
+
+    /// Number of processed pages whose saved file was rewritten with changed content
+    pub pages_changed: usize,
+
+    /// Number of processed pages whose content was unchanged, so their saved file was left untouched
+    pub pages_unchanged: usize,
+
+    /// Number of processed pages saved under a new file name, e.g. because their title changed
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_renamed: usize,
+
+    /// Number of pages the API returned without markdown
+    pub pages_empty: usize,
+
+    /// Number of pages answered with `401 Unauthorized` or `403 Forbidden`, which usually means credentials stopped working
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_unauthorized: usize,
+
+    /// URLs of the pages returned without markdown
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub empty_content: Vec<String>,
+
+    /// URLs of the pages that failed to process
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+
+    /// Number of pages that failed in earlier runs and were retried first
+    #[serde(skip_serializing_if = "is_zero")]
+    pub failures_retried: usize,
+
+    /// Number of pages that failed in earlier runs and were left out until their backoff has passed
+    #[serde(skip_serializing_if = "is_zero")]
+    pub failures_deferred: usize,
+
+    /// URLs of the pages given up on in this run after failing in `--max-failures` runs in a row
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub permanently_failing: Vec<String>,
+
+    /// Number of processed pages saved with warnings, which are listed in the manifest
+    pub pages_with_warnings: usize,
+
+    /// URLs of the pages saved with warnings
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub with_warnings: Vec<String>,
+
+    /// Number of pages left in the queue when the run stopped at `--max-pages` or `--max-bytes`, or was interrupted
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_unvisited: usize,
+
+    /// Whether Ctrl-C or SIGTERM stopped the run, leaving a checkpoint to resume from
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+
+    /// Number of pages by whether FireCrawl found them changed, with `--change-tracking`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub change_tracking: BTreeMap<ChangeStatus, usize>,
+
+    /// Number of pages skipped by a middleware hook
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_skipped: usize,
+
+    /// Number of pages left out because the site answered with an error status, with `--error-pages skip`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_error_status: usize,
+
+    /// Number of pages replaced by a tombstone because they were removed from the site
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_removed: usize,
+
+    /// Number of replacements made by content redaction rules
+    #[serde(skip_serializing_if = "is_zero")]
+    pub content_redactions: usize,
+
+    /// Number of replacements made by search-and-replace rules
+    #[serde(skip_serializing_if = "is_zero")]
+    pub content_replacements: usize,
+
+    /// Number of images left without alt text, listed in the accessibility report
+    #[serde(skip_serializing_if = "is_zero")]
+    pub images_without_alt: usize,
+
+    /// URLs of the pages saved in the review directory for scoring below `--min-quality`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quarantined: Vec<String>,
+
+    /// Retries made after transient API failures, by page URL
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub retries: BTreeMap<String, u32>,
+
+    /// Saved files found edited or corrupted since they were written, and fetched again
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modified_files: Vec<String>,
+
+    /// Outcome of the compliance pre-check, if it was run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compliance: Option<ComplianceReport>,
+
+    /// Latency and size percentiles of the API calls made for pages, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_calls: Option<MetricsSummary>,
+
+    /// Bytes downloaded during the run, once pages have been scraped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<BandwidthSummary>,
+
+    /// Images and PDFs handled with `--download-assets`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assets: Option<AssetSummary>,
+}
+
+impl RunReport {
+    /// Creates an empty report for a run starting now.
+    pub fn new(start_url: &str) -> Self {
+        RunReport {
+            start_url: start_url.to_string(),
+            started_at: Utc::now(),
+            finished_at: None,
+            pages_found: 0,
+            pages_sampled: 0,
+            pages_processed: 0,
+            pages_failed: 0,
+            pages_added: 0,
+            pages_changed: 0,
+            pages_renamed: 0,
+            pages_unchanged: 0,
+            pages_empty: 0,
+            pages_unauthorized: 0,
+            empty_content: Vec::new(),
+            failed: Vec::new(),
+            failures_retried: 0,
+            failures_deferred: 0,
+            permanently_failing: Vec::new(),
+            pages_with_warnings: 0,
+            with_warnings: Vec::new(),
+            pages_unvisited: 0,
+            interrupted: false,
+            change_tracking: BTreeMap::new(),
+            pages_skipped: 0,
+            pages_error_status: 0,
+            pages_removed: 0,
+            content_redactions: 0,
+            content_replacements: 0,
+            images_without_alt: 0,
+            quarantined: Vec::new(),
+            retries: BTreeMap::new(),
+            modified_files: Vec::new(),
+            compliance: None,
+            api_calls: None,
+            bandwidth: None,
+            assets: None,
+        }
+    }
+
+    /// Returns the pages of the run that failed or were saved with warnings.
+    ///
+    /// Pages returned without content count as failed, since nothing was saved for them.
+    pub fn page_counts(&self) -> PageCounts {
+        PageCounts {
+            failed: self.pages_failed + self.pages_empty,
+            with_warnings: self.pages_with_warnings,
+            interrupted: self.interrupted,
+        }
+    }
+
+    /// Writes the report as pretty-printed JSON into the state directory of `output_dir`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the path of the written report
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state directory cannot be created or the file cannot be written
+    pub fn write(&self, output_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        let dir = crate::state_dir(output_dir);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(REPORT_FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| format!("Failed to write report {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+/// Pages of one or more runs that decide the exit status of the scraper.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageCounts {
+    /// Pages that failed or were returned without content
+    pub failed: usize,
+
+    /// Pages saved with warnings
+    pub with_warnings: usize,
+
+    /// Whether a run was stopped by Ctrl-C or SIGTERM
+    pub interrupted: bool,
+}
+
+impl PageCounts {
+    /// Adds the pages of another run.
+    pub fn add(&mut self, other: PageCounts) {
+        self.failed += other.failed;
+        self.with_warnings += other.with_warnings;
+        self.interrupted |= other.interrupted;
+    }
+}
+
+/// Returns whether a count is zero, for skipping counts of features not in use.
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
@@ -0,0 +1,144 @@
+//! Retries of page scrapes that failed for a transient reason.
+//!
+//! A timeout, a `429 Too Many Requests` or a `5xx` answer usually says nothing
+//! about the page: asked again a moment later, the API answers normally. Such
+//! failures are retried up to `--retries` times, waiting `--retry-delay-ms`
+//! before the first retry and twice as long before every further one, with
+//! random jitter so concurrent pages don't retry in lockstep. A `Retry-After`
+//! header is honored when it asks for a longer wait.
+//!
+//! When the retries run out while the API stays unreachable, the page is
+//! handed to the outage handling, which parks the queue and probes the API.
+
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::api::ApiUnavailable;
+
+/// Retries made after a transient failure when `--retries` isn't given.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Delay before the first retry when `--retry-delay-ms` isn't given.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest backoff between two attempts, however many retries were made.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Error returned when the API answers with a status worth retrying, such as `429` or `500`.
+#[derive(Debug)]
+pub struct TransientFailure {
+    /// Description of the failure
+    pub message: String,
+
+    /// How long the API asked to wait with a `Retry-After` header, if it did
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for TransientFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for TransientFailure {}
+
+/// Retries the API requests of one page, counting the retries made.
+#[derive(Debug)]
+pub struct Retrier {
+    /// Retries made at most per request
+    max_retries: u32,
+
+    /// Delay before the first retry
+    base_delay: Duration,
+
+    /// Retries made so far
+    retries: AtomicU32,
+}
+
+impl Retrier {
+    /// Creates a retrier making up to `max_retries` retries, starting `base_delay` apart.
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Retrier {
+            max_retries,
+            base_delay,
+            retries: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns the number of retries made so far.
+    pub fn retries(&self) -> u32 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Runs `attempt` until it succeeds, fails for a reason that isn't transient,
+    /// or the retries run out; the last result is returned.
+    pub async fn run<T, F, Fut>(&self, mut attempt: F) -> Result<T, Box<dyn Error>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn Error>>>,
+    {
+        let mut retry = 0;
+        loop {
+            // The error isn't kept while waiting, so a future running this stays `Send`
+            let delay = {
+                let error = match attempt().await {
+                    Err(e) if retry < self.max_retries && is_transient(e.as_ref()) => e,
+                    result => return result,
+                };
+                let retry_after = error.downcast_ref::<TransientFailure>().and_then(|failure| failure.retry_after);
+                let delay = backoff(self.base_delay, retry, jitter()).max(retry_after.unwrap_or_default());
+                warn!("{}; retrying in {} ms ({}/{})", error, delay.as_millis(), retry + 1, self.max_retries);
+                delay
+            };
+            retry += 1;
+            self.retries.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Returns whether an error may pass when the request is made again.
+pub fn is_transient(error: &(dyn Error + 'static)) -> bool {
+    error.is::<TransientFailure>() || error.is::<ApiUnavailable>()
+}
+
+/// Returns the delay before retry number `retry` (counting from 0).
+///
+/// The delay doubles with every retry up to `MAX_RETRY_DELAY`; `jitter` in
+/// `[0, 1)` picks a point in its upper half.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(backoff(Duration::from_millis(500), 2, 0.0), Duration::from_millis(1000));
+/// assert_eq!(backoff(Duration::from_millis(500), 2, 0.5), Duration::from_millis(1500));
+/// ```
+fn backoff(base_delay: Duration, retry: u32, jitter: f64) -> Duration {
+    let delay = base_delay.saturating_mul(2u32.saturating_pow(retry)).min(MAX_RETRY_DELAY);
+    delay / 2 + (delay / 2).mul_f64(jitter)
+}
+
+/// Returns a random number in `[0, 1)`.
+fn jitter() -> f64 {
+    // Every `RandomState` is seeded with fresh random keys
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Parses a `Retry-After` header given in seconds; HTTP dates are not supported.
+///
+/// # Examples
+///
+/// ```text
+/// assert_eq!(parse_retry_after("2"), Some(Duration::from_secs(2)));
+/// assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+/// ```
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
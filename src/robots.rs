@@ -0,0 +1,255 @@
+//! Minimal robots.txt parser.
+//!
+//! Supports `User-agent`, `Allow` and `Disallow` records with `*` wildcards and
+//! `$` end anchors. When both an allow and a disallow rule match a path, the
+//! longest rule wins, as described in RFC 9309. The non-standard `Sitemap` and
+//! `Crawl-delay` records are read as well.
+
+/// A group of rules that applies to one or more user agents.
+#[derive(Debug, Default, Clone)]
+struct Group {
+    user_agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<f64>,
+}
+
+/// A single `Allow` or `Disallow` rule.
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// Parsed contents of a robots.txt file.
+#[derive(Debug, Default, Clone)]
+pub struct Robots {
+    groups: Vec<Group>,
+    sitemaps: Vec<String>,
+}
+
+impl Robots {
+    /// Parses the text of a robots.txt file.
+    ///
+    /// Unknown records and malformed lines are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let robots = Robots::parse("User-agent: *\nDisallow: /private/\n");
+    /// assert!(!robots.is_allowed("scraper", "/private/page"));
+    /// assert!(robots.is_allowed("scraper", "/docs/"));
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        let mut robots = Robots::default();
+        let mut current: Option<Group> = None;
+        let mut last_was_agent = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    // Consecutive user-agent lines share the same group
+                    if !last_was_agent {
+                        if let Some(group) = current.take() {
+                            robots.groups.push(group);
+                        }
+                    }
+                    let group = current.get_or_insert_with(Group::default);
+                    // An empty token names no crawler; kept, it would match every user agent
+                    if !value.is_empty() {
+                        group.user_agents.push(value.to_ascii_lowercase());
+                    }
+                    last_was_agent = true;
+                }
+                "allow" | "disallow" => {
+                    last_was_agent = false;
+                    // An empty Disallow allows everything and adds no rule
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = current.as_mut() {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    last_was_agent = false;
+                    let delay = value.parse::<f64>().ok().filter(|delay| delay.is_finite() && *delay >= 0.0);
+                    if let (Some(group), Some(delay)) = (current.as_mut(), delay) {
+                        group.crawl_delay = Some(delay);
+                    }
+                }
+                // Sitemaps don't belong to a group and don't end one
+                "sitemap" => {
+                    if !value.is_empty() {
+                        robots.sitemaps.push(value.to_string());
+                    }
+                }
+                _ => {
+                    last_was_agent = false;
+                }
+            }
+        }
+
+        if let Some(group) = current {
+            robots.groups.push(group);
+        }
+
+        robots
+    }
+
+    /// Returns whether the given user agent may fetch the given path.
+    ///
+    /// Groups naming the user agent take precedence over the `*` group.
+    /// Paths not matched by any rule are allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - Product token of the crawler, matched case-insensitively
+    /// * `path` - Path (and optional query) of the URL to check
+    pub fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        let best = self
+            .groups_for(user_agent)
+            .iter()
+            .flat_map(|g| g.rules.iter())
+            .filter(|rule| pattern_matches(&rule.pattern, path))
+            .max_by(|a, b| {
+                a.pattern
+                    .len()
+                    .cmp(&b.pattern.len())
+                    // Allow wins ties, per RFC 9309
+                    .then(a.allow.cmp(&b.allow))
+            });
+
+        best.map(|rule| rule.allow).unwrap_or(true)
+    }
+
+    /// Returns the URLs of the sitemaps listed in the file.
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+
+    /// Returns the seconds the given user agent is asked to wait between requests, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let robots = Robots::parse("User-agent: *\nCrawl-delay: 2.5\n");
+    /// assert_eq!(robots.crawl_delay("scraper"), Some(2.5));
+    /// ```
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<f64> {
+        self.groups_for(user_agent).iter().find_map(|g| g.crawl_delay)
+    }
+
+    /// Returns the groups applying to a user agent.
+    ///
+    /// Groups naming the user agent take precedence over the `*` group.
+    fn groups_for(&self, user_agent: &str) -> Vec<&Group> {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let specific: Vec<&Group> = self
+            .groups
+            .iter()
+            .filter(|g| g.user_agents.iter().any(|ua| ua != "*" && user_agent.contains(ua.as_str())))
+            .collect();
+        if specific.is_empty() {
+            self.groups
+                .iter()
+                .filter(|g| g.user_agents.iter().any(|ua| ua == "*"))
+                .collect()
+        } else {
+            specific
+        }
+    }
+}
+
+/// Matches a robots.txt path pattern supporting `*` wildcards and a trailing `$` anchor.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let Some((first, rest)) = parts.split_first() else {
+        return false;
+    };
+    if !path.starts_with(first) {
+        return false;
+    }
+    let mut position = first.len();
+
+    let Some((last, middle)) = rest.split_last() else {
+        return !anchored || position == path.len();
+    };
+    for part in middle {
+        match path[position..].find(part) {
+            Some(offset) => position += offset + part.len(),
+            None => return false,
+        }
+    }
+
+    if anchored {
+        path.len() >= position + last.len() && path.ends_with(last)
+    } else {
+        path[position..].contains(last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_groups_take_precedence_over_the_wildcard() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /private\n\nUser-agent: Scraper\nUser-agent: other\nDisallow: /drafts\nCrawl-delay: 3\n",
+        );
+        assert_eq!(robots.groups_for("scraper/1.0").len(), 1);
+        assert!(robots.is_allowed("Scraper/1.0", "/private"));
+        assert!(!robots.is_allowed("scraper/1.0", "/drafts/a"));
+        assert!(!robots.is_allowed("curl", "/private/a"));
+        assert!(robots.is_allowed("curl", "/drafts/a"));
+        assert_eq!(robots.crawl_delay("other"), Some(3.0));
+        assert_eq!(robots.crawl_delay("curl"), None);
+    }
+
+    #[test]
+    fn empty_user_agents_match_nobody() {
+        let robots = Robots::parse("User-agent:\nDisallow: /\n\nUser-agent: *\nDisallow: /private\n");
+        assert_eq!(robots.groups_for("scraper").len(), 1);
+        assert!(robots.is_allowed("scraper", "/docs"));
+        assert!(!robots.is_allowed("scraper", "/private"));
+    }
+
+    #[test]
+    fn longest_match_wins_and_allow_wins_ties() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /docs\nAllow: /docs/public\nDisallow: /same\nAllow: /same\n");
+        assert!(!robots.is_allowed("scraper", "/docs/private"));
+        assert!(robots.is_allowed("scraper", "/docs/public/page"));
+        assert!(robots.is_allowed("scraper", "/same"));
+        assert!(robots.is_allowed("scraper", "/elsewhere"));
+    }
+
+    #[test]
+    fn patterns_support_wildcards_and_anchors() {
+        assert!(pattern_matches("/", "/anything"));
+        assert!(pattern_matches("/*.pdf", "/files/report.pdf?download=1"));
+        assert!(pattern_matches("/*.pdf$", "/files/report.pdf"));
+        assert!(!pattern_matches("/*.pdf$", "/files/report.pdf?download=1"));
+        assert!(pattern_matches("/a*b*c", "/a-x-b-y-c-z"));
+        assert!(!pattern_matches("/a*c*b", "/a-b-c"));
+        assert!(pattern_matches("/exact$", "/exact"));
+        assert!(!pattern_matches("/exact$", "/exact/more"));
+        assert!(!pattern_matches("/docs", "/other/docs"));
+        assert!(pattern_matches("*", "/"));
+        assert!(!pattern_matches("/ab*b$", "/ab"));
+    }
+}
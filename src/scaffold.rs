@@ -0,0 +1,296 @@
+//! Scaffolding of new mirror repositories.
+//!
+//! `init <template>` writes the skeleton of a repository that holds scraped
+//! documentation: a workspace file (see `workspace`) already set up to save
+//! pages where the chosen renderer expects them, the renderer's own
+//! configuration, and a README explaining how to update the mirror.
+
+use clap::ValueEnum;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::urls;
+use crate::workspace::DEFAULT_WORKSPACE_FILE;
+
+/// Start URL written into the workspace file when none is given.
+const PLACEHOLDER_URL: &str = "https://docs.example.com/";
+
+/// Kind of repository created by `init`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// mdBook book with pages saved under `src/`
+    Mdbook,
+    /// Docusaurus site with pages saved under `docs/`
+    Docusaurus,
+    /// Plain markdown mirror with a README and configuration
+    Mirror,
+}
+
+/// A file written by `init`, relative to the target directory.
+#[derive(Debug)]
+pub struct ScaffoldFile {
+    /// Path relative to the target directory
+    pub path: PathBuf,
+    /// File content
+    pub content: String,
+}
+
+const GITIGNORE: &str = "\
+# Scraper state that is only useful on the machine that ran the scrape
+**/.scraper/frontier.json
+**/.scraper/run.lock
+**/.scraper/checkpoint.json
+**/.scraper/empty-responses/
+workspace-report.json
+";
+
+const SCRAPER_TOML: &str = r#"# Settings merged into every scrape, see the scraper README.
+#
+# [[rewrite]]
+# pattern = "^http://"
+# replacement = "https://"
+#
+# [[credentials]]
+# host = "docs.internal.example.com"
+# username = "reader"
+# password_env = "DOCS_PASSWORD"
+"#;
+
+const MIRROR_README: &str = "\
+# {name} documentation mirror
+
+Markdown mirror of <{url}>, kept up to date with the documentation scraper.
+
+## Updating
+
+```bash
+scraper run-all
+```
+
+Pages are saved to `{dir}/`. The sites and their options are defined in
+`sites.toml`; settings shared by all scrapes (URL rewrites, credentials) live
+in `scraper.toml`. After each run, `dashboard.md` summarizes what was scraped.
+";
+
+const MDBOOK_README: &str = "\
+# {name} documentation
+
+mdBook rendering of <{url}>, kept up to date with the documentation scraper.
+
+## Updating
+
+```bash
+scraper run-all
+mdbook build
+```
+
+Pages are saved to `src/` following the URL structure of the site. mdBook only
+renders pages listed in `src/SUMMARY.md`, so add new pages there after a scrape.
+";
+
+const MDBOOK_TOML: &str = "\
+[book]
+title = \"{name} documentation\"
+src = \"src\"
+
+[build]
+create-missing = false
+";
+
+const MDBOOK_SUMMARY: &str = "\
+# Summary
+
+[Introduction](README.md)
+";
+
+const MDBOOK_INTRO: &str = "\
+# {name} documentation
+
+Mirrored from <{url}>.
+";
+
+const DOCUSAURUS_README: &str = "\
+# {name} documentation
+
+Docusaurus site built from <{url}>, kept up to date with the documentation
+scraper.
+
+## Updating
+
+```bash
+scraper run-all
+npm install
+npm run build
+```
+
+Pages are saved to `docs/` following the URL structure of the site, and the
+sidebar is generated from that structure. Heading anchors in the exported
+`docs/pages.jsonl` use Docusaurus slugs, so deep links keep working.
+";
+
+const DOCUSAURUS_PACKAGE: &str = r#"{
+  "name": "{package}-docs",
+  "private": true,
+  "scripts": {
+    "start": "docusaurus start",
+    "build": "docusaurus build",
+    "serve": "docusaurus serve"
+  },
+  "dependencies": {
+    "@docusaurus/core": "^3.5.2",
+    "@docusaurus/preset-classic": "^3.5.2",
+    "react": "^18.3.1",
+    "react-dom": "^18.3.1"
+  }
+}
+"#;
+
+const DOCUSAURUS_CONFIG: &str = "\
+// Docusaurus configuration for the {name} documentation mirror.
+module.exports = {
+  title: '{name} documentation',
+  url: '{origin}',
+  baseUrl: '/',
+  onBrokenLinks: 'warn',
+  onBrokenMarkdownLinks: 'warn',
+  presets: [
+    [
+      'classic',
+      {
+        docs: { routeBasePath: '/', sidebarPath: require.resolve('./sidebars.js') },
+        blog: false,
+      },
+    ],
+  ],
+};
+";
+
+const DOCUSAURUS_SIDEBARS: &str = "\
+// The sidebar follows the directory structure of docs/.
+module.exports = {
+  docs: [{ type: 'autogenerated', dirName: '.' }],
+};
+";
+
+/// Returns the files of a template for the given site.
+///
+/// # Arguments
+///
+/// * `template` - The kind of repository
+/// * `name` - Name of the site, used as the workspace site name and in titles
+/// * `url` - The URL the site is scraped from
+pub fn files(template: Template, name: &str, url: &str) -> Vec<ScaffoldFile> {
+    let (dir, site_args) = match template {
+        Template::Mdbook => ("src", r#"["--path-template", "{path}.md"]"#),
+        Template::Docusaurus => (
+            "docs",
+            r#"["--path-template", "{path}.md", "--export", "jsonl", "--slug-style", "jsonl=docusaurus"]"#,
+        ),
+        Template::Mirror => (name, "[]"),
+    };
+    let origin = Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| url.to_string());
+    let fill = |text: &str| {
+        text.replace("{name}", name)
+            .replace("{url}", url)
+            .replace("{dir}", dir)
+            .replace("{origin}", &origin)
+            .replace("{package}", &name.to_ascii_lowercase().replace(|c: char| !c.is_ascii_alphanumeric(), "-"))
+    };
+
+    let sites = format!(
+        "# Sites mirrored in this repository; run `scraper run-all` to update them.\n\n[[site]]\nname = {:?}\nurl = {:?}\ndir = {:?}\nschedule = \"daily\"\nargs = {}\n",
+        name, url, dir, site_args
+    );
+
+    let mut files = vec![
+        file(DEFAULT_WORKSPACE_FILE, sites),
+        file("scraper.toml", SCRAPER_TOML.to_string()),
+        file(".gitignore", GITIGNORE.to_string()),
+    ];
+    match template {
+        Template::Mdbook => files.extend([
+            file("README.md", fill(MDBOOK_README)),
+            file("book.toml", fill(MDBOOK_TOML)),
+            file("src/SUMMARY.md", fill(MDBOOK_SUMMARY)),
+            file("src/README.md", fill(MDBOOK_INTRO)),
+        ]),
+        Template::Docusaurus => files.extend([
+            file("README.md", fill(DOCUSAURUS_README)),
+            file("package.json", fill(DOCUSAURUS_PACKAGE)),
+            file("docusaurus.config.js", fill(DOCUSAURUS_CONFIG)),
+            file("sidebars.js", DOCUSAURUS_SIDEBARS.to_string()),
+        ]),
+        Template::Mirror => files.push(file("README.md", fill(MIRROR_README))),
+    }
+    files
+}
+
+fn file(path: &str, content: String) -> ScaffoldFile {
+    ScaffoldFile {
+        path: PathBuf::from(path),
+        content,
+    }
+}
+
+/// Writes a template into `dir`, creating it if needed.
+///
+/// Nothing is written if any of the template's files already exists, so an
+/// existing repository is never partly overwritten.
+///
+/// # Arguments
+///
+/// * `template` - The kind of repository
+/// * `dir` - The directory to scaffold
+/// * `url` - The URL the site is scraped from; a placeholder if `None`
+/// * `name` - Name of the site; derived from the URL's host if `None`
+///
+/// # Returns
+///
+/// A `Result` containing the paths of the written files
+///
+/// # Errors
+///
+/// Returns an error if a file already exists or cannot be written
+///
+/// # Examples
+///
+/// ```text
+/// let written = scaffold::init(Template::Mdbook, Path::new("stripe-docs"), Some("https://docs.stripe.com/"), None)?;
+/// ```
+pub fn init(template: Template, dir: &Path, url: Option<&str>, name: Option<&str>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let url = url.unwrap_or(PLACEHOLDER_URL);
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => Url::parse(url)
+            .ok()
+            .and_then(|url| urls::ascii_host(&url))
+            .map(|host| host.trim_start_matches("www.").replace('.', "-"))
+            .ok_or_else(|| format!("Cannot derive a site name from {}; pass --name", url))?,
+    };
+
+    let files = files(template, &name, url);
+    let existing: Vec<String> = files
+        .iter()
+        .map(|file| dir.join(&file.path))
+        .filter(|path| path.exists())
+        .map(|path| path.display().to_string())
+        .collect();
+    if !existing.is_empty() {
+        return Err(format!("Refusing to overwrite existing files: {}", existing.join(", ")).into());
+    }
+
+    let mut written = Vec::new();
+    for file in files {
+        let path = dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, file.content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
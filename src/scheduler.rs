@@ -0,0 +1,558 @@
+//! Scraping the pages of a run, several at a time, and recording what became of them.
+//!
+//! Pages are started up to `--concurrency` at once and recorded one at a time
+//! as they finish, so the manifest, report, checkpoint and exports stay
+//! consistent. The scheduler also stops the run at its limits, parks the queue
+//! while the API is unreachable, and saves the checkpoint an interrupted run
+//! is resumed from.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::pin::pin;
+use std::time::{Duration, Instant};
+use futures::stream::{FuturesUnordered, StreamExt};
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::accessibility::{AccessibilityReport, PageImages};
+use crate::api::{Api, ApiUnavailable, ScrapeData};
+use crate::assets::AssetFetcher;
+use crate::bandwidth::{self, Bandwidth};
+use crate::checkpoint::{self, Checkpoint};
+use crate::cli::{Backend, ErrorPages, ScrapeOptions};
+use crate::crawl::CrawlJob;
+use crate::domain_profile::DomainProfiles;
+use crate::export::{ExportPage, Exporters};
+use crate::frontier::{self, Frontier, PendingPage};
+use crate::manifest::Manifest;
+use crate::metrics::MetricsSummary;
+use crate::output::FileClaims;
+use crate::page::{self, PageChange, PageJob, PageOutcome};
+use crate::preset::Preset;
+use crate::report::RunReport;
+use crate::site::retrier;
+use crate::throttle::{self, CreditPacer};
+use crate::{backlog, debug_bundle, pause, quality, shutdown};
+
+/// What the pages of a run share: the API they are scraped with, where they
+/// are saved, and the limits every page counts against.
+#[derive(Clone, Copy)]
+pub struct Run<'a> {
+    /// The FireCrawl API pages are scraped with
+    pub api: Api<'a>,
+    /// Directory to save the markdown files
+    pub output_dir: &'a Path,
+    /// Options controlling how pages are saved
+    pub options: &'a ScrapeOptions,
+    /// Pacer every API call is made through
+    pub pacer: &'a CreditPacer,
+    /// Meter every download is counted by
+    pub bandwidth: &'a Bandwidth,
+    /// Fetcher of the images and PDFs the pages refer to, with `--download-assets`
+    pub assets: Option<&'a AssetFetcher<'a>>,
+}
+
+/// Scrapes a list of pages into an output directory, recording results in the manifest and report.
+/// 
+/// Errors for individual pages are logged and counted but don't stop the run.
+/// Each page is processed in a `page` span carrying its URL. Every page
+/// is handed to the exporters as soon as it has been processed, and images
+/// without alt text are collected into the accessibility report.
+/// 
+/// If the API becomes unreachable, the remaining pages are parked in the
+/// persistent frontier and the current page is retried with exponential
+/// backoff until the API answers again, at which point the run resumes and the
+/// frontier is cleared.
+/// 
+/// The direct backend waits between pages of a host for as long as its
+/// robots.txt asks. FireCrawl scrapes use the main-content extraction setting
+/// learned for the host, and their outcomes are recorded in its profile.
+/// 
+/// With a crawl job, the pages it finishes are added to the queue whenever it
+/// runs empty, until the job has returned all of its results.
+/// 
+/// With `--stop-at-max-bytes`, the run stops once the byte cap is reached and
+/// the pages not yet scraped are parked for the next run.
+/// 
+/// When Ctrl-C or SIGTERM asks the run to stop, no more pages are started; once
+/// the pages in flight are saved, the checkpoint is saved with the pages not yet
+/// scraped and the report is marked as interrupted.
+/// 
+/// Up to `--concurrency` pages are scraped at once; their results are saved
+/// one at a time, in the order they finish.
+/// 
+/// # Arguments
+/// 
+/// * `run` - The API, output directory, options and limits of the run
+/// * `pages` - Pages to scrape, each with an optional fixed file name relative to the output directory
+/// * `crawl` - Crawl job whose results are saved as well, if any
+/// * `profiles` - Profiles of the hosts the pages are on
+/// * `manifest` - Manifest updated with every saved page
+/// * `report` - Report updated with page counts and API call metrics
+/// * `exporters` - Exporters receiving every processed page
+/// 
+/// # Errors
+/// 
+/// Returns an error if the API stays unreachable for longer than `--max-outage`;
+/// the pages not yet scraped are left parked in the frontier
+pub async fn scrape_pages(
+    run: &Run<'_>,
+    pages: Vec<PendingPage>,
+    mut crawl: Option<CrawlJob>,
+    profiles: &mut DomainProfiles,
+    manifest: &mut Manifest,
+    report: &mut RunReport,
+    exporters: &mut Exporters,
+) -> Result<(), Box<dyn Error>> {
+    let Run { api, output_dir, options, pacer, bandwidth, .. } = *run;
+    let Api { client, key: api_key, .. } = api;
+    if options.change_tracking && options.backend == Backend::Direct {
+        return Err("--change-tracking requires the FireCrawl backend".into());
+    }
+    if !options.writes_page_files() && (options.tombstones || options.download_assets || !options.formats.is_empty()) {
+        return Err("--output-format jsonl and sqlite save no page files for --tombstones, --download-assets or --formats to work on".into());
+    }
+
+    // Files edited or corrupted since they were written must not be kept as unchanged
+    let modified = manifest.verify_files(output_dir);
+    for file in &modified {
+        warn!("Modified since last scrape, fetching again: {}", file);
+    }
+    report.modified_files = modified;
+
+    let mut calls = Vec::new();
+    let mut accessibility = AccessibilityReport::new();
+    let file_claims = &FileClaims::new(manifest);
+    debug_bundle::record_site(&report.start_url, options, pages.iter().map(|page| page.url.clone()));
+    let mut queued: HashSet<String> = pages.iter().map(|page| page.url.clone()).collect();
+    let mut pending = VecDeque::from(pages);
+    let mut crawled: HashMap<String, ScrapeData> = HashMap::new();
+    let mut outage_started: Option<Instant> = None;
+    let mut probe_delay = frontier::INITIAL_PROBE_DELAY;
+    let max_outage = Duration::from_secs(options.max_outage);
+    let mut visited = 0;
+    let concurrency = options.concurrency.unwrap_or(1).max(1);
+    let mut in_flight = FuturesUnordered::new();
+    // Pages that finished while the next ones were being started, in the order they finished
+    let mut finished = VecDeque::new();
+    let mut running: Vec<PendingPage> = Vec::new();
+    let mut checkpoint = Checkpoint::new(&report.start_url);
+    let mut checkpoint_saved: Option<Instant> = None;
+    let _scraping = shutdown::scraping();
+
+    loop {
+        // Pages in flight are saved as still to be scraped, since an interruption loses them
+        if checkpoint_saved.is_none_or(|saved| saved.elapsed() >= checkpoint::CHECKPOINT_INTERVAL) {
+            checkpoint.save(output_dir, running.iter().chain(&pending).cloned().collect())?;
+            checkpoint_saved = Some(Instant::now());
+        }
+
+        let crawl_running = crawl.as_ref().is_some_and(|job| !job.is_done());
+        let limit = if let Some(max_pages) = options.max_pages.filter(|max_pages| visited + in_flight.len() + finished.len() >= *max_pages) {
+            Some(format!("--max-pages of {} reached", max_pages))
+        } else if options.stop_at_max_bytes && bandwidth.cap_reached() {
+            Some(format!("--max-bytes of {} reached", bandwidth::format_bytes(bandwidth.cap().unwrap_or_default())))
+        } else {
+            None
+        };
+
+        if shutdown::requested() && (!pending.is_empty() || crawl_running) {
+            // No more pages are started; once those in flight are saved, the checkpoint keeps the rest
+            if in_flight.is_empty() && finished.is_empty() {
+                checkpoint.save(output_dir, pending.iter().cloned().collect())?;
+                report.interrupted = true;
+                report.pages_unvisited = pending.len();
+                if let Some(job) = crawl.as_mut().filter(|job| !job.is_done()) {
+                    job.cancel(client, api_key, pacer).await;
+                    calls.extend(job.take_calls());
+                }
+                report.api_calls = MetricsSummary::from_calls(&calls);
+                accessibility.write(output_dir)?;
+                return Ok(());
+            }
+        } else if let Some(reason) = limit.filter(|_| !pending.is_empty() || crawl_running) {
+            // No more pages are started; the run stops once those in flight are done
+            if in_flight.is_empty() && finished.is_empty() {
+                park_unvisited(output_dir, &pending, &reason, report)?;
+                if let Some(job) = crawl.as_mut().filter(|job| !job.is_done()) {
+                    job.cancel(client, api_key, pacer).await;
+                    calls.extend(job.take_calls());
+                }
+                report.api_calls = MetricsSummary::from_calls(&calls);
+                accessibility.write(output_dir)?;
+                return Ok(());
+            }
+        } else if pending.is_empty() && in_flight.is_empty() && finished.is_empty() {
+            let Some(job) = crawl.as_mut().filter(|job| !job.is_done()) else {
+                break;
+            };
+            // Once the queue is empty, wait for the crawl job to finish more pages
+            let result = tokio::select! {
+                result = job.next_pages(client, api_key, max_outage, pacer) => result,
+                () = shutdown::requested_wait() => continue,
+            };
+            let job_calls = job.take_calls();
+            for call in &job_calls {
+                bandwidth.record_payload(call.response_bytes);
+            }
+            calls.extend(job_calls);
+            let results = match result {
+                Ok(results) => results,
+                Err(e) => {
+                    report.api_calls = MetricsSummary::from_calls(&calls);
+                    accessibility.write(output_dir)?;
+                    return Err(e);
+                }
+            };
+            for data in results {
+                let Some(url) = data.metadata.source_url.as_deref().and_then(|url| options.normalization.canonical_url(url).ok()) else {
+                    warn!("Skipping a crawl result without a source URL");
+                    continue;
+                };
+                if options.filter.allows(&url) && queued.insert(url.clone()) {
+                    report.pages_found += 1;
+                    pending.push_back(PendingPage { url: url.clone(), file: None });
+                    crawled.insert(url, data);
+                }
+            }
+            continue;
+        } else {
+            // Start pages up to --concurrency; while the API is unavailable, one page probes it
+            let slots = if outage_started.is_some() { 1 } else { concurrency };
+            while in_flight.len() < slots && options.max_pages.is_none_or(|max_pages| visited + in_flight.len() + finished.len() < max_pages) {
+                let Some(page) = pending.pop_front() else {
+                    break;
+                };
+                let crawled_data = crawled.remove(&page.url);
+                // Only reuse cache validators if the previously saved file is still there; with
+                // --output-format jsonl or sqlite there is none, and the manifest alone tells what changed
+                let previous = manifest
+                    .pages
+                    .get(&page.url)
+                    .filter(|entry| !options.writes_page_files() || output_dir.join(&entry.file).exists())
+                    .cloned();
+                // Pages of a crawl job were scraped at the job's own pace, and the direct
+                // backend fetches pages from the site itself and costs no credits
+                let charged = options.backend == Backend::Firecrawl && crawled_data.is_none();
+                // Detecting the generator, fetching robots.txt and the crawl delay take a while; the pages
+                // in flight go on meanwhile, and those finishing are recorded once the page is started
+                let (learned, detected) = {
+                    let mut prepare = pin!(async {
+                        let profile = match options.backend {
+                            // The generator is detected once per host, from the first page scraped there
+                            Backend::Firecrawl if crawled_data.is_none() && options.preset.unwrap_or_default() == Preset::Auto => {
+                                profiles.with_preset(client, &page.url, options.credentials_for(&page.url)).await
+                            }
+                            Backend::Firecrawl => profiles.get(&page.url),
+                            Backend::Direct => {
+                                let mut profile = profiles.with_robots(client, &page.url).await;
+                                if let Some(profile) = profile.as_deref_mut() {
+                                    profile.wait_politely().await;
+                                }
+                                profile
+                            }
+                        };
+                        profile.map_or((None, None), |profile| (profile.only_main_content, profile.preset))
+                    });
+                    loop {
+                        tokio::select! {
+                            profile = &mut prepare => break profile,
+                            Some(done) = in_flight.next(), if !in_flight.is_empty() => finished.push_back(done),
+                        }
+                    }
+                };
+                let preset = page::extraction_preset(options, detected);
+                let only_main_content = match (&crawled_data, &crawl) {
+                    (Some(_), Some(job)) => job.only_main_content(),
+                    _ => page::only_main_content(&page.url, options, learned),
+                };
+                let span = info_span!("page", url = %page.url);
+                let file_claim = file_claims.ticket();
+                running.push(page.clone());
+                in_flight.push(async move {
+                    if charged {
+                        pacer.acquire(throttle::SCRAPE_CREDITS).await;
+                    }
+                    let retrier = retrier(options);
+                    let job = PageJob {
+                        url: &page.url,
+                        only_main_content,
+                        preset,
+                        previous: previous.as_ref(),
+                        file_name: page.file.as_deref(),
+                        crawled: crawled_data,
+                    };
+                    let result = page::process_page(run, job, &file_claim, &retrier)
+                    .instrument(span.clone())
+                    .await
+                    .map_err(page_error);
+                    (page, span, only_main_content, retrier.retries(), result)
+                });
+            }
+        }
+
+        // Pages are recorded one at a time as they finish, so the manifest, report and exports stay consistent
+        let next = match finished.pop_front() {
+            Some(done) => Some(done),
+            None => in_flight.next().await,
+        };
+        let Some((page, span, only_main_content, retries, result)) = next else {
+            continue;
+        };
+        let url = page.url.clone();
+        running.retain(|running| running.url != url);
+        if retries > 0 {
+            *report.retries.entry(url.clone()).or_default() += retries;
+        }
+        // Refused pages count whatever the policy, so `pause_when.auth_broken` sees them even as failures
+        if let Ok(PageOutcome::ErrorStatus(status)) = &result {
+            if pause::is_unauthorized(i32::from(*status)) {
+                report.pages_unauthorized += 1;
+            }
+        }
+        // With `--error-pages retry` an error status fails the page, so later runs retry it like any other failure
+        let result = match result {
+            Ok(PageOutcome::ErrorStatus(status)) if options.error_pages.unwrap_or_default() == ErrorPages::Retry => {
+                Err(format!("Request failed with status {}", status).into())
+            }
+            result => result,
+        };
+
+        if let (Backend::Firecrawl, Some(profile)) = (options.backend, profiles.get(&url)) {
+            match &result {
+                Ok(PageOutcome::Saved(_)) if only_main_content != Some(false) => profile.record_main_content(false),
+                Ok(PageOutcome::EmptyContent(_)) if only_main_content != Some(false) => profile.record_main_content(true),
+                _ => {}
+            }
+        }
+
+        if let Err(e) = &result {
+            if e.is::<ApiUnavailable>() {
+                pending.push_front(page);
+                let started = *outage_started.get_or_insert_with(Instant::now);
+                // The pages still in flight come back to the queue before it is parked and the API probed
+                if !in_flight.is_empty() || !finished.is_empty() {
+                    continue;
+                }
+                if probe_delay == frontier::INITIAL_PROBE_DELAY {
+                    let path = Frontier::park(output_dir, pending.iter().cloned().collect(), &e.to_string())?;
+                    warn!("API unavailable ({}); parked {} pages in {}", e, pending.len(), path.display());
+                }
+
+                if started.elapsed() >= max_outage {
+                    report.api_calls = MetricsSummary::from_calls(&calls);
+                    accessibility.write(output_dir)?;
+                    return Err(format!(
+                        "API unavailable for more than {} seconds; {} pages remain parked in {}",
+                        options.max_outage,
+                        pending.len(),
+                        Frontier::path(output_dir).display()
+                    )
+                    .into());
+                }
+
+                info!("Probing the API again in {} seconds", probe_delay.as_secs());
+                tokio::select! {
+                    () = tokio::time::sleep(probe_delay) => {}
+                    () = shutdown::requested_wait() => {}
+                }
+                probe_delay = frontier::next_probe_delay(probe_delay);
+                continue;
+            }
+        }
+
+        if outage_started.take().is_some() {
+            info!("API reachable again; resuming");
+            Frontier::clear(output_dir)?;
+            probe_delay = frontier::INITIAL_PROBE_DELAY;
+        }
+        visited += 1;
+
+        let outcome = match &result {
+            Ok(PageOutcome::Saved(page)) => format!("saved: {}", page.entry.file),
+            Ok(PageOutcome::EmptyContent(_)) => "no markdown content".to_string(),
+            Ok(PageOutcome::Removed(_)) => "removed".to_string(),
+            Ok(PageOutcome::Skipped) => "skipped by middleware".to_string(),
+            Ok(PageOutcome::ErrorStatus(status)) => format!("skipped: status {}", status),
+            Err(e) => format!("failed: {}", e),
+        };
+        debug_bundle::record_outcome(&url, outcome);
+
+        match result {
+            Ok(PageOutcome::Saved(page)) => {
+                if page.metadata.as_ref().and_then(|metadata| metadata.status_code).is_some_and(pause::is_unauthorized) {
+                    report.pages_unauthorized += 1;
+                }
+                match page.change {
+                    PageChange::Added => report.pages_added += 1,
+                    PageChange::Changed => report.pages_changed += 1,
+                    PageChange::Unchanged => report.pages_unchanged += 1,
+                }
+                // Unchanged pages were not requested again, so FireCrawl wasn't asked about them
+                if let Some(change_status) = page.entry.change_status.filter(|_| page.metadata.is_some()) {
+                    *report.change_tracking.entry(change_status).or_default() += 1;
+                }
+                // A page saved under a new file name, e.g. after its title changed, is the same document
+                let previous_file = manifest
+                    .pages
+                    .get(&url)
+                    .filter(|previous| previous.removed.is_none() && previous.file != page.entry.file)
+                    .map(|previous| previous.file.clone());
+                if let Some(previous_file) = &previous_file {
+                    report.pages_renamed += 1;
+                    span.in_scope(|| info!("Renamed from {}", previous_file));
+                    // The old file goes, unless another page has been saved under that name since
+                    let claimed = manifest.pages.iter().any(|(other, entry)| *other != url && entry.file == *previous_file);
+                    if options.writes_page_files() && !claimed {
+                        let old_path = output_dir.join(previous_file);
+                        let _ = fs::remove_file(old_path.with_extension("meta.json"));
+                        let _ = fs::remove_file(old_path);
+                    }
+                }
+                // Pages saved for review are kept out of the exports like the rest of the output
+                if quality::is_quarantined(&page.entry.file) {
+                    report.quarantined.push(url.clone());
+                } else {
+                    span.in_scope(|| {
+                        exporters.export_page(&ExportPage {
+                            url: &url,
+                            entry: &page.entry,
+                            markdown: &page.body,
+                            metadata: page.metadata.as_ref(),
+                            change: page.change,
+                            previous_file: previous_file.as_deref(),
+                        })
+                    });
+                }
+                // Unchanged pages were not requested again in this run
+                if page.metadata.is_some() {
+                    calls.extend(page.entry.api_call);
+                    if !page.entry.warnings.is_empty() {
+                        report.pages_with_warnings += 1;
+                        report.with_warnings.push(url.clone());
+                    }
+                }
+                report.content_redactions += page.redactions;
+                report.content_replacements += page.replacements;
+                if !page.images.is_empty() {
+                    report.images_without_alt += page.images.iter().filter(|image| image.missing_alt()).count();
+                    accessibility.pages.push(PageImages {
+                        url: url.clone(),
+                        file: page.entry.file.clone(),
+                        images: page.images,
+                    });
+                }
+                // The response cached when the page last came back empty is no longer needed
+                if let Some(file) = manifest.empty_content.remove(&url).and_then(|entry| entry.response_file) {
+                    let _ = fs::remove_file(output_dir.join(file));
+                }
+                backlog::record_success(manifest, &url);
+                checkpoint.completed.insert(url.clone(), page.entry.clone());
+                manifest.pages.insert(url, page.entry);
+            }
+            Ok(PageOutcome::Removed(entry)) => {
+                report.pages_removed += 1;
+                // Pages already removed in an earlier run were passed on then
+                if manifest.pages.get(&url).is_none_or(|previous| previous.removed.is_none()) {
+                    span.in_scope(|| exporters.remove_page(&url, &entry));
+                }
+                backlog::record_success(manifest, &url);
+                checkpoint.completed.insert(url.clone(), (*entry).clone());
+                manifest.pages.insert(url, *entry);
+            }
+            Ok(PageOutcome::Skipped) => {
+                report.pages_skipped += 1;
+                continue;
+            }
+            Ok(PageOutcome::ErrorStatus(status)) => {
+                span.in_scope(|| info!("Skipped: answered with status {}", status));
+                report.pages_error_status += 1;
+                backlog::record_success(manifest, &url);
+                checkpoint.skipped.insert(url, status);
+                continue;
+            }
+            Ok(PageOutcome::EmptyContent(entry)) => {
+                calls.extend(entry.api_call);
+                report.pages_empty += 1;
+                if entry.status_code.is_some_and(pause::is_unauthorized) {
+                    report.pages_unauthorized += 1;
+                }
+                report.empty_content.push(url.clone());
+                backlog::record_success(manifest, &url);
+                checkpoint.empty_content.insert(url.clone(), entry.clone());
+                manifest.empty_content.insert(url, entry);
+                continue;
+            }
+            Err(e) => {
+                error!(parent: &span, "Error processing page: {}", e);
+                report.pages_failed += 1;
+                report.failed.push(url.clone());
+                let max_failures = options.max_failures.unwrap_or(backlog::DEFAULT_MAX_FAILURES);
+                let (failure, given_up) =
+                    span.in_scope(|| backlog::record_failure(manifest, &url, &e.to_string(), max_failures));
+                if given_up {
+                    report.permanently_failing.push(url.clone());
+                }
+                checkpoint.failed.insert(url, failure);
+                continue; // Continue with next URL on error
+            }
+        }
+        report.pages_processed += 1;
+    }
+
+    info!(
+        "{} pages added, {} changed and {} unchanged",
+        report.pages_added, report.pages_changed, report.pages_unchanged
+    );
+    if !report.change_tracking.is_empty() {
+        let counts: Vec<String> = report
+            .change_tracking
+            .iter()
+            .map(|(status, count)| format!("{} {}", count, status))
+            .collect();
+        info!("FireCrawl reports {} pages", counts.join(", "));
+    }
+
+    // A completed run has scraped any pages parked by an earlier one, unless it only took a sample
+    if options.sample.is_none() {
+        Frontier::clear(output_dir)?;
+    }
+    report.api_calls = MetricsSummary::from_calls(&calls);
+    accessibility.write(output_dir)?;
+    Ok(())
+}
+
+/// Parks the pages left in the queue of a run that stops early at a limit, for the next run.
+/// 
+/// # Errors
+/// 
+/// Returns an error if the frontier cannot be written
+fn park_unvisited(
+    output_dir: &Path,
+    pending: &VecDeque<PendingPage>,
+    reason: &str,
+    report: &mut RunReport,
+) -> Result<(), Box<dyn Error>> {
+    report.pages_unvisited = pending.len();
+    if pending.is_empty() {
+        warn!("{}", reason);
+    } else {
+        let path = Frontier::park(output_dir, pending.iter().cloned().collect(), reason)?;
+        warn!("{}; parked {} unvisited pages in {}", reason, pending.len(), path.display());
+    }
+    Ok(())
+}
+
+/// Error of a page waiting to be recorded while other pages are scraped.
+/// 
+/// It is `Send`, so a scrape can run on any thread of a multi-threaded runtime.
+type PageError = Box<dyn Error + Send + Sync>;
+
+/// Turns a page's error into a `PageError`, keeping `ApiUnavailable` so the run still waits for the API.
+fn page_error(error: Box<dyn Error>) -> PageError {
+    match error.downcast::<ApiUnavailable>() {
+        Ok(unavailable) => unavailable,
+        Err(error) => error.to_string().into(),
+    }
+}
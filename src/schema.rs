@@ -0,0 +1,66 @@
+//! Validation of generated frontmatter against a user-provided JSON Schema.
+//!
+//! Static site generators and content pipelines often expect every page to
+//! carry certain frontmatter fields, or fields of a certain shape. With
+//! `--frontmatter-schema` the frontmatter of each page is converted to JSON
+//! (see `frontmatter::to_json`) and checked against the schema before the page
+//! is written. By default a page that doesn't match fails and is not saved;
+//! with `--schema-violations warn` it is saved and the violations are recorded
+//! as warnings in the manifest.
+
+use jsonschema::Validator;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::frontmatter;
+
+/// A compiled JSON Schema that frontmatter is checked against.
+#[derive(Debug, Clone)]
+pub struct FrontmatterSchema {
+    /// File the schema was read from, for messages
+    path: PathBuf,
+
+    /// The compiled schema
+    validator: Validator,
+}
+
+impl FrontmatterSchema {
+    /// Reads and compiles the JSON Schema at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, isn't JSON or isn't a valid schema
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read schema {}: {}", path, e))?;
+        let schema: serde_json::Value =
+            serde_json::from_str(&text).map_err(|e| format!("Schema {} is not valid JSON: {}", path, e))?;
+        let validator =
+            jsonschema::validator_for(&schema).map_err(|e| format!("Schema {} is not a valid JSON Schema: {}", path, e))?;
+        Ok(FrontmatterSchema {
+            path: PathBuf::from(path),
+            validator,
+        })
+    }
+
+    /// Returns the file the schema was read from.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Checks a frontmatter block, returning a description of each way it
+    /// doesn't match the schema, such as `/title: "Intro" is shorter than 10 characters`.
+    pub fn violations(&self, frontmatter: &str) -> Vec<String> {
+        let instance = frontmatter::to_json(frontmatter);
+        self.validator
+            .iter_errors(&instance)
+            .map(|error| {
+                let path = error.instance_path().to_string();
+                if path.is_empty() {
+                    error.to_string()
+                } else {
+                    format!("{}: {}", path, error)
+                }
+            })
+            .collect()
+    }
+}
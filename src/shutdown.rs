@@ -0,0 +1,89 @@
+//! Stopping a run cleanly on Ctrl-C or SIGTERM.
+//!
+//! Killing the scraper part way could leave a page file half written and the
+//! checkpoint up to ten seconds behind. Once pages are being scraped, the first
+//! Ctrl-C (SIGINT) or SIGTERM asks the run to stop instead: no more pages are
+//! started, the pages in flight are finished and saved, and the checkpoint is
+//! saved with the pages still to be scraped, so `scraper resume` continues
+//! exactly where the run stopped. A second signal, or one arriving while no
+//! pages are being scraped (during discovery, say), ends the process at once.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// Exit status of a run stopped by a signal, as shells report an interrupted command.
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// Whether a signal asked the running scrape to stop.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Number of scrapes in progress that stop cleanly when asked.
+static SCRAPING: AtomicUsize = AtomicUsize::new(0);
+
+/// Wakes the tasks waiting in `requested_wait` when a stop is requested.
+static STOP: Notify = Notify::const_new();
+
+/// A scrape in progress, during which a signal stops the run cleanly.
+///
+/// Created with `scraping`; the process ends at once on a signal again when it is dropped.
+pub struct Scraping(());
+
+impl Drop for Scraping {
+    fn drop(&mut self) {
+        SCRAPING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Installs the handler of Ctrl-C, and SIGTERM on Unix, for the command line.
+///
+/// Applications embedding the scraper keep their own signal handling; their
+/// runs are never asked to stop.
+pub fn install() {
+    tokio::spawn(async {
+        #[cfg(unix)]
+        let Ok(mut terminate) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) else {
+            return;
+        };
+        loop {
+            #[cfg(unix)]
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => if result.is_err() { return; },
+                _ = terminate.recv() => {}
+            }
+            #[cfg(not(unix))]
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+
+            if SCRAPING.load(Ordering::SeqCst) == 0 || REQUESTED.swap(true, Ordering::SeqCst) {
+                warn!("Interrupted");
+                std::process::exit(EXIT_INTERRUPTED.into());
+            }
+            warn!("Stopping once the pages in flight are saved; press Ctrl-C again to quit at once");
+            STOP.notify_waiters();
+        }
+    });
+}
+
+/// Marks a scrape as in progress until the returned guard is dropped.
+pub fn scraping() -> Scraping {
+    SCRAPING.fetch_add(1, Ordering::SeqCst);
+    Scraping(())
+}
+
+/// Returns whether a signal asked the running scrape to stop.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Waits until a signal asks the running scrape to stop.
+pub async fn requested_wait() {
+    loop {
+        let notified = STOP.notified();
+        if requested() {
+            return;
+        }
+        notified.await;
+    }
+}
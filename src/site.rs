@@ -0,0 +1,866 @@
+//! Scraping a site, or pages already known, from start to finish.
+//!
+//! A run takes the lock of its output directory, discovers the site's pages
+//! (unless they are given), puts the pages parked or failed in earlier runs
+//! first, has the scheduler scrape them, and writes the manifest, exports and
+//! run report at the end, whether or not the run succeeds.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::Utc;
+use clap::Parser;
+use reqwest::Client;
+use tracing::{info, warn};
+
+use crate::api::Api;
+use crate::assets::AssetFetcher;
+use crate::bandwidth::{self, Bandwidth};
+use crate::checkpoint::Checkpoint;
+use crate::cli::{Backend, Cli, Command, ScrapeOptions};
+use crate::config::{self, Config};
+use crate::crawl::CrawlJob;
+use crate::discovery::{self, DiscoveryContext, DiscoveryKind, DiscoverySource, MapSource};
+use crate::domain_profile::DomainProfiles;
+use crate::export::Exporters;
+use crate::frontier::{Frontier, PendingPage};
+use crate::lock::{Acquired, RunLock};
+use crate::manifest::Manifest;
+use crate::output::{self, DirNaming, PageVariables};
+use crate::page;
+use crate::platform::SiteHosts;
+use crate::preset::Preset;
+use crate::report::{PageCounts, RunReport};
+use crate::retry::{self, Retrier};
+use crate::scheduler::{self, Run};
+use crate::throttle::{self, CreditPacer};
+use crate::{backlog, compliance, input_url, mirror, page_type, urls};
+
+/// Scrapes documentation from a website and saves it as markdown files.
+/// 
+/// Downloads content from all pages on the same domain as the start URL,
+/// converts them to markdown format, and saves them with YAML frontmatter.
+/// 
+/// # Arguments
+/// 
+/// * `client` - The HTTP client
+/// * `api_url` - The FireCrawl API endpoint
+/// * `api_key` - The API authentication key
+/// * `start_url` - The URL to start scraping from; without a scheme, HTTPS and then HTTP are tried
+/// * `options` - Options controlling how pages are saved
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the number of pages that failed or were saved with
+/// warnings; failing pages don't fail the operation
+/// 
+/// # Errors
+/// 
+/// Returns an error if:
+/// - Directory creation fails
+/// - The compliance check finds the site disallows scraping and `--acknowledge` was not given
+/// - Link extraction fails
+/// - API requests fail
+/// - File writing fails
+/// 
+/// # Examples
+/// 
+/// ```text
+/// scrape_documentation(&client, &api_url, &api_key, "https://docs.example.com", &options).await?;
+/// // Creates markdown files in a directory named after the domain, below `--output-dir` if given
+/// ```
+pub async fn scrape_documentation(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    start_url: &str,
+    options: &ScrapeOptions,
+) -> Result<PageCounts, Box<dyn Error>> {
+    let start_url = input_url::resolve(client, start_url, options).await?;
+    let output_dir = site_output_dir(&start_url, options)?;
+    let pacer = credit_pacer(options, api_key)?;
+    let api = Api { client, url: api_url, key: api_key };
+    scrape_site(&api, &start_url, None, &output_dir, options, &pacer).await
+}
+
+/// Scrapes exactly the pages listed in a file, for `--from-file`.
+/// 
+/// URLs listed without a scheme are resolved like a start URL given without
+/// one. The listed URLs are rewritten and deduplicated like discovered ones, then
+/// grouped by the output directory they belong in, so pages of several sites
+/// each end up in their site's directory. Every group is scraped without link
+/// discovery, as if its first URL was the start URL, and all groups share
+/// one credit pacer.
+/// 
+/// # Arguments
+/// 
+/// * `client` - The HTTP client
+/// * `api_url` - The FireCrawl API endpoint
+/// * `api_key` - The API authentication key
+/// * `list` - File with one URL per line, or `-` to read the URLs from standard input
+/// * `options` - Options controlling how pages are saved
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the number of pages of all groups that failed or
+/// were saved with warnings
+/// 
+/// # Errors
+/// 
+/// Returns an error if:
+/// - The list cannot be read, is empty or contains a URL that isn't HTTP(S)
+/// - A URL listed without a scheme answers neither over HTTPS nor over HTTP
+/// - Scraping one of the groups fails, in the same cases as `scrape_documentation`
+/// 
+/// # Examples
+/// 
+/// ```text
+/// scrape_url_list(&client, &api_url, &api_key, Path::new("urls.txt"), &options).await?;
+/// ```
+pub async fn scrape_url_list(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    list: &Path,
+    options: &ScrapeOptions,
+) -> Result<PageCounts, Box<dyn Error>> {
+    let from_stdin = list == Path::new("-");
+    let content = if from_stdin {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Failed to read URLs from standard input: {}", e))?;
+        content
+    } else {
+        fs::read_to_string(list).map_err(|e| format!("Failed to read {}: {}", list.display(), e))?
+    };
+
+    let mut seen = HashSet::new();
+    let mut groups: Vec<(PathBuf, Vec<String>)> = Vec::new();
+    let mut sites: Vec<SiteHosts> = Vec::new();
+    for url in discovery::parse_url_list(&content) {
+        let url = input_url::resolve(client, &url, options).await?;
+        let url = urls::rewrite_url(&options.rewrite_rules, &url);
+        let url = options.normalization.canonical_url(&url)?;
+        // URLs on another host of a documentation platform site, or on one of its
+        // subdomains, join the site's first listed URL
+        let parsed = url::Url::parse(&url)?;
+        let (url, site_url) = match sites.iter().find_map(|site| Some((site.unify(parsed.clone())?, site.start_url()))) {
+            Some((unified, site_url)) => (unified.to_string(), site_url.to_string()),
+            None => {
+                sites.push(SiteHosts::new(&url, options)?);
+                (url.clone(), url)
+            }
+        };
+        if !seen.insert(url.clone()) {
+            continue;
+        }
+
+        let dir = if options.no_domain_dir {
+            PathBuf::new()
+        } else {
+            output::domain_dir_name(&site_url, options.dir_naming, options.dir_template.as_deref())?
+        };
+        match groups.iter_mut().find(|(group_dir, _)| *group_dir == dir) {
+            Some((_, group)) => group.push(url),
+            None => groups.push((dir, vec![url])),
+        }
+    }
+    if groups.is_empty() {
+        let source = if from_stdin { "standard input".to_string() } else { list.display().to_string() };
+        return Err(format!("No URLs listed in {}", source).into());
+    }
+
+    let api = Api { client, url: api_url, key: api_key };
+    let pacer = credit_pacer(options, api_key)?;
+    let mut counts = PageCounts::default();
+    for (_, pages) in groups {
+        let start_url = pages[0].clone();
+        let output_dir = site_output_dir(&start_url, options)?;
+        counts.add(scrape_site(&api, &start_url, Some(pages), &output_dir, options, &pacer).await?);
+        if counts.interrupted {
+            break;
+        }
+    }
+    Ok(counts)
+}
+
+/// Lists the URLs of a site with FireCrawl's map endpoint.
+/// 
+/// The URLs are treated like those of `--discover map`: rewrite rules are
+/// applied, and they are limited to the site's hosts and to those allowed by
+/// `--include` and `--exclude`.
+/// 
+/// # Arguments
+/// 
+/// * `client` - The HTTP client
+/// * `api_url` - The FireCrawl API endpoint
+/// * `api_key` - The API authentication key
+/// * `start_url` - The URL of the site to map
+/// * `options` - Options with the filters and rewrite rules to apply
+/// 
+/// # Returns
+/// 
+/// The URLs found, deduplicated and in the order chosen with `--order`
+/// 
+/// # Errors
+/// 
+/// Returns an error if the backend isn't FireCrawl or the map request fails
+pub async fn map_site(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    start_url: &str,
+    options: &ScrapeOptions,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let pacer = credit_pacer(options, api_key)?;
+    let bandwidth = Bandwidth::new(None, options.max_response_size.unwrap_or(bandwidth::DEFAULT_MAX_RESPONSE_SIZE));
+    let hosts = SiteHosts::new(start_url, options)?;
+    let context = DiscoveryContext {
+        client,
+        api_url,
+        api_key,
+        backend: options.backend,
+        start_url,
+        hosts: &hosts,
+        output_dir: Path::new(""),
+        auth: options.credentials_for(start_url),
+        pacer: &pacer,
+        retrier: &retrier(options),
+        bandwidth: &bandwidth,
+        sitemaps: &[],
+    };
+    let sources: Vec<Box<dyn DiscoverySource>> = vec![Box::new(MapSource {
+        search: options.seed_query.clone(),
+    })];
+    let mut urls = discovery::discover(&sources, &context, &options.rewrite_rules, &options.normalization, &options.filter).await?;
+    discovery::order_urls(&mut urls, options.order.unwrap_or_default());
+    info!("Found {} URLs", urls.len());
+    Ok(urls)
+}
+
+/// Returns the directory a site is saved in, creating it unless this is a dry run.
+/// 
+/// The directory is named after the site below `--output-dir`, or is
+/// `--output-dir` itself with `--no-domain-dir`.
+/// 
+/// # Errors
+/// 
+/// Returns an error if the directory name cannot be derived from the URL or
+/// the directory cannot be created
+fn site_output_dir(start_url: &str, options: &ScrapeOptions) -> Result<PathBuf, Box<dyn Error>> {
+    let root = options.output_dir.as_deref().unwrap_or(Path::new(""));
+    let output_dir = if options.dry_run {
+        let dir = if options.no_domain_dir {
+            root.to_path_buf()
+        } else {
+            root.join(output::domain_dir_name(start_url, options.dir_naming, options.dir_template.as_deref())?)
+        };
+        info!("Dry run: nothing will be scraped or written");
+        dir
+    } else if options.no_domain_dir {
+        fs::create_dir_all(root)
+            .map_err(|e| format!("Failed to create output directory {}: {}", root.display(), e))?;
+        root.to_path_buf()
+    } else {
+        create_domain_directory(start_url, root, options.dir_naming, options.dir_template.as_deref())
+            .map_err(|e| format!("Failed to create output directory: {}", e))?
+    };
+    if options.dry_run {
+        info!("Would save files to: {}", output_dir.display());
+    } else {
+        info!("Saving files to: {}", output_dir.display());
+    }
+    Ok(output_dir)
+}
+
+/// Scrapes documentation from a website into the given output directory.
+/// 
+/// This is `scrape_documentation` without choosing the output directory, so
+/// callers running several sites (see `workspace`) can place each site and
+/// share one credit pacer between them. The run report is written to the
+/// state directory of `output_dir` whether or not the run succeeds, and the
+/// profiles of the scraped hosts are saved after every run that isn't a dry run.
+/// 
+/// # Arguments
+/// 
+/// * `api` - The FireCrawl API the run makes its requests to
+/// * `start_url` - The URL to start scraping from
+/// * `pages` - Pages to scrape instead of discovering them from the start URL
+/// * `output_dir` - Directory to save the markdown files; must exist
+/// * `options` - Options controlling how pages are saved
+/// * `pacer` - Pacer every API call is made through
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the number of pages that failed or were saved with warnings
+/// 
+/// # Errors
+/// 
+/// Returns an error in the same cases as `scrape_documentation`, except
+/// directory creation
+pub async fn scrape_site(
+    api: &Api<'_>,
+    start_url: &str,
+    pages: Option<Vec<String>>,
+    output_dir: &Path,
+    options: &ScrapeOptions,
+    pacer: &CreditPacer,
+) -> Result<PageCounts, Box<dyn Error>> {
+    let Api { client, url: api_url, key: api_key } = *api;
+    // Dry runs write nothing and leave the directory to other runs
+    let _lock = if options.dry_run {
+        None
+    } else {
+        match RunLock::acquire(output_dir, start_url, options.if_locked).await? {
+            Acquired::Locked(lock) => Some(lock),
+            Acquired::Observed(pages) => return Ok(pages),
+        }
+    };
+    let mut report = RunReport::new(start_url);
+    let mut manifest = Manifest::load(output_dir)?;
+    // Starting over after an interruption still keeps track of the pages the interrupted run saved
+    if let Some(checkpoint) = Checkpoint::load(output_dir)? {
+        info!(
+            "Recording the {} pages saved by an interrupted run; `scraper resume` would have continued it instead",
+            checkpoint.completed.len()
+        );
+        checkpoint.restore(&mut manifest);
+    }
+    let mut profiles = DomainProfiles::open(options);
+    let bandwidth = Bandwidth::new(
+        options.max_bytes,
+        options.max_response_size.unwrap_or(bandwidth::DEFAULT_MAX_RESPONSE_SIZE),
+    );
+
+    if options.compliance_check {
+        let robots_txt = profiles
+            .with_robots(client, start_url)
+            .await
+            .and_then(|profile| profile.robots_txt().map(str::to_string));
+        let mut compliance = compliance::check_compliance(client, start_url, robots_txt.as_deref()).await?;
+        for finding in &compliance.findings {
+            if finding.blocking {
+                warn!("Disallowed ({}): {}", finding.source, finding.message);
+            } else {
+                info!("Note ({}): {}", finding.source, finding.message);
+            }
+        }
+
+        let proceed = compliance.decide(options.acknowledge);
+        if proceed && compliance.disallowed {
+            warn!("Proceeding despite compliance findings (--acknowledge given)");
+        }
+        report.compliance = Some(compliance);
+        if !proceed {
+            if !options.dry_run {
+                report.write(output_dir)?;
+            }
+            return Err(
+                "The site appears to disallow scraping; review the findings above and pass --acknowledge to proceed anyway"
+                    .into(),
+            );
+        }
+    }
+
+    let mut crawl = None;
+    let mut doc_urls = match pages {
+        Some(pages) => {
+            info!("Scraping {} listed pages", pages.len());
+            pages
+        }
+        // The crawl job finds the pages itself; they are scraped as its results come in
+        None if options.crawl_job => {
+            if options.backend == Backend::Direct {
+                return Err("--crawl-job requires the FireCrawl backend".into());
+            }
+            let learned = profiles.get(start_url).and_then(|profile| profile.only_main_content);
+            let only_main_content = learned.filter(|only| !only);
+            let detected = match options.preset.unwrap_or_default() {
+                Preset::Auto => profiles
+                    .with_preset(client, start_url, options.credentials_for(start_url))
+                    .await
+                    .and_then(|profile| profile.preset),
+                _ => None,
+            };
+            let preset = page::extraction_preset(options, detected);
+            crawl = Some(
+                CrawlJob::start(client, api_url, api_key, start_url, options, only_main_content, preset, pacer).await?,
+            );
+            Vec::new()
+        }
+        None => {
+            // Sitemap discovery reads the sitemaps listed in robots.txt
+            let sitemaps = if options.discovery.contains(&DiscoveryKind::Sitemap) {
+                let profile = profiles.with_robots(client, start_url).await;
+                profile.map(|profile| profile.sitemaps.clone()).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            let hosts = SiteHosts::new(start_url, options)?;
+            if hosts.is_grouped() {
+                info!("Treating the other hosts of this documentation platform as part of the site");
+            }
+            if hosts.has_subdomains() {
+                info!("Following links to subdomains of {}", start_url);
+            }
+            let context = DiscoveryContext {
+                client,
+                api_url,
+                api_key,
+                backend: options.backend,
+                start_url,
+                hosts: &hosts,
+                output_dir,
+                auth: options.credentials_for(start_url),
+                pacer,
+                retrier: &retrier(options),
+                bandwidth: &bandwidth,
+                sitemaps: &sitemaps,
+            };
+            let sources =
+                discovery::sources(&options.discovery, options.url_file.as_deref(), options.seed_query.as_deref());
+            let doc_urls = discovery::discover(&sources, &context, &options.rewrite_rules, &options.normalization, &options.filter).await?;
+            info!("Found {} documentation pages", doc_urls.len());
+            doc_urls
+        }
+    };
+    report.pages_found = doc_urls.len();
+    discovery::order_urls(&mut doc_urls, options.order.unwrap_or_default());
+    if let Some(size) = options.sample {
+        doc_urls = discovery::sample_urls(&doc_urls, size);
+        report.pages_sampled = doc_urls.len();
+        info!("Sampling {} of the {} pages found across the site's sections", doc_urls.len(), report.pages_found);
+    }
+
+    let mut pages: Vec<PendingPage> = doc_urls
+        .into_iter()
+        .map(|url| PendingPage { url, file: None })
+        .collect();
+
+    // Pages parked by an earlier run that gave up waiting for the API or stopped at --max-bytes
+    // or --max-pages, filtered again in case this run's filters are different. They are scraped
+    // first, so runs limited by --max-pages work through the whole site over time. A sample
+    // leaves them to the full run.
+    let mut parked = Frontier::load(output_dir)?.pending;
+    parked.retain(|page| options.sample.is_none() && options.filter.allows(&page.url));
+    if !parked.is_empty() {
+        info!("Picking up {} pages parked by a previous run", parked.len());
+        let known: HashSet<String> = parked.iter().map(|page| page.url.clone()).collect();
+        pages.retain(|page| !known.contains(&page.url));
+        pages.splice(0..0, parked);
+        report.pages_found = pages.len();
+    }
+    // Pages that failed in earlier runs come before all others, as far as their backoff allows
+    if options.sample.is_none() {
+        let backlog = backlog::prioritize(&mut pages, &mut manifest, options, Utc::now());
+        report.failures_retried = backlog.retried;
+        report.failures_deferred = backlog.deferred;
+    }
+
+    if options.dry_run {
+        print_dry_run(&pages, options, pacer)?;
+        return Ok(PageCounts::default());
+    }
+
+    let mut exporters = Exporters::open(&options.export_formats(), output_dir, &options.slug_styles)?;
+    let assets = options.download_assets.then(|| AssetFetcher::new(client, output_dir, options));
+    let sampled: Vec<String> = pages.iter().filter(|_| options.sample.is_some()).map(|page| page.url.clone()).collect();
+    let run = Run {
+        api: *api,
+        output_dir,
+        options,
+        pacer,
+        bandwidth: &bandwidth,
+        assets: assets.as_ref(),
+    };
+    let result = scheduler::scrape_pages(&run, pages, crawl, &mut profiles, &mut manifest, &mut report, &mut exporters).await;
+    report.bandwidth = Some(bandwidth.summary());
+    if let Some(assets) = &assets {
+        report.assets = Some(assets.finish(output_dir)?);
+    }
+    info!("Downloaded {}", bandwidth::format_bytes(bandwidth.total()));
+
+    profiles.save();
+    manifest.update_reading_order();
+    manifest.save(output_dir)?;
+    if options.sample.is_some() {
+        print_sample_review(&sampled, &manifest, &report, output_dir);
+    }
+    if report.interrupted {
+        warn!(
+            "Interrupted with {} pages to go; continue with: scraper resume {}",
+            report.pages_unvisited,
+            output_dir.display()
+        );
+    } else {
+        Checkpoint::clear(output_dir)?;
+    }
+    if result.is_ok() && !report.interrupted {
+        exporters.finish()?;
+        report.finished_at = Some(Utc::now());
+    }
+    let report_path = report.write(output_dir)?;
+    info!("Report written to: {}", report_path.display());
+
+    result.map(|()| report.page_counts())
+}
+
+/// Prints the pages a run would scrape, for `--dry-run`.
+/// 
+/// Lists every page with the file it would be saved to and estimates the
+/// requests the run would make. Pages named after their title can only be
+/// named once scraped, so their file is shown as `<title>.md`; path templates
+/// are rendered without the title and language.
+/// 
+/// # Arguments
+/// 
+/// * `pages` - The pages the run would scrape
+/// * `options` - Options controlling how pages are saved
+/// * `pacer` - The pacer discovery made its API calls through
+/// 
+/// # Errors
+/// 
+/// Returns an error if a path template cannot be rendered for a page
+fn print_dry_run(pages: &[PendingPage], options: &ScrapeOptions, pacer: &CreditPacer) -> Result<(), Box<dyn Error>> {
+    let (pages, unvisited) = pages.split_at(options.max_pages.map_or(pages.len(), |max_pages| max_pages.min(pages.len())));
+    for page in pages {
+        let file = match (&page.file, &options.path_template) {
+            (Some(file), _) => file.clone(),
+            (None, Some(template)) => output::page_path(
+                template,
+                &PageVariables {
+                    url: &page.url,
+                    title: None,
+                    language: None,
+                    page_type: page_type::classify(&page.url, ""),
+                    scraped_at: Utc::now(),
+                },
+            )?,
+            (None, None) => "<title>.md".to_string(),
+        };
+        println!("{} -> {}", page.url, file);
+    }
+
+    println!("Would scrape {} pages", pages.len());
+    if !unvisited.is_empty() {
+        println!("Would leave {} pages unvisited because of --max-pages", unvisited.len());
+    }
+    match options.backend {
+        Backend::Firecrawl => println!(
+            "Estimated {} scrape requests ({} API credits); discovery used {} credits",
+            pages.len(),
+            pages.len() as u64 * u64::from(throttle::SCRAPE_CREDITS),
+            pacer.credits_spent()
+        ),
+        Backend::Direct => println!("Estimated {} HTTP requests to the site; no API credits are used", pages.len()),
+    }
+    Ok(())
+}
+
+/// Prints the quality of the sampled pages, for `--sample`, so the extraction can be checked before the full run.
+/// 
+/// Pages with quality issues or warnings are listed with them; pages that
+/// weren't saved in this run are listed as such.
+fn print_sample_review(urls: &[String], manifest: &Manifest, report: &RunReport, output_dir: &Path) {
+    println!("Sampled pages, saved in {}:", output_dir.display());
+    for url in urls {
+        let saved = !report.failed.contains(url) && !report.empty_content.contains(url);
+        let Some(entry) = manifest.pages.get(url).filter(|entry| saved && entry.removed.is_none()) else {
+            println!("  not saved  {}", url);
+            continue;
+        };
+        let mut notes: Vec<String> = entry.warnings.clone();
+        let score = match &entry.quality {
+            Some(quality) => {
+                if !quality.issues.is_empty() {
+                    notes.insert(0, format!("{:?}", quality.issues));
+                }
+                format!("{:.2}", quality.score)
+            }
+            None => "-".to_string(),
+        };
+        if notes.is_empty() {
+            println!("  {:>9}  {}", score, entry.file);
+        } else {
+            println!("  {:>9}  {} ({})", score, entry.file, notes.join("; "));
+        }
+    }
+    println!("Check these pages, then run again without --sample to scrape the whole site");
+}
+
+/// Re-scrapes the pages of an existing mirror in place.
+/// 
+/// Reads the `url` field from the frontmatter of every markdown file in `dir`
+/// and scrapes exactly those pages again, overwriting each file at its current
+/// path. No link discovery is performed, so hand-curated subsets of a site can
+/// be kept up to date.
+/// 
+/// # Arguments
+/// 
+/// * `client` - The HTTP client
+/// * `api_url` - The FireCrawl API endpoint
+/// * `api_key` - The API authentication key
+/// * `dir` - The mirror directory to refresh
+/// * `options` - Options controlling how pages are saved
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the number of pages that failed or were saved with warnings
+/// 
+/// # Errors
+/// 
+/// Returns an error if:
+/// - The directory or its files cannot be read
+/// - The manifest or report cannot be written
+/// 
+/// # Examples
+/// 
+/// ```text
+/// refresh_mirror(&client, &api_url, &api_key, Path::new("docs_example_com"), &options).await?;
+/// ```
+pub async fn refresh_mirror(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    dir: &Path,
+    options: &ScrapeOptions,
+) -> Result<PageCounts, Box<dyn Error>> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()).into());
+    }
+    let _lock = if options.dry_run {
+        None
+    } else {
+        match RunLock::acquire(dir, &dir.display().to_string(), options.if_locked).await? {
+            Acquired::Locked(lock) => Some(lock),
+            Acquired::Observed(pages) => return Ok(pages),
+        }
+    };
+
+    let mut report = RunReport::new(&dir.display().to_string());
+    let manifest = Manifest::load(dir)?;
+
+    let pages: Vec<PendingPage> = mirror::mirrored_pages(dir)?
+        .into_iter()
+        .map(|page| PendingPage {
+            url: page.url,
+            file: Some(page.file),
+        })
+        .collect();
+    info!("Refreshing {} pages in {}", pages.len(), dir.display());
+    report.pages_found = pages.len();
+
+    scrape_known_pages(&Api { client, url: api_url, key: api_key }, dir, pages, manifest, report, options).await
+}
+
+/// Continues a run that was interrupted, from the checkpoint it left in `dir`.
+/// 
+/// The pages the run had finished are recorded in the manifest, and only the
+/// pages it still had to scrape are scraped, without discovering pages again.
+/// 
+/// # Arguments
+/// 
+/// * `client` - The HTTP client
+/// * `api_url` - The FireCrawl API endpoint
+/// * `api_key` - The API authentication key
+/// * `dir` - The output directory of the interrupted run
+/// * `options` - Options of the interrupted run, see `checkpoint_options`
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the number of pages that failed or were saved with warnings
+/// 
+/// # Errors
+/// 
+/// Returns an error if:
+/// - There is no checkpoint in `dir`, or it cannot be read
+/// - The manifest or report cannot be written
+pub async fn resume_run(
+    client: &Client,
+    api_url: &str,
+    api_key: &str,
+    dir: &Path,
+    options: &ScrapeOptions,
+) -> Result<PageCounts, Box<dyn Error>> {
+    let _lock = if options.dry_run {
+        None
+    } else {
+        match RunLock::acquire(dir, &dir.display().to_string(), options.if_locked).await? {
+            Acquired::Locked(lock) => Some(lock),
+            Acquired::Observed(pages) => return Ok(pages),
+        }
+    };
+    // Read again under the lock, in case the run was still going when the command started
+    let checkpoint = Checkpoint::load(dir)?.ok_or_else(|| format!("No interrupted run to resume in {}", dir.display()))?;
+
+    let mut report = RunReport::new(&checkpoint.start_url);
+    let mut manifest = Manifest::load(dir)?;
+    checkpoint.restore(&mut manifest);
+    info!(
+        "Resuming the run from {}: {} pages done, {} to go",
+        checkpoint.start_url,
+        checkpoint.completed.len() + checkpoint.empty_content.len() + checkpoint.failed.len() + checkpoint.skipped.len(),
+        checkpoint.pending.len()
+    );
+    report.pages_found = checkpoint.pending.len();
+
+    scrape_known_pages(&Api { client, url: api_url, key: api_key }, dir, checkpoint.pending, manifest, report, options).await
+}
+
+/// Returns the scrape options of the command line a checkpoint was saved by.
+/// 
+/// # Errors
+/// 
+/// Returns an error if the command isn't a scrape, `refresh` or `run`, or its
+/// options or the configuration file can no longer be read
+pub fn checkpoint_options(command: &[String]) -> Result<ScrapeOptions, Box<dyn Error>> {
+    if command.is_empty() {
+        return Err("The checkpoint was saved by an application embedding the scraper and can't be resumed here".into());
+    }
+    let cli = Cli::try_parse_from(std::iter::once("scraper").chain(command.iter().map(String::as_str)))?;
+    let mut options = match cli.command {
+        None => cli.options,
+        Some(Command::Refresh { options, .. }) => *options,
+        Some(Command::Run { name, args }) => return Ok(config::site_options(&name, &args)?.1),
+        Some(_) => return Err(format!("Runs of `scraper {}` can't be resumed; run it again", command.join(" ")).into()),
+    };
+    Config::load(options.config.as_deref())?.apply(&mut options)?;
+    Ok(options)
+}
+
+/// Scrapes pages that are already known into an existing directory, for `refresh` and `resume`.
+/// 
+/// # Errors
+/// 
+/// Returns an error if the manifest, report or exports cannot be written, or
+/// in the same cases as `scheduler::scrape_pages`
+async fn scrape_known_pages(
+    api: &Api<'_>,
+    dir: &Path,
+    pages: Vec<PendingPage>,
+    mut manifest: Manifest,
+    mut report: RunReport,
+    options: &ScrapeOptions,
+) -> Result<PageCounts, Box<dyn Error>> {
+    let pacer = credit_pacer(options, api.key)?;
+    if options.dry_run {
+        print_dry_run(&pages, options, &pacer)?;
+        return Ok(PageCounts::default());
+    }
+    let mut profiles = DomainProfiles::open(options);
+    let mut exporters = Exporters::open(&options.export_formats(), dir, &options.slug_styles)?;
+    let bandwidth = Bandwidth::new(
+        options.max_bytes,
+        options.max_response_size.unwrap_or(bandwidth::DEFAULT_MAX_RESPONSE_SIZE),
+    );
+    let assets = options.download_assets.then(|| AssetFetcher::new(api.client, dir, options));
+    let run = Run {
+        api: *api,
+        output_dir: dir,
+        options,
+        pacer: &pacer,
+        bandwidth: &bandwidth,
+        assets: assets.as_ref(),
+    };
+    let result = scheduler::scrape_pages(&run, pages, None, &mut profiles, &mut manifest, &mut report, &mut exporters).await;
+    report.bandwidth = Some(bandwidth.summary());
+    if let Some(assets) = &assets {
+        report.assets = Some(assets.finish(dir)?);
+    }
+    info!("Downloaded {}", bandwidth::format_bytes(bandwidth.total()));
+
+    profiles.save();
+    manifest.update_reading_order();
+    manifest.save(dir)?;
+    if report.interrupted {
+        warn!(
+            "Interrupted with {} pages to go; continue with: scraper resume {}",
+            report.pages_unvisited,
+            dir.display()
+        );
+    } else {
+        Checkpoint::clear(dir)?;
+    }
+    if result.is_ok() && !report.interrupted {
+        exporters.finish()?;
+        report.finished_at = Some(Utc::now());
+    }
+    let report_path = report.write(dir)?;
+    info!("Report written to: {}", report_path.display());
+
+    result.map(|()| report.page_counts())
+}
+
+/// Creates a directory based on the domain name from a URL.
+/// 
+/// Builds the directory name from the URL's host according to the naming
+/// strategy (see `output::domain_dir_name`) and creates the directory below
+/// `root` if it doesn't exist.
+/// 
+/// # Arguments
+/// 
+/// * `url` - The URL to extract the domain from
+/// * `root` - The directory to create it in; an empty path for the current directory
+/// * `naming` - The directory naming strategy
+/// * `template` - Directory name template used by `DirNaming::CustomTemplate`
+/// 
+/// # Returns
+/// 
+/// A `Result` containing the `PathBuf` of the created directory
+/// 
+/// # Errors
+/// 
+/// Returns an error if:
+/// - URL parsing fails
+/// - The directory name cannot be built
+/// - Directory creation fails
+/// 
+/// # Examples
+/// 
+/// ```text
+/// let path = create_domain_directory("https://docs.example.com/page", Path::new(""), DirNaming::Flat, None)?;
+/// // Creates directory "docs_example_com" and returns its PathBuf
+/// 
+/// let path = create_domain_directory("https://docs.example.com/page", Path::new("mirrors"), DirNaming::Domain, None)?;
+/// // Creates directory "mirrors/example.com/docs" and returns its PathBuf
+/// ```
+fn create_domain_directory(
+    url: &str,
+    root: &Path,
+    naming: DirNaming,
+    template: Option<&str>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = root.join(output::domain_dir_name(url, naming, template)?);
+    fs::create_dir_all(&path)?;
+    
+    Ok(path)
+}
+
+/// Creates the credit pacer of a run, with the request limits of `--rps` and `--delay-ms`,
+/// sharing its budget with other processes with `--shared-budget`.
+/// 
+/// # Errors
+/// 
+/// Returns an error if `--shared-budget` is given without any of `--credits-per-minute`,
+/// `--rps` and `--delay-ms`, or the shared budget's ledger directory cannot be created
+pub fn credit_pacer(options: &ScrapeOptions, api_key: &str) -> Result<CreditPacer, Box<dyn Error>> {
+    let pacer = CreditPacer::new(options.credits_per_minute)
+        .limit_requests(options.rps, Duration::from_millis(options.delay_ms.unwrap_or_default()));
+    if options.shared_budget {
+        pacer.share(api_key)
+    } else {
+        Ok(pacer)
+    }
+}
+
+/// Creates a retrier for the FireCrawl requests of one page, with `--retries` and `--retry-delay-ms`.
+pub fn retrier(options: &ScrapeOptions) -> Retrier {
+    Retrier::new(
+        options.retries.unwrap_or(retry::DEFAULT_RETRIES),
+        options.retry_delay_ms.map_or(retry::DEFAULT_RETRY_DELAY, Duration::from_millis),
+    )
+}
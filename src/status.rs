@@ -0,0 +1,381 @@
+//! Status files for dashboards, written by `run-all` and each round of `daemon`.
+//!
+//! Next to the markdown dashboard, `run-all` writes `status.json`, a summary
+//! of every site meant to be read by other tools, and one SVG badge per site
+//! in `badges/`. Served from wherever the workspace is published (a web
+//! server, GitHub Pages, an internal wiki), they let teams show how fresh
+//! each mirror is on their own dashboards:
+//!
+//! ```markdown
+//! ![stripe docs](https://mirrors.example.com/badges/stripe.svg)
+//! ```
+//!
+//! `daemon --status-addr` also serves the files itself over HTTP, so
+//! dashboards can read them straight from the daemon without publishing them.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+use crate::manifest::Manifest;
+use crate::output::sanitize_filename;
+use crate::workspace::{SiteResult, SiteStatus, WorkspaceReport};
+
+/// File name of the status summary, next to the workspace file.
+pub const STATUS_FILE_NAME: &str = "status.json";
+
+/// Directory of the site badges, next to the workspace file.
+pub const BADGES_DIR_NAME: &str = "badges";
+
+/// Longest a connection to the status listener may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest request the status listener reads; a request line and a few headers fit easily.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Success rate below which a badge turns from green to yellow.
+const WARN_SUCCESS_RATE: f64 = 0.9;
+
+/// Badge colors, as used by shields.io.
+const GREEN: &str = "#4c1";
+const YELLOW: &str = "#dfb317";
+const RED: &str = "#e05d44";
+const GREY: &str = "#9f9f9f";
+
+/// Summary of every site of a workspace, as written to `status.json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStatus {
+    /// When the summary was written
+    pub generated_at: DateTime<Utc>,
+
+    /// Number of sites whose last run succeeded or that were not due, and aren't paused
+    pub sites_ok: usize,
+
+    /// Number of sites whose run failed
+    pub sites_failed: usize,
+
+    /// Number of sites paused for tripping a pause condition
+    pub sites_paused: usize,
+
+    /// One entry per site, in workspace order
+    pub sites: Vec<MirrorStatus>,
+}
+
+/// Summary of one site.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorStatus {
+    /// Name of the site
+    pub name: String,
+
+    /// The URL the site is scraped from
+    pub url: String,
+
+    /// What happened to the site in the `run-all` that wrote the summary
+    pub status: SiteStatus,
+
+    /// When the site last finished a run
+    pub last_run: Option<DateTime<Utc>>,
+
+    /// When a skipped site is due again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_due: Option<DateTime<Utc>>,
+
+    /// Why a paused site was paused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_reason: Option<String>,
+
+    /// Share of the pages of the site's last run that were saved, from 0 to 1;
+    /// `None` if the run didn't process any page
+    pub success_rate: Option<f64>,
+
+    /// Number of pages in the site's manifest
+    pub pages_tracked: usize,
+
+    /// Path of the site's badge, relative to the workspace file
+    pub badge: String,
+}
+
+impl MirrorStatus {
+    /// Summarizes a site from its result in the combined report and its manifest.
+    fn new(site: &SiteResult) -> Self {
+        let attempted = site.pages.pages_processed + site.pages.pages_failed + site.pages.pages_empty;
+        let success_rate = (attempted > 0).then(|| site.pages.pages_processed as f64 / attempted as f64);
+        // A site that has never run has no manifest yet
+        let pages_tracked = Manifest::load(&site.dir).map_or(0, |manifest| manifest.pages.len());
+        MirrorStatus {
+            name: site.name.clone(),
+            url: site.url.clone(),
+            status: site.status,
+            last_run: site.last_run,
+            next_due: site.next_due,
+            pause_reason: site.pause_reason.clone(),
+            success_rate,
+            pages_tracked,
+            badge: format!("{}/{}.svg", BADGES_DIR_NAME, sanitize_filename(&site.name)),
+        }
+    }
+
+    /// Returns the message and color of the site's badge.
+    ///
+    /// The message is the date of the last run and its success rate; the color
+    /// is red for a failed run or a paused site, yellow for a success rate below
+    /// 90% and grey for a site that has never run.
+    fn badge_message(&self) -> (String, &'static str) {
+        if self.status == SiteStatus::Failed {
+            return ("failed".to_string(), RED);
+        }
+        if self.status == SiteStatus::Paused {
+            return ("paused".to_string(), RED);
+        }
+        let Some(last_run) = self.last_run else {
+            return ("never run".to_string(), GREY);
+        };
+        let date = last_run.format("%Y-%m-%d");
+        match self.success_rate {
+            Some(rate) if rate < WARN_SUCCESS_RATE => (format!("{} | {:.0}%", date, rate * 100.0), YELLOW),
+            Some(rate) => (format!("{} | {:.0}%", date, rate * 100.0), GREEN),
+            None => (date.to_string(), GREEN),
+        }
+    }
+}
+
+/// Writes `status.json` and the badge of every site into `root`.
+///
+/// # Errors
+///
+/// Returns an error if the summary or a badge cannot be written
+pub fn write(root: &Path, report: &WorkspaceReport) -> Result<(), Box<dyn Error>> {
+    let sites: Vec<MirrorStatus> = report.sites.iter().map(MirrorStatus::new).collect();
+
+    let badges_dir = root.join(BADGES_DIR_NAME);
+    fs::create_dir_all(&badges_dir)
+        .map_err(|e| format!("Failed to create directory {}: {}", badges_dir.display(), e))?;
+    for site in &sites {
+        let (message, color) = site.badge_message();
+        let path = root.join(&site.badge);
+        fs::write(&path, badge(&site.name, &message, color))
+            .map_err(|e| format!("Failed to write badge {}: {}", path.display(), e))?;
+    }
+
+    let sites_failed = sites.iter().filter(|site| site.status == SiteStatus::Failed).count();
+    let sites_paused = sites.iter().filter(|site| site.status == SiteStatus::Paused).count();
+    let status = WorkspaceStatus {
+        generated_at: report.finished_at,
+        sites_ok: sites.len() - sites_failed - sites_paused,
+        sites_failed,
+        sites_paused,
+        sites,
+    };
+    let path = root.join(STATUS_FILE_NAME);
+    fs::write(&path, serde_json::to_string_pretty(&status)?)
+        .map_err(|e| format!("Failed to write status {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Serves `status.json` and the site badges in `root` over HTTP, for `daemon --status-addr`.
+///
+/// Only `GET` and `HEAD` requests for `/status.json` and `/badges/<site>.svg`
+/// are answered. The files are read for every request, so they are always
+/// those of the daemon's latest round. Runs until the process exits.
+pub async fn serve(listener: TcpListener, root: PathBuf) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // E.g. out of file descriptors; waiting a moment lets connections close
+                warn!("Failed to accept a status request: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let root = root.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer(stream, &root).await {
+                debug!("Failed to answer the status request from {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Reads one request from a connection to the status listener and answers it.
+///
+/// Connections that don't send a complete request within `REQUEST_TIMEOUT`
+/// are closed without an answer.
+async fn answer(mut stream: TcpStream, root: &Path) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    // Only the request line matters, so reading stops at the end of the headers
+    let read = tokio::time::timeout(REQUEST_TIMEOUT, async {
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+            let count = stream.read(&mut buffer).await?;
+            if count == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..count]);
+        }
+        Ok::<(), io::Error>(())
+    })
+    .await;
+    let Ok(read) = read else {
+        return Ok(());
+    };
+    read?;
+
+    let request = String::from_utf8_lossy(&request);
+    let response = respond(root, request.lines().next().unwrap_or_default());
+    stream.write_all(&response).await?;
+    stream.shutdown().await
+}
+
+/// Returns the HTTP response to a request for a status file, given the request's first line.
+fn respond(root: &Path, request_line: &str) -> Vec<u8> {
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let head = method == "HEAD";
+    if method != "GET" && !head {
+        return response("405 Method Not Allowed", "text/plain", b"Only GET and HEAD are supported\n", false);
+    }
+
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    if path == format!("/{}", STATUS_FILE_NAME) {
+        return match fs::read(root.join(STATUS_FILE_NAME)) {
+            Ok(body) => response("200 OK", "application/json", &body, head),
+            // Before the daemon's first round has finished there is nothing to report
+            Err(_) => response("503 Service Unavailable", "text/plain", b"No round has finished yet\n", head),
+        };
+    }
+    // Badges are looked up by file name only, so requests can't reach other files
+    let badge = path
+        .strip_prefix(&format!("/{}/", BADGES_DIR_NAME))
+        .filter(|name| name.ends_with(".svg") && !name.contains(['/', '\\']));
+    match badge.and_then(|name| fs::read(root.join(BADGES_DIR_NAME).join(name)).ok()) {
+        Some(body) => response("200 OK", "image/svg+xml", &body, head),
+        None => response("404 Not Found", "text/plain", b"Not found\n", head),
+    }
+}
+
+/// Builds an HTTP response; the body is left out for a `HEAD` request.
+///
+/// Responses aren't cached, since the files change after every round, and can
+/// be read by dashboards served from other origins.
+fn response(status: &str, content_type: &str, body: &[u8], head: bool) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    if !head {
+        response.extend_from_slice(body);
+    }
+    response
+}
+
+/// Renders a flat badge in the style of shields.io, with a grey label and a colored message.
+///
+/// Text widths are estimated from the number of characters, which is close
+/// enough for the 11px Verdana the badges are drawn in.
+fn badge(label: &str, message: &str, color: &str) -> String {
+    let text_width = |text: &str| text.chars().count() * 7 + 10;
+    let (label_width, message_width) = (text_width(label), text_width(message));
+    let width = label_width + message_width;
+    let (label, message) = (escape_xml(label), escape_xml(message));
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+<title>{label}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{label_x}" y="14">{label}</text><text x="{message_x}" y="14">{message}</text>
+</g>
+</svg>
+"##,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+/// Escapes text for an SVG attribute or element.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a workspace directory holding a status summary and one badge.
+    fn workspace(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("scraper-status-{}-{}", name, std::process::id()));
+        fs::create_dir_all(root.join(BADGES_DIR_NAME)).unwrap();
+        fs::write(root.join(STATUS_FILE_NAME), r#"{"sitesOk":1}"#).unwrap();
+        fs::write(root.join(BADGES_DIR_NAME).join("stripe.svg"), "<svg/>").unwrap();
+        root
+    }
+
+    fn text(response: Vec<u8>) -> String {
+        String::from_utf8(response).unwrap()
+    }
+
+    #[test]
+    fn serves_the_summary_and_badges() {
+        let root = workspace("files");
+        let status = text(respond(&root, "GET /status.json HTTP/1.1"));
+        assert!(status.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(status.contains("Content-Type: application/json\r\n"));
+        assert!(status.ends_with("\r\n\r\n{\"sitesOk\":1}"));
+
+        let badge = text(respond(&root, "GET /badges/stripe.svg?v=2 HTTP/1.1"));
+        assert!(badge.contains("Content-Type: image/svg+xml\r\nContent-Length: 6\r\n"));
+        assert!(badge.ends_with("<svg/>"));
+
+        // A HEAD request gets the headers only
+        let head = text(respond(&root, "HEAD /badges/stripe.svg HTTP/1.1"));
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n") && head.ends_with("\r\n\r\n"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn refuses_other_requests() {
+        let root = workspace("refused");
+        fs::write(root.join("secret.svg"), "<svg/>").unwrap();
+        for request in ["GET /badges/unknown.svg HTTP/1.1", "GET /badges/../secret.svg HTTP/1.1", "GET /sites.toml HTTP/1.1"] {
+            assert!(text(respond(&root, request)).starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", request);
+        }
+        assert!(text(respond(&root, "POST /status.json HTTP/1.1")).starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+        // Before the first round has written the summary
+        fs::remove_file(root.join(STATUS_FILE_NAME)).unwrap();
+        assert!(text(respond(&root, "GET /status.json HTTP/1.1")).starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn answers_over_http() {
+        let root = workspace("http");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, root.clone()));
+
+        let response = reqwest::get(format!("http://{}/status.json", addr)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["access-control-allow-origin"], "*");
+        assert_eq!(response.text().await.unwrap(), r#"{"sitesOk":1}"#);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
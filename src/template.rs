@@ -0,0 +1,227 @@
+//! Frontmatter templates, so teams can match their own frontmatter conventions.
+//!
+//! The frontmatter of every page is rendered from a Handlebars template. The
+//! built-in one (`frontmatter.hbs`) writes the fields described in the README;
+//! `--frontmatter-template` replaces it with a template of the user's own,
+//! which sees the same values: the page's metadata, its URL and identifier,
+//! its headings and translation, and how it was crawled. Values are written
+//! as they are, so text fields go through the `yaml` helper to be quoted.
+
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use crate::frontmatter::escape_yaml_string;
+
+/// Name the template is registered under.
+const TEMPLATE_NAME: &str = "frontmatter";
+
+/// The built-in template, writing the frontmatter the scraper has always written.
+static BUILT_IN: LazyLock<FrontmatterTemplate> = LazyLock::new(|| {
+    FrontmatterTemplate::compile(PathBuf::from("frontmatter.hbs"), include_str!("frontmatter.hbs"))
+        .expect("the built-in frontmatter template is valid")
+});
+
+// Quotes a text value as a double-quoted YAML string; other values are written as JSON, which YAML reads too
+handlebars_helper!(yaml: |value: Json| match value {
+    Value::String(text) => format!("\"{}\"", escape_yaml_string(text)),
+    Value::Null => "\"\"".to_string(),
+    other => other.to_string(),
+});
+
+/// A compiled Handlebars template rendering the frontmatter of a page.
+#[derive(Debug, Clone)]
+pub struct FrontmatterTemplate {
+    /// File the template was read from, for messages
+    path: PathBuf,
+
+    /// The registry holding the compiled template and its helpers
+    registry: Handlebars<'static>,
+}
+
+impl FrontmatterTemplate {
+    /// Returns the built-in template.
+    pub fn built_in() -> &'static FrontmatterTemplate {
+        &BUILT_IN
+    }
+
+    /// Reads and compiles the template at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a valid Handlebars template
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read template {}: {}", path, e))?;
+        Self::compile(PathBuf::from(path), &text)
+    }
+
+    /// Compiles a template, registering the helpers it may use.
+    fn compile(path: PathBuf, text: &str) -> Result<Self, String> {
+        let mut registry = Handlebars::new();
+        // The output is YAML, not HTML
+        registry.register_escape_fn(no_escape);
+        registry.register_helper("yaml", Box::new(yaml));
+        registry
+            .register_template_string(TEMPLATE_NAME, text)
+            .map_err(|e| format!("Template {} is not a valid Handlebars template: {}", path.display(), e))?;
+        Ok(FrontmatterTemplate { path, registry })
+    }
+
+    /// Renders the frontmatter block of a page from its values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if rendering fails, or if the result isn't a block
+    /// between `---` lines, which later steps add fields to and read back
+    pub fn render(&self, context: &Value) -> Result<String, Box<dyn Error>> {
+        let mut rendered = self
+            .registry
+            .render(TEMPLATE_NAME, context)
+            .map_err(|e| format!("Failed to render frontmatter template {}: {}", self.path.display(), e))?;
+        if !rendered.starts_with("---\n") || !rendered[3..].contains("\n---\n") {
+            return Err(format!(
+                "Frontmatter template {} must render a YAML block between --- lines",
+                self.path.display()
+            )
+            .into());
+        }
+        if !rendered.ends_with("\n\n") {
+            rendered.push('\n');
+        }
+        Ok(rendered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use crate::cli::Backend;
+    use crate::frontmatter::{create_frontmatter, Crawl, DateStyle};
+    use crate::page_type::PageType;
+    use crate::translate::Translation;
+    use crate::{ChangeStatus, Metadata};
+
+    fn template(text: &str) -> FrontmatterTemplate {
+        FrontmatterTemplate::compile(PathBuf::from("test.hbs"), text).unwrap()
+    }
+
+    const CRAWL: Crawl<'static> = Crawl {
+        url: "https://example.com/guide",
+        file: "Guide.md",
+        backend: Backend::Direct,
+        preset: None,
+    };
+
+    #[test]
+    fn built_in_template_writes_the_frontmatter_written_before_templates() {
+        let metadata = Metadata {
+            title: Some("The \"Quick\" Guide".to_string()),
+            source_url: Some("https://example.com/guide".to_string()),
+            description: Some("Not written by the built-in template".to_string()),
+            ..Default::default()
+        };
+        let translation = Translation {
+            language: "de".to_string(),
+            original_language: "en".to_string(),
+            original_hash: "sha256:abc".to_string(),
+        };
+        let headings = vec!["Install".to_string(), "Install > C:\\Tools".to_string()];
+        let frontmatter = create_frontmatter(
+            FrontmatterTemplate::built_in(),
+            &metadata,
+            "100680ad546ce6a5",
+            PageType::Guide,
+            &headings,
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(),
+            Some(Utc.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap()),
+            Some(ChangeStatus::Same),
+            &DateStyle::default(),
+            Some(&translation),
+            &CRAWL,
+        )
+        .unwrap();
+        assert_eq!(
+            frontmatter,
+            "---\n\
+             title: \"The \\\"Quick\\\" Guide\"\n\
+             url: \"https://example.com/guide\"\n\
+             id: \"100680ad546ce6a5\"\n\
+             language: \"de\"\n\
+             originalLanguage: \"en\"\n\
+             originalHash: \"sha256:abc\"\n\
+             pageType: guide\n\
+             scrapeDate: 2024-01-01T12:00:00+00:00\n\
+             lastScrapeDate: 2024-02-01T12:00:00+00:00\n\
+             changeStatus: same\n\
+             headings:\n  \
+               - \"Install\"\n  \
+               - \"Install > C:\\\\Tools\"\n\
+             ---\n\n"
+        );
+    }
+
+    #[test]
+    fn built_in_template_leaves_out_missing_values() {
+        let frontmatter = create_frontmatter(
+            FrontmatterTemplate::built_in(),
+            &Metadata::default(),
+            "100680ad546ce6a5",
+            PageType::Reference,
+            &[],
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(),
+            None,
+            None,
+            &DateStyle::default(),
+            None,
+            &CRAWL,
+        )
+        .unwrap();
+        assert_eq!(
+            frontmatter,
+            "---\nid: \"100680ad546ce6a5\"\npageType: reference\nscrapeDate: 2024-01-01T12:00:00+00:00\n---\n\n"
+        );
+    }
+
+    #[test]
+    fn yaml_helper_quotes_and_escapes_text() {
+        let template = template("---\na: {{yaml a}}\nb: {{yaml b}}\nc: {{yaml c}}\nd: {{yaml d}}\ne: {{yaml e}}\n---\n");
+        let context = json!({
+            "a": "say \"hi\" to C:\\dir",
+            "b": "two\nlines",
+            "c": null,
+            "d": 3,
+            "e": [1, "x"],
+        });
+        assert_eq!(
+            template.render(&context).unwrap(),
+            "---\na: \"say \\\"hi\\\" to C:\\\\dir\"\nb: \"two lines\"\nc: \"\"\nd: 3\ne: [1,\"x\"]\n---\n\n"
+        );
+    }
+
+    #[test]
+    fn values_are_not_html_escaped() {
+        let template = template("---\nurl: {{url}}\n---\n");
+        let rendered = template.render(&json!({ "url": "https://example.com/?a=1&b=<2>" })).unwrap();
+        assert_eq!(rendered, "---\nurl: https://example.com/?a=1&b=<2>\n---\n\n");
+    }
+
+    #[test]
+    fn output_must_be_a_block_between_dashes() {
+        assert!(template("title: {{title}}\n").render(&json!({ "title": "A" })).is_err());
+        assert!(template("---\ntitle: {{title}}\n").render(&json!({ "title": "A" })).is_err());
+        // A block already ending in a blank line isn't given another
+        assert_eq!(template("---\nx: 1\n---\n\n").render(&json!({})).unwrap(), "---\nx: 1\n---\n\n");
+    }
+
+    #[test]
+    fn invalid_templates_are_refused() {
+        let error = FrontmatterTemplate::compile(PathBuf::from("broken.hbs"), "---\n{{#if title}}\n---\n").unwrap_err();
+        assert!(error.contains("broken.hbs"), "{}", error);
+    }
+}
@@ -0,0 +1,486 @@
+//! Pacing of API calls by credit rate.
+//!
+//! FireCrawl bills every call in credits, and a team sharing one API key
+//! shares its credit budget. `--credits-per-minute` spreads the calls of a run
+//! evenly so one large crawl cannot use up the budget in a burst. All API
+//! calls of a run draw from the same `CreditPacer`, so it keeps the overall
+//! rate even when several kinds of calls (discovery and page scrapes) are made.
+//!
+//! Plans and self-hosted instances also limit the number of requests,
+//! whatever they cost. `--rps` caps the requests per second with a token
+//! bucket and `--delay-ms` keeps a minimum gap between requests; both apply to
+//! every FireCrawl request, including those that cost no credits, such as
+//! polling a crawl job.
+//!
+//! Several scraper processes using one API key on the same machine can share
+//! the budget with `--shared-budget`. Their pacers then keep the next free
+//! slot, and the request limits of `--rps` and `--delay-ms`, in a ledger file
+//! in the cache directory, one per API key, which each process locks while it
+//! reserves its credits or its next request.
+
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::domain_profile;
+
+/// Directory in the cache directory holding the ledgers of shared budgets.
+const BUDGETS_DIR_NAME: &str = "budgets";
+
+/// Credits charged for scraping one page.
+pub const SCRAPE_CREDITS: u32 = 1;
+
+/// Credits charged for one call to the map endpoint.
+pub const MAP_CREDITS: u32 = 1;
+
+/// Spaces API calls so that no more than a given number of credits is spent per minute.
+#[derive(Debug)]
+pub struct CreditPacer {
+    /// Time one credit takes to become available again; `None` when unlimited
+    interval: Option<Duration>,
+
+    /// Earliest time the next call may start
+    next_slot: Mutex<Option<Instant>>,
+
+    /// Credits reserved so far
+    spent: AtomicU64,
+
+    /// Ledger file of a budget shared with other processes
+    ledger: Option<PathBuf>,
+
+    /// Limit on the rate of requests, whatever they cost
+    requests: RequestLimiter,
+}
+
+/// Token bucket limiting API requests per second, with an optional minimum gap between them.
+#[derive(Debug)]
+struct RequestLimiter {
+    /// Requests per second; `None` when unlimited
+    per_second: Option<f64>,
+
+    /// Minimum time between the starts of two requests
+    min_delay: Duration,
+
+    /// Tokens in the bucket and when the next request may start, unless kept in a shared ledger
+    state: Mutex<BucketState>,
+
+    /// Instant the times of `state` are measured from
+    origin: Instant,
+}
+
+/// Mutable state of a `RequestLimiter`.
+///
+/// Times are measured from the limiter's origin, or from the Unix epoch in a
+/// shared ledger, since `Instant`s can't be compared across processes.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct BucketState {
+    /// Tokens available; negative while requests are waiting for tokens
+    tokens: f64,
+
+    /// When the tokens were last topped up
+    refilled_at: Option<Duration>,
+
+    /// When the latest request was allowed to start
+    last_start: Option<Duration>,
+}
+
+/// Contents of the ledger file of a shared budget.
+///
+/// The first line holds the next free credit slot, the second, once a run
+/// limits its requests, the tokens of the shared bucket followed by when it
+/// was topped up and when the latest request started, all times in
+/// milliseconds since the Unix epoch.
+#[derive(Debug, Default, PartialEq)]
+struct Ledger {
+    /// Earliest time the next call may start under `--credits-per-minute`
+    slot: Option<Duration>,
+
+    /// State of the request limits shared by the processes
+    requests: Option<BucketState>,
+}
+
+impl CreditPacer {
+    /// Creates a pacer allowing `credits_per_minute` credits per minute, or any number if `None`.
+    pub fn new(credits_per_minute: Option<u32>) -> Self {
+        CreditPacer {
+            interval: credits_per_minute
+                .filter(|&credits| credits > 0)
+                .map(|credits| Duration::from_secs(60) / credits),
+            next_slot: Mutex::new(None),
+            spent: AtomicU64::new(0),
+            ledger: None,
+            requests: RequestLimiter::new(None, Duration::ZERO),
+        }
+    }
+
+    /// Also limits the pacer's requests to `per_second` requests per second
+    /// and spaces them at least `min_delay` apart.
+    ///
+    /// The bucket holds one second's worth of requests (at least one), so a
+    /// run may start with a short burst.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let pacer = CreditPacer::new(None).limit_requests(Some(2.0), Duration::from_millis(100));
+    /// ```
+    pub fn limit_requests(mut self, per_second: Option<f64>, min_delay: Duration) -> Self {
+        self.requests = RequestLimiter::new(per_second, min_delay);
+        self
+    }
+
+    /// Shares the pacer's budget and request limits with the other processes
+    /// that use the same API key and `--shared-budget`.
+    ///
+    /// Call it after `limit_requests`, so the request limits are shared as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pacer has neither a credit budget nor request
+    /// limits to share, there is no cache directory, or the ledger directory
+    /// cannot be created
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let pacer = CreditPacer::new(None).limit_requests(Some(2.0), Duration::ZERO).share(&api_key)?;
+    /// ```
+    pub fn share(self, api_key: &str) -> Result<Self, Box<dyn Error>> {
+        if !self.is_limited() {
+            return Err("--shared-budget needs --credits-per-minute, --rps or --delay-ms (credits_per_minute, rps or \
+                 delay_ms in a configuration file); without a limit there is nothing to share"
+                .into());
+        }
+        let dir = domain_profile::cache_dir()
+            .ok_or("No cache directory to keep the shared budget in; set XDG_CACHE_HOME")?
+            .join(BUDGETS_DIR_NAME);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+        // The key itself never ends up on disk
+        let key = format!("{:x}", Sha256::digest(api_key.as_bytes()));
+        Ok(CreditPacer {
+            ledger: Some(dir.join(format!("{}.slot", &key[..16]))),
+            ..self
+        })
+    }
+
+    /// Returns whether the pacer limits its calls at all, by credits or by requests.
+    fn is_limited(&self) -> bool {
+        self.interval.is_some() || self.requests.per_second.is_some() || !self.requests.min_delay.is_zero()
+    }
+
+    /// Waits until a call costing `credits` may be made, and reserves its credits.
+    ///
+    /// The first call is never delayed; every call pushes the next slot back
+    /// by the time its credits take to become available again. The call then
+    /// also waits for the request limits, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let pacer = CreditPacer::new(Some(30));
+    /// pacer.acquire(throttle::SCRAPE_CREDITS).await; // immediately
+    /// pacer.acquire(throttle::SCRAPE_CREDITS).await; // two seconds later
+    /// ```
+    pub async fn acquire(&self, credits: u32) {
+        self.spent.fetch_add(u64::from(credits), Ordering::Relaxed);
+        self.pace_credits(credits).await;
+        self.acquire_request().await;
+    }
+
+    /// Waits until a request that costs no credits, such as a crawl job poll, may be made.
+    pub async fn acquire_request(&self) {
+        let wait = self.requests.reserve(self.ledger.as_deref());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Waits until the credits of a call are available under `--credits-per-minute`.
+    async fn pace_credits(&self, credits: u32) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+
+        let cost = interval * credits;
+        let shared = self.ledger.as_deref().and_then(|ledger| {
+            update_ledger(ledger, |entry, now| {
+                let start = entry.slot.filter(|slot| *slot > now).unwrap_or(now);
+                entry.slot = Some(start + cost);
+                start - now
+            })
+            .map_err(|e| warn!("Failed to use the shared budget in {}: {}; pacing this run alone", ledger.display(), e))
+                .ok()
+        });
+        let wait = shared.unwrap_or_else(|| {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let start = next_slot.map_or(now, |slot| slot.max(now));
+            *next_slot = Some(start + cost);
+            start - now
+        });
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Returns the credits reserved through this pacer so far.
+    pub fn credits_spent(&self) -> u64 {
+        self.spent.load(Ordering::Relaxed)
+    }
+}
+
+impl RequestLimiter {
+    /// Creates a limiter of `per_second` requests per second, spaced at least `min_delay` apart, with a full bucket.
+    fn new(per_second: Option<f64>, min_delay: Duration) -> Self {
+        let per_second = per_second.filter(|rate| *rate > 0.0);
+        RequestLimiter {
+            per_second,
+            min_delay,
+            state: Mutex::new(BucketState {
+                tokens: per_second.map_or(0.0, bucket_size),
+                ..BucketState::default()
+            }),
+            origin: Instant::now(),
+        }
+    }
+
+    /// Takes a token for one request, returning how long to wait before making it.
+    ///
+    /// With a `ledger`, the bucket is the one shared through it; if the ledger
+    /// can't be used, the limiter's own bucket is.
+    fn reserve(&self, ledger: Option<&Path>) -> Duration {
+        if self.per_second.is_none() && self.min_delay.is_zero() {
+            return Duration::ZERO;
+        }
+        if let Some(ledger) = ledger {
+            let shared = update_ledger(ledger, |entry, now| {
+                let state = entry.requests.get_or_insert_with(|| BucketState {
+                    tokens: self.per_second.map_or(0.0, bucket_size),
+                    ..BucketState::default()
+                });
+                self.take(state, now)
+            });
+            match shared {
+                Ok(wait) => return wait,
+                Err(e) => warn!(
+                    "Failed to use the shared request limits in {}: {}; limiting this run's requests alone",
+                    ledger.display(),
+                    e
+                ),
+            }
+        }
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.take(&mut state, self.origin.elapsed())
+    }
+
+    /// Takes a token from `state` for a request made at `now`, returning how long it waits.
+    fn take(&self, state: &mut BucketState, now: Duration) -> Duration {
+        let mut start = now;
+        if let Some(rate) = self.per_second {
+            let elapsed = state.refilled_at.map_or(Duration::ZERO, |refilled_at| now.saturating_sub(refilled_at));
+            state.tokens = (state.tokens + elapsed.as_secs_f64() * rate).min(bucket_size(rate));
+            state.refilled_at = Some(now);
+            // A request finding the bucket empty waits for its token; later ones queue behind it
+            state.tokens -= 1.0;
+            if state.tokens < 0.0 {
+                start += Duration::from_secs_f64(-state.tokens / rate);
+            }
+        }
+        if let Some(last_start) = state.last_start {
+            start = start.max(last_start + self.min_delay);
+        }
+        state.last_start = Some(start);
+        start - now
+    }
+}
+
+/// Returns the number of tokens a bucket refilled at `rate` per second holds.
+fn bucket_size(rate: f64) -> f64 {
+    rate.max(1.0)
+}
+
+/// Reads a shared ledger, lets `update` change it and writes it back, returning what `update` returns.
+///
+/// `update` is given the current time since the Unix epoch. The ledger is
+/// locked while it is read and rewritten.
+fn update_ledger<T>(path: &Path, update: impl FnOnce(&mut Ledger, Duration) -> T) -> io::Result<T> {
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+    file.lock()?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut ledger = Ledger::parse(&text);
+    let result = update(&mut ledger, now);
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(ledger.to_text().as_bytes())?;
+    // The lock is released when the file is closed
+    Ok(result)
+}
+
+impl Ledger {
+    /// Parses a ledger, ignoring lines that can't be read, as in ledgers of older versions.
+    fn parse(text: &str) -> Self {
+        let millis = |field: Option<&str>| field?.parse().ok().map(Duration::from_millis);
+        let mut lines = text.lines();
+        let slot = millis(lines.next().map(str::trim));
+        let requests = lines.next().and_then(|line| {
+            let mut fields = line.split_whitespace();
+            Some(BucketState {
+                tokens: fields.next()?.parse().ok()?,
+                refilled_at: millis(fields.next()),
+                last_start: millis(fields.next()),
+            })
+        });
+        Ledger { slot, requests }
+    }
+
+    /// Returns the ledger as it is written to its file.
+    fn to_text(&self) -> String {
+        let millis = |time: Option<Duration>| time.unwrap_or_default().as_millis();
+        let mut text = format!("{}\n", millis(self.slot));
+        if let Some(requests) = &self.requests {
+            text.push_str(&format!(
+                "{} {} {}\n",
+                requests.tokens,
+                millis(requests.refilled_at),
+                millis(requests.last_start)
+            ));
+        }
+        text
+    }
+}
+
+/// Parses an `--rps` rate, which may be fractional, such as `0.5` for one request every two seconds.
+///
+/// # Errors
+///
+/// Returns an error if the value is not a positive number
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value.parse().map_err(|_| format!("{} is not a number", value))?;
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(format!("{} is not a positive number", value));
+    }
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn state(limiter: &RequestLimiter) -> BucketState {
+        limiter.state.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_queues_requests_behind_it() {
+        let limiter = RequestLimiter::new(Some(2.0), Duration::ZERO);
+        let mut state = state(&limiter);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), Duration::ZERO);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), Duration::ZERO);
+        // The bucket is empty; each further request waits for the token after the previous one's
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), 500 * MS);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), 1000 * MS);
+        assert_eq!(state.tokens, -2.0);
+    }
+
+    #[test]
+    fn bucket_refills_at_its_rate_up_to_its_size() {
+        let limiter = RequestLimiter::new(Some(2.0), Duration::ZERO);
+        let mut state = state(&limiter);
+        limiter.take(&mut state, Duration::ZERO);
+        limiter.take(&mut state, Duration::ZERO);
+        assert_eq!(limiter.take(&mut state, 500 * MS), Duration::ZERO);
+        assert_eq!(limiter.take(&mut state, 500 * MS), 500 * MS);
+
+        // A long pause fills the bucket no further than one second's worth of requests
+        limiter.take(&mut state, Duration::from_secs(60));
+        assert_eq!(state.tokens, 1.0);
+        assert_eq!(limiter.take(&mut state, Duration::from_secs(60)), Duration::ZERO);
+        assert!(limiter.take(&mut state, Duration::from_secs(60)) > Duration::ZERO);
+    }
+
+    #[test]
+    fn min_delay_spaces_request_starts() {
+        let limiter = RequestLimiter::new(None, 100 * MS);
+        let mut state = state(&limiter);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), Duration::ZERO);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), 100 * MS);
+        assert_eq!(limiter.take(&mut state, 50 * MS), 150 * MS);
+        assert_eq!(limiter.take(&mut state, Duration::from_secs(1)), Duration::ZERO);
+    }
+
+    #[test]
+    fn min_delay_spaces_requests_the_bucket_would_allow() {
+        let limiter = RequestLimiter::new(Some(10.0), 200 * MS);
+        let mut state = state(&limiter);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), Duration::ZERO);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), 200 * MS);
+        assert_eq!(limiter.take(&mut state, Duration::ZERO), 400 * MS);
+        assert_eq!(state.tokens, 7.0);
+    }
+
+    #[test]
+    fn unlimited_limiter_never_waits() {
+        let limiter = RequestLimiter::new(None, Duration::ZERO);
+        for _ in 0..10 {
+            assert_eq!(limiter.reserve(None), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn ledger_round_trips_and_reads_older_ledgers() {
+        let ledger = Ledger {
+            slot: Some(Duration::from_millis(1_700_000_000_000)),
+            requests: Some(BucketState {
+                tokens: -1.5,
+                refilled_at: Some(Duration::from_millis(1_700_000_000_100)),
+                last_start: Some(Duration::from_millis(1_700_000_000_200)),
+            }),
+        };
+        assert_eq!(Ledger::parse(&ledger.to_text()), ledger);
+
+        let older = Ledger::parse("1700000000000");
+        assert_eq!(older.slot, Some(Duration::from_millis(1_700_000_000_000)));
+        assert_eq!(older.requests, None);
+        assert_eq!(Ledger::parse(""), Ledger::default());
+    }
+
+    #[test]
+    fn any_limit_can_be_shared() {
+        assert!(CreditPacer::new(Some(60)).is_limited());
+        assert!(CreditPacer::new(None).limit_requests(Some(2.0), Duration::ZERO).is_limited());
+        assert!(CreditPacer::new(None).limit_requests(None, Duration::from_millis(500)).is_limited());
+
+        // Without any limit there is nothing to share
+        let unlimited = CreditPacer::new(Some(0)).limit_requests(Some(0.0), Duration::ZERO);
+        assert!(!unlimited.is_limited());
+        assert!(unlimited.share("key").is_err());
+    }
+
+    #[test]
+    fn processes_sharing_a_ledger_share_the_request_limits() {
+        let dir = std::env::temp_dir().join(format!("scraper-throttle-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ledger = dir.join("requests.slot");
+        let _ = fs::remove_file(&ledger);
+
+        // Each limiter stands for another process; alone, neither would wait
+        let first = RequestLimiter::new(None, Duration::from_secs(10));
+        let second = RequestLimiter::new(None, Duration::from_secs(10));
+        assert_eq!(first.reserve(Some(&ledger)), Duration::ZERO);
+        assert!(second.reserve(Some(&ledger)) > Duration::from_secs(9));
+        assert_eq!(state(&second).last_start, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+//! Tombstones for pages removed from the source site.
+//!
+//! With `--tombstones`, a previously scraped page that now answers `404 Not
+//! Found` or `410 Gone` is not simply left stale or dropped. Its last saved
+//! version is moved to `_removed/` and its file is replaced by a short
+//! tombstone recording when the page disappeared and the hash of its last
+//! known content. Links to the page keep resolving, and the history of the
+//! mirror stays readable.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::frontmatter::{self, escape_yaml_string};
+use crate::manifest::ManifestEntry;
+
+/// Directory inside the output directory that keeps the last version of removed pages.
+pub const REMOVED_DIR_NAME: &str = "_removed";
+
+/// Record of a page that was removed from the source site.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Removal {
+    /// When the page was first found to be gone
+    pub removed_at: DateTime<Utc>,
+
+    /// HTTP status the page answered with
+    pub status: u16,
+
+    /// Hash of the last known content, as `sha256:<hex>`
+    pub content_hash: String,
+
+    /// Path of the last saved version, relative to the output directory
+    pub archived_file: String,
+}
+
+/// Returns whether a status code means the page no longer exists.
+pub fn is_gone(status: u16) -> bool {
+    status == 404 || status == 410
+}
+
+/// Returns the hash of a page's content, as `sha256:<hex>`.
+///
+/// # Examples
+///
+/// ```text
+/// assert!(content_hash("# Intro\n").starts_with("sha256:"));
+/// ```
+pub fn content_hash(body: &str) -> String {
+    format!("sha256:{:x}", Sha256::digest(body.as_bytes()))
+}
+
+/// Archives the saved file of a removed page and replaces it with a tombstone.
+///
+/// The content hash is taken from the saved markdown without its frontmatter,
+/// so it only changes when the page content did.
+///
+/// # Arguments
+///
+/// * `output_dir` - The directory the page was saved to
+/// * `url` - URL of the page
+/// * `entry` - Manifest entry of the last saved version
+/// * `status` - The status the page answered with
+///
+/// # Returns
+///
+/// A `Result` containing the manifest entry of the tombstone
+///
+/// # Errors
+///
+/// Returns an error if the saved file cannot be read, archived or replaced
+pub fn bury(output_dir: &Path, url: &str, entry: &ManifestEntry, status: u16) -> Result<ManifestEntry, Box<dyn Error>> {
+    let file_path = output_dir.join(&entry.file);
+    let content = fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read file {}: {}", file_path.display(), e))?;
+
+    let archived_file = format!("{}/{}", REMOVED_DIR_NAME, entry.file);
+    let archived_path = output_dir.join(&archived_file);
+    if let Some(parent) = archived_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&archived_path, &content)
+        .map_err(|e| format!("Failed to archive {}: {}", archived_path.display(), e))?;
+
+    let removal = Removal {
+        removed_at: Utc::now(),
+        status,
+        content_hash: content_hash(frontmatter::strip_frontmatter(&content)),
+        archived_file,
+    };
+    fs::write(&file_path, tombstone(url, entry, &removal))
+        .map_err(|e| format!("Failed to write file {}: {}", file_path.display(), e))?;
+
+    // Cache validators belong to the removed version and must not be reused
+    Ok(ManifestEntry {
+        file: entry.file.clone(),
+        title: entry.title.clone(),
+        scraped_at: entry.scraped_at,
+        page_type: entry.page_type,
+        removed: Some(removal),
+        ..Default::default()
+    })
+}
+
+/// Renders the tombstone file of a removed page.
+fn tombstone(url: &str, entry: &ManifestEntry, removal: &Removal) -> String {
+    let title = entry.title.as_deref().unwrap_or(url);
+    // The archived copy mirrors the file's path, so the link climbs out of the file's directories
+    let depth = entry.file.matches('/').count();
+    let archived_link = format!("{}{}", "../".repeat(depth), removal.archived_file);
+
+    format!(
+        "---\ntitle: \"{}\"\nurl: \"{}\"\nremoved: {}\nlastScraped: {}\nlastContentHash: \"{}\"\n---\n\n# {} (removed)\n\n<{}> answered {} when it was checked on {}; the page has been removed from the site.\n\nThe last version, scraped on {}, is kept in [{}]({}).\n",
+        escape_yaml_string(title),
+        escape_yaml_string(url),
+        removal.removed_at.to_rfc3339(),
+        entry.scraped_at.to_rfc3339(),
+        removal.content_hash,
+        title,
+        url,
+        removal.status,
+        removal.removed_at.format("%Y-%m-%d"),
+        entry.scraped_at.format("%Y-%m-%d"),
+        removal.archived_file,
+        archived_link.replace(' ', "%20"),
+    )
+}
@@ -0,0 +1,262 @@
+//! Translation of scraped pages into another language.
+//!
+//! With `--translate-to <LANG>`, every page is translated by a language model
+//! behind an OpenAI-compatible chat completions endpoint
+//! (`--translation-endpoint`) before it is saved. The frontmatter records the
+//! language the page was translated to, the language it was written in, and a
+//! hash of the original, so a page whose original hasn't changed keeps its
+//! saved translation instead of being translated again.
+//!
+//! Translation happens after content redaction, so redacted content is never
+//! sent to the endpoint.
+
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::error::Error;
+
+use crate::cli::ScrapeOptions;
+use crate::frontmatter;
+use crate::markdown::{self, Fence};
+use crate::redact;
+use crate::tombstone;
+use crate::Metadata;
+
+/// Environment variable holding the API key of the translation endpoint.
+pub const TRANSLATION_API_KEY_VAR: &str = "TRANSLATION_API_KEY";
+
+/// Language recorded for pages whose language is unknown.
+pub const UNDETERMINED: &str = "und";
+
+/// Longest piece of a page sent in one request, in characters.
+const MAX_CHUNK_CHARS: usize = 12_000;
+
+/// Client of an OpenAI-compatible chat completions endpoint translating pages.
+pub struct Translator {
+    endpoint: String,
+    model: String,
+    target: String,
+    api_key: Option<String>,
+}
+
+/// How a saved page was translated, recorded in its frontmatter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    /// Language the page was translated to
+    pub language: String,
+
+    /// Language the page was written in, `und` if unknown
+    pub original_language: String,
+
+    /// Hash of the original markdown, as `sha256:<hex>`
+    pub original_hash: String,
+}
+
+impl Translator {
+    /// Creates a translator for `--translate-to`, if it was given.
+    ///
+    /// The API key is read from `TRANSLATION_API_KEY` and registered for
+    /// redaction; endpoints that need no key work without it.
+    pub fn from_options(options: &ScrapeOptions) -> Option<Self> {
+        let target = options.translate_to.clone()?;
+        let endpoint = options.translation_endpoint.clone()?;
+        let api_key = std::env::var(TRANSLATION_API_KEY_VAR).ok().filter(|key| !key.is_empty());
+        if let Some(key) = &api_key {
+            redact::register_secret(key);
+        }
+        Some(Translator {
+            endpoint,
+            model: options.translation_model.clone(),
+            target,
+            api_key,
+        })
+    }
+
+    /// Returns the language pages are translated to.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Returns whether a page in `language` needs translating.
+    ///
+    /// Pages already in the target language, ignoring the region, are saved as
+    /// they are.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// // With --translate-to en
+    /// assert!(!translator.needs_translation(Some("en-US")));
+    /// assert!(translator.needs_translation(Some("ja")));
+    /// assert!(translator.needs_translation(None));
+    /// ```
+    pub fn needs_translation(&self, language: Option<&str>) -> bool {
+        language.is_none_or(|language| primary_subtag(language) != primary_subtag(&self.target))
+    }
+
+    /// Translates a page's markdown.
+    ///
+    /// Long pages are split between paragraphs, outside code blocks, and
+    /// translated piece by piece.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a request fails or a response holds no text
+    pub async fn translate_markdown(&self, client: &Client, markdown: &str) -> Result<String, Box<dyn Error>> {
+        let instruction = format!(
+            "Translate this part of a markdown documentation page into {}. Keep the markdown structure, \
+             code blocks, inline code, URLs and link targets unchanged. Reply with the translated markdown only.",
+            self.target
+        );
+        let mut translated = String::with_capacity(markdown.len());
+        for chunk in chunks(markdown, MAX_CHUNK_CHARS) {
+            if chunk.trim().is_empty() {
+                translated.push_str(chunk);
+                continue;
+            }
+            let text = self.complete(client, &instruction, chunk).await?;
+            translated.push_str(text.trim_end_matches('\n'));
+            // Keep the blank lines the page was split at
+            translated.push_str(&chunk[chunk.trim_end_matches('\n').len()..]);
+        }
+        Ok(translated)
+    }
+
+    /// Translates a single line of text, such as a page title.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response holds no text
+    pub async fn translate_line(&self, client: &Client, text: &str) -> Result<String, Box<dyn Error>> {
+        let instruction = format!(
+            "Translate this documentation page title or description into {}. Reply with the translation only.",
+            self.target
+        );
+        let text = self.complete(client, &instruction, text).await?;
+        Ok(text.trim().trim_matches('"').replace(['\r', '\n'], " "))
+    }
+
+    /// Sends one chat completion request and returns the reply.
+    async fn complete(&self, client: &Client, instruction: &str, text: &str) -> Result<String, Box<dyn Error>> {
+        let body = json!({
+            "model": self.model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": instruction },
+                { "role": "user", "content": text }
+            ]
+        });
+        let mut request = client.post(&self.endpoint).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(format!("Translation endpoint answered with status {}: {}", status, redact::preview(&text)).into());
+        }
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| format!("Failed to parse translation response ({}): {}", e, redact::preview(&text)))?;
+        let translated = value["choices"][0]["message"]["content"]
+            .as_str()
+            .filter(|content| !content.trim().is_empty())
+            .ok_or_else(|| format!("No translation in response: {}", redact::preview(&text)))?;
+        Ok(translated.to_string())
+    }
+}
+
+/// Translates a page's markdown and title, unless the saved file already holds their translation.
+///
+/// The saved translation is kept when the file was translated into the same
+/// language from the same original, so unchanged pages cost nothing and keep
+/// their wording.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client
+/// * `translator` - The translation endpoint
+/// * `markdown` - The page's markdown in its original language
+/// * `metadata` - The page's metadata; its title is replaced by the translation
+/// * `saved` - Content of the page's previously saved file, if any
+///
+/// # Returns
+///
+/// A `Result` containing the translated markdown and the translation details for the frontmatter
+///
+/// # Errors
+///
+/// Returns an error if a translation request fails
+pub async fn translate_page(
+    client: &Client,
+    translator: &Translator,
+    markdown: &str,
+    metadata: &mut Metadata,
+    saved: Option<&str>,
+) -> Result<(String, Translation), Box<dyn Error>> {
+    let translation = Translation {
+        language: translator.target.clone(),
+        original_language: metadata.language.clone().unwrap_or_else(|| UNDETERMINED.to_string()),
+        original_hash: tombstone::content_hash(markdown),
+    };
+
+    let fields = saved.and_then(frontmatter::parse_frontmatter);
+    let saved_translation = fields.as_ref().filter(|fields| {
+        fields.get("language") == Some(&translation.language)
+            && fields.get("originalHash") == Some(&translation.original_hash)
+    });
+    if let (Some(fields), Some(saved)) = (saved_translation, saved) {
+        if metadata.title.is_some() {
+            metadata.title = fields.get("title").cloned();
+        }
+        let body = markdown::strip_citation_footer(frontmatter::strip_frontmatter(saved));
+        return Ok((body.to_string(), translation));
+    }
+
+    let translated = translator.translate_markdown(client, markdown).await?;
+    if let Some(title) = &metadata.title {
+        metadata.title = Some(translator.translate_line(client, title).await?);
+    }
+    Ok((translated, translation))
+}
+
+/// Returns the language of a tag without region or script, e.g. `pt` for `pt-BR`.
+fn primary_subtag(language: &str) -> String {
+    language.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase()
+}
+
+/// Splits markdown into pieces of at most `max_chars` characters.
+///
+/// Pieces end after a blank line outside code blocks; a single paragraph or
+/// code block longer than `max_chars` becomes a piece of its own. Joining the
+/// pieces gives back the markdown.
+fn chunks(markdown: &str, max_chars: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut last_break = None;
+    let mut fence: Option<Fence> = None;
+    let mut position = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        match fence {
+            Some(open) if open.closes(line) => fence = None,
+            Some(_) => {}
+            None => fence = Fence::open(line),
+        }
+        let end = position + line.len();
+        if markdown[start..end].chars().count() > max_chars {
+            if let Some(split) = last_break.filter(|split| *split > start) {
+                chunks.push(&markdown[start..split]);
+                start = split;
+            }
+        }
+        if fence.is_none() && line.trim().is_empty() {
+            last_break = Some(end);
+        }
+        position = end;
+    }
+    if start < markdown.len() {
+        chunks.push(&markdown[start..]);
+    }
+    chunks
+}
@@ -0,0 +1,442 @@
+//! URL helpers shared by link filtering and file naming.
+//!
+//! Hosts are handled in two forms: the ASCII (punycode) form is used whenever
+//! hosts are compared, and the Unicode display form is used whenever a host
+//! ends up in a directory or file name a person will read.
+
+use clap::Args;
+use regex::Regex;
+use std::error::Error;
+use url::Url;
+
+use crate::page_type::{self, PageType};
+
+/// Returns the host of a URL in its canonical ASCII form for comparisons.
+///
+/// The host is lowercased, internationalized labels are in punycode and a
+/// trailing dot (fully qualified form) is removed, so `Example.COM.`,
+/// `example.com` and `xn--bcher-kva.example` / `bücher.example` compare equal
+/// to their counterparts.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::urls::ascii_host;
+/// # use url::Url;
+/// let url = Url::parse("https://Bücher.Example./docs").unwrap();
+/// assert_eq!(ascii_host(&url).as_deref(), Some("xn--bcher-kva.example"));
+/// ```
+pub fn ascii_host(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// Returns the form of a page URL the scraper keys pages by.
+///
+/// The URL is normalized as by any URL parser (lowercase scheme and host,
+/// punycode hosts, no default port, `/` for an empty path, `./` and `../`
+/// segments resolved), the trailing dot of a fully qualified host and an empty
+/// query (`?`) are dropped, and its fragment is removed, since fragments point
+/// into a page rather than to another one. The manifest, reports and exports
+/// refer to pages by this form.
+///
+/// # Errors
+///
+/// Returns an error if the URL cannot be parsed or is not an HTTP(S) URL
+///
+/// # Examples
+///
+/// ```
+/// use scraper::urls::canonical_url;
+///
+/// assert_eq!(canonical_url("HTTPS://Docs.Example.com.:443/a/./b/../guide?#install")?, "https://docs.example.com/a/guide");
+/// assert_eq!(canonical_url("https://bücher.example")?, "https://xn--bcher-kva.example/");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn canonical_url(url: &str) -> Result<String, Box<dyn Error>> {
+    let mut parsed = Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Not an HTTP(S) URL: {}", url).into());
+    }
+    if let Some(host) = parsed.host_str().and_then(|host| host.strip_suffix('.')).map(str::to_string) {
+        parsed.set_host(Some(&host)).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    }
+    if parsed.query() == Some("") {
+        parsed.set_query(None);
+    }
+    parsed.set_fragment(None);
+    Ok(parsed.to_string())
+}
+
+/// Further normalization of page URLs, chosen on the command line.
+///
+/// Sites often link one page under several URLs: with and without a trailing
+/// slash, or with tracking and session parameters. Normalized the same way,
+/// they are scraped once and saved to one file.
+#[derive(Debug, Clone, Default, Args)]
+pub struct UrlNormalization {
+    /// Treat `/guide/` and `/guide` as the same page by dropping trailing slashes from paths other than `/`
+    #[arg(long)]
+    pub strip_trailing_slash: bool,
+
+    /// Drop this query parameter from page URLs, e.g. 'ref'; a trailing '*' matches a prefix, as in 'utm_*'; may be repeated
+    #[arg(long = "strip-query-param", value_name = "NAME")]
+    pub strip_query_params: Vec<String>,
+}
+
+impl UrlNormalization {
+    /// Returns the form of a page URL the scraper keys pages by, as `canonical_url`
+    /// does, with the chosen trailing slash and query parameter normalization.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL cannot be parsed or is not an HTTP(S) URL
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scraper::urls::UrlNormalization;
+    ///
+    /// let normalization = UrlNormalization {
+    ///     strip_trailing_slash: true,
+    ///     strip_query_params: vec!["utm_*".to_string(), "ref".to_string()],
+    /// };
+    /// assert_eq!(normalization.canonical_url("https://example.com/guide/?utm_source=x&v=2")?, "https://example.com/guide?v=2");
+    /// assert_eq!(normalization.canonical_url("https://example.com/?ref=nav")?, "https://example.com/");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn canonical_url(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        let canonical = canonical_url(url)?;
+        if !self.strip_trailing_slash && self.strip_query_params.is_empty() {
+            return Ok(canonical);
+        }
+
+        let mut parsed = Url::parse(&canonical)?;
+        if self.strip_trailing_slash && parsed.path().len() > 1 && parsed.path().ends_with('/') {
+            let path = parsed.path().trim_end_matches('/').to_string();
+            parsed.set_path(if path.is_empty() { "/" } else { &path });
+        }
+        if let Some(query) = parsed.query().filter(|_| !self.strip_query_params.is_empty()) {
+            // The kept parameters are left as written, so their encoding doesn't change
+            let kept: Vec<&str> = query
+                .split('&')
+                .filter(|pair| !self.strips(pair.split('=').next().unwrap_or_default()))
+                .collect();
+            let kept = kept.join("&");
+            parsed.set_query((!kept.is_empty()).then_some(kept.as_str()));
+        }
+        Ok(parsed.to_string())
+    }
+
+    /// Returns whether a query parameter is dropped.
+    fn strips(&self, name: &str) -> bool {
+        self.strip_query_params.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        })
+    }
+}
+
+/// Returns the host of a URL in its Unicode display form.
+///
+/// Punycode labels are decoded; labels that fail to decode are kept as-is.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::urls::display_host;
+/// # use url::Url;
+/// let url = Url::parse("https://xn--bcher-kva.example/docs").unwrap();
+/// assert_eq!(display_host(&url).as_deref(), Some("bücher.example"));
+/// ```
+pub fn display_host(url: &Url) -> Option<String> {
+    let host = ascii_host(url)?;
+    if !host.split('.').any(|label| label.starts_with("xn--")) {
+        return Some(host);
+    }
+
+    let (unicode, result) = idna::domain_to_unicode(&host);
+    Some(if result.is_ok() { unicode } else { host })
+}
+
+/// Include and exclude patterns limiting which discovered URLs are scraped.
+#[derive(Debug, Clone, Default, Args)]
+pub struct UrlFilter {
+    /// Only scrape URLs whose path matches this glob, e.g. '/docs/**'; may be repeated
+    #[arg(long = "include", value_name = "GLOB", value_parser = UrlGlob::parse)]
+    pub include: Vec<UrlGlob>,
+
+    /// Don't scrape URLs whose path matches this glob, e.g. '**/changelog/**'; may be repeated
+    #[arg(long = "exclude", value_name = "GLOB", value_parser = UrlGlob::parse)]
+    pub exclude: Vec<UrlGlob>,
+
+    /// Only scrape URLs matching this regular expression; may be repeated
+    #[arg(long = "include-regex", value_name = "REGEX", value_parser = parse_regex)]
+    pub include_regex: Vec<Regex>,
+
+    /// Don't scrape URLs matching this regular expression; may be repeated
+    #[arg(long = "exclude-regex", value_name = "REGEX", value_parser = parse_regex)]
+    pub exclude_regex: Vec<Regex>,
+
+    /// Don't scrape landing pages: the site root and language roots such as /en/
+    #[arg(long)]
+    pub skip_landing_pages: bool,
+}
+
+impl UrlFilter {
+    /// Returns whether a URL matches at least one include pattern (if there are any) and no exclude pattern.
+    ///
+    /// Globs and regular expressions are combined: a URL matching an include
+    /// glob or an include expression is included. With `--skip-landing-pages`
+    /// URLs classified as landing pages are excluded as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use regex::Regex;
+    /// # use scraper::urls::{UrlFilter, UrlGlob};
+    /// let filter = UrlFilter {
+    ///     include: vec![UrlGlob::parse("/docs/**").unwrap()],
+    ///     exclude_regex: vec![Regex::new("/docs/v1/").unwrap()],
+    ///     ..UrlFilter::default()
+    /// };
+    /// assert!(filter.allows("https://example.com/docs/intro"));
+    /// assert!(!filter.allows("https://example.com/docs/v1/intro"));
+    /// assert!(!filter.allows("https://example.com/blog/"));
+    /// ```
+    pub fn allows(&self, url: &str) -> bool {
+        let has_includes = !self.include.is_empty() || !self.include_regex.is_empty();
+        let included = self.include.iter().any(|glob| glob.matches(url))
+            || self.include_regex.iter().any(|regex| regex.is_match(url));
+        let excluded = self.exclude.iter().any(|glob| glob.matches(url))
+            || self.exclude_regex.iter().any(|regex| regex.is_match(url))
+            || (self.skip_landing_pages && page_type::classify_url(url) == Some(PageType::Landing));
+        (!has_includes || included) && !excluded
+    }
+}
+
+/// A glob pattern matched against the path of a URL.
+///
+/// `*` matches within one path segment, `**` across segments and `?` matches
+/// a single character other than `/`. `/docs/**` matches `/docs` and
+/// everything below it, and `**/changelog/**` matches a `changelog` directory
+/// at any depth. A glob containing `://` is matched against the full URL
+/// instead, so a single host or scheme can be picked out.
+#[derive(Debug, Clone)]
+pub struct UrlGlob {
+    /// The glob as given
+    pattern: String,
+
+    /// The glob translated into an anchored regular expression
+    regex: Regex,
+}
+
+impl UrlGlob {
+    /// Parses a glob given on the command line.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the glob is empty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use scraper::urls::UrlGlob;
+    /// let glob = UrlGlob::parse("/docs/*/intro").unwrap();
+    /// assert!(glob.matches("https://example.com/docs/v2/intro"));
+    /// assert!(!glob.matches("https://example.com/docs/v2/guides/intro"));
+    /// ```
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("the glob is empty".to_string());
+        }
+        let mut regex = String::from("^");
+        let mut rest = pattern;
+        while let Some(c) = rest.chars().next() {
+            let (translated, consumed) = if rest == "/**" {
+                ("(?:/.*)?".to_string(), 3)
+            } else if rest.starts_with("**/") {
+                ("(?:.*/)?".to_string(), 3)
+            } else if rest.starts_with("**") {
+                (".*".to_string(), 2)
+            } else if c == '*' {
+                ("[^/]*".to_string(), 1)
+            } else if c == '?' {
+                ("[^/]".to_string(), 1)
+            } else {
+                (regex::escape(&c.to_string()), c.len_utf8())
+            };
+            regex.push_str(&translated);
+            rest = &rest[consumed..];
+        }
+        regex.push('$');
+        Ok(UrlGlob {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&regex).map_err(|e| e.to_string())?,
+        })
+    }
+
+    /// Returns the anchored regular expression the glob matches paths with, or
+    /// `None` for a glob matched against the full URL.
+    ///
+    /// This is the form FireCrawl's crawl jobs take path filters in.
+    pub fn path_regex(&self) -> Option<&str> {
+        (!self.pattern.contains("://")).then(|| self.regex.as_str())
+    }
+
+    /// Returns the glob as given.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns whether the URL's path, or the full URL for globs containing `://`, matches.
+    pub fn matches(&self, url: &str) -> bool {
+        if self.pattern.contains("://") {
+            return self.regex.is_match(url);
+        }
+        match Url::parse(url) {
+            Ok(parsed) => self.regex.is_match(parsed.path()),
+            Err(_) => self.regex.is_match(url),
+        }
+    }
+}
+
+/// Parses a regular expression given on the command line.
+///
+/// # Errors
+///
+/// Returns an error if the expression is invalid
+pub fn parse_regex(value: &str) -> Result<Regex, String> {
+    // Regex errors span several lines to point at the problem; keep them on one
+    Regex::new(value).map_err(|e| e.to_string().split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Returns a URL string with its host replaced by the Unicode display form.
+///
+/// Used when a URL is turned into a file name; URLs that cannot be parsed are
+/// returned unchanged.
+///
+/// # Examples
+///
+/// ```
+/// # use scraper::urls::display_url;
+/// assert_eq!(display_url("https://xn--bcher-kva.example/a"), "https://bücher.example/a");
+/// ```
+pub fn display_url(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    match (parsed.host_str(), display_host(&parsed)) {
+        (Some(ascii), Some(display)) if ascii != display => url.replacen(ascii, &display, 1),
+        _ => url.to_string(),
+    }
+}
+
+/// A regular expression rewrite applied to discovered URLs.
+#[derive(Debug, Clone)]
+pub struct RewriteRule {
+    regex: Regex,
+    replacement: String,
+}
+
+impl RewriteRule {
+    /// Creates a rule replacing every match of `regex` with `replacement`.
+    ///
+    /// The replacement may reference capture groups as `$1` or `${name}`.
+    pub fn new(regex: Regex, replacement: String) -> Self {
+        RewriteRule { regex, replacement }
+    }
+}
+
+/// Applies rewrite rules to a URL, in order, each to the output of the previous one.
+///
+/// # Examples
+///
+/// ```
+/// # use regex::Regex;
+/// # use scraper::urls::{rewrite_url, RewriteRule};
+/// let rules = vec![RewriteRule::new(Regex::new("^http://").unwrap(), "https://".to_string())];
+/// assert_eq!(rewrite_url(&rules, "http://docs.example.com/a"), "https://docs.example.com/a");
+/// ```
+pub fn rewrite_url(rules: &[RewriteRule], url: &str) -> String {
+    rules.iter().fold(url.to_string(), |current, rule| {
+        rule.regex
+            .replace_all(&current, rule.replacement.as_str())
+            .into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalization(strip_trailing_slash: bool, params: &[&str]) -> UrlNormalization {
+        UrlNormalization {
+            strip_trailing_slash,
+            strip_query_params: params.iter().map(|param| param.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn query_params_are_stripped_by_name_or_prefix() {
+        let normalization = normalization(false, &["utm_*", "ref"]);
+        assert_eq!(
+            normalization.canonical_url("https://example.com/a?utm_source=x&v=2&utm_medium=y&ref=nav").unwrap(),
+            "https://example.com/a?v=2"
+        );
+        // A name without `*` must match whole, and a prefix only matches the start
+        assert_eq!(
+            normalization.canonical_url("https://example.com/a?referrer=x&utmost=1&x_utm_a=2").unwrap(),
+            "https://example.com/a?referrer=x&utmost=1&x_utm_a=2"
+        );
+        assert_eq!(normalization.canonical_url("https://example.com/a?ref=nav&utm_x").unwrap(), "https://example.com/a");
+    }
+
+    #[test]
+    fn kept_query_params_keep_their_order_and_encoding() {
+        let normalization = normalization(false, &["sid"]);
+        assert_eq!(
+            normalization.canonical_url("https://example.com/search?q=a%20b&sid=1&lang=en").unwrap(),
+            "https://example.com/search?q=a%20b&lang=en"
+        );
+    }
+
+    #[test]
+    fn trailing_slashes_are_stripped_except_from_the_root() {
+        let normalization = normalization(true, &[]);
+        assert_eq!(normalization.canonical_url("https://example.com/guide/").unwrap(), "https://example.com/guide");
+        assert_eq!(normalization.canonical_url("https://example.com/guide//").unwrap(), "https://example.com/guide");
+        assert_eq!(normalization.canonical_url("https://example.com/").unwrap(), "https://example.com/");
+        assert_eq!(normalization.canonical_url("https://example.com").unwrap(), "https://example.com/");
+        assert_eq!(normalization.canonical_url("https://example.com/a/?v=1#top").unwrap(), "https://example.com/a?v=1");
+    }
+
+    #[test]
+    fn without_options_urls_are_only_made_canonical() {
+        let normalization = UrlNormalization::default();
+        assert_eq!(
+            normalization.canonical_url("HTTPS://Example.com/guide/?utm_source=x#top").unwrap(),
+            "https://example.com/guide/?utm_source=x"
+        );
+    }
+
+    #[test]
+    fn normalizing_twice_changes_nothing() {
+        let normalization = normalization(true, &["utm_*", "ref"]);
+        for url in [
+            "https://Example.com/guide/?utm_source=x&v=2#install",
+            "https://example.com/a/b/../c/?ref=1",
+            "https://example.com/?utm_medium=y",
+            "https://bücher.example/docs//?q=a%20b",
+        ] {
+            let once = normalization.canonical_url(url).unwrap();
+            assert_eq!(normalization.canonical_url(&once).unwrap(), once, "{}", url);
+        }
+    }
+
+    #[test]
+    fn non_http_urls_are_refused() {
+        assert!(normalization(true, &[]).canonical_url("ftp://example.com/file").is_err());
+        assert!(normalization(true, &[]).canonical_url("not a url").is_err());
+    }
+}
@@ -0,0 +1,392 @@
+//! Interactive setup of a configuration file and `.env`.
+//!
+//! `init` without a template asks for the settings a new user needs before the
+//! first scrape (the API key, where to save pages, a credit budget and extra
+//! formats) and writes them to `scraper.toml` and `.env`, so nobody has to
+//! look up variable names first. The key isn't echoed while it is typed, and
+//! an existing `.env` keeps its other variables.
+
+use clap::ValueEnum;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+#[cfg(unix)]
+use std::io::IsTerminal;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use crate::config::DEFAULT_CONFIG_FILE;
+use crate::formats::PageFormat;
+
+/// File the API settings are written to.
+const ENV_FILE: &str = ".env";
+
+/// API URL used when `FIRECRAWL_API_URL` is not set.
+const DEFAULT_API_URL: &str = "https://api.firecrawl.dev";
+
+/// Commented examples appended to the written configuration file.
+const CONFIG_EXAMPLES: &str = r#"
+# More settings, see the scraper README:
+#
+# date_format = "date"
+# min_quality = 0.5
+#
+# [[rewrite]]
+# pattern = "^http://"
+# replacement = "https://"
+"#;
+
+/// Settings collected by the wizard.
+#[derive(Debug)]
+struct Answers {
+    api_key: String,
+    api_url: String,
+    output_dir: Option<String>,
+    credits_per_minute: Option<u32>,
+    formats: Vec<PageFormat>,
+}
+
+/// Asks for the settings of a new setup and writes `scraper.toml` and `.env` into `dir`.
+///
+/// Every question has a default taken by pressing enter. An existing
+/// `scraper.toml` is only replaced, and the FireCrawl settings of an existing
+/// `.env` only updated, after confirmation.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to write the files to
+/// * `input` - Where the answers are read from
+/// * `output` - Where the questions are written to
+///
+/// # Returns
+///
+/// A `Result` containing the paths of the written files
+///
+/// # Errors
+///
+/// Returns an error if the input ends before all questions are answered or a
+/// file cannot be written
+///
+/// # Examples
+///
+/// ```text
+/// let written = wizard::run(Path::new("."), &mut io::stdin().lock(), &mut io::stderr())?;
+/// ```
+pub fn run(dir: &Path, input: &mut impl BufRead, output: &mut impl Write) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    writeln!(output, "Setting up the scraper in {}. Press enter to accept a default.", display_dir(dir))?;
+
+    let current_key = std::env::var("FIRECRAWL_API_KEY").ok().filter(|key| !key.is_empty());
+    let keep_key = current_key.as_ref().map(|_| "keep the current key");
+    let hidden = HiddenInput::start();
+    let api_key = ask(input, output, "FireCrawl API key", keep_key, |value| match (value, &current_key) {
+        ("", Some(key)) => Ok(key.clone()),
+        ("", None) => Err("An API key is needed for the FireCrawl backend".to_string()),
+        (key, _) => Ok(key.to_string()),
+    });
+    drop(hidden);
+    let api_key = api_key?;
+    writeln!(output, "Using API key {}", mask_key(&api_key))?;
+
+    let api_url = ask(input, output, "FireCrawl API URL", Some(DEFAULT_API_URL), |value| match value {
+        "" => Ok(std::env::var("FIRECRAWL_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string())),
+        url if url.starts_with("http://") || url.starts_with("https://") => Ok(url.trim_end_matches('/').to_string()),
+        _ => Err("The URL must start with http:// or https://".to_string()),
+    })?;
+
+    let output_dir = ask(input, output, "Directory to save sites in", Some("the current directory"), |value| {
+        Ok((!value.is_empty()).then(|| value.to_string()))
+    })?;
+
+    let credits_per_minute = ask(input, output, "API credits to spend per minute at most", Some("no limit"), |value| {
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match value.parse::<u32>() {
+            Ok(credits) if credits > 0 => Ok(Some(credits)),
+            _ => Err("Enter a whole number above 0".to_string()),
+        }
+    })?;
+
+    let question = "Formats to save next to each page (html, raw-html, links, screenshot)";
+    let formats = ask(input, output, question, Some("markdown only"), |value| {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|format| !format.is_empty())
+            .map(|format| PageFormat::from_str(format, true).map_err(|_| format!("Unknown format {}", format)))
+            .collect()
+    })?;
+
+    let answers = Answers {
+        api_key,
+        api_url,
+        output_dir,
+        credits_per_minute,
+        formats,
+    };
+
+    let mut written = Vec::new();
+    let config_path = dir.join(DEFAULT_CONFIG_FILE);
+    if confirm(&config_path, "Replace it", input, output)? {
+        fs::create_dir_all(dir)?;
+        fs::write(&config_path, config_file(&answers))
+            .map_err(|e| format!("Failed to write {}: {}", config_path.display(), e))?;
+        written.push(config_path);
+    }
+    let env_path = dir.join(ENV_FILE);
+    if confirm(&env_path, "Update its FireCrawl settings", input, output)? {
+        let existing = match fs::read_to_string(&env_path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("Failed to read {}: {}", env_path.display(), e).into()),
+        };
+        fs::create_dir_all(dir)?;
+        write_private(&env_path, &env_file(&answers, existing.as_deref()))
+            .map_err(|e| format!("Failed to write {}: {}", env_path.display(), e))?;
+        written.push(env_path);
+    }
+    Ok(written)
+}
+
+/// Asks a question until `parse` accepts the answer.
+///
+/// `default` describes what an empty answer means; `parse` receives the
+/// trimmed answer and returns an error message to show for invalid ones.
+fn ask<T>(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: Option<&str>,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<T, Box<dyn Error>> {
+    loop {
+        match default {
+            Some(default) => write!(output, "{} [{}]: ", question, default)?,
+            None => write!(output, "{}: ", question)?,
+        }
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err("Input ended before all questions were answered".into());
+        }
+        match parse(line.trim()) {
+            Ok(value) => return Ok(value),
+            Err(e) => writeln!(output, "{}", e)?,
+        }
+    }
+}
+
+/// Returns whether a file may be written: it doesn't exist or `action` on it was confirmed.
+fn confirm(path: &Path, action: &str, input: &mut impl BufRead, output: &mut impl Write) -> Result<bool, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(true);
+    }
+    let question = format!("{} exists. {}?", path.display(), action);
+    let replace = ask(input, output, &question, Some("y/N"), |value| match value.to_ascii_lowercase().as_str() {
+        "" | "n" | "no" => Ok(false),
+        "y" | "yes" => Ok(true),
+        _ => Err("Answer y or n".to_string()),
+    })?;
+    if !replace {
+        writeln!(output, "Keeping {}", path.display())?;
+    }
+    Ok(replace)
+}
+
+/// Renders the configuration file for the answers.
+fn config_file(answers: &Answers) -> String {
+    let mut content = String::from("# Written by `scraper init`; command line options take precedence.\n");
+    if let Some(dir) = &answers.output_dir {
+        content.push_str(&format!("output_dir = {}\n", toml::Value::String(dir.clone())));
+    }
+    if let Some(credits) = answers.credits_per_minute {
+        content.push_str(&format!("credits_per_minute = {}\n", credits));
+    }
+    if !answers.formats.is_empty() {
+        let formats: Vec<String> = answers
+            .formats
+            .iter()
+            .filter_map(|format| format.to_possible_value())
+            .map(|value| format!("\"{}\"", value.get_name()))
+            .collect();
+        content.push_str(&format!("formats = [{}]\n", formats.join(", ")));
+    }
+    content.push_str(CONFIG_EXAMPLES);
+    content
+}
+
+/// Renders the `.env` file for the answers.
+///
+/// With the `existing` content of the file only the `FIRECRAWL_API_KEY` and
+/// `FIRECRAWL_API_URL` lines are replaced; every other line is kept and
+/// settings the file lacks are appended.
+fn env_file(answers: &Answers, existing: Option<&str>) -> String {
+    let mut settings = vec![("FIRECRAWL_API_KEY", answers.api_key.as_str(), true)];
+    settings.push(("FIRECRAWL_API_URL", answers.api_url.as_str(), answers.api_url != DEFAULT_API_URL));
+
+    let mut content = String::new();
+    let mut set = Vec::new();
+    match existing {
+        Some(existing) => {
+            for line in existing.lines() {
+                let name = line.trim_start().trim_start_matches("export ").split('=').next().unwrap_or("").trim();
+                match settings.iter().find(|(setting, _, _)| *setting == name) {
+                    Some((setting, value, _)) if !set.contains(setting) => {
+                        content.push_str(&format!("{}={}\n", setting, value));
+                        set.push(*setting);
+                    }
+                    // A repeated setting would override the one just written
+                    Some(_) => {}
+                    None => content.push_str(&format!("{}\n", line)),
+                }
+            }
+        }
+        None => content.push_str("# Written by `scraper init`. Keep this file out of version control.\n"),
+    }
+    for (setting, value, needed) in settings {
+        if needed && !set.contains(&setting) {
+            content.push_str(&format!("{}={}\n", setting, value));
+        }
+    }
+    content
+}
+
+/// Writes a file only its owner can read, as it holds the API key.
+fn write_private(path: &Path, content: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to new files; an existing one keeps its permissions otherwise
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(content.as_bytes())
+}
+
+/// Masks an API key for display, showing only its last four characters.
+fn mask_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let shown: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}", "*".repeat(chars.len() - 4), shown)
+}
+
+/// Turns off the terminal's echo while the API key is typed, restoring it when dropped.
+#[cfg(unix)]
+struct HiddenInput(rustix::termios::Termios);
+
+#[cfg(unix)]
+impl HiddenInput {
+    /// Hides typed characters, or returns `None` when stdin isn't a terminal.
+    fn start() -> Option<Self> {
+        use rustix::termios::{tcgetattr, tcsetattr, LocalModes, OptionalActions};
+
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return None;
+        }
+        let original = tcgetattr(&stdin).ok()?;
+        let mut hidden = original.clone();
+        hidden.local_modes.remove(LocalModes::ECHO);
+        // Still echo the newline so the next question starts on its own line
+        hidden.local_modes.insert(LocalModes::ECHONL);
+        tcsetattr(&stdin, OptionalActions::Now, &hidden).ok()?;
+        Some(HiddenInput(original))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for HiddenInput {
+    fn drop(&mut self) {
+        let _ = rustix::termios::tcsetattr(io::stdin(), rustix::termios::OptionalActions::Now, &self.0);
+    }
+}
+
+/// Elsewhere the key is echoed, and only its masked confirmation hides it.
+#[cfg(not(unix))]
+struct HiddenInput;
+
+#[cfg(not(unix))]
+impl HiddenInput {
+    fn start() -> Option<Self> {
+        None
+    }
+}
+
+/// Returns a directory for display, naming the current directory as such.
+fn display_dir(dir: &Path) -> String {
+    if dir == Path::new(".") || dir.as_os_str().is_empty() {
+        "the current directory".to_string()
+    } else {
+        dir.display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(api_url: &str) -> Answers {
+        Answers {
+            api_key: "fc-new-key".to_string(),
+            api_url: api_url.to_string(),
+            output_dir: None,
+            credits_per_minute: None,
+            formats: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn new_env_file_holds_the_key_and_a_custom_url() {
+        let content = env_file(&answers(DEFAULT_API_URL), None);
+        assert!(content.starts_with("# Written by `scraper init`"));
+        assert!(content.ends_with("\nFIRECRAWL_API_KEY=fc-new-key\n"));
+
+        let content = env_file(&answers("http://localhost:3002"), None);
+        assert!(content.ends_with("FIRECRAWL_API_KEY=fc-new-key\nFIRECRAWL_API_URL=http://localhost:3002\n"));
+    }
+
+    #[test]
+    fn existing_env_file_keeps_other_variables() {
+        let existing = "# secrets\nDATABASE_URL=postgres://db\nexport FIRECRAWL_API_KEY=fc-old\nFIRECRAWL_API_KEY=fc-older\nRUST_LOG=debug\n";
+        let content = env_file(&answers(DEFAULT_API_URL), Some(existing));
+        assert_eq!(content, "# secrets\nDATABASE_URL=postgres://db\nFIRECRAWL_API_KEY=fc-new-key\nRUST_LOG=debug\n");
+
+        let existing = "RUST_LOG=debug\nFIRECRAWL_API_URL=http://old:3002\n";
+        let content = env_file(&answers("http://localhost:3002"), Some(existing));
+        assert_eq!(content, "RUST_LOG=debug\nFIRECRAWL_API_URL=http://localhost:3002\nFIRECRAWL_API_KEY=fc-new-key\n");
+    }
+
+    #[test]
+    fn keys_are_masked() {
+        assert_eq!(mask_key("fc-0123456789abcdef"), "***************cdef");
+        assert_eq!(mask_key("short"), "*****");
+    }
+
+    #[test]
+    fn wizard_updates_an_existing_env_file_privately() {
+        let dir = std::env::temp_dir().join(format!("scraper-wizard-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let env_path = dir.join(ENV_FILE);
+        fs::write(&env_path, "OTHER=1\nFIRECRAWL_API_KEY=fc-old\n").unwrap();
+
+        let mut input = io::Cursor::new("fc-0123456789abcdef\nhttps://api.firecrawl.dev\n\n\n\ny\n");
+        let mut output = Vec::new();
+        let written = run(&dir, &mut input, &mut output).unwrap();
+
+        assert_eq!(written, vec![dir.join(DEFAULT_CONFIG_FILE), env_path.clone()]);
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), "OTHER=1\nFIRECRAWL_API_KEY=fc-0123456789abcdef\n");
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Using API key ***************cdef"));
+        assert!(!output.contains("fc-0123456789abcdef"));
+        #[cfg(unix)]
+        assert_eq!(fs::metadata(&env_path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,810 @@
+//! Workspace mode: many documentation sites run with one command.
+//!
+//! A workspace file (`sites.toml` by default) lists the sites to mirror. Each
+//! site takes its scrape options from an optional named profile plus its own
+//! arguments, written exactly as they would be on the command line. `run-all`
+//! scrapes every site that is due according to its schedule, sharing one HTTP
+//! client and one credit budget between them, and writes a combined report,
+//! a dashboard and status files (see `status`) next to the workspace file. Everything logged for a site is
+//! logged in a `site` span carrying its name. `daemon` keeps running and does
+//! the same whenever a site comes due. Sites whose run trips a `pause_when`
+//! condition are paused until unpaused (see `pause`).
+//!
+//! ```toml
+//! [workspace]
+//! credits_per_minute = 120
+//!
+//! [profiles.reference]
+//! args = ["--discover", "sitemap", "--path-template", "{path_dir}/{slug}.md"]
+//!
+//! [[site]]
+//! name = "stripe"
+//! url = "https://docs.stripe.com/"
+//! profile = "reference"
+//! schedule = "daily"
+//! exports = ["jsonl"]
+//!
+//! [[site]]
+//! name = "internal"
+//! url = "https://docs.internal.example.com/"
+//! args = ["--backend", "direct"]
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use croner::Cron;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tokio::net::TcpListener;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::api::Api;
+use crate::cli::{Backend, ScrapeOptions};
+use crate::config::Config;
+use crate::input_url;
+use crate::notify::{Event, Notification, NotifierConfig, Notifiers};
+use crate::pause::{Pause, PauseConditions};
+use crate::report::REPORT_FILE_NAME;
+use crate::shutdown;
+use crate::status;
+use crate::throttle::CreditPacer;
+
+/// Workspace file read when none is given.
+pub const DEFAULT_WORKSPACE_FILE: &str = "sites.toml";
+
+/// File name of the combined JSON report, next to the workspace file.
+pub const WORKSPACE_REPORT_FILE_NAME: &str = "workspace-report.json";
+
+/// File name of the combined markdown dashboard, next to the workspace file.
+pub const DASHBOARD_FILE_NAME: &str = "dashboard.md";
+
+/// Shortest wait between two rounds of the daemon, so a failing site is not retried in a tight loop.
+const DAEMON_MIN_WAIT: Duration = Duration::minutes(5);
+
+/// Longest wait between two rounds of the daemon, so `always` sites and edits
+/// to the workspace file are picked up.
+const DAEMON_MAX_WAIT: Duration = Duration::hours(1);
+
+/// Contents of a workspace file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Workspace {
+    /// Settings shared by all sites
+    pub workspace: WorkspaceSettings,
+
+    /// Named sets of scrape arguments that sites can refer to
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// The sites to scrape, in order
+    #[serde(rename = "site")]
+    pub sites: Vec<Site>,
+}
+
+/// Settings shared by all sites of a workspace.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceSettings {
+    /// Credits per minute shared by all sites, like `--credits-per-minute`
+    pub credits_per_minute: Option<u32>,
+
+    /// Also share the budget with other scraper processes, like `--shared-budget`
+    pub shared_budget: bool,
+
+    /// Conditions under which a site is paused after its run, unless the site sets its own
+    pub pause_when: PauseConditions,
+
+    /// Webhook a JSON message is posted to when a site is paused, like a `webhook` notifier for `pause` events
+    pub notify_url: Option<String>,
+
+    /// Channels told about failed, paused and changed sites
+    pub notify: Vec<NotifierConfig>,
+}
+
+impl WorkspaceSettings {
+    /// Builds the notifiers of the workspace, including `notify_url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a notifier is misconfigured
+    fn notifiers(&self, client: &Client) -> Result<Notifiers, Box<dyn Error>> {
+        let mut notifiers = Notifiers::from_config(client, &self.notify)?;
+        if let Some(url) = &self.notify_url {
+            let webhook = NotifierConfig::Webhook {
+                url: url.clone(),
+                events: vec![Event::Pause],
+            };
+            notifiers.add(webhook.build(client)?, webhook.events().to_vec());
+        }
+        Ok(notifiers)
+    }
+}
+
+/// A named set of scrape arguments.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Command line arguments, e.g. `["--discover", "sitemap"]`
+    pub args: Vec<String>,
+}
+
+/// One documentation site of a workspace.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Site {
+    /// Unique name of the site, used in reports and as the default output directory
+    pub name: String,
+
+    /// The URL to start scraping from
+    pub url: String,
+
+    /// Output directory relative to the workspace file; defaults to the site name
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+
+    /// Name of the profile whose arguments are applied first
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// How often the site is scraped
+    #[serde(default)]
+    pub schedule: Schedule,
+
+    /// Export formats, like `--export`
+    #[serde(default)]
+    pub exports: Vec<String>,
+
+    /// Further command line arguments, applied after the profile's
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Conditions under which the site is paused after its run, instead of the workspace's
+    #[serde(default)]
+    pub pause_when: Option<PauseConditions>,
+}
+
+/// How often a site is scraped by `run-all` and `daemon`.
+///
+/// Written as `always`, `hourly`, `daily` or `weekly`, as an interval such as
+/// `every 6h` (units `m`, `h`, `d` and `w`), or as a five-field cron expression
+/// such as `0 6 * * 1-5`, evaluated in UTC.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Schedule {
+    /// On every run
+    #[default]
+    Always,
+    /// At most once an hour
+    Hourly,
+    /// At most once a day
+    Daily,
+    /// At most once a week
+    Weekly,
+    /// At most once per interval
+    Every(Duration),
+    /// Once for every time the cron expression matches since the last run
+    Cron(Box<Cron>),
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        match value {
+            "always" => return Ok(Schedule::Always),
+            "hourly" => return Ok(Schedule::Hourly),
+            "daily" => return Ok(Schedule::Daily),
+            "weekly" => return Ok(Schedule::Weekly),
+            _ => {}
+        }
+        if let Some(interval) = value.strip_prefix("every ") {
+            return parse_interval(interval.trim())
+                .map(Schedule::Every)
+                .ok_or_else(|| format!("invalid interval {:?}; expected e.g. \"every 6h\"", interval.trim()));
+        }
+        if value.split_whitespace().count() != 5 {
+            return Err(format!(
+                "invalid schedule {:?}; expected always, hourly, daily, weekly, \"every <interval>\" or a cron expression",
+                value
+            ));
+        }
+        Cron::new(value)
+            .parse()
+            .map(|cron| Schedule::Cron(Box::new(cron)))
+            .map_err(|e| format!("invalid cron expression {:?}: {}", value, e))
+    }
+}
+
+impl Schedule {
+    /// Returns the minimum time between two runs of a site, or `None` for
+    /// `Always` and cron expressions.
+    pub fn period(&self) -> Option<Duration> {
+        match self {
+            Schedule::Always | Schedule::Cron(_) => None,
+            Schedule::Hourly => Some(Duration::hours(1)),
+            Schedule::Daily => Some(Duration::days(1)),
+            Schedule::Weekly => Some(Duration::weeks(1)),
+            Schedule::Every(interval) => Some(*interval),
+        }
+    }
+
+    /// Returns when a site last finished at `last_run` is due again; `None` if it is due now.
+    ///
+    /// A site with a cron expression is due once the expression has matched
+    /// since its last run.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// let last_run = Utc::now() - Duration::hours(2);
+    /// assert!(Schedule::Hourly.next_due(Some(last_run), Utc::now()).is_none());
+    /// assert!(Schedule::Daily.next_due(Some(last_run), Utc::now()).is_some());
+    /// ```
+    pub fn next_due(&self, last_run: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let due = match self {
+            Schedule::Cron(cron) => cron.find_next_occurrence(&last_run?, false).ok()?,
+            _ => last_run? + self.period()?,
+        };
+        (due > now).then_some(due)
+    }
+}
+
+/// Parses an interval such as `30m`, `6h`, `2d` or `1w`.
+fn parse_interval(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = value.split_at(split);
+    let number: i64 = number.parse().ok().filter(|number| *number > 0)?;
+    match unit {
+        "m" => Some(Duration::minutes(number)),
+        "h" => Some(Duration::hours(number)),
+        "d" => Some(Duration::days(number)),
+        "w" => Some(Duration::weeks(number)),
+        _ => None,
+    }
+}
+
+/// Outcome of one site in a `run-all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SiteStatus {
+    /// The site was scraped without a fatal error
+    Succeeded,
+    /// The site's run stopped with an error
+    Failed,
+    /// The site was not due according to its schedule
+    Skipped,
+    /// The site's run tripped a pause condition, in this run or an earlier one, and it is not run until unpaused
+    Paused,
+}
+
+/// Result of one site in the combined report.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteResult {
+    /// Name of the site
+    pub name: String,
+
+    /// The URL the site is scraped from
+    pub url: String,
+
+    /// Output directory of the site
+    pub dir: PathBuf,
+
+    /// What happened to the site in this run
+    pub status: SiteStatus,
+
+    /// The error that stopped the site's run, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// When the site last finished a run (this one, if it succeeded)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<DateTime<Utc>>,
+
+    /// When a skipped site is due again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_due: Option<DateTime<Utc>>,
+
+    /// Why a paused site was paused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_reason: Option<String>,
+
+    /// Page counts from the site's own run report
+    #[serde(flatten)]
+    pub pages: PageCounts,
+}
+
+/// Page counts read back from a site's run report.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PageCounts {
+    /// Number of pages found during discovery
+    pub pages_found: usize,
+    /// Number of pages processed without error
+    pub pages_processed: usize,
+    /// Number of pages that failed to process
+    pub pages_failed: usize,
+    /// Number of pages returned without markdown
+    pub pages_empty: usize,
+    /// Number of pages saved for the first time
+    pub pages_added: usize,
+    /// Number of pages saved again with changed content
+    pub pages_changed: usize,
+    /// Number of pages replaced by a tombstone because they were removed from the site
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_removed: usize,
+    /// Number of pages answered with `401 Unauthorized` or `403 Forbidden`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pages_unauthorized: usize,
+}
+
+/// Returns whether a count is zero, to leave it out of the report.
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+/// The parts of a site's run report that `run-all` reads back.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SiteRunReport {
+    finished_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pages: PageCounts,
+}
+
+/// Combined report of a `run-all`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceReport {
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+
+    /// When the run finished
+    pub finished_at: DateTime<Utc>,
+
+    /// One entry per site, in workspace order
+    pub sites: Vec<SiteResult>,
+}
+
+impl Site {
+    /// Returns the output directory of the site, relative to the workspace file's directory `root`.
+    pub fn output_dir(&self, root: &Path) -> PathBuf {
+        root.join(self.dir.as_deref().unwrap_or(Path::new(&self.name)))
+    }
+
+    /// Returns the conditions the site is paused under: its own, or else the workspace's.
+    pub fn pause_conditions<'a>(&'a self, settings: &'a WorkspaceSettings) -> &'a PauseConditions {
+        self.pause_when.as_ref().unwrap_or(&settings.pause_when)
+    }
+}
+
+impl Workspace {
+    /// Loads a workspace file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file cannot be read or parsed
+    /// - Two sites have the same name
+    /// - A site refers to a profile that is not defined
+    /// - A `pause_when` share is not between 0 and 1
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read workspace {}: {}", path.display(), e))?;
+        let workspace: Workspace = toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse workspace {}: {}", path.display(), e))?;
+
+        workspace
+            .workspace
+            .pause_when
+            .validate()
+            .map_err(|e| format!("Invalid workspace {}: {}", path.display(), e))?;
+        let mut names = HashSet::new();
+        for site in &workspace.sites {
+            if let Some(conditions) = &site.pause_when {
+                conditions.validate().map_err(|e| format!("Invalid site {}: {}", site.name, e))?;
+            }
+            if !names.insert(site.name.as_str()) {
+                return Err(format!("Site {} is defined more than once in {}", site.name, path.display()).into());
+            }
+            if let Some(profile) = &site.profile {
+                if !workspace.profiles.contains_key(profile) {
+                    return Err(format!("Site {} uses undefined profile {}", site.name, profile).into());
+                }
+            }
+        }
+        Ok(workspace)
+    }
+
+    /// Builds the scrape options of a site from its profile, exports and arguments.
+    ///
+    /// The configuration file (`--config`, or `scraper.toml`) is merged in as
+    /// for a single-site run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments are invalid or the configuration cannot be applied
+    pub fn site_options(&self, site: &Site) -> Result<ScrapeOptions, Box<dyn Error>> {
+        let mut args: Vec<String> = Vec::new();
+        if let Some(profile) = site.profile.as_ref().and_then(|name| self.profiles.get(name)) {
+            args.extend(profile.args.iter().cloned());
+        }
+        for export in &site.exports {
+            args.extend(["--export".to_string(), export.clone()]);
+        }
+        args.extend(site.args.iter().cloned());
+
+        let mut options = ScrapeOptions::parse_args(&args)
+            .map_err(|e| format!("Invalid arguments for site {}: {}", site.name, e))?;
+        Config::load(options.config.as_deref())?.apply(&mut options)?;
+        Ok(options)
+    }
+}
+
+/// Scrapes every site of a workspace that is due, then writes the combined report and dashboard.
+///
+/// A failing site is reported and the remaining sites still run. A site whose
+/// run trips its pause conditions is paused and notified about; paused sites
+/// are not run, even with `force`, until unpaused.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client, shared by all sites
+/// * `api_url` - The FireCrawl API endpoint
+/// * `path` - The workspace file; output directories are relative to its directory
+/// * `only` - Names of the sites to run; all sites when empty
+/// * `force` - Run sites even if they are not due according to their schedule
+///
+/// # Returns
+///
+/// The combined report
+///
+/// # Errors
+///
+/// Returns an error if the workspace cannot be loaded, `only` names an unknown
+/// site, the reports cannot be written, or any site failed or was paused by this run
+pub async fn run_all(
+    client: &Client,
+    api_url: &str,
+    path: &Path,
+    only: &[String],
+    force: bool,
+) -> Result<WorkspaceReport, Box<dyn Error>> {
+    let workspace = Workspace::load(path)?;
+    if let Some(unknown) = only.iter().find(|name| !workspace.sites.iter().any(|site| &site.name == *name)) {
+        return Err(format!("No site named {} in {}", unknown, path.display()).into());
+    }
+
+    let root = path.parent().unwrap_or(Path::new(""));
+    let pacer = CreditPacer::new(workspace.workspace.credits_per_minute);
+    let pacer = if workspace.workspace.shared_budget {
+        pacer.share(&crate::api::api_key(Backend::Firecrawl)?)?
+    } else {
+        pacer
+    };
+    let notifiers = workspace.workspace.notifiers(client)?;
+    let started_at = Utc::now();
+    let mut results = Vec::new();
+    let mut paused = 0;
+
+    for site in &workspace.sites {
+        if !only.is_empty() && !only.contains(&site.name) {
+            continue;
+        }
+
+        let span = info_span!("site", name = %site.name);
+        let dir = site.output_dir(root);
+        let previous = read_site_report(&dir);
+        if let Some(pause) = Pause::load(&dir) {
+            warn!(parent: &span, "Paused since {}: {}", pause.paused_at.format("%Y-%m-%d %H:%M UTC"), pause.reason);
+            results.push(SiteResult {
+                name: site.name.clone(),
+                url: site.url.clone(),
+                dir,
+                status: SiteStatus::Paused,
+                error: None,
+                last_run: previous.finished_at,
+                next_due: None,
+                pause_reason: Some(pause.reason),
+                pages: previous.pages,
+            });
+            continue;
+        }
+        let next_due = site.schedule.next_due(previous.finished_at, Utc::now()).filter(|_| !force);
+        if let Some(next_due) = next_due {
+            info!(parent: &span, "Skipped: not due until {}", next_due.format("%Y-%m-%d %H:%M UTC"));
+            results.push(SiteResult {
+                name: site.name.clone(),
+                url: site.url.clone(),
+                dir,
+                status: SiteStatus::Skipped,
+                error: None,
+                last_run: previous.finished_at,
+                next_due: Some(next_due),
+                pause_reason: None,
+                pages: previous.pages,
+            });
+            continue;
+        }
+
+        info!(parent: &span, "Scraping {} into {}", site.url, dir.display());
+        let outcome = run_site(client, api_url, &workspace, site, &dir, &pacer)
+            .instrument(span.clone())
+            .await;
+        let report = read_site_report(&dir);
+        let (mut status, error) = match outcome {
+            Ok(()) => (SiteStatus::Succeeded, None),
+            Err(e) => {
+                error!(parent: &span, "Failed: {}", e);
+                notifiers
+                    .send(&Notification::failure(&site.name, &site.url, &e.to_string()))
+                    .instrument(span.clone())
+                    .await;
+                (SiteStatus::Failed, Some(e.to_string()))
+            }
+        };
+        let mut pause_reason = None;
+        if status == SiteStatus::Succeeded {
+            let previous_pages = previous.finished_at.map(|_| &previous.pages);
+            if let Some(reason) = site.pause_conditions(&workspace.workspace).check(previous_pages, &report.pages) {
+                error!(parent: &span, "Paused: {}; run `scraper unpause {}` once it is fixed", reason, site.name);
+                Pause {
+                    paused_at: Utc::now(),
+                    reason: reason.clone(),
+                }
+                .save(&dir)?;
+                notifiers
+                    .send(&Notification::pause(&site.name, &site.url, &reason))
+                    .instrument(span.clone())
+                    .await;
+                status = SiteStatus::Paused;
+                pause_reason = Some(reason);
+                paused += 1;
+            }
+        }
+        if status == SiteStatus::Succeeded {
+            let pages = &report.pages;
+            let changes =
+                Notification::changes(&site.name, &site.url, pages.pages_added, pages.pages_changed, pages.pages_removed);
+            if let Some(notification) = changes {
+                notifiers.send(&notification).instrument(span.clone()).await;
+            }
+        }
+        results.push(SiteResult {
+            name: site.name.clone(),
+            url: site.url.clone(),
+            dir,
+            status,
+            error,
+            last_run: report.finished_at.or(previous.finished_at),
+            next_due: None,
+            pause_reason,
+            pages: report.pages,
+        });
+        if shutdown::requested() {
+            warn!("Interrupted; the remaining sites are not run");
+            break;
+        }
+    }
+
+    let report = WorkspaceReport {
+        started_at,
+        finished_at: Utc::now(),
+        sites: results,
+    };
+    write_report(root, &report)?;
+
+    let failed = report.sites.iter().filter(|site| site.status == SiteStatus::Failed).count();
+    let total = report.sites.len();
+    match (failed, paused) {
+        (0, 0) => {}
+        (failed, 0) => return Err(format!("{} of {} sites failed", failed, total).into()),
+        (0, paused) => return Err(format!("{} of {} sites were paused", paused, total).into()),
+        (failed, paused) => return Err(format!("{} of {} sites failed and {} were paused", failed, total, paused).into()),
+    }
+    Ok(report)
+}
+
+/// Runs `run_all` whenever a site of the workspace is due, until stopped.
+///
+/// Each round only scrapes the sites that are due, and each site's manifest
+/// keeps unchanged pages from being rewritten, so a round costs little more
+/// than the pages that changed. A failing round is logged and the daemon
+/// carries on. Between rounds it sleeps until the next site is due, at least
+/// five minutes and at most an hour; the workspace file is read again for
+/// every round. A signal while sleeping ends the process at once; one during
+/// a round stops it cleanly, as for `run-all`.
+///
+/// With a status address, `status.json` and the badges written after every
+/// round are served over HTTP on it for as long as the daemon runs.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client, shared by all sites and rounds
+/// * `api_url` - The FireCrawl API endpoint
+/// * `path` - The workspace file; output directories are relative to its directory
+/// * `only` - Names of the sites to run; all sites when empty
+/// * `status_addr` - Address to serve the status files on, if any
+///
+/// # Errors
+///
+/// Returns an error if the workspace cannot be loaded when the daemon starts,
+/// `only` names an unknown site, or the status address cannot be listened on
+pub async fn run_daemon(
+    client: &Client,
+    api_url: &str,
+    path: &Path,
+    only: &[String],
+    status_addr: Option<SocketAddr>,
+) -> Result<(), Box<dyn Error>> {
+    let workspace = Workspace::load(path)?;
+    if let Some(unknown) = only.iter().find(|name| !workspace.sites.iter().any(|site| &site.name == *name)) {
+        return Err(format!("No site named {} in {}", unknown, path.display()).into());
+    }
+    if let Some(addr) = status_addr {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+        info!("Serving status.json and badges on http://{}", listener.local_addr()?);
+        tokio::spawn(status::serve(listener, path.parent().unwrap_or(Path::new("")).to_path_buf()));
+    }
+    info!("Running {} as a daemon; press Ctrl-C to stop", path.display());
+
+    loop {
+        match run_all(client, api_url, path, only, false).await {
+            Ok(report) => {
+                let scraped = report.sites.iter().filter(|site| site.status == SiteStatus::Succeeded).count();
+                info!("Round finished: {} of {} sites scraped", scraped, report.sites.len());
+            }
+            Err(e) => error!("Round failed: {}", e),
+        }
+        if shutdown::requested() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let wake = next_round(path, only, now)
+            .unwrap_or(now + DAEMON_MAX_WAIT)
+            .clamp(now + DAEMON_MIN_WAIT, now + DAEMON_MAX_WAIT);
+        info!("Next round at {}", wake.format("%Y-%m-%d %H:%M UTC"));
+        tokio::time::sleep((wake - now).to_std()?).await;
+    }
+}
+
+/// Returns when the first of the selected sites is due again, or `None` if
+/// none has a due time (they run on every round, are paused, or the workspace no longer loads).
+///
+/// Sites that are already due again after a round, such as failed ones, are
+/// left out so they are retried on the next round rather than at once.
+fn next_round(path: &Path, only: &[String], now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let workspace = Workspace::load(path).ok()?;
+    let root = path.parent().unwrap_or(Path::new(""));
+    workspace
+        .sites
+        .iter()
+        .filter(|site| only.is_empty() || only.contains(&site.name))
+        .filter(|site| Pause::load(&site.output_dir(root)).is_none())
+        .filter_map(|site| {
+            let previous = read_site_report(&site.output_dir(root));
+            site.schedule.next_due(previous.finished_at, now)
+        })
+        .min()
+}
+
+/// Unpauses sites of a workspace, so they run again when next due.
+///
+/// # Returns
+///
+/// The names of the sites that were paused
+///
+/// # Errors
+///
+/// Returns an error if the workspace cannot be loaded, `names` includes an
+/// unknown site, or a pause record cannot be removed
+pub fn unpause(path: &Path, names: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let workspace = Workspace::load(path)?;
+    let root = path.parent().unwrap_or(Path::new(""));
+    let mut unpaused = Vec::new();
+    for name in names {
+        let site = workspace
+            .sites
+            .iter()
+            .find(|site| &site.name == name)
+            .ok_or_else(|| format!("No site named {} in {}", name, path.display()))?;
+        if Pause::clear(&site.output_dir(root))? {
+            unpaused.push(name.clone());
+        }
+    }
+    Ok(unpaused)
+}
+
+/// Scrapes one site of a workspace into `dir`.
+async fn run_site(
+    client: &Client,
+    api_url: &str,
+    workspace: &Workspace,
+    site: &Site,
+    dir: &Path,
+    pacer: &CreditPacer,
+) -> Result<(), Box<dyn Error>> {
+    let options = workspace.site_options(site)?;
+    let api_key = crate::api::api_key(options.backend)?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create output directory {}: {}", dir.display(), e))?;
+    let start_url = input_url::resolve(client, &site.url, &options).await?;
+    let api = Api { client, url: api_url, key: &api_key };
+    let pages = crate::site::scrape_site(&api, &start_url, None, dir, &options, pacer).await?;
+    if pages.interrupted {
+        return Err(format!("Interrupted; continue with: scraper resume {}", dir.display()).into());
+    }
+    Ok(())
+}
+
+/// Reads the run report of a site's output directory, returning an empty report if there is none.
+fn read_site_report(dir: &Path) -> SiteRunReport {
+    fs::read_to_string(crate::state_dir(dir).join(REPORT_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the combined JSON report, the markdown dashboard and the status files into `root`.
+///
+/// # Errors
+///
+/// Returns an error if any of the files cannot be written
+fn write_report(root: &Path, report: &WorkspaceReport) -> Result<(), Box<dyn Error>> {
+    let report_path = root.join(WORKSPACE_REPORT_FILE_NAME);
+    fs::write(&report_path, serde_json::to_string_pretty(report)?)
+        .map_err(|e| format!("Failed to write report {}: {}", report_path.display(), e))?;
+
+    let dashboard_path = root.join(DASHBOARD_FILE_NAME);
+    fs::write(&dashboard_path, dashboard(report))
+        .map_err(|e| format!("Failed to write dashboard {}: {}", dashboard_path.display(), e))?;
+
+    status::write(root, report)?;
+
+    info!("Workspace report written to: {}", report_path.display());
+    info!("Dashboard written to: {}", dashboard_path.display());
+    Ok(())
+}
+
+/// Renders the combined report as a markdown table, one row per site.
+fn dashboard(report: &WorkspaceReport) -> String {
+    let mut out = format!(
+        "# Documentation Mirrors\n\nLast run: {}\n\n| Site | Status | Last run | Found | Processed | Failed | Empty | Notes |\n|------|--------|----------|-------|-----------|--------|-------|-------|\n",
+        report.finished_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    for site in &report.sites {
+        let status = match site.status {
+            SiteStatus::Succeeded => "succeeded",
+            SiteStatus::Failed => "failed",
+            SiteStatus::Skipped => "skipped",
+            SiteStatus::Paused => "paused",
+        };
+        let last_run = site
+            .last_run
+            .map_or("never".to_string(), |time| time.format("%Y-%m-%d %H:%M").to_string());
+        let notes = match (site.error.as_ref().or(site.pause_reason.as_ref()), site.next_due) {
+            (Some(error), _) => error.replace('|', "\\|").replace('\n', " "),
+            (None, Some(next_due)) => format!("due {}", next_due.format("%Y-%m-%d %H:%M")),
+            (None, None) => String::new(),
+        };
+        out.push_str(&format!(
+            "| [{}]({}) | {} | {} | {} | {} | {} | {} | {} |\n",
+            site.name,
+            site.url,
+            status,
+            last_run,
+            site.pages.pages_found,
+            site.pages.pages_processed,
+            site.pages.pages_failed,
+            site.pages.pages_empty,
+            notes
+        ));
+    }
+    out
+}